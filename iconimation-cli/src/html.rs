@@ -0,0 +1,97 @@
+//! Single file html preview of a Lottie, handy for attaching to bug reports
+
+/// lottie-web 5.12.2, pinned so a preview renders the same way later as it did when it was made.
+/// Fetched by vendor/fetch_lottie.sh.
+const LOTTIE_WEB: &str = include_str!("../vendor/lottie.min.js");
+
+/// Produce an html page that embeds the Lottie and a player with play/pause and scrub controls;
+/// it plays offline, nothing is loaded from elsewhere
+pub fn html_preview(title: &str, lottie_json: &str) -> String {
+    // Don't let the json, or the player, terminate the script it lives in
+    let lottie_json = lottie_json.replace("</", "<\\/");
+    let player = LOTTIE_WEB.replace("</script", "<\\/script");
+    let title = escape(title);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en-US">
+  <head>
+    <meta charset="utf-8" />
+    <title>{title}</title>
+    <style>
+    #player {{
+        width: 30vw;
+        height: 30vw;
+    }}
+    #scrub {{
+        width: 30vw;
+    }}
+    </style>
+  </head>
+  <body>
+    <script>{player}</script>
+    <h1>{title}</h1>
+    <div id="player"></div>
+    <div>
+      <button id="play">Pause</button>
+      <input id="scrub" type="range" min="0" value="0" step="1">
+      <span id="frame"></span>
+    </div>
+    <script id="lottie" type="application/json">{lottie_json}</script>
+    <script>
+      const animation = lottie.loadAnimation({{
+        container: document.getElementById("player"),
+        renderer: "svg",
+        loop: true,
+        autoplay: true,
+        animationData: JSON.parse(document.getElementById("lottie").textContent),
+      }});
+      const play = document.getElementById("play");
+      const scrub = document.getElementById("scrub");
+      const frame = document.getElementById("frame");
+      animation.addEventListener("DOMLoaded", () => {{
+        scrub.max = Math.max(0, Math.floor(animation.totalFrames) - 1);
+      }});
+      animation.addEventListener("enterFrame", () => {{
+        scrub.value = Math.floor(animation.currentFrame);
+        frame.innerText = `frame ${{scrub.value}}`;
+      }});
+      play.addEventListener("click", () => {{
+        animation.togglePause();
+        play.innerText = animation.isPaused ? "Play" : "Pause";
+      }});
+      scrub.addEventListener("input", () => {{
+        animation.goToAndStop(Number(scrub.value), true);
+        play.innerText = "Play";
+        frame.innerText = `frame ${{scrub.value}}`;
+      }});
+    </script>
+  </body>
+</html>
+"#
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::html_preview;
+
+    #[test]
+    fn preview_is_self_contained() {
+        let html = html_preview("settings <twirl>", r#"{"v":"5.7.0","nm":"</script>"}"#);
+        // Nothing is loaded from elsewhere
+        for external in ["<script src", "src=\"http", "href=\"http"] {
+            assert!(!html.contains(external), "{external} in the preview");
+        }
+        assert!(html.contains("lottie.loadAnimation"));
+        assert!(html.contains(&super::LOTTIE_WEB[..100]));
+        assert!(html.contains(r#"{"v":"5.7.0","nm":"<\/script>"}"#));
+        assert!(html.contains("<title>settings &lt;twirl&gt;</title>"));
+    }
+}
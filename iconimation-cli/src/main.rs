@@ -1,26 +1,73 @@
-use std::str::FromStr;
-use std::{fs, path::Path};
+mod html;
+
+use std::{fs, io::BufWriter, path::Path, process, str::FromStr, time::Duration};
 
 use bodymovin::Bodymovin as Lottie;
-use clap::Parser;
-use iconimation::android::AnimatedVectorDrawable;
-use iconimation::ir::{Animation, FromAnimation};
-use iconimation::plan::parse_plan;
-use skrifa::instance::Location;
-use skrifa::raw::types::InvalidTag;
+use clap::{Parser, Subcommand, ValueEnum};
+use iconimation::android::{to_avd, AndroidOptions};
+use iconimation::audit::{check_icon_varying, Variation};
+use iconimation::bezop::Precision;
+use iconimation::debug::DebugAnimation;
+use iconimation::font_info::icon_names;
+use iconimation::ir::Animation;
+use iconimation::labels::PartHints;
+use iconimation::lottie::{
+    find_placeholders, inject_into_template, lottie_json, to_lottie, LottieOptions, LottieProfile,
+};
+use iconimation::plan::{parse_plan, Anchor, Granularity, PlanOptions, VariationMode};
+use iconimation::preview::{filmstrip_svg, sample_frames};
+use iconimation::spring::{AnimatedValue, AnimatedValueType, Spring};
+use iconimation::spring2cubic::cubic_approximation;
+use iconimation::syntax::syntax;
+use log::LevelFilter;
+use serde_json::json;
 use skrifa::raw::FontRef;
-use skrifa::{MetadataProvider, Tag};
-use thiserror::Error;
 
+/// Without a subcommand, generates an animation from a font as `generate` does
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    subcommand: Option<Command>,
+
+    /// Log more about what's going on, -v for progress, -vv for debugging, -vvv for everything.
+    /// RUST_LOG, if set, takes precedence.
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    #[command(flatten)]
+    generate: GenerateArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate an animation from a font, the default if no subcommand is given
+    Generate(GenerateArgs),
+    /// List the icons in a font
+    ListIcons(ListIconsArgs),
+    /// Report whether an icon can be animated, without generating anything
+    Preflight(PreflightArgs),
+    /// Draw frames sampled from an animation side by side in an SVG
+    Preview(PreviewArgs),
+    /// Write an animation saved by --emit ir in a playback format, no font required
+    Render(RenderArgs),
+    /// Print the named springs, sampled every frame and as the cubics that approximate them
+    Springs(SpringsArgs),
+}
+
+#[derive(clap::Args)]
+struct GenerateArgs {
     #[arg(short, long)]
-    #[clap(required(true))]
-    command: String,
+    #[clap(required_unless_present("help_commands"))]
+    command: Option<String>,
 
     #[arg(short, long)]
-    #[clap(required(true))]
-    font: String,
+    #[clap(required_unless_present("help_commands"))]
+    font: Option<String>,
+
+    /// Describe what --command accepts and exit
+    #[arg(long, exclusive = true)]
+    help_commands: bool,
 
     #[arg(short, long)]
     #[clap(default_value = "lottie.json")]
@@ -29,67 +76,612 @@ struct Args {
     #[arg(short, long)]
     #[clap(default_value = "avd.xml")]
     android_output: String,
+
+    /// Put the animation into the placeholders of this Lottie rather than a Lottie of its own
+    #[arg(long)]
+    template: Option<String>,
+
+    /// Also write a self-contained html page that plays the Lottie
+    #[arg(long)]
+    html: Option<String>,
+
+    /// Also write every keyframe of the animation, as json, to help debug
+    #[arg(long)]
+    debug_json: Option<String>,
+
+    /// Print intermediate data, as json, to stdout. May be repeated.
+    #[arg(long, value_enum)]
+    emit: Vec<Emit>,
+
+    /// Turn on an optional pass, e.g. snap, or loop to make the animation loop seamlessly.
+    /// May be repeated.
+    #[arg(long)]
+    enable_pass: Vec<String>,
+
+    /// Turn off a pass that runs by default, e.g. dedupe. May be repeated.
+    #[arg(long)]
+    disable_pass: Vec<String>,
+
+    /// Write both the whole-icon and per-part versions of the plan, e.g. lottie-whole.json
+    /// and lottie-parts.json, rather than just the one named
+    #[arg(long)]
+    whole_and_parts: bool,
+
+    /// Write the plan played forward and backward, e.g. lottie-forward.json and
+    /// lottie-backward.json, for toggling between two states
+    #[arg(long, conflicts_with = "whole_and_parts")]
+    toggle: bool,
+
+    /// Write compact Lottie json, numbers rounded to this many decimal places (default 3)
+    #[arg(long, num_args = 0..=1, default_missing_value = "3")]
+    minify: Option<u32>,
+
+    /// Round path coordinates, in Lottie and AVD alike, to this many decimal places
+    #[arg(long)]
+    precision: Option<u32>,
+
+    /// Write byte-identical output on every platform, e.g. for golden tests
+    #[arg(long)]
+    stable: bool,
+
+    /// Put each part of the icon in a Lottie layer of its own
+    #[arg(long)]
+    layer_per_part: bool,
+
+    /// The players the Lottie is for, skottie also draws motion blur, e.g. from
+    /// --enable-pass motion-blur
+    #[arg(long, value_enum, default_value = "portable")]
+    lottie_profile: Profile,
+
+    /// Emit a placeholder box, with a warning, if the icon can't be found or drawn
+    #[arg(long)]
+    placeholder_on_error: bool,
+
+    /// Ease variation in the designspace and redraw the icon along the way, rather than
+    /// interpolating between drawings at the start and end
+    #[arg(long)]
+    redraw_variation: bool,
+
+    /// Keep overlapping contours together, but not unioned, when animating parts, so overlaps don't
+    /// come apart
+    #[arg(long)]
+    group_overlaps: bool,
+
+    /// What each part rotates and scales about when animating parts: bbox, centroid, nearest
+    /// (the point nearest the icon's center), or a fixed x,y
+    #[arg(long, default_value = "bbox", value_parser = Anchor::from_str)]
+    anchor: Anchor,
+
+    /// A json file naming the parts of icons, e.g. {"schedule": ["face", "hand"]}, so commands
+    /// can pick them out with `part <name>`
+    #[arg(long)]
+    part_hints: Option<String>,
+
+    /// Draw parts that move above those that hold still, e.g. so a pulsing part isn't hidden
+    #[arg(long)]
+    moving_parts_on_top: bool,
+
+    /// Draw parts in the icon's color rather than a color each to show how the icon was grouped
+    #[arg(long)]
+    plain_parts: bool,
+
+    /// Frames per second to write, rather than the 60 plans are made at
+    #[arg(long)]
+    fps: Option<f64>,
+
+    /// Speed up or slow down the animation to last this many milliseconds
+    #[arg(long)]
+    duration_ms: Option<u64>,
+
+    /// Draw the icon this many pixels square, rather than at the font's units per em
+    #[arg(long)]
+    size: Option<f64>,
 }
 
-#[derive(Debug, Error)]
-pub enum LocationError {
-    #[error("Position must be a csv of tag:value pairs, e.g. FILL:1,wght:100")]
-    InvalidPosition,
-    #[error("Invalid tag '{0}'")]
-    InvalidTag(InvalidTag),
-    #[error("Font does not support tag '{0}'")]
-    NoSuchAxis(Tag),
-    #[error("Unable to parse value of '{0}'")]
-    InvalidValue(Tag),
-    #[error("Value for '{0}' must be in [{1:.2}, {2:.2}]")]
-    OutOfBounds(Tag, f32, f32),
-}
-
-/// Avoid orphan rule
-trait LocationParser {
-    fn parse_location(&self, s: Option<&str>) -> Result<Location, LocationError>;
-}
-
-impl LocationParser for FontRef<'_> {
-    fn parse_location(&self, s: Option<&str>) -> Result<Location, LocationError> {
-        let Some(s) = s else {
-            return Ok(Location::default());
-        };
-        let mut axis_positions = Vec::new();
-        for raw_pos in s.split(',') {
-            let parts: Vec<_> = raw_pos.split(':').collect();
-            if parts.len() != 2 {
-                return Err(LocationError::InvalidPosition);
-            }
-            let tag = Tag::from_str(parts[0]).map_err(LocationError::InvalidTag)?;
-            let value: f32 = parts[1]
-                .parse()
-                .map_err(|_| LocationError::InvalidValue(tag))?;
-            axis_positions.push((tag, value));
+#[derive(clap::Args)]
+struct ListIconsArgs {
+    #[arg(short, long)]
+    font: String,
+
+    /// Print the icons as json
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct PreflightArgs {
+    #[arg(short, long)]
+    font: String,
+
+    /// The icon name, e.g. settings
+    #[arg(short, long)]
+    icon: String,
+
+    /// Also check the icon can animate along an axis, e.g. FILL:0..1
+    #[arg(long)]
+    vary: Option<Variation>,
+
+    /// Print the report as json
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct PreviewArgs {
+    /// Json, as written by --emit ir, rather than a --font and --command
+    #[arg(required_unless_present = "command")]
+    input: Option<String>,
+
+    #[arg(short, long, requires = "font", conflicts_with = "input")]
+    command: Option<String>,
+
+    #[arg(short, long, requires = "command")]
+    font: Option<String>,
+
+    /// How many frames to draw, evenly spaced from the first to the last
+    #[arg(long, default_value_t = 5)]
+    frames: usize,
+
+    #[arg(short, long, default_value = "preview.svg")]
+    output: String,
+}
+
+#[derive(clap::Args)]
+struct SpringsArgs {
+    /// The springs to print, every named spring if none are given
+    #[arg(value_parser = clap::builder::PossibleValuesParser::new(Spring::NAMES))]
+    springs: Vec<String>,
+
+    #[arg(long, default_value_t = 0.0)]
+    from: f64,
+
+    #[arg(long, default_value_t = 1.0)]
+    to: f64,
+
+    #[arg(long, default_value_t = 60.0)]
+    fps: f64,
+}
+
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Json, as written by --emit ir
+    input: String,
+
+    #[arg(long, value_enum)]
+    format: Format,
+
+    /// Where to write the result, stdout if not set
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Write compact Lottie json, numbers rounded to this many decimal places (default 3)
+    #[arg(long, num_args = 0..=1, default_missing_value = "3")]
+    minify: Option<u32>,
+
+    /// Round path coordinates, in Lottie and AVD alike, to this many decimal places
+    #[arg(long)]
+    precision: Option<u32>,
+
+    /// Write byte-identical output on every platform, e.g. for golden tests
+    #[arg(long)]
+    stable: bool,
+
+    /// Put each part of the icon in a Lottie layer of its own
+    #[arg(long)]
+    layer_per_part: bool,
+
+    /// The players the Lottie is for, skottie also draws motion blur, e.g. from
+    /// --enable-pass motion-blur
+    #[arg(long, value_enum, default_value = "portable")]
+    lottie_profile: Profile,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Lottie,
+    /// AnimatedVectorDrawable
+    Avd,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Profile {
+    /// Anything every player draws
+    Portable,
+    /// Skia's Skottie
+    Skottie,
+}
+
+impl From<Profile> for LottieProfile {
+    fn from(profile: Profile) -> Self {
+        match profile {
+            Profile::Portable => LottieProfile::Portable,
+            Profile::Skottie => LottieProfile::Skottie,
         }
-        Ok(self.axes().location(axis_positions))
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// The command as parsed
+    Plan,
+    /// The animation once the plan is applied and passes have run
+    Ir,
+}
+
 fn main() {
     let args = Args::parse();
+    env_logger::Builder::new()
+        .filter_level(match args.verbose {
+            0 => LevelFilter::Warn,
+            1 => LevelFilter::Info,
+            2 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        })
+        .parse_default_env()
+        .init();
+    match &args.subcommand {
+        Some(Command::Generate(generate_args)) => generate(generate_args),
+        Some(Command::ListIcons(list_args)) => list_icons(list_args),
+        Some(Command::Preflight(preflight_args)) => preflight(preflight_args),
+        Some(Command::Preview(preview_args)) => preview(preview_args),
+        Some(Command::Render(render_args)) => render(render_args),
+        Some(Command::Springs(springs_args)) => springs(springs_args),
+        None => generate(&args.generate),
+    }
+}
+
+fn print_command_help() {
+    let syntax = syntax();
+    println!("{}\n", syntax.command);
+    for (heading, clauses) in [("Plans", syntax.plans), ("Modifiers", syntax.modifiers)] {
+        println!("{heading}:");
+        for clause in clauses {
+            println!("  {}", clause.syntax);
+            println!("      {}", clause.description);
+            println!("      e.g. {}", clause.example);
+            if !clause.chainable {
+                println!("      can't be combined with then or while");
+            }
+        }
+        println!();
+    }
+    println!("Springs: {}", syntax.springs.join(", "));
+    println!("Spring schemes: {}", syntax.spring_schemes.join(", "));
+    println!("Channels: {}", syntax.channels.join(", "));
+    println!("Eases: {}", syntax.eases.join(", "));
+    println!("Benders: {}", syntax.benders.join(", "));
+    println!("Presets:");
+    for preset in syntax.presets {
+        println!("  {}: {}", preset.name, preset.description);
+    }
+}
+
+fn render(args: &RenderArgs) {
+    let json = fs::read_to_string(&args.input).unwrap();
+    let animation = Animation::from_json(&json).unwrap();
+    let precision = args.precision.map(Precision);
+    let output = match args.format {
+        Format::Lottie => {
+            let options = LottieOptions {
+                minify: args.minify,
+                precision,
+                stable: args.stable,
+                layer_per_part: args.layer_per_part,
+                profile: args.lottie_profile.into(),
+                ..Default::default()
+            };
+            let lottie = to_lottie(&animation, &options).unwrap();
+            lottie_json(&lottie, animation.segments(), &options).unwrap()
+        }
+        Format::Avd => to_avd(
+            &animation,
+            &AndroidOptions {
+                precision,
+                stable: args.stable,
+            },
+        )
+        .unwrap()
+        .to_avd_xml()
+        .unwrap(),
+    };
+    match &args.output {
+        Some(path) => {
+            fs::write(path, output).unwrap();
+            eprintln!("Wrote {path}");
+        }
+        None => println!("{output}"),
+    }
+}
+
+fn list_icons(args: &ListIconsArgs) {
+    let font_bytes = fs::read(&args.font).unwrap();
+    let font = FontRef::new(&font_bytes).unwrap();
+    let icons = icon_names(&font).unwrap();
+    if args.json {
+        let icons: Vec<_> = icons
+            .iter()
+            .map(|icon| {
+                json!({
+                    "name": icon.name,
+                    "gid": icon.gid.to_u16(),
+                    "codepoint": icon.codepoint,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&icons).unwrap());
+        return;
+    }
+    for icon in icons {
+        match icon.codepoint {
+            Some(codepoint) => println!("{} U+{codepoint:04X}", icon.name),
+            None => println!("{}", icon.name),
+        }
+    }
+}
+
+fn preview(args: &PreviewArgs) {
+    let animation = match (&args.input, &args.command, &args.font) {
+        (Some(input), ..) => Animation::from_json(&fs::read_to_string(input).unwrap()).unwrap(),
+        (None, Some(command), Some(font)) => {
+            let font_bytes = fs::read(font).unwrap();
+            let font = FontRef::new(&font_bytes).unwrap();
+            Animation::of_command(&font, command, &PlanOptions::default()).unwrap()
+        }
+        _ => unreachable!("clap requires an input or --command and --font"),
+    };
+    for warning in animation.warnings() {
+        eprintln!("WARNING {warning}");
+    }
+    let frames = sample_frames(animation.frames(), args.frames);
+    fs::write(&args.output, filmstrip_svg(&animation, &frames)).unwrap();
+    eprintln!("Wrote {} frame(s) to {}", frames.len(), args.output);
+}
+
+fn springs(args: &SpringsArgs) {
+    let names: Vec<&str> = if args.springs.is_empty() {
+        Spring::NAMES.to_vec()
+    } else {
+        args.springs.iter().map(String::as_str).collect()
+    };
+    let curves: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            let spring: Spring = name.parse().unwrap();
+            let samples: Vec<_> = spring
+                .iter_frames(args.from, args.to, AnimatedValueType::Scale, args.fps)
+                .enumerate()
+                .map(|(frame, state)| (frame as f64, state.value))
+                .collect();
+            let animation = AnimatedValue::new(args.from, args.to, AnimatedValueType::Scale);
+            let cubics: Vec<_> = cubic_approximation(args.fps, animation, spring)
+                .unwrap()
+                .into_iter()
+                .map(|c| [c.p0, c.p1, c.p2, c.p3].map(|p| (p.x, p.y)))
+                .collect();
+            json!({
+                "name": name,
+                "spring": spring,
+                "frame_rate": args.fps,
+                "samples": samples,
+                "cubics": cubics,
+            })
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&curves).unwrap());
+}
+
+fn preflight(args: &PreflightArgs) {
+    let font_bytes = fs::read(&args.font).unwrap();
+    let font = FontRef::new(&font_bytes).unwrap();
+    let report = match check_icon_varying(&font, &args.icon, args.vary.as_ref()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("FAIL {}: {e}", args.icon);
+            process::exit(1);
+        }
+    };
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        let check = |ok: bool| if ok { "ok" } else { "FAIL" };
+        let axis = |compatible: Option<bool>| compatible.map(check).unwrap_or("no axis");
+        println!("{}", report.icon_name);
+        println!("  outline      {}", check(report.drawable));
+        println!("  parts        {}", report.parts);
+        println!("  FILL         {}", axis(report.fill_compatible));
+        println!("  wght         {}", axis(report.wght_compatible));
+        if let (Some(vary), Some(compatible)) = (&args.vary, report.variation_compatible) {
+            println!(
+                "  {} {}..{} {}",
+                vary.tag,
+                vary.from,
+                vary.to,
+                check(compatible)
+            );
+        }
+        for warning in report.warnings.iter() {
+            println!("WARNING {warning}");
+        }
+    }
+    if !report.drawable || report.variation_compatible == Some(false) {
+        process::exit(1);
+    }
+}
+
+/// The part hints in `file`, exiting if they can't be read
+fn load_part_hints(file: &str) -> PartHints {
+    let hints = fs::read_to_string(file)
+        .map_err(|e| e.to_string())
+        .and_then(|json| PartHints::from_json(&json).map_err(|e| e.to_string()));
+    match hints {
+        Ok(hints) => hints,
+        Err(e) => {
+            eprintln!("Unable to load part hints from {file}: {e}");
+            process::exit(1);
+        }
+    }
+}
 
-    let font_file = Path::new(args.font.as_str());
+fn generate(args: &GenerateArgs) {
+    if args.help_commands {
+        print_command_help();
+        return;
+    }
+    let (Some(command), Some(font)) = (&args.command, &args.font) else {
+        unreachable!("clap requires --command and --font unless --help-commands is given");
+    };
+    let font_file = Path::new(font.as_str());
     let font_bytes = fs::read(font_file).unwrap();
     let font = FontRef::new(&font_bytes).unwrap();
 
-    let (plan, glyph_shape) = parse_plan(&font, &args.command).unwrap();
-    let animation = Animation::of_icon(&plan, &glyph_shape).unwrap();
+    let mut options = PlanOptions {
+        placeholder_on_error: args.placeholder_on_error,
+        variation: if args.redraw_variation {
+            VariationMode::Redraw
+        } else {
+            VariationMode::Interpolate
+        },
+        group_overlaps: args.group_overlaps,
+        anchor: args.anchor,
+        part_hints: args
+            .part_hints
+            .as_deref()
+            .map(load_part_hints)
+            .unwrap_or_default(),
+        moving_parts_on_top: args.moving_parts_on_top,
+        plain_parts: args.plain_parts,
+        frame_rate: args.fps,
+        duration: args.duration_ms.map(Duration::from_millis),
+        size: args.size,
+        ..Default::default()
+    };
+    for name in args.enable_pass.iter() {
+        options.passes.enable(name).unwrap();
+    }
+    for name in args.disable_pass.iter() {
+        options.passes.disable(name).unwrap();
+    }
+    if args.emit.contains(&Emit::Plan) {
+        let (plan, _) = parse_plan(&font, command).unwrap();
+        println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+    }
+
+    let animations = if args.whole_and_parts {
+        let (plan, glyph_shape) = parse_plan(&font, command).unwrap();
+        let (whole, parts) =
+            Animation::of_icon_whole_and_parts(&plan, &glyph_shape, &options).unwrap();
+        vec![
+            (Some(Granularity::Whole.name()), whole),
+            (Some(Granularity::Parts.name()), parts),
+        ]
+    } else if args.toggle {
+        let (plan, glyph_shape) = parse_plan(&font, command).unwrap();
+        let (forward, backward) = Animation::toggle_of_icon(&plan, &glyph_shape, &options).unwrap();
+        vec![(Some("forward"), forward), (Some("backward"), backward)]
+    } else {
+        vec![(
+            None,
+            Animation::of_command(&font, command, &options).unwrap(),
+        )]
+    };
+    for (variant, animation) in animations {
+        write_outputs(args, &font, command, variant, &animation);
+    }
+}
+
+/// Write the requested outputs for one animation, `variant` is added to each file name if set
+fn write_outputs(
+    args: &GenerateArgs,
+    font: &FontRef,
+    command: &str,
+    variant: Option<&str>,
+    animation: &Animation,
+) {
+    if args.emit.contains(&Emit::Ir) {
+        println!("{}", serde_json::to_string_pretty(animation).unwrap());
+    }
+    for warning in animation.warnings() {
+        eprintln!("WARNING {warning}");
+    }
+
+    if let Some(debug_output) = &args.debug_json {
+        let debug_output = variant_path(debug_output, variant);
+        let mut debug = DebugAnimation::from(animation);
+        debug.name_icon(font);
+        fs::write(&debug_output, serde_json::to_string_pretty(&debug).unwrap()).unwrap();
+        eprintln!("Wrote debug json {debug_output}");
+    }
+
+    let precision = args.precision.map(Precision);
+    let lottie_options = LottieOptions {
+        minify: args.minify,
+        precision,
+        stable: args.stable,
+        layer_per_part: args.layer_per_part,
+        profile: args.lottie_profile.into(),
+        ..Default::default()
+    };
+    let lottie_output = variant_path(&args.lottie_output, variant);
+    let lottie = match &args.template {
+        Some(template) => {
+            let lottie: Lottie =
+                serde_json::from_str(&fs::read_to_string(template).unwrap()).unwrap();
+            for placeholder in find_placeholders(&lottie).unwrap() {
+                eprintln!(
+                    "Placeholder {} in layer {} fills {} rect(s)",
+                    placeholder.name.as_deref().unwrap_or("(unnamed)"),
+                    placeholder.layer,
+                    placeholder.rects.len()
+                );
+            }
+            let mut lottie = lottie.into();
+            inject_into_template(&mut lottie, animation, &lottie_options).unwrap();
+            lottie
+        }
+        None => to_lottie(animation, &lottie_options).unwrap(),
+    };
+    let lottie = lottie_json(&lottie, animation.segments(), &lottie_options).unwrap();
+    fs::write(&lottie_output, &lottie).unwrap();
+    eprintln!("Wrote Lottie {lottie_output}, {} bytes", lottie.len());
 
-    let lottie = Lottie::from_animation(&animation).unwrap();
-    fs::write(
-        &args.lottie_output,
-        serde_json::to_string_pretty(&lottie).unwrap(),
+    if let Some(html_output) = &args.html {
+        let html_output = variant_path(html_output, variant);
+        fs::write(&html_output, html::html_preview(command, &lottie)).unwrap();
+        eprintln!("Wrote html preview {html_output}");
+    }
+
+    let android_output = variant_path(&args.android_output, variant);
+    let avd = to_avd(
+        animation,
+        &AndroidOptions {
+            precision,
+            stable: args.stable,
+        },
     )
     .unwrap();
-    eprintln!("Wrote Lottie {}", args.lottie_output);
+    let mut file = BufWriter::new(fs::File::create(&android_output).unwrap());
+    avd.write_to(&mut file).unwrap();
+    eprintln!("Wrote AnimatedVectorDrawable {android_output}");
+}
 
-    let avd = AnimatedVectorDrawable::from_animation(&animation).unwrap();
-    fs::write(&args.android_output, avd.to_avd_xml().unwrap()).unwrap();
-    eprintln!("Wrote AnimatedVectorDrawable {}", args.android_output);
+/// lottie.json => lottie-whole.json for variant whole
+fn variant_path(path: &str, variant: Option<&str>) -> String {
+    let Some(variant) = variant else {
+        return path.to_string();
+    };
+    let path = Path::new(path);
+    let mut file_name = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned();
+    file_name.push('-');
+    file_name.push_str(variant);
+    if let Some(extension) = path.extension() {
+        file_name.push('.');
+        file_name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
 }
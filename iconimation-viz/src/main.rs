@@ -34,20 +34,10 @@ pub fn main() {
 
     let mut value_seqs = Vec::new();
     for (_, spring) in springs.iter() {
-        // 60fps, run until complete or 5s
-        let mut frame_values = Vec::new();
-        let mut animated_value = animation;
-        for frame in 0..300 {
-            let time = frame as f64 / frame_rate;
-            animated_value = spring.update(time, animated_value);
-            frame_values.push(animated_value);
-            if animated_value.is_at_equilibrium() {
-                break;
-            }
-        }
+        let frame_values = spring.sample(args.from, args.to, AnimatedValueType::Scale, frame_rate);
         assert!(
-            frame_values.len() < 300,
-            "Should finish within 300 frames\n{frame_values:#?}"
+            frame_values.last().unwrap().is_at_equilibrium(),
+            "Should finish within 5s\n{frame_values:#?}"
         );
         value_seqs.push(frame_values);
     }
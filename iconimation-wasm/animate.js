@@ -2,7 +2,7 @@
 // modified to not specify any axis positions
 let ttf_url = "https://fonts.gstatic.com/s/materialsymbolsoutlined/v161/kJEhBvYX7BgnkSrUwT8OhrdQw4oELdPIeeII9v6oFsc.ttf";
 
-import init, { generate_animation } from './pkg/iconimation_wasm.js';
+import init, { generate_animation, check_icon } from './pkg/iconimation_wasm.js';
 
 var font_buffer = null;
 let result = document.getElementById("result");
@@ -19,22 +19,51 @@ export async function initialize() {
     result.innerText = `${font_buffer.byteLength} byte font ready for action!`
 }
 
+// Errors from wasm are objects with a code, a message, and sometimes context
+function describe_error(e) {
+    if (e.code === "unknown_icon" && e.icon_name) {
+        return `ERROR No icon named '${e.icon_name}'`;
+    }
+    return `ERROR ${e.message ?? e}`;
+}
+
 export function generate_and_play_animation(command) {
     command = command.trim();
     console.log("generate_and_play_animation " + command);
     result.innerText = "Generating... ";
     lottie_content.innerText = '';
+
+    let icon_name = command.match(/^Animate\s+(\w+)/);
+    if (icon_name != null) {
+        var report;
+        try {
+            report = JSON.parse(check_icon(font_buffer, icon_name[1]));
+        } catch (e) {
+            result.innerText += describe_error(e);
+            return;
+        }
+        if (!report.drawable) {
+            result.innerText += `ERROR ${report.icon_name} cannot be drawn: ${report.warnings.join(", ")}`;
+            return;
+        }
+        for (const warning of report.warnings) {
+            result.innerText += `\nWARNING ${warning}`;
+        }
+    }
+
     var animation;
     try {
         animation = generate_animation(font_buffer, command);
         animation = JSON.parse(animation);
     } catch (e) {
-        let message = `ERROR ${e}`;
+        let message = describe_error(e);
         console.log(message);
         result.innerText += message;
         return;
     }
-    result.innerText += "success!\n" + animation.debug;
+    result.innerText += "success!";
+    // Every keyframe, for bug reports
+    console.log(JSON.parse(animation.debug));
     lottie_content.innerText = animation.lottie;
     avd_content.innerText = animation.avd;
     console.log(animation);
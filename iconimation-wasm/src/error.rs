@@ -0,0 +1,136 @@
+//! Errors as structured values so a web frontend can act on, or localize, them
+
+use iconimation::error::{CubicApproximationError, Error, IconNameError, SpringBuildError};
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+
+/// Thrown to JavaScript as an object with a `code`, a `message`, and any context fields
+#[derive(Debug, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum WasmError {
+    InvalidFont {
+        message: String,
+    },
+    /// The icon name isn't in the font, `icon_name` is set if we know what was sought and
+    /// `suggestions` are similarly named icons that are in the font
+    UnknownIcon {
+        message: String,
+        icon_name: Option<String>,
+        suggestions: Vec<String>,
+    },
+    /// A variation position is malformed or names an unusable axis
+    InvalidAxis {
+        message: String,
+    },
+    /// The command doesn't parse, `position` is where in it things went wrong if we know
+    InvalidCommand {
+        message: String,
+        position: Option<usize>,
+    },
+    InvalidSpring {
+        message: String,
+    },
+    /// The command was understood but the animation couldn't be produced
+    CannotAnimate {
+        message: String,
+    },
+    /// The animation was produced but couldn't be written as `format`
+    OutputFailed {
+        message: String,
+        format: &'static str,
+    },
+}
+
+impl WasmError {
+    pub fn invalid_font(e: impl std::fmt::Display) -> Self {
+        WasmError::InvalidFont {
+            message: format!("Unable to read font: {e}"),
+        }
+    }
+
+    pub fn output_failed(format: &'static str, e: impl std::fmt::Display) -> Self {
+        WasmError::OutputFailed {
+            message: format!("{format} generation failed: {e}"),
+            format,
+        }
+    }
+}
+
+impl From<Error> for WasmError {
+    fn from(e: Error) -> Self {
+        let message = format!("{e}");
+        match e {
+            Error::IconNameError(e) => {
+                let (icon_name, suggestions) = match e {
+                    IconNameError::NoLigature { name, suggestions } => (Some(name), suggestions),
+                    IconNameError::NoGlyphIds(name) => (Some(name), Vec::new()),
+                    _ => (None, Vec::new()),
+                };
+                WasmError::UnknownIcon {
+                    message,
+                    icon_name,
+                    suggestions,
+                }
+            }
+            Error::InvalidLocation
+            | Error::InvalidTag(..)
+            | Error::NoSuchInstance(..)
+            | Error::NoSuchAxis(..)
+            | Error::OutOfBounds { .. } => WasmError::InvalidAxis { message },
+            Error::UnrecognizedSpring | Error::SpringBuildError(..) => {
+                WasmError::InvalidSpring { message }
+            }
+            Error::Syntax { position, .. } => WasmError::InvalidCommand {
+                message,
+                position: Some(position),
+            },
+            Error::InvalidF64(..)
+            | Error::UnrecognizedCommand
+            | Error::UnrecognizedEase(..)
+            | Error::UnrecognizedBender(..)
+            | Error::UnrecognizedAnchor(..)
+            | Error::InvalidSequenceStep(..)
+            | Error::NotChainable(..)
+            | Error::UnrecognizedChannel(..)
+            | Error::UnknownPreset(..) => WasmError::InvalidCommand {
+                message,
+                position: None,
+            },
+            Error::LottieError(..) => WasmError::OutputFailed {
+                message,
+                format: "Lottie",
+            },
+            Error::AndroidError(..) => WasmError::OutputFailed {
+                message,
+                format: "AVD",
+            },
+            // Anything else, now or added later, stopped the animation being produced
+            _ => WasmError::CannotAnimate { message },
+        }
+    }
+}
+
+impl From<SpringBuildError> for WasmError {
+    fn from(e: SpringBuildError) -> Self {
+        WasmError::InvalidSpring {
+            message: format!("{e}"),
+        }
+    }
+}
+
+impl From<CubicApproximationError> for WasmError {
+    fn from(e: CubicApproximationError) -> Self {
+        WasmError::CannotAnimate {
+            message: format!("Cubic approximation failed: {e}"),
+        }
+    }
+}
+
+impl From<WasmError> for JsValue {
+    fn from(e: WasmError) -> Self {
+        serde_json::to_string(&e)
+            .ok()
+            .and_then(|json| js_sys::JSON::parse(&json).ok())
+            .unwrap_or_else(|| JsValue::from_str(&format!("{e:?}")))
+    }
+}
@@ -1,10 +1,24 @@
 //! Animate arbitrary icons based on text commands
+//!
+//! Failures are thrown as structured objects, see [`WasmError`].
+
+mod error;
+
+use std::time::Duration;
 
-use bodymovin::Bodymovin as Lottie;
 use iconimation::{
-    android::AnimatedVectorDrawable,
-    ir::{Animation, FromAnimation},
-    plan::parse_plan,
+    android::{to_avd, AndroidOptions},
+    audit,
+    bezop::Precision,
+    debug::DebugAnimation,
+    error::Error,
+    font_info,
+    ir::Animation,
+    lottie::{lottie_json, to_lottie, LottieOptions},
+    plan::{parse_plan, PlanOptions},
+    spring::{AnimatedValue, AnimatedValueType, Spring},
+    spring2cubic::cubic_approximation,
+    syntax::syntax,
 };
 
 use js_sys::{ArrayBuffer, Uint8Array};
@@ -13,6 +27,8 @@ use skrifa::raw::FontRef;
 
 use wasm_bindgen::prelude::*;
 
+pub use crate::error::WasmError;
+
 #[derive(Serialize)]
 struct Animations {
     lottie: String,
@@ -20,27 +36,240 @@ struct Animations {
     debug: String,
 }
 
+/// Points are (frame, value)
+#[derive(Serialize)]
+struct SpringCurve {
+    frame_rate: f64,
+    samples: Vec<(f64, f64)>,
+    cubics: Vec<[(f64, f64); 4]>,
+}
+
+#[derive(Serialize)]
+struct Icon {
+    name: String,
+    gid: u16,
+    /// The codepoint, typically private use, that maps directly to the icon if there is one
+    codepoint: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct NamedInstance {
+    name: Option<String>,
+    location: Vec<(String, f32)>,
+}
+
+#[derive(Serialize)]
+struct Axis {
+    tag: String,
+    name: Option<String>,
+    min: f32,
+    default: f32,
+    max: f32,
+}
+
+#[wasm_bindgen]
+pub fn generate_animation(
+    raw_font: &ArrayBuffer,
+    raw_command: String,
+) -> Result<String, WasmError> {
+    generate(
+        raw_font,
+        &raw_command,
+        &PlanOptions::default(),
+        &LottieOptions::default(),
+    )
+}
+
+/// As [`generate_animation`] but the Lottie is compact, numbers rounded to `decimals` places
+#[wasm_bindgen]
+pub fn generate_minified_animation(
+    raw_font: &ArrayBuffer,
+    raw_command: String,
+    decimals: u32,
+) -> Result<String, WasmError> {
+    let options = LottieOptions {
+        minify: Some(decimals),
+        ..Default::default()
+    };
+    generate(raw_font, &raw_command, &PlanOptions::default(), &options)
+}
+
+/// As [`generate_animation`] but at `fps` frames a second, lasting `duration_ms`, and
+/// `size` pixels square; each is left as the plan has it if unset
 #[wasm_bindgen]
-pub fn generate_animation(raw_font: &ArrayBuffer, raw_command: String) -> Result<String, String> {
+pub fn generate_sized_animation(
+    raw_font: &ArrayBuffer,
+    raw_command: String,
+    fps: Option<f64>,
+    duration_ms: Option<u32>,
+    size: Option<f64>,
+) -> Result<String, WasmError> {
+    let options = PlanOptions {
+        frame_rate: fps,
+        duration: duration_ms.map(|ms| Duration::from_millis(ms.into())),
+        size,
+        ..Default::default()
+    };
+    generate(raw_font, &raw_command, &options, &LottieOptions::default())
+}
+
+/// As [`generate_animation`] but path coordinates are rounded to `precision` decimal places, if
+/// set, output is byte-identical on every platform if `stable`, and each top-level part gets a
+/// Lottie layer of its own if `layer_per_part`; see [`LottieOptions`]
+#[wasm_bindgen]
+pub fn generate_animation_with_output_options(
+    raw_font: &ArrayBuffer,
+    raw_command: String,
+    precision: Option<u32>,
+    stable: bool,
+    layer_per_part: bool,
+) -> Result<String, WasmError> {
+    let options = LottieOptions {
+        precision: precision.map(Precision),
+        stable,
+        layer_per_part,
+        ..Default::default()
+    };
+    generate(raw_font, &raw_command, &PlanOptions::default(), &options)
+}
+
+/// Both outputs of `raw_command`, the AVD rounded and stable as `lottie_options` says the Lottie is
+fn generate(
+    raw_font: &ArrayBuffer,
+    raw_command: &str,
+    plan_options: &PlanOptions,
+    lottie_options: &LottieOptions,
+) -> Result<String, WasmError> {
     let rust_buf = Uint8Array::new(raw_font).to_vec();
-    let font = FontRef::new(&rust_buf).map_err(|e| format!("FontRef::new failed: {e}"))?;
+    let font = FontRef::new(&rust_buf).map_err(WasmError::invalid_font)?;
 
-    let (plan, glyph_shape) = parse_plan(&font, &raw_command).map_err(|e| format!("{e}"))?;
-    let animation = Animation::of_icon(&plan, &glyph_shape)
-        .map_err(|e| format!("Animation::new failed: {e}"))?;
+    let (plan, glyph_shape) = parse_plan(&font, raw_command)?;
+    let animation = Animation::of_icon_with_options(&plan, &glyph_shape, plan_options)
+        .map_err(Error::AnimationError)?;
 
     let lottie =
-        Lottie::from_animation(&animation).map_err(|e| format!("Lottie generation failed: {e}"))?;
-    let avd = AnimatedVectorDrawable::from_animation(&animation)
-        .map_err(|e| format!("AVD generation failed: {e}"))?;
+        to_lottie(&animation, lottie_options).map_err(|e| WasmError::output_failed("Lottie", e))?;
+    let avd_options = AndroidOptions {
+        precision: lottie_options.precision,
+        stable: lottie_options.stable,
+    };
+    let avd = to_avd(&animation, &avd_options).map_err(|e| WasmError::output_failed("AVD", e))?;
+
+    let mut debug = DebugAnimation::from(&animation);
+    debug.name_icon(&font);
 
     Ok(serde_json::to_string_pretty(&Animations {
-        lottie: serde_json::to_string_pretty(&lottie)
-            .map_err(|e| format!("Lottie to json failed: {e}"))?,
+        lottie: lottie_json(&lottie, animation.segments(), lottie_options)
+            .map_err(|e| WasmError::output_failed("Lottie", e))?,
         avd: avd
             .to_avd_xml()
-            .map_err(|e| format!("AVD to xml failed: {e}"))?,
-        debug: "".to_string(),
+            .map_err(|e| WasmError::output_failed("AVD", e))?,
+        debug: serde_json::to_string_pretty(&debug)
+            .map_err(|e| WasmError::output_failed("Debug", e))?,
     })
     .unwrap())
 }
+
+/// Pre-flight an icon, returns an [`audit::Report`] as json
+#[wasm_bindgen]
+pub fn check_icon(raw_font: &ArrayBuffer, icon_name: String) -> Result<String, WasmError> {
+    let rust_buf = Uint8Array::new(raw_font).to_vec();
+    let font = FontRef::new(&rust_buf).map_err(WasmError::invalid_font)?;
+
+    let report = audit::check_icon(&font, &icon_name)?;
+    serde_json::to_string_pretty(&report).map_err(|e| WasmError::output_failed("Report", e))
+}
+
+/// Sample a spring at every frame and fit cubics to it, returns [`SpringCurve`] as json
+#[wasm_bindgen]
+pub fn generate_spring_curve(
+    damping: f64,
+    stiffness: f64,
+    from: f64,
+    to: f64,
+) -> Result<String, WasmError> {
+    let frame_rate = 60.0;
+    let spring = Spring::new(damping, stiffness)?;
+    let animation = AnimatedValue::new(from, to, AnimatedValueType::Scale);
+
+    let samples = spring
+        .iter_frames(from, to, AnimatedValueType::Scale, frame_rate)
+        .enumerate()
+        .map(|(frame, state)| (frame as f64, state.value))
+        .collect();
+    let cubics = cubic_approximation(frame_rate, animation, spring)?
+        .into_iter()
+        .map(|c| [c.p0, c.p1, c.p2, c.p3].map(|p| (p.x, p.y)))
+        .collect();
+
+    serde_json::to_string_pretty(&SpringCurve {
+        frame_rate,
+        samples,
+        cubics,
+    })
+    .map_err(|e| WasmError::output_failed("Spring curve", e))
+}
+
+/// Every icon in the font, returns a list of [`Icon`] as json
+#[wasm_bindgen]
+pub fn list_icons(raw_font: &ArrayBuffer) -> Result<String, WasmError> {
+    let rust_buf = Uint8Array::new(raw_font).to_vec();
+    let font = FontRef::new(&rust_buf).map_err(WasmError::invalid_font)?;
+
+    let icons: Vec<_> = font_info::icon_names(&font)?
+        .into_iter()
+        .map(|icon| Icon {
+            name: icon.name,
+            gid: icon.gid.to_u16(),
+            codepoint: icon.codepoint,
+        })
+        .collect();
+    serde_json::to_string_pretty(&icons).map_err(|e| WasmError::output_failed("Icon list", e))
+}
+
+/// The variation axes of the font, returns a list of [`Axis`] as json
+#[wasm_bindgen]
+pub fn list_axes(raw_font: &ArrayBuffer) -> Result<String, WasmError> {
+    let rust_buf = Uint8Array::new(raw_font).to_vec();
+    let font = FontRef::new(&rust_buf).map_err(WasmError::invalid_font)?;
+
+    let axes: Vec<_> = font_info::axes(&font)
+        .into_iter()
+        .map(|axis| Axis {
+            tag: axis.tag.to_string(),
+            name: axis.name,
+            min: axis.min,
+            default: axis.default,
+            max: axis.max,
+        })
+        .collect();
+    serde_json::to_string_pretty(&axes).map_err(|e| WasmError::output_failed("Axis list", e))
+}
+
+/// The named instances of the font, returns a list of [`NamedInstance`] as json
+#[wasm_bindgen]
+pub fn list_named_instances(raw_font: &ArrayBuffer) -> Result<String, WasmError> {
+    let rust_buf = Uint8Array::new(raw_font).to_vec();
+    let font = FontRef::new(&rust_buf).map_err(WasmError::invalid_font)?;
+
+    let instances: Vec<_> = font_info::named_instances(&font)
+        .into_iter()
+        .map(|instance| NamedInstance {
+            name: instance.name,
+            location: instance
+                .location
+                .into_iter()
+                .map(|(tag, value)| (tag.to_string(), value))
+                .collect(),
+        })
+        .collect();
+    serde_json::to_string_pretty(&instances)
+        .map_err(|e| WasmError::output_failed("Named instance list", e))
+}
+
+/// The commands, springs, eases and presets the command parser understands, returns a
+/// [`iconimation::syntax::Syntax`] as json
+#[wasm_bindgen]
+pub fn command_syntax() -> Result<String, WasmError> {
+    serde_json::to_string_pretty(&syntax()).map_err(|e| WasmError::output_failed("Syntax", e))
+}
@@ -0,0 +1,123 @@
+//! How long the expensive steps of animating an icon take, see `cargo bench -p iconimation`
+//!
+//! Icons are from the bundled font: settings has many parts, bolt one, check_circle a part with
+//! a hole in it.
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use iconimation::{
+    batch::animate_many,
+    ir::Animation,
+    lottie::{lottie_json, to_lottie, LottieOptions},
+    plan::{PlanOptions, VariationMode},
+    spring::{AnimatedValue, AnimatedValueType, Spring},
+    spring2cubic::cubic_approximation,
+};
+use skrifa::raw::FontRef;
+
+const FONT: &[u8] = include_bytes!("../../resources/fonts/Symbols-reduced.ttf");
+
+const ICONS: &[&str] = &["settings", "bolt", "check_circle"];
+
+fn font() -> FontRef<'static> {
+    FontRef::new(FONT).unwrap()
+}
+
+fn group_parts(c: &mut Criterion) {
+    let font = font();
+    let mut group = c.benchmark_group("group_parts");
+    // Redrawing the glyph as it varies gives every subpath many keyframes to split and group
+    let redraw = PlanOptions {
+        variation: VariationMode::Redraw,
+        ..Default::default()
+    };
+    for icon in ICONS {
+        let still = Animation::of_command(
+            &font,
+            &format!("Animate {icon}: none"),
+            &PlanOptions::default(),
+        )
+        .unwrap();
+        let redrawn = Animation::of_command(
+            &font,
+            &format!("Animate {icon}: pulse-whole vary FILL:0 to FILL:1"),
+            &redraw,
+        )
+        .unwrap();
+        for (id, animation) in [
+            (BenchmarkId::new("still", icon), still),
+            (BenchmarkId::new("redrawn", icon), redrawn),
+        ] {
+            group.bench_with_input(id, &animation, |b, animation| {
+                b.iter_batched(
+                    || animation.root().clone(),
+                    |mut root| {
+                        root.group_parts();
+                        root
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+    group.finish();
+}
+
+fn spring_fitting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cubic_approximation");
+    let springs = [
+        ("standard", Spring::standard()),
+        ("expressive-spatial", Spring::expressive_spatial()),
+        ("smooth-non-spatial", Spring::smooth_non_spatial()),
+    ];
+    for (name, spring) in springs {
+        let animation = AnimatedValue::new(0.0, 90.0, AnimatedValueType::Rotation);
+        group.bench_function(name, |b| {
+            b.iter(|| cubic_approximation(60.0, animation, spring).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn command_to_lottie(c: &mut Criterion) {
+    let font = font();
+    let options = LottieOptions::default();
+    let mut group = c.benchmark_group("command_to_lottie");
+    for command in [
+        "Animate settings: twirl",
+        "Animate check_circle: pulse-whole vary FILL:0 to FILL:1",
+        "Animate bolt: rotate 90 degrees using expressive-spatial",
+    ] {
+        group.bench_function(command, |b| {
+            b.iter(|| {
+                let animation =
+                    Animation::of_command(&font, command, &PlanOptions::default()).unwrap();
+                let lottie = to_lottie(&animation, &options).unwrap();
+                lottie_json(&lottie, animation.segments(), &options).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Compare with and without the rayon feature to see what parallelism buys
+fn batch(c: &mut Criterion) {
+    let font = font();
+    let commands: Vec<_> = ICONS
+        .iter()
+        .cycle()
+        .take(48)
+        .map(|icon| format!("Animate {icon}: twirl"))
+        .collect();
+    c.bench_function("animate_many", |b| {
+        b.iter(|| animate_many(&font, &commands, &PlanOptions::default()))
+    });
+}
+
+criterion_group!(
+    benches,
+    group_parts,
+    spring_fitting,
+    command_to_lottie,
+    batch
+);
+criterion_main!(benches);
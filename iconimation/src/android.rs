@@ -1,36 +1,169 @@
 //! Produce an output suitable for Android, e.g. an AnimatedVectorDrawable, from an Animation
 
-use kurbo::{BezPath, Point};
+use std::{io, ops::Range};
+
+use kurbo::{Affine, BezPath, PathEl, Point, Vec2};
 
 use crate::{
+    bezop::{quantize, Precision},
     error::AndroidError,
-    ir::{self, FromAnimation},
+    hex_color,
+    ir::{self, Ease, FromAnimation},
+    path_commands,
 };
 
-/// An in memory representation of an [AndroidVectorDrawable](https://developer.android.com/reference/android/graphics/drawable/AnimatedVectorDrawable)
+/// An in memory representation of an [AnimatedVectorDrawable](https://developer.android.com/reference/android/graphics/drawable/AnimatedVectorDrawable)
 ///
-/// Limited to capabilities needed for icon animation. Can emit a [[single-file](https://developer.android.com/reference/android/graphics/drawable/AnimatedVectorDrawable#define-an-animatedvectordrawable-all-in-one-xml-file)
-/// representation for use in Android projects.
+/// Limited to capabilities needed for icon animation. Produced from the IR like every output
+/// format, see [`FromAnimation`]. Can emit a [single-file](https://developer.android.com/reference/android/graphics/drawable/AnimatedVectorDrawable#define-an-animatedvectordrawable-all-in-one-xml-file)
+/// representation for use in Android projects, see [`AnimatedVectorDrawable::to_avd_xml`].
 #[derive(Debug)]
 pub struct AnimatedVectorDrawable {
     width: f64,
     height: f64,
     drawable: Group,
+    /// What animates, by name of the element animated
+    targets: Vec<Target>,
+}
+
+/// Choices about how an [`ir::Animation`] is expressed as an AnimatedVectorDrawable
+#[derive(Debug, Clone, Default)]
+pub struct AndroidOptions {
+    /// If set, pathData coordinates, transforms and gradients are rounded, see [`Precision`]
+    pub precision: Option<Precision>,
+    /// Write byte-identical xml on every platform, numbers are rounded to
+    /// [`Precision::STABLE`] unless [`AndroidOptions::precision`] is set
+    pub stable: bool,
+}
+
+impl AndroidOptions {
+    fn precision(&self) -> Option<Precision> {
+        self.precision.or(self.stable.then_some(Precision::STABLE))
+    }
 }
 
 impl FromAnimation for AnimatedVectorDrawable {
     type Err = AndroidError;
 
     fn from_animation(animation: &crate::ir::Animation) -> Result<Self, Self::Err> {
-        Ok(AnimatedVectorDrawable {
-            width: animation.width,
-            height: animation.height,
-            drawable: to_avd_group(&animation.root),
-        })
+        to_avd(animation, &AndroidOptions::default())
+    }
+}
+
+/// As [`FromAnimation::from_animation`] but with control over the output
+pub fn to_avd(
+    animation: &ir::Animation,
+    options: &AndroidOptions,
+) -> Result<AnimatedVectorDrawable, AndroidError> {
+    log::debug!("Writing AnimatedVectorDrawable");
+    let mut context = Context {
+        options,
+        duration: animation.duration,
+        visible: 0.0..animation.duration,
+        paths: 0,
+        clips: 0,
+        groups: 0,
+        targets: Vec::new(),
+    };
+    let animation = animation.resolved();
+    // Vector drawables clip but have no alpha masks so mattes are dropped
+    let drawable = to_avd_group(&animation.root.unmatted(), &mut context)?;
+    let drawable = match &animation.camera {
+        Some(camera) => to_avd_camera(camera, &animation, drawable, &mut context),
+        None => drawable,
+    };
+    Ok(AnimatedVectorDrawable {
+        width: animation.width,
+        height: animation.height,
+        drawable,
+        targets: context.targets,
+    })
+}
+
+/// State threaded through the conversion of an [`ir::Group`] tree
+struct Context<'a> {
+    options: &'a AndroidOptions,
+    /// Seconds
+    duration: f64,
+    /// When, in seconds, the group being converted, and all its ancestors, are visible
+    visible: Range<f64>,
+    /// How many paths have been named
+    paths: usize,
+    /// How many clip paths have been named
+    clips: usize,
+    /// How many groups have been named
+    groups: usize,
+    targets: Vec<Target>,
+}
+
+impl Context<'_> {
+    /// Milliseconds, which is what Android animators count in, at `time` seconds
+    fn millis(&self, time: f64) -> u64 {
+        (time * 1000.0).round() as u64
+    }
+
+    /// `ease` as a pathInterpolator, rounded as path coordinates are
+    fn interpolator(&self, ease: Ease) -> Ease {
+        Ease {
+            p1: self.round_point(ease.p1),
+            p2: self.round_point(ease.p2),
+        }
+    }
+
+    fn round(&self, value: f64) -> f64 {
+        self.options
+            .precision()
+            .map_or(value, |precision| precision.round(value))
+    }
+
+    fn round_point(&self, p: Point) -> Point {
+        Point::new(self.round(p.x), self.round(p.y))
+    }
+
+    fn round_gradient(&self, gradient: &ir::Gradient) -> ir::Gradient {
+        let stops = gradient
+            .stops()
+            .iter()
+            .map(|stop| ir::ColorStop {
+                offset: self.round(stop.offset),
+                color: stop.color,
+            })
+            .collect();
+        match gradient {
+            ir::Gradient::Linear { start, end, .. } => ir::Gradient::Linear {
+                start: self.round_point(*start),
+                end: self.round_point(*end),
+                stops,
+            },
+            ir::Gradient::Radial { center, radius, .. } => ir::Gradient::Radial {
+                center: self.round_point(*center),
+                radius: self.round(*radius),
+                stops,
+            },
+        }
+    }
+}
+
+/// Streams xml to an [`io::Write`], holding on to the first failure so writing an element
+/// needn't check every push
+struct XmlWriter<'a> {
+    out: &'a mut dyn io::Write,
+    error: Option<io::Error>,
+}
+
+impl XmlWriter<'_> {
+    fn push_str(&mut self, s: &str) {
+        if self.error.is_none() {
+            self.error = self.out.write_all(s.as_bytes()).err();
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
     }
 }
 
-fn start_el(xml: &mut String, depth: u32, name: &str, attrs: Vec<&str>) {
+fn start_el(xml: &mut XmlWriter<'_>, depth: u32, name: &str, attrs: Vec<&str>) {
     for _ in 0..(depth * 2) {
         xml.push(' ');
     }
@@ -48,7 +181,7 @@ fn start_el(xml: &mut String, depth: u32, name: &str, attrs: Vec<&str>) {
     xml.push_str(">\n");
 }
 
-fn end_el(xml: &mut String, depth: u32, name: &str) {
+fn end_el(xml: &mut XmlWriter<'_>, depth: u32, name: &str) {
     for _ in 0..(depth * 2) {
         xml.push(' ');
     }
@@ -57,7 +190,7 @@ fn end_el(xml: &mut String, depth: u32, name: &str) {
     xml.push_str(">\n");
 }
 
-fn write_attr(xml: &mut String, depth: u32, content: &str) {
+fn write_attr(xml: &mut XmlWriter<'_>, depth: u32, content: &str) {
     for _ in 0..(depth * 2 + 4) {
         xml.push(' ');
     }
@@ -66,12 +199,24 @@ fn write_attr(xml: &mut String, depth: u32, content: &str) {
 
 impl AnimatedVectorDrawable {
     /// Writes an AnimatedVectorDrawable in xml format
+    pub fn to_avd_xml(&self) -> Result<String, AndroidError> {
+        let mut xml = Vec::new();
+        self.write_to(&mut xml)?;
+        Ok(String::from_utf8(xml).expect("We only write str"))
+    }
+
+    /// Streams the xml of [`AnimatedVectorDrawable::to_avd_xml`] to `writer`, which is written
+    /// to in many small pieces so is best buffered, e.g. by [`io::BufWriter`]
     ///
     /// The namespaces are tiresome with serde, just do it by hand for now
-    pub fn to_avd_xml(&self) -> Result<String, AndroidError> {
-        let mut xml = String::new();
+    pub fn write_to(&self, writer: &mut impl io::Write) -> Result<(), AndroidError> {
+        let mut xml = XmlWriter {
+            out: writer,
+            error: None,
+        };
+        let xml = &mut xml;
         start_el(
-            &mut xml,
+            xml,
             0,
             "animated-vector",
             vec![
@@ -80,10 +225,9 @@ impl AnimatedVectorDrawable {
             ],
         );
 
-        start_el(&mut xml, 1, r#"aapt:attr name="android:drawable""#, vec![]);
-        eprint!("What width/height?");
+        start_el(xml, 1, r#"aapt:attr name="android:drawable""#, vec![]);
         start_el(
-            &mut xml,
+            xml,
             2,
             "vector",
             vec![
@@ -93,14 +237,19 @@ impl AnimatedVectorDrawable {
                 &format!("android:viewportHeight=\"{}\"", self.height),
             ],
         );
-        self.drawable.to_avd_xml(&mut xml, 3)?;
-        end_el(&mut xml, 2, "vector");
-        end_el(&mut xml, 1, "aapt:attr");
+        self.drawable.to_avd_xml(xml, 3)?;
+        end_el(xml, 2, "vector");
+        end_el(xml, 1, "aapt:attr");
 
-        xml.push_str("\n   <!-- TODO: animated state -->\n\n");
+        for target in &self.targets {
+            target.to_avd_xml(xml, 1);
+        }
 
-        end_el(&mut xml, 0, "animated-vector");
-        Ok(xml)
+        end_el(xml, 0, "animated-vector");
+        match xml.error.take() {
+            Some(e) => Err(AndroidError::Io(e)),
+            None => Ok(()),
+        }
     }
 }
 
@@ -110,26 +259,62 @@ impl AnimatedVectorDrawable {
 pub(crate) enum Element {
     Group(Group),
     Path(Path),
+    ClipPath(ClipPath),
 }
 
 impl Element {
-    fn to_avd_xml(&self, xml: &mut String, depth: u32) -> Result<(), AndroidError> {
+    fn to_avd_xml(&self, xml: &mut XmlWriter<'_>, depth: u32) -> Result<(), AndroidError> {
         match self {
             Element::Group(g) => g.to_avd_xml(xml, depth),
             Element::Path(p) => p.to_avd_xml(xml, depth),
+            Element::ClipPath(c) => c.to_avd_xml(xml, depth),
         }
     }
 }
 
-#[derive(Debug, Default)]
+/// The transform is that of the first frame, animators targeting the group's name move it from
+/// there; rotation and scale are around the pivot
+#[derive(Debug)]
 pub(crate) struct Group {
+    /// Only needed if the group is animated
+    name: Option<String>,
     children: Vec<Element>,
-    _pivot: Point,
+    pivot: Point,
+    translate: Vec2,
+    /// Degrees clockwise
+    rotation: f64,
+    /// 1.0 is unscaled
+    scale: (f64, f64),
 }
 
 impl Group {
-    fn to_avd_xml(&self, xml: &mut String, depth: u32) -> Result<(), AndroidError> {
-        start_el(xml, depth, "group", vec![]);
+    fn to_avd_xml(&self, xml: &mut XmlWriter<'_>, depth: u32) -> Result<(), AndroidError> {
+        // Only what differs from Android's defaults
+        let mut attrs = Vec::new();
+        if let Some(name) = &self.name {
+            attrs.push(format!("android:name=\"{name}\""));
+        }
+        if self.pivot != Point::ZERO {
+            attrs.push(format!("android:pivotX=\"{}\"", self.pivot.x));
+            attrs.push(format!("android:pivotY=\"{}\"", self.pivot.y));
+        }
+        if self.translate != Vec2::ZERO {
+            attrs.push(format!("android:translateX=\"{}\"", self.translate.x));
+            attrs.push(format!("android:translateY=\"{}\"", self.translate.y));
+        }
+        if self.rotation != 0.0 {
+            attrs.push(format!("android:rotation=\"{}\"", self.rotation));
+        }
+        if self.scale != (1.0, 1.0) {
+            attrs.push(format!("android:scaleX=\"{}\"", self.scale.0));
+            attrs.push(format!("android:scaleY=\"{}\"", self.scale.1));
+        }
+        start_el(
+            xml,
+            depth,
+            "group",
+            attrs.iter().map(String::as_str).collect(),
+        );
         for el in &self.children {
             el.to_avd_xml(xml, depth + 1)?;
         }
@@ -138,56 +323,1014 @@ impl Group {
     }
 }
 
-fn to_avd_group(group: &ir::Group) -> Group {
-    let mut children = Vec::with_capacity(group.children.len());
-    for i in 0..group.children.len() {
-        let next = &group.children[i];
-        match next {
-            ir::Element::Group(g) => children.push(Element::Group(to_avd_group(g))),
-            ir::Element::Shape(s) => {
-                if let Some(Element::Path(p)) = children.last_mut() {
-                    // glue paths back together because unlike Lottie independent AVD paths do *not* cut holes in each other
-                    p.path += &s.earliest().value.to_svg();
-                } else {
-                    children.push(Element::Path(to_avd_path(group.fill, s)));
+fn to_avd_group(group: &ir::Group, context: &mut Context) -> Result<Group, AndroidError> {
+    let outer = context.visible.clone();
+    if let Some(visible) = &group.visible {
+        context.visible = outer.start.max(visible.start)..outer.end.min(visible.end);
+    }
+    let mut children = Vec::with_capacity(group.children.len() + 1);
+    // A clip-path clips the siblings that follow it so it goes first
+    if let Some(clip) = &group.clip {
+        children.push(Element::ClipPath(to_avd_clip(clip, context)?));
+    }
+    // glue runs of shapes back together because unlike Lottie independent AVD paths do *not* cut holes in each other
+    let mut shapes = Vec::new();
+    // Draw higher z on top, that is later
+    let mut ordered: Vec<_> = group.children.iter().collect();
+    ordered.sort_by_key(|e| e.z());
+    for child in ordered {
+        match child {
+            ir::Element::Group(g) => {
+                if !shapes.is_empty() {
+                    let path = to_avd_path(group, &shapes, context)?;
+                    children.push(Element::Path(path));
+                    shapes.clear();
                 }
+                children.push(Element::Group(to_avd_group(g, context)?));
             }
+            ir::Element::Shape(s) => shapes.push(s),
         }
     }
-    Group {
-        _pivot: group.center,
+    if !shapes.is_empty() {
+        children.push(Element::Path(to_avd_path(group, &shapes, context)?));
+    }
+    context.visible = outer;
+    let (scale_x, scale_y) = group.scale.earliest().value;
+    let translate = context.round_point(group.translate.earliest().value.to_point());
+    let pivot = context.round_point(group.center);
+    // The IR scales in percent
+    let scale = (
+        context.round(scale_x / 100.0),
+        context.round(scale_y / 100.0),
+    );
+    let rotation = group.rotate.earliest().value;
+    if !group.is_skewed() {
+        let mut avd = Group {
+            name: None,
+            children,
+            pivot,
+            translate: translate.to_vec2(),
+            rotation: context.round(rotation),
+            scale,
+        };
+        let mut animators = translate_animators(group, context);
+        animators.extend(rotation_animators(group, 0.0, context));
+        animators.extend(scale_animators(group, context));
+        animate_group(&mut avd, animators, context);
+        return Ok(avd);
+    }
+    // AVD groups don't skew so rotate, stretch and rotate again, which is a skew, by nesting.
+    // The skew holds at that of the first frame, the rest animates around it.
+    let (rotate_after, stretch, rotate_before) = decompose_skew(group.skew.earliest().value);
+    let mut skewed = Group {
+        name: None,
         children,
+        pivot,
+        translate: Vec2::ZERO,
+        rotation: context.round(rotate_before),
+        scale,
+    };
+    let animators = scale_animators(group, context);
+    animate_group(&mut skewed, animators, context);
+    let mut avd = Group {
+        name: None,
+        children: vec![Element::Group(skewed)],
+        pivot,
+        translate: translate.to_vec2(),
+        rotation: context.round(rotation + rotate_after),
+        scale: (context.round(stretch.0), context.round(stretch.1)),
+    };
+    let mut animators = translate_animators(group, context);
+    animators.extend(rotation_animators(group, rotate_after, context));
+    animate_group(&mut avd, animators, context);
+    Ok(avd)
+}
+
+/// Name `avd` and target it with `animators`, unless there are none
+fn animate_group(avd: &mut Group, animators: Vec<ObjectAnimator>, context: &mut Context) {
+    if animators.is_empty() {
+        return;
     }
+    let name = format!("group_{}", context.groups);
+    context.groups += 1;
+    avd.name = Some(name.clone());
+    context.targets.push(Target { name, animators });
+}
+
+/// translateX and translateY animators of `group`, none if it holds still
+fn translate_animators(group: &ir::Group, context: &Context) -> Vec<ObjectAnimator> {
+    let mut animators = float_animators("translateX", &group.translate, |t| t.x, context);
+    animators.extend(float_animators(
+        "translateY",
+        &group.translate,
+        |t| t.y,
+        context,
+    ));
+    animators
+}
+
+/// rotation animators of `group`, turned a further `offset` degrees, none if it holds still
+fn rotation_animators(group: &ir::Group, offset: f64, context: &Context) -> Vec<ObjectAnimator> {
+    float_animators("rotation", &group.rotate, |r| r + offset, context)
+}
+
+/// scaleX and scaleY animators of `group`, none if it holds still
+fn scale_animators(group: &ir::Group, context: &Context) -> Vec<ObjectAnimator> {
+    // The IR scales in percent
+    let mut animators = float_animators("scaleX", &group.scale, |s| s.0 / 100.0, context);
+    animators.extend(float_animators(
+        "scaleY",
+        &group.scale,
+        |s| s.1 / 100.0,
+        context,
+    ));
+    animators
+}
+
+/// A skew of degrees along an axis, see [`ir::Group::skew`], as a rotation by degrees of a
+/// scale of a rotation by degrees, that is (after, scale, before)
+fn decompose_skew((skew, axis): (f64, f64)) -> (f64, (f64, f64), f64) {
+    let axis = axis.to_radians();
+    let skew =
+        Affine::rotate(axis) * Affine::skew(-skew.to_radians().tan(), 0.0) * Affine::rotate(-axis);
+    // The closed form singular value decomposition of a 2x2 matrix
+    let [a, b, c, d, ..] = skew.as_coeffs();
+    let (e, f, g, h) = ((a + d) / 2.0, (a - d) / 2.0, (b + c) / 2.0, (b - c) / 2.0);
+    let (q, r) = (e.hypot(h), f.hypot(g));
+    let (a1, a2) = (g.atan2(f), h.atan2(e));
+    (
+        ((a2 + a1) / 2.0).to_degrees(),
+        (q + r, q - r),
+        ((a2 - a1) / 2.0).to_degrees(),
+    )
 }
 
 #[derive(Debug)]
 pub(crate) struct Path {
+    name: String,
     fill: String,
+    /// Drawn instead of the fill if set
+    gradient: Option<ir::Gradient>,
     path: String,
+    /// Not shown until an animator pops it in
+    hidden: bool,
 }
 
 impl Path {
-    fn to_avd_xml(&self, xml: &mut String, depth: u32) -> Result<(), AndroidError> {
+    fn to_avd_xml(&self, xml: &mut XmlWriter<'_>, depth: u32) -> Result<(), AndroidError> {
+        let name = format!("android:name=\"{}\"", self.name);
+        let fill = format!("android:fillColor=\"{}\"", self.fill);
+        let path = format!("android:pathData=\"{}\"", self.path);
+        let mut attrs = vec![name.as_str()];
+        if self.gradient.is_none() {
+            attrs.push(&fill);
+        }
+        attrs.push(&path);
+        if self.hidden {
+            attrs.push(r#"android:fillAlpha="0""#);
+        }
+        start_el(xml, depth, "path", attrs);
+        if let Some(gradient) = &self.gradient {
+            write_gradient(xml, depth + 1, gradient);
+        }
+        end_el(xml, depth, "path");
+        Ok(())
+    }
+}
+
+/// A [gradient](https://developer.android.com/reference/android/graphics/drawable/GradientDrawable)
+/// fill, which AVD takes as a complex color in place of fillColor
+fn write_gradient(xml: &mut XmlWriter<'_>, depth: u32, gradient: &ir::Gradient) {
+    start_el(xml, depth, r#"aapt:attr name="android:fillColor""#, vec![]);
+    let attrs = match gradient {
+        ir::Gradient::Linear { start, end, .. } => vec![
+            r#"android:type="linear""#.to_string(),
+            format!("android:startX=\"{}\"", start.x),
+            format!("android:startY=\"{}\"", start.y),
+            format!("android:endX=\"{}\"", end.x),
+            format!("android:endY=\"{}\"", end.y),
+        ],
+        ir::Gradient::Radial { center, radius, .. } => vec![
+            r#"android:type="radial""#.to_string(),
+            format!("android:centerX=\"{}\"", center.x),
+            format!("android:centerY=\"{}\"", center.y),
+            format!("android:gradientRadius=\"{radius}\""),
+        ],
+    };
+    start_el(
+        xml,
+        depth + 1,
+        "gradient",
+        attrs.iter().map(String::as_str).collect(),
+    );
+    for stop in gradient.stops() {
         start_el(
             xml,
-            depth,
-            "path",
+            depth + 2,
+            "item",
             vec![
-                &format!("android:fillColor=\"{}\"", self.fill),
-                &format!("android:pathData=\"{}\"", self.path),
+                &format!("android:offset=\"{}\"", stop.offset),
+                &format!("android:color=\"{}\"", hex_color(stop.color)),
             ],
         );
-        end_el(xml, depth, "path");
+        end_el(xml, depth + 2, "item");
+    }
+    end_el(xml, depth + 1, "gradient");
+    end_el(xml, depth, "aapt:attr");
+}
+
+/// A path drawing `shapes` and, if they change shape or aren't always visible, a target to
+/// morph it or pop it in and out
+fn to_avd_path(
+    group: &ir::Group,
+    shapes: &[&ir::Keyframed<BezPath>],
+    context: &mut Context,
+) -> Result<Path, AndroidError> {
+    let name = format!("path_{}", context.paths);
+    context.paths += 1;
+
+    // Android morphs a whole path so the shapes must change in step
+    let times_of =
+        |shape: &ir::Keyframed<BezPath>| -> Vec<f64> { shape.iter().map(|k| k.time).collect() };
+    let times = times_of(shapes[0]);
+    if let Some(other) = shapes.iter().map(|&s| times_of(s)).find(|t| *t != times) {
+        return Err(AndroidError::UnalignedShapes(times, other));
+    }
+    // The ease of the first shape is taken for all, Lottie would let them differ
+    let keyframes: Vec<(f64, BezPath, Ease, bool)> = shapes[0]
+        .iter()
+        .enumerate()
+        .map(|(i, keyframe)| {
+            let mut path = BezPath::new();
+            for shape in shapes {
+                path.extend(shape.iter().nth(i).unwrap().value.iter());
+            }
+            (
+                keyframe.time,
+                path,
+                keyframe.ease.unwrap_or_default(),
+                keyframe.hold,
+            )
+        })
+        .collect();
+    let path = path_data(&keyframes[0].1, context.options.precision())?;
+    let mut animators = morph_animators(&keyframes, context)?;
+    // Pop in and out by switching fillAlpha, instantly, as visibility starts and ends
+    let visible = context.visible.clone();
+    let hidden = visible.start > 0.0 || visible.is_empty();
+    let pop = |time: f64, from: &str, to: &str| ObjectAnimator {
+        property: "fillAlpha",
+        start_offset: context.millis(time),
+        duration: 0,
+        value_from: from.to_string(),
+        value_to: to.to_string(),
+        value_type: "floatType",
+        interpolator: Ease::LINEAR,
+    };
+    if !visible.is_empty() {
+        if visible.start > 0.0 {
+            animators.push(pop(visible.start, "0", "1"));
+        }
+        if visible.end < context.duration {
+            animators.push(pop(visible.end, "1", "0"));
+        }
+    }
+    if !animators.is_empty() {
+        context.targets.push(Target {
+            name: name.clone(),
+            animators,
+        });
+    }
+
+    Ok(Path {
+        name,
+        fill: group.fill.map(hex_color).unwrap_or(String::from("#000000")),
+        gradient: group.gradient.as_ref().map(|g| context.round_gradient(g)),
+        path,
+        hidden,
+    })
+}
+
+/// pathData animators from each keyframe to the next, none if there is only one
+fn morph_animators(
+    keyframes: &[(f64, BezPath, Ease, bool)],
+    context: &Context,
+) -> Result<Vec<ObjectAnimator>, AndroidError> {
+    if keyframes.len() < 2 {
+        return Ok(Vec::new());
+    }
+    // pathType animators require every value to have the same commands, with the same
+    // number of numbers, so write them in full rather than compact
+    let precision = context.options.precision();
+    let commands = path_commands(&keyframes[0].1);
+    let mut values = Vec::with_capacity(keyframes.len());
+    for (time, path, ..) in keyframes.iter() {
+        if path_commands(path) != commands {
+            return Err(AndroidError::IncompatiblePaths {
+                time: *time,
+                expected: commands,
+                actual: path_commands(path),
+            });
+        }
+        values.push(write_path_data(path, precision, false)?);
+    }
+    Ok(animators(
+        "pathData", "pathType", keyframes, &values, context,
+    ))
+}
+
+/// Animators of `property` from each keyframe to the next, `values` being the keyframe values
+/// as written
+fn animators<T>(
+    property: &'static str,
+    value_type: &'static str,
+    keyframes: &[(f64, T, Ease, bool)],
+    values: &[String],
+    context: &Context,
+) -> Vec<ObjectAnimator> {
+    keyframes
+        .windows(2)
+        .zip(values.windows(2))
+        .map(|(keyframes, values)| {
+            let (start, end) = (
+                context.millis(keyframes[0].0),
+                context.millis(keyframes[1].0),
+            );
+            // A hold is a discrete change, an instant animator as it ends
+            let (start_offset, duration, interpolator) = if keyframes[0].3 {
+                (end, 0, Ease::LINEAR)
+            } else {
+                (start, end - start, context.interpolator(keyframes[0].2))
+            };
+            ObjectAnimator {
+                property,
+                start_offset,
+                duration,
+                value_from: values[0].clone(),
+                value_to: values[1].clone(),
+                value_type,
+                interpolator,
+            }
+        })
+        .collect()
+}
+
+/// floatType animators of `property`, `value` of each keyframe of `keyframed`, none if it holds
+/// still
+fn float_animators<T>(
+    property: &'static str,
+    keyframed: &ir::Keyframed<T>,
+    value: impl Fn(&T) -> f64,
+    context: &Context,
+) -> Vec<ObjectAnimator> {
+    if !keyframed.is_animated() {
+        return Vec::new();
+    }
+    let keyframes: Vec<_> = keyframed
+        .iter()
+        .map(|k| (k.time, (), k.ease.unwrap_or_default(), k.hold))
+        .collect();
+    let values: Vec<_> = keyframed
+        .iter()
+        .map(|k| context.round(value(&k.value)).to_string())
+        .collect();
+    animators(property, "floatType", &keyframes, &values, context)
+}
+
+/// A group named "camera", holding `content`, that zooms and pans as `camera` does
+fn to_avd_camera(
+    camera: &ir::Camera,
+    animation: &ir::Animation,
+    content: Group,
+    context: &mut Context,
+) -> Group {
+    let view = camera.to_group(animation.width(), animation.height());
+    let (translate, zoom) = (&view.translate, &camera.zoom);
+    let mut animators = float_animators("translateX", translate, |t| t.x, context);
+    animators.extend(float_animators("translateY", translate, |t| t.y, context));
+    // The IR zooms in percent
+    for property in ["scaleX", "scaleY"] {
+        animators.extend(float_animators(property, zoom, |z| z / 100.0, context));
+    }
+    if !animators.is_empty() {
+        context.targets.push(Target {
+            name: "camera".to_string(),
+            animators,
+        });
+    }
+    let zoom = context.round(zoom.earliest().value / 100.0);
+    Group {
+        name: Some("camera".to_string()),
+        children: vec![Element::Group(content)],
+        pivot: context.round_point(view.center),
+        translate: context
+            .round_point(translate.earliest().value.to_point())
+            .to_vec2(),
+        rotation: 0.0,
+        scale: (zoom, zoom),
+    }
+}
+
+/// A [clip-path](https://developer.android.com/reference/android/graphics/drawable/VectorDrawable#clip-path-attributes)
+/// of `clip` and, if it changes shape, a target to morph it
+fn to_avd_clip(
+    clip: &ir::Keyframed<BezPath>,
+    context: &mut Context,
+) -> Result<ClipPath, AndroidError> {
+    let name = format!("clip_{}", context.clips);
+    context.clips += 1;
+    let keyframes: Vec<_> = clip
+        .iter()
+        .map(|k| (k.time, k.value.clone(), k.ease.unwrap_or_default(), k.hold))
+        .collect();
+    let path = path_data(&keyframes[0].1, context.options.precision())?;
+    let animators = morph_animators(&keyframes, context)?;
+    if !animators.is_empty() {
+        context.targets.push(Target {
+            name: name.clone(),
+            animators,
+        });
+    }
+    Ok(ClipPath { name, path })
+}
+
+/// Clips what follows it in its group
+#[derive(Debug)]
+pub(crate) struct ClipPath {
+    name: String,
+    path: String,
+}
+
+impl ClipPath {
+    fn to_avd_xml(&self, xml: &mut XmlWriter<'_>, depth: u32) -> Result<(), AndroidError> {
+        let name = format!("android:name=\"{}\"", self.name);
+        let path = format!("android:pathData=\"{}\"", self.path);
+        start_el(xml, depth, "clip-path", vec![&name, &path]);
+        end_el(xml, depth, "clip-path");
         Ok(())
     }
 }
 
-fn to_avd_path(fill: Option<(u8, u8, u8)>, shape: &ir::Keyframed<BezPath>) -> Path {
-    let initial_state = &shape.earliest().value;
-    Path {
-        fill: fill
-            .map(|(r, g, b)| format!("#{r:02x}{g:02x}{b:02x}"))
-            .unwrap_or(String::from("#000000")),
-        path: initial_state.to_svg(),
+/// Animators for the element with a given `android:name`
+#[derive(Debug)]
+struct Target {
+    name: String,
+    animators: Vec<ObjectAnimator>,
+}
+
+impl Target {
+    fn to_avd_xml(&self, xml: &mut XmlWriter<'_>, depth: u32) {
+        start_el(
+            xml,
+            depth,
+            "target",
+            vec![&format!("android:name=\"{}\"", self.name)],
+        );
+        start_el(
+            xml,
+            depth + 1,
+            r#"aapt:attr name="android:animation""#,
+            vec![],
+        );
+        start_el(
+            xml,
+            depth + 2,
+            "set",
+            vec![r#"android:ordering="together""#],
+        );
+        for animator in &self.animators {
+            animator.to_avd_xml(xml, depth + 3);
+        }
+        end_el(xml, depth + 2, "set");
+        end_el(xml, depth + 1, "aapt:attr");
+        end_el(xml, depth, "target");
+    }
+}
+
+/// An [objectAnimator](https://developer.android.com/reference/android/animation/ObjectAnimator),
+/// times are in milliseconds
+#[derive(Debug)]
+struct ObjectAnimator {
+    property: &'static str,
+    start_offset: u64,
+    duration: u64,
+    value_from: String,
+    value_to: String,
+    value_type: &'static str,
+    /// The motion from value_from to value_to, the same cubic Lottie uses so the two play alike
+    interpolator: Ease,
+}
+
+impl ObjectAnimator {
+    fn to_avd_xml(&self, xml: &mut XmlWriter<'_>, depth: u32) {
+        start_el(
+            xml,
+            depth,
+            "objectAnimator",
+            vec![
+                &format!("android:propertyName=\"{}\"", self.property),
+                &format!("android:startOffset=\"{}\"", self.start_offset),
+                &format!("android:duration=\"{}\"", self.duration),
+                &format!("android:valueFrom=\"{}\"", self.value_from),
+                &format!("android:valueTo=\"{}\"", self.value_to),
+                &format!("android:valueType=\"{}\"", self.value_type),
+            ],
+        );
+        start_el(
+            xml,
+            depth + 1,
+            r#"aapt:attr name="android:interpolator""#,
+            vec![],
+        );
+        let Ease { p1, p2 } = self.interpolator;
+        start_el(
+            xml,
+            depth + 2,
+            "pathInterpolator",
+            vec![
+                &format!("android:controlX1=\"{}\"", p1.x),
+                &format!("android:controlY1=\"{}\"", p1.y),
+                &format!("android:controlX2=\"{}\"", p2.x),
+                &format!("android:controlY2=\"{}\"", p2.y),
+            ],
+        );
+        end_el(xml, depth + 2, "pathInterpolator");
+        end_el(xml, depth + 1, "aapt:attr");
+        end_el(xml, depth, "objectAnimator");
+    }
+}
+
+/// Writes `path` as [pathData](https://developer.android.com/reference/android/graphics/drawable/VectorDrawable#path-attributes)
+///
+/// Commands are relative, and shortened to h or v where possible, except that every subpath
+/// starts with an absolute M: some Android releases resolve a relative move after a close against
+/// the last point rather than the start of the subpath. Numbers never use exponents, which not
+/// every Android parser accepts, and drop what they can of zeros and separators.
+pub fn path_data(path: &BezPath, precision: Option<Precision>) -> Result<String, AndroidError> {
+    write_path_data(path, precision, true)
+}
+
+/// If not `compact` every command is written out, never implied or shortened, so paths with the
+/// same commands have the same pathData structure
+fn write_path_data(
+    path: &BezPath,
+    precision: Option<Precision>,
+    compact: bool,
+) -> Result<String, AndroidError> {
+    let path = quantize(path, precision);
+    let mut writer = PathDataWriter {
+        data: String::new(),
+        command: None,
+        precision,
+        compact,
+    };
+    let mut current = Point::ZERO;
+    let mut subpath_start = Point::ZERO;
+    for el in path.iter() {
+        if let Some(p) = points(&el).into_iter().flatten().find(|p| !p.is_finite()) {
+            return Err(AndroidError::NonFiniteCoordinate(p));
+        }
+        match el {
+            PathEl::MoveTo(p) => {
+                writer.command('M');
+                writer.vec(p.to_vec2());
+                subpath_start = p;
+                current = p;
+            }
+            PathEl::LineTo(p) => {
+                let delta = p - current;
+                if compact && delta.y == 0.0 {
+                    writer.command('h');
+                    writer.number(delta.x);
+                } else if compact && delta.x == 0.0 {
+                    writer.command('v');
+                    writer.number(delta.y);
+                } else {
+                    writer.command('l');
+                    writer.vec(delta);
+                }
+                current = p;
+            }
+            PathEl::QuadTo(c0, p) => {
+                writer.command('q');
+                writer.vec(c0 - current);
+                writer.vec(p - current);
+                current = p;
+            }
+            PathEl::CurveTo(c0, c1, p) => {
+                writer.command('c');
+                writer.vec(c0 - current);
+                writer.vec(c1 - current);
+                writer.vec(p - current);
+                current = p;
+            }
+            PathEl::ClosePath => {
+                writer.command('z');
+                current = subpath_start;
+            }
+        }
+    }
+    Ok(writer.data)
+}
+
+fn points(el: &PathEl) -> [Option<Point>; 3] {
+    match *el {
+        PathEl::MoveTo(p) | PathEl::LineTo(p) => [Some(p), None, None],
+        PathEl::QuadTo(p0, p1) => [Some(p0), Some(p1), None],
+        PathEl::CurveTo(p0, p1, p2) => [Some(p0), Some(p1), Some(p2)],
+        PathEl::ClosePath => [None, None, None],
+    }
+}
+
+struct PathDataWriter {
+    data: String,
+    /// The last command written, repeats of it are implied
+    command: Option<char>,
+    precision: Option<Precision>,
+    compact: bool,
+}
+
+impl PathDataWriter {
+    fn command(&mut self, command: char) {
+        // A repeated M would be read as a line, a repeated z is pointless; always write both
+        if self.compact && self.command == Some(command) && !matches!(command, 'M' | 'z') {
+            return;
+        }
+        self.data.push(command);
+        self.command = Some(command);
+    }
+
+    fn vec(&mut self, v: Vec2) {
+        self.number(v.x);
+        self.number(v.y);
+    }
+
+    fn number(&mut self, value: f64) {
+        let value = self.precision.map_or(value, |p| p.round(value)) + 0.0;
+        // Display for f64 never uses an exponent
+        let mut number = value.to_string();
+        if let Some(fraction) = number.strip_prefix("0.") {
+            number = format!(".{fraction}");
+        } else if let Some(fraction) = number.strip_prefix("-0.") {
+            number = format!("-.{fraction}");
+        }
+        // A minus separates numbers by itself
+        let follows_number = self
+            .data
+            .ends_with(|c: char| c.is_ascii_digit() || c == '.');
+        if follows_number && !number.starts_with('-') {
+            self.data.push(' ');
+        }
+        self.data.push_str(&number);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kurbo::{BezPath, Rect, Shape};
+
+    use crate::{
+        bezop::Precision,
+        error::AndroidError,
+        ir::{
+            Animation, Camera, ColorStop, Ease, Element, FromAnimation, Gradient, Group, Keyframe,
+            Keyframed,
+        },
+        motion::Motion,
+        spring::{AnimatedValue, AnimatedValueType, Spring},
+    };
+
+    use super::{path_data, to_avd, AndroidOptions, AnimatedVectorDrawable};
+
+    fn square(size: f64) -> BezPath {
+        Rect::new(0.0, 0.0, size, size).to_path(0.1)
+    }
+
+    /// A half second morph from a 10x10 square to `end`
+    fn morph(end: BezPath) -> Animation {
+        let mut shape = Keyframed::new(0.0, square(10.0));
+        shape.push(Keyframe::new(0.5, end));
+        let mut root = Group::default();
+        root.children.push(Element::Shape(shape));
+        Animation::new(100.0, 100.0, Duration::from_millis(500), root)
+    }
+
+    fn two_subpaths() -> BezPath {
+        let mut path = BezPath::new();
+        path.move_to((0.5, -0.25));
+        path.quad_to((1.5, 0.75), (2.5, -0.25));
+        path.line_to((3.5, 0.75));
+        path.line_to((4.5, 1.75));
+        path.close_path();
+        path.move_to((0.1 + 0.2, 1e-7));
+        path.line_to((1.0 / 3.0, 2.0));
+        path.close_path();
+        path
+    }
+
+    #[test]
+    fn path_data_is_relative() {
+        assert_eq!(
+            "M0 0h10v10h-10z",
+            path_data(&Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1), None).unwrap()
+        );
+    }
+
+    #[test]
+    fn path_data_has_no_exponents() {
+        let data = path_data(&two_subpaths(), None).unwrap();
+        assert!(!data.contains('e'), "{data}");
+    }
+
+    #[test]
+    fn path_data_rounds() {
+        assert_eq!(
+            "M.5-.25q1 1 2 0l1 1 1 1zM.3 0l.03 2z",
+            path_data(&two_subpaths(), Some(Precision(2))).unwrap()
+        );
+    }
+
+    #[test]
+    fn path_data_must_be_finite() {
+        let mut path = BezPath::new();
+        path.move_to((f64::NAN, 0.0));
+        assert!(matches!(
+            path_data(&path, None),
+            Err(AndroidError::NonFiniteCoordinate(..))
+        ));
+    }
+
+    #[test]
+    fn morph_becomes_an_animator() {
+        let xml = AnimatedVectorDrawable::from_animation(&morph(square(20.0)))
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        assert_eq!(2, xml.matches(r#"android:name="path_0""#).count(), "{xml}");
+        for attr in [
+            r#"android:propertyName="pathData""#,
+            r#"android:duration="500""#,
+            r#"android:valueFrom="M0 0l10 0l0 10l-10 0z""#,
+            r#"android:valueTo="M0 0l20 0l0 20l-20 0z""#,
+            r#"android:valueType="pathType""#,
+            r#"android:controlX1="0.4""#,
+            r#"android:controlY2="1""#,
+        ] {
+            assert!(xml.contains(attr), "{attr} missing from {xml}");
+        }
+    }
+
+    #[test]
+    fn held_morph_jumps_as_it_ends() {
+        let mut animation = morph(square(20.0));
+        let Element::Shape(shape) = &mut animation.root.children[0] else {
+            panic!("Should be a shape");
+        };
+        shape.iter_mut().next().unwrap().hold = true;
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        for attr in [r#"android:startOffset="500""#, r#"android:duration="0""#] {
+            assert!(xml.contains(attr), "{attr} missing from {xml}");
+        }
+    }
+
+    #[test]
+    fn morph_requires_matching_commands() {
+        let mut triangle = BezPath::new();
+        triangle.move_to((0.0, 0.0));
+        triangle.line_to((10.0, 0.0));
+        triangle.line_to((5.0, 10.0));
+        triangle.close_path();
+        assert!(matches!(
+            AnimatedVectorDrawable::from_animation(&morph(triangle)),
+            Err(AndroidError::IncompatiblePaths { time, .. }) if time == 0.5
+        ));
+    }
+
+    #[test]
+    fn group_transform_is_written() {
+        let mut animation = morph(square(20.0));
+        animation.root.center = (5.0, 5.0).into();
+        animation.root.rotate = Keyframed::new(0.0, 45.0);
+        animation.root.scale = Keyframed::new(0.0, (150.0, 50.0));
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        for attr in [
+            r#"android:pivotX="5""#,
+            r#"android:pivotY="5""#,
+            r#"android:rotation="45""#,
+            r#"android:scaleX="1.5""#,
+            r#"android:scaleY="0.5""#,
+        ] {
+            assert!(xml.contains(attr), "{attr} missing from {xml}");
+        }
+        assert!(!xml.contains("android:translateX"), "{xml}");
+    }
+
+    #[test]
+    fn spring_rotate_animates_the_group() {
+        let motion = Motion::new(
+            60.0,
+            AnimatedValue::new(0.0, 90.0, AnimatedValueType::Rotation),
+            Spring::expressive_spatial(),
+        )
+        .unwrap();
+        let rotate: Vec<_> = motion
+            .keyframes
+            .iter()
+            .map(|k| match k.ease {
+                Some((p1, p2)) => Keyframe::new(k.frame / 60.0, k.value).with_ease(Ease { p1, p2 }),
+                None => Keyframe::new(k.frame / 60.0, k.value),
+            })
+            .collect();
+        let mut animation = morph(square(10.0));
+        animation.root.rotate = rotate.try_into().unwrap();
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        // The group is named once on itself and once on its target
+        assert_eq!(2, xml.matches(r#"android:name="group_0""#).count(), "{xml}");
+        assert_eq!(
+            motion.keyframes.len() - 1,
+            xml.matches(r#"android:propertyName="rotation""#).count(),
+            "{xml}"
+        );
+        // Each segment eases by the cubic fitted to the spring
+        for (p1, p2) in motion.keyframes.iter().filter_map(|k| k.ease) {
+            for attr in [
+                format!("android:controlX1=\"{}\"", p1.x),
+                format!("android:controlY1=\"{}\"", p1.y),
+                format!("android:controlX2=\"{}\"", p2.x),
+                format!("android:controlY2=\"{}\"", p2.y),
+            ] {
+                assert!(xml.contains(&attr), "{attr} missing from {xml}");
+            }
+        }
+        assert!(!xml.contains("scaleX"), "{xml}");
+    }
+
+    #[test]
+    fn skew_nests_groups() {
+        let mut animation = morph(square(20.0));
+        animation.root.set_skew(Keyframed::new(0.0, (45.0, 0.0)));
+        let avd = to_avd(
+            &animation,
+            &AndroidOptions {
+                precision: Some(Precision(3)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let xml = avd.to_avd_xml().unwrap();
+        assert_eq!(2, xml.matches("<group").count(), "{xml}");
+        // A 45 degree skew stretches by the golden ratio
+        for attr in [
+            r#"android:rotation="-31.717""#,
+            r#"android:scaleX="1.618""#,
+            r#"android:scaleY="0.618""#,
+            r#"android:rotation="58.283""#,
+        ] {
+            assert!(xml.contains(attr), "{attr} missing from {xml}");
+        }
+    }
+
+    #[test]
+    fn higher_z_draws_later() {
+        let mut animation = morph(square(20.0));
+        for red in [1, 2, 3] {
+            let mut part = Group::default();
+            part.set_fill(Some((red, 0, 0)));
+            part.children_mut()
+                .push(Element::Shape(Keyframed::new(0.0, square(5.0))));
+            if red == 2 {
+                part.set_z(1);
+            }
+            animation.root.children_mut().push(Element::Group(part));
+        }
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        let at = |color: &str| xml.find(&format!("android:fillColor=\"{color}\"")).unwrap();
+        assert!(at("#010000") < at("#030000"), "{xml}");
+        assert!(at("#030000") < at("#020000"), "{xml}");
+    }
+
+    #[test]
+    fn gradient_replaces_fill_color() {
+        let mut animation = morph(square(20.0));
+        animation.root.set_gradient(Some(Gradient::Radial {
+            center: (5.0, 5.0).into(),
+            radius: 5.0,
+            stops: vec![ColorStop {
+                offset: 0.5,
+                color: (255, 0, 0),
+            }],
+        }));
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        assert!(!xml.contains("android:fillColor="), "{xml}");
+        for attr in [
+            r#"aapt:attr name="android:fillColor""#,
+            r#"android:type="radial""#,
+            r#"android:gradientRadius="5""#,
+            r#"android:offset="0.5""#,
+            r##"android:color="#ff0000""##,
+        ] {
+            assert!(xml.contains(attr), "{attr} missing from {xml}");
+        }
+    }
+
+    #[test]
+    fn visible_group_pops_in() {
+        let mut animation = morph(square(20.0));
+        let mut part = Group::default();
+        part.children
+            .push(Element::Shape(Keyframed::new(0.0, square(5.0))));
+        part.set_visible(Some(0.25..0.5));
+        animation.root.children.push(Element::Group(part));
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        // Only the part starts hidden, it's visible until the end so never pops out
+        assert_eq!(1, xml.matches(r#"android:fillAlpha="0""#).count(), "{xml}");
+        assert_eq!(2, xml.matches(r#"android:name="path_1""#).count(), "{xml}");
+        assert_eq!(
+            1,
+            xml.matches(r#"android:propertyName="fillAlpha""#).count(),
+            "{xml}"
+        );
+        for attr in [
+            r#"android:startOffset="250""#,
+            r#"android:valueTo="1""#,
+            r#"android:valueType="floatType""#,
+        ] {
+            assert!(xml.contains(attr), "{attr} missing from {xml}");
+        }
+    }
+
+    #[test]
+    fn camera_wraps_drawing() {
+        let mut animation = morph(square(20.0));
+        animation.set_camera(Some(Camera {
+            zoom: vec![(0.0, 100.0), (0.5, 200.0)].try_into().unwrap(),
+            ..Default::default()
+        }));
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        // The camera is named once on its group and once on its target
+        assert_eq!(2, xml.matches(r#"android:name="camera""#).count(), "{xml}");
+        for property in ["scaleX", "scaleY"] {
+            let attr = format!(r#"android:propertyName="{property}""#);
+            assert_eq!(1, xml.matches(&attr).count(), "{xml}");
+        }
+        assert!(xml.contains(r#"android:valueTo="2""#), "{xml}");
+        assert!(!xml.contains("translateX"), "{xml}");
+    }
+
+    #[test]
+    fn clip_wipes() {
+        let mut animation = morph(square(20.0));
+        let mut clip = Keyframed::new(0.0, square(0.0));
+        clip.push(Keyframe::new(0.5, square(20.0)));
+        animation.root.set_clip(Some(clip));
+        let xml = AnimatedVectorDrawable::from_animation(&animation)
+            .unwrap()
+            .to_avd_xml()
+            .unwrap();
+        // The clip is named once on the clip-path and once on its target
+        assert_eq!(2, xml.matches(r#"android:name="clip_0""#).count(), "{xml}");
+        let clip_at = xml.find("<clip-path").expect(&xml);
+        assert!(clip_at < xml.find("<path").unwrap(), "{xml}");
+        assert_eq!(
+            2,
+            xml.matches(r#"android:propertyName="pathData""#).count(),
+            "{xml}"
+        );
+    }
+
+    #[test]
+    fn write_failure_is_reported() {
+        let avd = AnimatedVectorDrawable::from_animation(&morph(square(20.0))).unwrap();
+        let mut small = [0u8; 16];
+        assert!(matches!(
+            avd.write_to(&mut &mut small[..]),
+            Err(AndroidError::Io(..))
+        ));
+        let mut streamed = Vec::new();
+        avd.write_to(&mut streamed).unwrap();
+        assert_eq!(avd.to_avd_xml().unwrap().as_bytes(), streamed);
     }
 }
@@ -0,0 +1,181 @@
+//! Pre-flight checks of whether, and how well, an icon can be animated
+
+use std::str::FromStr;
+
+use kurbo::BezPath;
+use serde::Serialize;
+use skrifa::{instance::Location, raw::FontRef, GlyphId, MetadataProvider, Tag};
+
+use crate::{
+    bezop::{Fit, FontToOutput, OutputUnits},
+    error::Error,
+    font_info,
+    ir::{compatible, Element, Group, Keyframed},
+    ligate::icon_name_to_gid,
+    GlyphShape,
+};
+
+/// A change along one axis to check, e.g. `FILL:0..1`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Variation {
+    pub tag: Tag,
+    pub from: f32,
+    pub to: f32,
+}
+
+impl FromStr for Variation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tag, range) = s.split_once(':').ok_or(Error::InvalidLocation)?;
+        let (from, to) = range.split_once("..").ok_or(Error::InvalidLocation)?;
+        Ok(Variation {
+            tag: Tag::from_str(tag).map_err(Error::InvalidTag)?,
+            from: f64::from_str(from)? as f32,
+            to: f64::from_str(to)? as f32,
+        })
+    }
+}
+
+/// The result of [`check_icon`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Report {
+    pub icon_name: String,
+    /// Whether the icon has an outline that can be drawn
+    pub drawable: bool,
+    /// How many parts part-wise animations, such as twirl, will move independently
+    pub parts: usize,
+    /// Whether FILL can animate from min to max. None if the font has no FILL axis.
+    pub fill_compatible: Option<bool>,
+    /// Whether wght can animate from min to max. None if the font has no wght axis.
+    pub wght_compatible: Option<bool>,
+    /// Whether the variation given to [`check_icon_varying`] can animate. None if there was none.
+    pub variation_compatible: Option<bool>,
+    pub warnings: Vec<String>,
+}
+
+/// Check an icon for problems without generating an animation
+///
+/// Only failure to find the icon is an error, other problems are reported.
+pub fn check_icon(font: &FontRef, icon_name: &str) -> Result<Report, Error> {
+    check_icon_varying(font, icon_name, None)
+}
+
+/// As [`check_icon`], also checking the icon can animate through `variation`
+pub fn check_icon_varying(
+    font: &FontRef,
+    icon_name: &str,
+    variation: Option<&Variation>,
+) -> Result<Report, Error> {
+    let gid = icon_name_to_gid(font, icon_name).map_err(Error::IconNameError)?;
+    let mut report = Report {
+        icon_name: icon_name.to_string(),
+        ..Default::default()
+    };
+
+    let glyph_shape = match GlyphShape::new(font, gid, Location::default(), None) {
+        Ok(glyph_shape) => glyph_shape,
+        Err(e) => {
+            report.warnings.push(format!("{e}"));
+            return Ok(report);
+        }
+    };
+    let shape = match draw(&glyph_shape) {
+        Ok(shape) => shape,
+        Err(e) => {
+            report.warnings.push(e);
+            return Ok(report);
+        }
+    };
+    report.drawable = true;
+
+    let mut group = Group {
+        children: vec![Element::Shape(shape)],
+        ..Default::default()
+    };
+    group.group_parts();
+    report.parts = group.children.len();
+
+    report.fill_compatible = axis_compatible(font, gid, Tag::new(b"FILL"), &mut report.warnings);
+    report.wght_compatible = axis_compatible(font, gid, Tag::new(b"wght"), &mut report.warnings);
+    report.variation_compatible = variation.map(|variation| {
+        if font.axes().get_by_tag(variation.tag).is_none() {
+            report
+                .warnings
+                .push(format!("The font has no {} axis", variation.tag));
+            return false;
+        }
+        variation_compatible(font, gid, variation, &mut report.warnings)
+    });
+
+    Ok(report)
+}
+
+fn draw(glyph_shape: &GlyphShape) -> Result<Keyframed<BezPath>, String> {
+    let drawbox = glyph_shape.drawbox();
+    let shape = Keyframed::<BezPath>::for_glyph(
+        1.0,
+        FontToOutput::fit(drawbox, OutputUnits(drawbox.0), Fit::Meet),
+        glyph_shape,
+    )
+    .map_err(|e| format!("{e}"))?;
+    if shape.iter().any(|k| k.value.elements().is_empty()) {
+        return Err(format!("{:?} has an empty outline", glyph_shape.gid));
+    }
+    Ok(shape)
+}
+
+/// Whether the glyph drawn at the min and max of the axis is interpolation compatible
+fn axis_compatible(
+    font: &FontRef,
+    gid: GlyphId,
+    tag: Tag,
+    warnings: &mut Vec<String>,
+) -> Option<bool> {
+    let axis = font.axes().get_by_tag(tag)?;
+    let variation = Variation {
+        tag,
+        from: axis.min_value(),
+        to: axis.max_value(),
+    };
+    Some(variation_compatible(font, gid, &variation, warnings))
+}
+
+/// Whether the glyph drawn at either end of the variation is interpolation compatible
+fn variation_compatible(
+    font: &FontRef,
+    gid: GlyphId,
+    variation: &Variation,
+    warnings: &mut Vec<String>,
+) -> bool {
+    let Variation { tag, from, to } = *variation;
+    if let Err(e) = font_info::check_location(font, &[(tag, from), (tag, to)]) {
+        warnings.push(format!("Unable to vary {tag} from {from} to {to}: {e}"));
+        return false;
+    }
+    let shape = GlyphShape::new(
+        font,
+        gid,
+        font.axes().location([(tag, from)]),
+        Some(font.axes().location([(tag, to)])),
+    )
+    .map_err(|e| format!("{e}"))
+    .and_then(|glyph_shape| draw(&glyph_shape));
+    let shape = match shape {
+        Ok(shape) => shape,
+        Err(e) => {
+            warnings.push(format!("Unable to draw at {tag} {from} and {to}: {e}"));
+            return false;
+        }
+    };
+    let start = &shape.earliest().value;
+    match shape.iter().try_for_each(|k| compatible(start, &k.value)) {
+        Ok(()) => true,
+        Err(mismatch) => {
+            warnings.push(format!(
+                "{tag} {from} and {to} are not interpolation compatible: {mismatch}"
+            ));
+            false
+        }
+    }
+}
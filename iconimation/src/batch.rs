@@ -0,0 +1,110 @@
+//! Many animations from one font, e.g. for a server that animates a whole icon set
+//!
+//! With the `rayon` feature commands are animated in parallel. The font is shared, read in
+//! place, by every thread rather than parsed per command.
+
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use skrifa::raw::FontRef;
+
+use crate::{
+    error::Error,
+    ir::Animation,
+    observe::{GenerationObserver, Recording},
+    plan::PlanOptions,
+};
+
+/// The name [`GenerationObserver::cache_lookup`] is given for reuse of a repeated command
+const BATCH_CACHE: &str = "batch";
+
+/// Animate each of `commands`, as [`Animation::of_command`], results in the order of `commands`
+///
+/// In parallel if the `rayon` feature is enabled, one after another otherwise. A command that
+/// fails doesn't affect the rest. A command that appears more than once is animated once.
+pub fn animate_many<C: AsRef<str> + Sync>(
+    font: &FontRef,
+    commands: &[C],
+    options: &PlanOptions,
+) -> Vec<Result<Animation, Error>> {
+    animate_many_observed(font, commands, options, &mut ())
+}
+
+/// As [`animate_many`], reporting progress to `observer`
+///
+/// Events for each command arrive together, in the order of `commands`, however the work was
+/// spread across threads. A repeated command is a "batch" cache hit, unless it failed the first
+/// time, errors aren't kept, in which case it is animated again.
+pub fn animate_many_observed<C: AsRef<str> + Sync>(
+    font: &FontRef,
+    commands: &[C],
+    options: &PlanOptions,
+    observer: &mut dyn GenerationObserver,
+) -> Vec<Result<Animation, Error>> {
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    for command in commands {
+        *uses.entry(command.as_ref()).or_default() += 1;
+    }
+    let mut seen = HashSet::new();
+    let firsts: Vec<_> = (0..commands.len())
+        .filter(|i| seen.insert(commands[*i].as_ref()))
+        .collect();
+    let mut animated: HashMap<_, _> = firsts
+        .iter()
+        .copied()
+        .zip(map_all(&firsts, |i| {
+            let mut recording = Recording::default();
+            let result = Animation::of_command_observed(
+                font,
+                commands[*i].as_ref(),
+                options,
+                &mut recording,
+            );
+            (result, recording)
+        }))
+        .collect();
+
+    // Animations wanted again by a later command
+    let mut reusable: HashMap<&str, Animation> = HashMap::new();
+    commands
+        .iter()
+        .enumerate()
+        .map(|(i, command)| {
+            let command = command.as_ref();
+            let remaining = uses.get_mut(command).unwrap();
+            *remaining -= 1;
+            let last_use = *remaining == 0;
+            if reusable.contains_key(command) {
+                observer.cache_lookup(BATCH_CACHE, true);
+                return Ok(if last_use {
+                    reusable.remove(command).unwrap()
+                } else {
+                    reusable[command].clone()
+                });
+            }
+            observer.cache_lookup(BATCH_CACHE, false);
+            let result = match animated.remove(&i) {
+                Some((result, recording)) => {
+                    recording.replay(observer);
+                    result
+                }
+                None => Animation::of_command_observed(font, command, options, observer),
+            };
+            if let (Ok(animation), false) = (&result, last_use) {
+                reusable.insert(command, animation.clone());
+            }
+            result
+        })
+        .collect()
+}
+
+#[cfg(feature = "rayon")]
+fn map_all<C: Sync, T: Send>(items: &[C], f: impl Fn(&C) -> T + Sync + Send) -> Vec<T> {
+    items.par_iter().map(f).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map_all<C, T>(items: &[C], f: impl Fn(&C) -> T) -> Vec<T> {
+    items.iter().map(f).collect()
+}
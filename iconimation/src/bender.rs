@@ -0,0 +1,163 @@
+//! Classic easing families, bounce, elastic and overshoot, for when a physical spring isn't wanted
+//!
+//! These can't be expressed as a single cubic ease so motion using them is sampled every frame,
+//! see [`crate::ir::Animation::of_command`] with e.g. `rotate 90 degrees using bounce`.
+
+use std::{f64::consts::PI, str::FromStr};
+
+use serde::Serialize;
+
+use crate::{error::Error, ir::Ease};
+
+/// Bends time, 0..1, into progress, 0 at the start and 1 at the end but free to stray
+/// outside 0..1 in between
+pub trait MotionBender {
+    fn progress(&self, t: f64) -> f64;
+}
+
+impl MotionBender for Ease {
+    fn progress(&self, t: f64) -> f64 {
+        Ease::progress(self, t)
+    }
+}
+
+impl<B: MotionBender + ?Sized> MotionBender for &B {
+    fn progress(&self, t: f64) -> f64 {
+        (**self).progress(t)
+    }
+}
+
+/// The easing families of <https://easings.net>, each arriving at the end
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum Bender {
+    /// Drop to the end and bounce, each bounce lower, like a ball
+    Bounce,
+    /// Overshoot and wobble about the end like a plucked rubber band. Amplitude, at least 1,
+    /// scales the wobble.
+    Elastic { amplitude: f64 },
+    /// Go past the end once and settle back. Amplitude, at least 0, is how far past; 0 doesn't
+    /// overshoot at all.
+    Overshoot { amplitude: f64 },
+}
+
+/// How long an elastic wobble takes, as a fraction of the motion
+const ELASTIC_PERIOD: f64 = 0.3;
+
+impl Bender {
+    /// The usual elastic, a wobble that starts at the size of the move
+    pub const DEFAULT_ELASTIC_AMPLITUDE: f64 = 1.0;
+    /// The usual overshoot, about 10% past the end
+    pub const DEFAULT_OVERSHOOT_AMPLITUDE: f64 = 1.70158;
+    /// The names [`Bender::from_str`] accepts
+    pub const NAMES: [&'static str; 3] = ["bounce", "elastic", "overshoot"];
+}
+
+impl MotionBender for Bender {
+    fn progress(&self, t: f64) -> f64 {
+        if t <= 0.0 {
+            return 0.0;
+        }
+        if t >= 1.0 {
+            return 1.0;
+        }
+        match *self {
+            Bender::Bounce => bounce(t),
+            Bender::Elastic { amplitude } => {
+                let amplitude = amplitude.max(1.0);
+                let shift = ELASTIC_PERIOD / (2.0 * PI) * (1.0 / amplitude).asin();
+                amplitude * 2f64.powf(-10.0 * t) * ((t - shift) * 2.0 * PI / ELASTIC_PERIOD).sin()
+                    + 1.0
+            }
+            Bender::Overshoot { amplitude } => {
+                let t = t - 1.0;
+                (amplitude + 1.0) * t.powi(3) + amplitude * t.powi(2) + 1.0
+            }
+        }
+    }
+}
+
+/// Parabolas landing at 1, each bounce a quarter the height of the last
+fn bounce(t: f64) -> f64 {
+    const HEIGHT: f64 = 7.5625;
+    const WIDTH: f64 = 2.75;
+    let (offset, rest) = if t < 1.0 / WIDTH {
+        (0.0, 0.0)
+    } else if t < 2.0 / WIDTH {
+        (1.5 / WIDTH, 0.75)
+    } else if t < 2.5 / WIDTH {
+        (2.25 / WIDTH, 0.9375)
+    } else {
+        (2.625 / WIDTH, 0.984375)
+    };
+    HEIGHT * (t - offset).powi(2) + rest
+}
+
+impl FromStr for Bender {
+    type Err = Error;
+
+    /// A name from [`Bender::NAMES`], with the default amplitude
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bounce" => Ok(Bender::Bounce),
+            "elastic" => Ok(Bender::Elastic {
+                amplitude: Bender::DEFAULT_ELASTIC_AMPLITUDE,
+            }),
+            "overshoot" => Ok(Bender::Overshoot {
+                amplitude: Bender::DEFAULT_OVERSHOOT_AMPLITUDE,
+            }),
+            _ => Err(Error::UnrecognizedBender(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{Bender, MotionBender};
+
+    fn samples(bender: Bender) -> Vec<f64> {
+        (0..=100)
+            .map(|i| bender.progress(i as f64 / 100.0))
+            .collect()
+    }
+
+    #[test]
+    fn start_at_rest_and_arrive() {
+        for name in Bender::NAMES {
+            let bender = Bender::from_str(name).unwrap();
+            assert_eq!(0.0, bender.progress(0.0), "{name}");
+            assert_eq!(1.0, bender.progress(1.0), "{name}");
+            // Nearly there just before the end
+            assert!((bender.progress(0.999) - 1.0).abs() < 0.01, "{name}");
+        }
+    }
+
+    #[test]
+    fn bounce_stays_within_the_move() {
+        let samples = samples(Bender::Bounce);
+        assert!(
+            samples.iter().all(|p| (0.0..=1.0).contains(p)),
+            "{samples:?}"
+        );
+        // Lands and rises again
+        assert!(samples.windows(2).any(|w| w[1] < w[0]), "{samples:?}");
+    }
+
+    #[test]
+    fn amplitude_sets_overshoot() {
+        let peak = |bender| samples(bender).into_iter().fold(f64::MIN, f64::max);
+        let usual = peak(Bender::Overshoot {
+            amplitude: Bender::DEFAULT_OVERSHOOT_AMPLITUDE,
+        });
+        assert!((usual - 1.1).abs() < 0.01, "{usual}");
+        assert!(peak(Bender::Overshoot { amplitude: 3.0 }) > usual);
+        assert_eq!(1.0, peak(Bender::Overshoot { amplitude: 0.0 }));
+        assert!(
+            peak(Bender::Elastic { amplitude: 1.5 })
+                > peak(Bender::Elastic {
+                    amplitude: Bender::DEFAULT_ELASTIC_AMPLITUDE
+                })
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape, Vec2};
+//! Geometry helpers
+
+use std::ops::Mul;
+
+use kurbo::{Affine, BezPath, PathEl, Point, QuadBez, Rect, Shape, Vec2};
+use serde::{Deserialize, Serialize};
 
 pub(crate) trait ContainedPoint {
     /// Find a point that is contained within the subpath
@@ -10,7 +15,7 @@ pub(crate) trait ContainedPoint {
 impl ContainedPoint for BezPath {
     fn contained_point(&self) -> Option<Point> {
         let Some(PathEl::MoveTo(p)) = self.elements().first() else {
-            eprintln!("Subpath doesn't start with a move!");
+            log::warn!("Subpath doesn't start with a move!");
             return None;
         };
 
@@ -24,34 +29,340 @@ impl ContainedPoint for BezPath {
     }
 }
 
-/// Simplified version of [Affine2D::rect_to_rect](https://github.com/googlefonts/picosvg/blob/a0bcfade7a60cbd6f47d8bfe65b6d471cee628c0/src/picosvg/svg_transform.py#L216-L263)
+/// Linear interpolation, t of 0 is self and 1 is other
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for (f64, f64) {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        (self.0.lerp(&other.0, t), self.1.lerp(&other.1, t))
+    }
+}
+
+impl Lerp for Vec2 {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        Vec2::lerp(*self, *other, t)
+    }
+}
+
+/// Point by point. Paths that aren't interpolation compatible hold self until t reaches 1.
+impl Lerp for BezPath {
+    fn lerp(&self, other: &Self, t: f64) -> Self {
+        if self.elements().len() != other.elements().len() {
+            return if t < 1.0 { self.clone() } else { other.clone() };
+        }
+        let mut result = BezPath::new();
+        for (a, b) in self.elements().iter().zip(other.elements()) {
+            let el = match (*a, *b) {
+                (PathEl::MoveTo(a0), PathEl::MoveTo(b0)) => PathEl::MoveTo(a0.lerp(b0, t)),
+                (PathEl::LineTo(a0), PathEl::LineTo(b0)) => PathEl::LineTo(a0.lerp(b0, t)),
+                (PathEl::QuadTo(a0, a1), PathEl::QuadTo(b0, b1)) => {
+                    PathEl::QuadTo(a0.lerp(b0, t), a1.lerp(b1, t))
+                }
+                (PathEl::CurveTo(a0, a1, a2), PathEl::CurveTo(b0, b1, b2)) => {
+                    PathEl::CurveTo(a0.lerp(b0, t), a1.lerp(b1, t), a2.lerp(b2, t))
+                }
+                (PathEl::ClosePath, PathEl::ClosePath) => PathEl::ClosePath,
+                _ => return if t < 1.0 { self.clone() } else { other.clone() },
+            };
+            result.push(el);
+        }
+        result
+    }
+}
+
+/// Coordinates are rounded to this many decimal places when paths are written out
+///
+/// Outlines otherwise carry full f64 precision, bloating output and making diffs noisy. Every
+/// output format rounds through here so they agree on where a point lands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Precision(pub u32);
+
+impl Precision {
+    /// Fine enough for any icon, coarse enough to hide floating point differences between
+    /// platforms; used for stable output
+    pub const STABLE: Precision = Precision(3);
+
+    pub fn round(&self, value: f64) -> f64 {
+        let scale = 10f64.powi(self.0 as i32);
+        // + 0.0 turns -0.0 into 0.0, which would otherwise write as -0
+        (value * scale).round() / scale + 0.0
+    }
+
+    pub fn round_point(&self, p: Point) -> Point {
+        Point::new(self.round(p.x), self.round(p.y))
+    }
+
+    /// Every point, on and off curve, rounded
+    pub fn round_path(&self, path: &BezPath) -> BezPath {
+        path.iter()
+            .map(|el| match el {
+                PathEl::MoveTo(p) => PathEl::MoveTo(self.round_point(p)),
+                PathEl::LineTo(p) => PathEl::LineTo(self.round_point(p)),
+                PathEl::QuadTo(p0, p1) => {
+                    PathEl::QuadTo(self.round_point(p0), self.round_point(p1))
+                }
+                PathEl::CurveTo(p0, p1, p2) => PathEl::CurveTo(
+                    self.round_point(p0),
+                    self.round_point(p1),
+                    self.round_point(p2),
+                ),
+                PathEl::ClosePath => PathEl::ClosePath,
+            })
+            .collect()
+    }
+}
+
+/// `path` rounded per `precision`, or as is if there is none
+pub(crate) fn quantize(path: &BezPath, precision: Option<Precision>) -> BezPath {
+    match precision {
+        Some(precision) => precision.round_path(path),
+        None => path.clone(),
+    }
+}
+
+/// `path` drawn only with cubics, lines become cubics with no handles
+///
+/// Paths that draw the same sequence of segments, whatever their degree, are
+/// interpolation compatible once converted.
+pub fn to_cubics(path: &BezPath) -> BezPath {
+    let mut current = Point::ZERO;
+    let mut start = Point::ZERO;
+    let mut cubics = BezPath::new();
+    for el in path.iter() {
+        match el {
+            PathEl::MoveTo(p) => {
+                (start, current) = (p, p);
+                cubics.move_to(p);
+            }
+            PathEl::LineTo(p) => {
+                cubics.curve_to(current, p, p);
+                current = p;
+            }
+            PathEl::QuadTo(p1, p2) => {
+                let cubic = QuadBez::new(current, p1, p2).raise();
+                cubics.curve_to(cubic.p1, cubic.p2, cubic.p3);
+                current = p2;
+            }
+            PathEl::CurveTo(p1, p2, p3) => {
+                cubics.curve_to(p1, p2, p3);
+                current = p3;
+            }
+            PathEl::ClosePath => {
+                cubics.close_path();
+                current = start;
+            }
+        }
+    }
+    cubics
+}
+
+/// How to reconcile differing aspect ratios in [`rect_to_rect`]
+///
+/// Modelled on SVG [preserveAspectRatio](https://developer.mozilla.org/en-US/docs/Web/SVG/Attribute/preserveAspectRatio),
+/// always centered (xMidYMid).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fit {
+    /// Uniform scale such that the whole source is visible, like SVG meet
+    Meet,
+    /// Uniform scale such that the whole destination is covered, like SVG slice
+    Slice,
+    /// Scale each axis independently to exactly match the destination, like SVG none
+    Stretch,
+}
+
+/// A value, such as a [`Rect`], in font units: y-up and scaled to the font's upem
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FontUnits<T>(pub T);
+
+/// A value, such as a [`Rect`], in the y-down units of the output, those of [`crate::ir::Animation`]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct OutputUnits<T>(pub T);
+
+/// The only way to turn [`FontUnits`] into [`OutputUnits`]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FontToOutput(Affine);
+
+impl FontToOutput {
+    /// Fit the font's drawbox into an area of the output, flipping y
+    pub fn fit(src: FontUnits<Rect>, dst: OutputUnits<Rect>, fit: Fit) -> Self {
+        Self(rect_to_rect(src.0, dst.0, true, fit))
+    }
+
+    /// For when the mapping is already known, e.g. it was computed elsewhere
+    pub fn new(affine: Affine) -> Self {
+        Self(affine)
+    }
+
+    pub fn affine(&self) -> Affine {
+        self.0
+    }
+
+    pub fn apply<T>(&self, value: FontUnits<T>) -> OutputUnits<T>
+    where
+        Affine: Mul<T, Output = T>,
+    {
+        OutputUnits(self.0 * value.0)
+    }
+
+    /// The bounds of the rect once transformed
+    pub fn apply_rect(&self, rect: FontUnits<Rect>) -> OutputUnits<Rect> {
+        OutputUnits(self.0.transform_rect_bbox(rect.0))
+    }
+}
+
+/// Port of [Affine2D::rect_to_rect](https://github.com/googlefonts/picosvg/blob/a0bcfade7a60cbd6f47d8bfe65b6d471cee628c0/src/picosvg/svg_transform.py#L216-L263)
 ///
-/// font_box is assumed y-up, dest_box y-down
-pub fn y_up_to_y_down(font_box: Rect, dest_box: Rect) -> Affine {
-    assert!(font_box.width() > 0.0);
-    assert!(font_box.height() > 0.0);
-    assert!(dest_box.width() > 0.0);
-    assert!(dest_box.height() > 0.0);
-
-    let (sx, sy) = (
-        dest_box.width() / font_box.width(),
-        dest_box.height() / font_box.height(),
-    );
-    let transform = Affine::IDENTITY
-        // Move the font box to touch the origin
-        .then_translate((-font_box.min_x(), -font_box.min_y()).into())
-        // Do a flip!
-        .then_scale_non_uniform(1.0, -1.0)
-        // Scale to match the target box
-        .then_scale_non_uniform(sx, sy);
-
-    // Line up
-    let adjusted_font_box = transform.transform_rect_bbox(font_box);
-    transform.then_translate(
-        (
-            dest_box.min_x() - adjusted_font_box.min_x(),
-            dest_box.min_y() - adjusted_font_box.min_y(),
-        )
-            .into(),
-    )
+/// If flip_y is set the source is treated as y-up (e.g. font units) and the destination y-down.
+pub fn rect_to_rect(src: Rect, dst: Rect, flip_y: bool, fit: Fit) -> Affine {
+    assert!(src.width() > 0.0);
+    assert!(src.height() > 0.0);
+    assert!(dst.width() > 0.0);
+    assert!(dst.height() > 0.0);
+
+    let (mut sx, mut sy) = (dst.width() / src.width(), dst.height() / src.height());
+    match fit {
+        Fit::Meet => {
+            sx = sx.min(sy);
+            sy = sx;
+        }
+        Fit::Slice => {
+            sx = sx.max(sy);
+            sy = sx;
+        }
+        Fit::Stretch => (),
+    }
+    if flip_y {
+        sy = -sy;
+    }
+
+    Affine::translate(-src.center().to_vec2())
+        .then_scale_non_uniform(sx, sy)
+        .then_translate(dst.center().to_vec2())
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::{Point, Rect};
+
+    use kurbo::{BezPath, Shape};
+
+    use super::{rect_to_rect, Fit, FontToOutput, FontUnits, Lerp, OutputUnits, Precision};
+
+    fn assert_near(expected: Rect, actual: Rect) {
+        let close = [
+            (expected.x0, actual.x0),
+            (expected.y0, actual.y0),
+            (expected.x1, actual.x1),
+            (expected.y1, actual.y1),
+        ]
+        .iter()
+        .all(|(e, a)| (e - a).abs() < 1e-9);
+        assert!(close, "{expected:?} != {actual:?}");
+    }
+
+    #[test]
+    fn font_units_to_24dp() {
+        let upem = Rect::new(0.0, 0.0, 960.0, 960.0);
+        let dest = Rect::new(0.0, 0.0, 24.0, 24.0);
+        let transform = rect_to_rect(upem, dest, true, Fit::Meet);
+        assert_near(dest, transform.transform_rect_bbox(upem));
+        // y-up origin is the bottom left
+        assert_eq!(Point::new(0.0, 24.0), transform * Point::ZERO);
+    }
+
+    #[test]
+    fn font_to_output_flips() {
+        let upem = Rect::new(0.0, 0.0, 1000.0, 1000.0);
+        let transform = FontToOutput::fit(
+            FontUnits(upem),
+            OutputUnits(Rect::new(0.0, 0.0, 100.0, 100.0)),
+            Fit::Meet,
+        );
+        assert_eq!(
+            OutputUnits(Point::new(10.0, 90.0)),
+            transform.apply(FontUnits(Point::new(100.0, 100.0)))
+        );
+        assert_near(
+            Rect::new(0.0, 0.0, 100.0, 100.0),
+            transform.apply_rect(FontUnits(upem)).0,
+        );
+    }
+
+    #[test]
+    fn lerp_compatible_paths() {
+        let small = Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1);
+        let large = Rect::new(0.0, 0.0, 30.0, 30.0).to_path(0.1);
+        assert_eq!(
+            Rect::new(0.0, 0.0, 20.0, 20.0).to_path(0.1),
+            small.lerp(&large, 0.5)
+        );
+    }
+
+    #[test]
+    fn lerp_incompatible_paths_holds() {
+        let square = Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1);
+        let mut line = BezPath::new();
+        line.move_to((0.0, 0.0));
+        line.line_to((5.0, 5.0));
+        assert_eq!(square, square.lerp(&line, 0.9));
+        assert_eq!(line, square.lerp(&line, 1.0));
+    }
+
+    #[test]
+    fn no_flip_is_just_scale() {
+        let src = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let transform = rect_to_rect(src, Rect::new(0.0, 0.0, 20.0, 20.0), false, Fit::Meet);
+        assert_eq!(Point::new(20.0, 4.0), transform * Point::new(10.0, 2.0));
+    }
+
+    #[test]
+    fn meet_wide_dest() {
+        let src = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let dest = Rect::new(0.0, 0.0, 40.0, 20.0);
+        let transform = rect_to_rect(src, dest, true, Fit::Meet);
+        assert_near(
+            Rect::new(10.0, 0.0, 30.0, 20.0),
+            transform.transform_rect_bbox(src),
+        );
+    }
+
+    #[test]
+    fn slice_wide_dest() {
+        let src = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let dest = Rect::new(0.0, 0.0, 40.0, 20.0);
+        let transform = rect_to_rect(src, dest, true, Fit::Slice);
+        assert_near(
+            Rect::new(0.0, -10.0, 40.0, 30.0),
+            transform.transform_rect_bbox(src),
+        );
+    }
+
+    #[test]
+    fn stretch_wide_dest() {
+        let src = Rect::new(-5.0, -5.0, 5.0, 5.0);
+        let dest = Rect::new(100.0, 100.0, 140.0, 120.0);
+        let transform = rect_to_rect(src, dest, true, Fit::Stretch);
+        assert_near(dest, transform.transform_rect_bbox(src));
+        assert_eq!(Point::new(100.0, 120.0), transform * Point::new(-5.0, -5.0));
+    }
+
+    #[test]
+    fn precision_rounds_path() {
+        let mut path = BezPath::new();
+        path.move_to((1.23456, -0.0001));
+        path.quad_to((2.0 / 3.0, 7.777), (10.0, 10.006));
+        path.close_path();
+        assert_eq!(
+            "M1.23,0 Q0.67,7.78 10,10.01 Z",
+            Precision(2).round_path(&path).to_svg()
+        );
+    }
 }
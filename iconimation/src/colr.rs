@@ -0,0 +1,135 @@
+//! Color glyphs: [COLR](https://learn.microsoft.com/en-us/typography/opentype/spec/colr) layers,
+//! colored from the first [CPAL](https://learn.microsoft.com/en-us/typography/opentype/spec/cpal) palette
+//!
+//! Only COLRv0, a stack of solid colored glyphs, is read. Each layer becomes a group of its own
+//! so it can be animated independently.
+
+use skrifa::{
+    raw::{tables::cpal::Cpal, FontRef, ReadError, TableProvider},
+    GlyphId,
+};
+
+/// A layer of a color glyph; layers are drawn in order, the first at the bottom
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColorLayer {
+    pub gid: GlyphId,
+    /// None for the foreground, e.g. text, color
+    pub color: Option<(u8, u8, u8)>,
+}
+
+/// CPAL's palette index for "use the foreground color"
+const FOREGROUND: u16 = 0xFFFF;
+
+/// The layers of `gid`, None if it isn't a color glyph
+pub fn color_layers(font: &FontRef, gid: GlyphId) -> Result<Option<Vec<ColorLayer>>, ReadError> {
+    let Ok(colr) = font.colr() else {
+        return Ok(None);
+    };
+    let Some(layers) = colr.v0_base_glyph(gid)? else {
+        return Ok(None);
+    };
+    let cpal = font.cpal().ok();
+    layers
+        .map(|i| {
+            let (gid, palette_index) = colr.v0_layer(i)?;
+            Ok(ColorLayer {
+                gid,
+                color: cpal
+                    .as_ref()
+                    .and_then(|cpal| palette_color(cpal, palette_index)),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+/// The color at `index` in the first palette
+fn palette_color(cpal: &Cpal, index: u16) -> Option<(u8, u8, u8)> {
+    if index == FOREGROUND {
+        return None;
+    }
+    let first = cpal.color_record_indices().first()?.get() as usize;
+    let records = cpal.color_records_array()?.ok()?;
+    let record = records.get(first + index as usize)?;
+    Some((record.red(), record.green(), record.blue()))
+}
+
+#[cfg(test)]
+mod tests {
+    use skrifa::{raw::FontRef, GlyphId};
+
+    use super::{color_layers, ColorLayer};
+
+    /// A font of nothing but `tables`, each a tag and its data
+    fn font_of(mut tables: Vec<(&[u8; 4], Vec<u8>)>) -> Vec<u8> {
+        tables.sort_by_key(|(tag, _)| **tag);
+        let mut font = Vec::new();
+        font.extend(0x00010000u32.to_be_bytes());
+        font.extend((tables.len() as u16).to_be_bytes());
+        font.extend([0u8; 6]); // searchRange, entrySelector, rangeShift
+        let mut offset = 12 + 16 * tables.len();
+        for (tag, data) in tables.iter() {
+            font.extend(*tag);
+            font.extend(0u32.to_be_bytes()); // checksum, unchecked
+            font.extend((offset as u32).to_be_bytes());
+            font.extend((data.len() as u32).to_be_bytes());
+            offset += data.len().next_multiple_of(4);
+        }
+        for (_, data) in tables {
+            let padding = data.len().next_multiple_of(4) - data.len();
+            font.extend(data);
+            font.extend(vec![0u8; padding]);
+        }
+        font
+    }
+
+    fn u16s(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    /// COLRv0 with glyph 1 drawn as glyphs 2, 3 and 4 colored by palette entries 0,
+    /// foreground and 1
+    fn colr() -> Vec<u8> {
+        let mut colr = u16s(&[0, 1]); // version, numBaseGlyphRecords
+        colr.extend(14u32.to_be_bytes()); // baseGlyphRecordsOffset
+        colr.extend(20u32.to_be_bytes()); // layerRecordsOffset
+        colr.extend(u16s(&[3])); // numLayerRecords
+        colr.extend(u16s(&[1, 0, 3])); // glyphID, firstLayerIndex, numLayers
+        colr.extend(u16s(&[2, 0, 3, 0xFFFF, 4, 1])); // glyphID, paletteIndex
+        colr
+    }
+
+    /// CPAL with one palette of red then blue
+    fn cpal() -> Vec<u8> {
+        // version, numPaletteEntries, numPalettes, numColorRecords
+        let mut cpal = u16s(&[0, 2, 1, 2]);
+        cpal.extend(14u32.to_be_bytes()); // colorRecordsArrayOffset
+        cpal.extend(u16s(&[0])); // colorRecordIndices
+        cpal.extend([0, 0, 0xFF, 0xFF, 0xFF, 0, 0, 0xFF]); // BGRA
+        cpal
+    }
+
+    #[test]
+    fn layers_take_palette_colors() {
+        let data = font_of(vec![(b"COLR", colr()), (b"CPAL", cpal())]);
+        let font = FontRef::new(&data).unwrap();
+        assert_eq!(
+            Some(vec![
+                ColorLayer {
+                    gid: GlyphId::new(2),
+                    color: Some((0xFF, 0, 0)),
+                },
+                ColorLayer {
+                    gid: GlyphId::new(3),
+                    color: None,
+                },
+                ColorLayer {
+                    gid: GlyphId::new(4),
+                    color: Some((0, 0, 0xFF)),
+                },
+            ]),
+            color_layers(&font, GlyphId::new(1)).unwrap()
+        );
+        assert_eq!(None, color_layers(&font, GlyphId::new(2)).unwrap());
+    }
+}
@@ -0,0 +1,210 @@
+//! Deprecated shims for the Template/Animator API that predates plans and the IR
+//!
+//! Existing integrations keep compiling while they migrate:
+//!
+//! * `default_template` + `Template::replace_shape` => [`crate::ir::Animation::of_command`]
+//!   or [`crate::ir::Animation::of_icon`] then [`crate::ir::FromAnimation`] for the output format,
+//!   or [`crate::lottie::inject_into_template`] to keep using a template
+//! * `animate::Animator` => an [`crate::plan::AnimationPlan`] such as `pulse-whole` or `twirl-whole`
+//!
+//! A template placeholder is a group at the top of a shape layer. Rectangles in it mark where
+//! the glyph goes; its transform is what an [`animate::Animator`] animates.
+#![allow(deprecated)]
+
+use bodymovin::{
+    layers::{AnyLayer, ShapeMixin},
+    properties::Value,
+    shapes::{AnyShape, Fill, Group, Rect as LottieRect, Transform},
+    Bodymovin as Lottie,
+};
+use kurbo::{BezPath, Rect};
+
+use crate::{
+    bezop::{Fit, FontToOutput, OutputUnits},
+    error::{Error, LottieError},
+    ir::Keyframed,
+    lottie::{fixed_rect, placeholders_mut, to_lottie_subpath},
+    GlyphShape,
+};
+
+/// A one second, 60fps, template with a single placeholder filling `font_drawbox`
+#[deprecated(note = "use ir::Animation::of_command and FromAnimation")]
+pub fn default_template(font_drawbox: &Rect) -> Lottie {
+    let mut rect = LottieRect::default();
+    rect.position.value = Value::Fixed(vec![font_drawbox.center().x, font_drawbox.center().y]);
+    rect.size.value = Value::Fixed(vec![font_drawbox.width(), font_drawbox.height()]);
+    let placeholder = Group {
+        items: vec![
+            AnyShape::Rect(rect),
+            AnyShape::Fill(Fill::default()),
+            AnyShape::Transform(Transform::default()),
+        ],
+        ..Default::default()
+    };
+    Lottie {
+        in_point: 0.0,
+        out_point: 60.0,
+        frame_rate: 60.0,
+        width: font_drawbox.width() as i64,
+        height: font_drawbox.height() as i64,
+        layers: vec![AnyLayer::Shape(bodymovin::layers::Shape {
+            in_point: 0.0,
+            out_point: 60.0,
+            mixin: ShapeMixin {
+                shapes: vec![AnyShape::Group(placeholder)],
+                ..Default::default()
+            },
+            ..Default::default()
+        })],
+        ..Default::default()
+    }
+}
+
+#[deprecated(note = "use ir::Animation::of_command and FromAnimation")]
+pub trait Template {
+    /// Replace every placeholder rectangle with the glyph, fit to the rectangle
+    fn replace_shape(&mut self, glyph_shape: &GlyphShape) -> Result<(), Error>;
+}
+
+impl Template for Lottie {
+    fn replace_shape(&mut self, glyph_shape: &GlyphShape) -> Result<(), Error> {
+        let (duration, frame_rate) = (self.out_point / self.frame_rate, self.frame_rate);
+        let mut placeholders = 0;
+        let mut replaced = 0;
+        for group in placeholders_mut(self)? {
+            placeholders += 1;
+            let mut items = Vec::with_capacity(group.items.len());
+            for item in group.items.drain(..) {
+                let AnyShape::Rect(rect) = item else {
+                    items.push(item);
+                    continue;
+                };
+                let bounds = fixed_rect(&rect).ok_or(Error::NoShapesUpdated)?;
+                let src_to_dest_units =
+                    FontToOutput::fit(glyph_shape.drawbox(), OutputUnits(bounds), Fit::Meet);
+                let shape =
+                    Keyframed::<BezPath>::for_glyph(duration, src_to_dest_units, glyph_shape)
+                        .map_err(Error::AnimationError)?;
+                let subpaths =
+                    to_lottie_subpath(&shape, frame_rate, None).map_err(Error::LottieError)?;
+                items.extend(subpaths.into_iter().map(AnyShape::Shape));
+                replaced += 1;
+            }
+            group.items = items;
+        }
+        match (placeholders, replaced) {
+            (0, _) => Err(LottieError::NoPlaceholders.into()),
+            (_, 0) => Err(Error::NoShapesUpdated),
+            _ => Ok(()),
+        }
+    }
+}
+
+pub mod animate {
+    //! Deprecated, see [`crate::compat`]
+
+    use bodymovin::{
+        properties::Value,
+        shapes::{AnyShape, Transform},
+        Bodymovin as Lottie,
+    };
+    use kurbo::Point;
+
+    use crate::{
+        bender::MotionBender,
+        error::{AnimationError, Error},
+        ir,
+        lottie::{placeholders_mut, to_lottie_transform},
+    };
+
+    #[deprecated(
+        note = "use a plan, e.g. pulse-whole or twirl-whole, with ir::Animation::of_command"
+    )]
+    pub trait Animator {
+        /// Animate the transform of every placeholder from the first to the last frame of the Lottie
+        fn animate(&self, lottie: &mut Lottie) -> Result<(), Error>;
+    }
+
+    /// Leaves placeholders where they are
+    pub struct Still;
+
+    /// Scale placeholders up and back down
+    pub struct Pulse;
+
+    /// Spin placeholders through a full rotation
+    pub struct Twirl;
+
+    /// Spin placeholders through a full rotation timed by a bender, e.g.
+    /// [`crate::bender::Bender::Bounce`]
+    pub struct BentTwirl<B>(pub B);
+
+    impl Animator for Still {
+        fn animate(&self, lottie: &mut Lottie) -> Result<(), Error> {
+            update_transforms(lottie, |_, _| Ok(()))
+        }
+    }
+
+    impl Animator for Pulse {
+        fn animate(&self, lottie: &mut Lottie) -> Result<(), Error> {
+            update_transforms(lottie, |group, (start, end)| {
+                group.scale = ir::pulse(start, end, 0)?;
+                Ok(())
+            })
+        }
+    }
+
+    impl Animator for Twirl {
+        fn animate(&self, lottie: &mut Lottie) -> Result<(), Error> {
+            update_transforms(lottie, |group, (start, end)| {
+                group.rotate = ir::twirl(start, end, 0)?;
+                Ok(())
+            })
+        }
+    }
+
+    impl<B: MotionBender> Animator for BentTwirl<B> {
+        fn animate(&self, lottie: &mut Lottie) -> Result<(), Error> {
+            let frame_rate = lottie.frame_rate;
+            update_transforms(lottie, |group, (start, end)| {
+                group.rotate = ir::bent(ir::twirl(start, end, 0)?, Some(&self.0), frame_rate);
+                Ok(())
+            })
+        }
+    }
+
+    /// Replace the transform of every placeholder with one produced from an IR group, animated
+    /// from the start to the end, in seconds, of the Lottie
+    fn update_transforms(
+        lottie: &mut Lottie,
+        animate: impl Fn(&mut ir::Group, (f64, f64)) -> Result<(), AnimationError>,
+    ) -> Result<(), Error> {
+        let frame_rate = lottie.frame_rate;
+        let times = (lottie.in_point / frame_rate, lottie.out_point / frame_rate);
+        let mut updated = 0;
+        for placeholder in placeholders_mut(lottie)? {
+            for item in placeholder.items.iter_mut() {
+                let AnyShape::Transform(transform) = item else {
+                    continue;
+                };
+                let mut group = ir::Group {
+                    center: anchor(transform),
+                    ..Default::default()
+                };
+                animate(&mut group, times)?;
+                *transform = to_lottie_transform(&group, frame_rate);
+                updated += 1;
+            }
+        }
+        if updated == 0 {
+            return Err(Error::NoTransformsUpdated);
+        }
+        Ok(())
+    }
+
+    fn anchor(transform: &Transform) -> Point {
+        match &transform.anchor_point.value {
+            Value::Fixed(v) if v.len() >= 2 => Point::new(v[0], v[1]),
+            _ => Point::ZERO,
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! A serializable snapshot of an [`Animation`], every keyframe of every property, for bug reports
+//!
+//! The layout follows the IR and will change when it does; this is for humans, not for reloading.
+
+use kurbo::{BezPath, Point, Vec2};
+use serde::Serialize;
+use skrifa::{raw::FontRef, GlyphId};
+
+use crate::{
+    ir::{Animation, Ease, Element, Group, Keyframed},
+    ligate::gid_to_icon_name,
+};
+
+#[derive(Debug, Serialize)]
+pub struct DebugAnimation {
+    pub gid: Option<u16>,
+    /// Set by [`DebugAnimation::name_icon`]
+    pub icon_name: Option<String>,
+    pub width: f64,
+    pub height: f64,
+    /// Seconds
+    pub duration: f64,
+    pub frame_rate: f64,
+    pub warnings: Vec<String>,
+    pub root: DebugGroup,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DebugGroup {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub center: (f64, f64),
+    pub fill: Option<(u8, u8, u8)>,
+    pub stroke: Option<((u8, u8, u8), f64)>,
+    pub translate: Vec<DebugKeyframe<(f64, f64)>>,
+    pub scale: Vec<DebugKeyframe<(f64, f64)>>,
+    pub rotate: Vec<DebugKeyframe<f64>>,
+    pub skew: Vec<DebugKeyframe<(f64, f64)>>,
+    pub opacity: Vec<DebugKeyframe<f64>>,
+    pub children: Vec<DebugElement>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugElement {
+    Group(DebugGroup),
+    /// Keyframes of svg path data
+    Shape(Vec<DebugKeyframe<String>>),
+}
+
+/// A keyframe, the ease is the CSS-style cubic-bezier (x1, y1, x2, y2) to the next keyframe
+#[derive(Debug, Serialize)]
+pub struct DebugKeyframe<T> {
+    /// Seconds from the start of the animation
+    pub time: f64,
+    pub value: T,
+    pub ease: Option<(f64, f64, f64, f64)>,
+    /// The value holds until the next keyframe rather than easing to it
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hold: bool,
+}
+
+impl From<&Animation> for DebugAnimation {
+    fn from(animation: &Animation) -> Self {
+        let animation = animation.resolved();
+        DebugAnimation {
+            gid: animation.gid().map(|gid| gid.to_u16()),
+            icon_name: None,
+            width: animation.width,
+            height: animation.height,
+            duration: animation.duration,
+            frame_rate: animation.frame_rate,
+            warnings: animation.warnings().to_vec(),
+            root: animation.root().into(),
+        }
+    }
+}
+
+impl DebugAnimation {
+    /// Look up the name of the icon animated, ignoring failure; this is debug output
+    pub fn name_icon(&mut self, font: &FontRef) {
+        self.icon_name = self
+            .gid
+            .and_then(|gid| gid_to_icon_name(font, GlyphId::new(gid)).ok().flatten());
+    }
+}
+
+impl From<&Group> for DebugGroup {
+    fn from(group: &Group) -> Self {
+        DebugGroup {
+            name: group.name().map(str::to_string),
+            center: point(group.center()),
+            fill: group.fill(),
+            stroke: group.stroke().map(|s| (s.color, s.width)),
+            translate: keyframes(&group.translate, |v: &Vec2| (v.x, v.y)),
+            scale: keyframes(&group.scale, |v| *v),
+            rotate: keyframes(&group.rotate, |v| *v),
+            skew: keyframes(&group.skew, |v| *v),
+            opacity: keyframes(&group.opacity, |v| *v),
+            children: group
+                .children()
+                .iter()
+                .map(|e| match e {
+                    Element::Group(g) => DebugElement::Group(g.into()),
+                    Element::Shape(s) => DebugElement::Shape(keyframes(s, BezPath::to_svg)),
+                })
+                .collect(),
+        }
+    }
+}
+
+fn point(p: Point) -> (f64, f64) {
+    (p.x, p.y)
+}
+
+fn keyframes<T, U>(keyframed: &Keyframed<T>, value: impl Fn(&T) -> U) -> Vec<DebugKeyframe<U>> {
+    keyframed
+        .iter()
+        .map(|k| DebugKeyframe {
+            time: k.time,
+            value: value(&k.value),
+            ease: k.ease.map(|Ease { p1, p2 }| (p1.x, p1.y, p2.x, p2.y)),
+            hold: k.hold,
+        })
+        .collect()
+}
@@ -1,86 +1,257 @@
 //! Error types
-use std::num::ParseFloatError;
+//!
+//! [`Error`] is what the crate's entry points, such as [`crate::ir::Animation::of_command`],
+//! return. Each stage has its own error, e.g. [`AnimationError`] or [`LottieError`], that converts
+//! into it with `?` and is its [`std::error::Error::source`]. Every enum is `#[non_exhaustive]`
+//! so match with a wildcard arm.
+use std::{num::ParseFloatError, time::Duration};
 
-use kurbo::BezPath;
-use skrifa::{outline::DrawError, raw::ReadError, GlyphId};
+use kurbo::Point;
+use skrifa::{outline::DrawError, raw::ReadError, GlyphId, Tag};
 use thiserror::Error;
 use write_fonts::types::InvalidTag;
 
-use crate::{ir::Keyframed, spring::AnimatedValueType};
+use crate::spring2cubic::FitQuality;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum Error {
-    #[error("Unable to draw: {0}")]
-    DrawError(skrifa::outline::DrawError),
     #[error("No shapes updated")]
     NoShapesUpdated,
     #[error("No keyframes updated")]
     NoTransformsUpdated,
-    #[error("No placeholders found")]
-    NoPlaceholders,
-    #[error("No outline for {0}")]
-    NoOutline(GlyphId),
-    #[error("Inconsistent number of {0:?} values: {1:?} vs {2:?}")]
-    ValueLengthMismatch(AnimatedValueType, Vec<f64>, Vec<f64>),
-    #[error("{0}")]
-    IconNameError(IconNameError),
+    #[error("Icon lookup failed: {0}")]
+    IconNameError(#[from] IconNameError),
+    #[error("Animation failed: {0}")]
+    AnimationError(#[from] AnimationError),
+    #[error("Lottie output failed: {0}")]
+    LottieError(#[from] LottieError),
+    #[error("AVD output failed: {0}")]
+    AndroidError(#[from] AndroidError),
+    #[error("Pass failed: {0}")]
+    PassError(#[from] PassError),
+    #[error("Spring build failed: {0}")]
+    SpringBuildError(#[from] SpringBuildError),
     #[error("Invalid variation parameters")]
     InvalidLocation,
-    #[error("Invalid tag")]
-    InvalidTag(InvalidTag),
+    #[error("No named instance '{0}'")]
+    NoSuchInstance(String),
+    #[error("Font does not support tag '{0}'")]
+    NoSuchAxis(Tag),
+    #[error("Value {value} for '{tag}' must be in [{min:.2}, {max:.2}]")]
+    OutOfBounds {
+        tag: Tag,
+        value: f32,
+        min: f32,
+        max: f32,
+    },
+    #[error("Invalid tag: {0}")]
+    InvalidTag(#[source] InvalidTag),
     #[error("Invalid f64 {0}")]
     InvalidF64(#[from] ParseFloatError),
-    #[error("No capture for {0} at {1}")]
-    NoCapture(&'static str, usize),
+    /// The command doesn't parse, `position` is in chars from the start of the command
+    #[error("Expected {expected} at {position} but found {found}")]
+    Syntax {
+        position: usize,
+        expected: &'static str,
+        found: String,
+    },
     #[error("Unrecognized command")]
     UnrecognizedCommand,
     #[error("Unrecognized spring")]
     UnrecognizedSpring,
+    #[error("Unrecognized channel '{0}', expected translate, scale, rotate, opacity or variation")]
+    UnrecognizedChannel(String),
+    #[error("No preset named '{0}'")]
+    UnknownPreset(String),
+    #[error("Unrecognized ease '{0}'")]
+    UnrecognizedEase(String),
+    #[error("Unrecognized bender '{0}', expected bounce, elastic or overshoot")]
+    UnrecognizedBender(String),
+    #[error("Unrecognized anchor '{0}', expected bbox, centroid, nearest or x,y")]
+    UnrecognizedAnchor(String),
+    #[error(
+        "Invalid sequence step '{0}', expected icon name, optionally followed by frames and ease"
+    )]
+    InvalidSequenceStep(String),
+    #[error("'{0}' can't be combined with other plans")]
+    NotChainable(String),
+    #[error("Invalid IR json: {0}")]
+    InvalidIrJson(#[source] serde_json::Error),
+    #[error("Invalid part hints json: {0}")]
+    InvalidPartHints(#[source] serde_json::Error),
 }
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum SpringBuildError {
     #[error("Damping must be >= 0")]
     InvalidDamping,
 }
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum IconNameError {
-    #[error("{0}")]
-    ReadError(skrifa::raw::ReadError),
+    #[error(transparent)]
+    ReadError(#[from] ReadError),
     #[error("No character mapping for '{0}'")]
     UnmappedCharError(char),
     #[error("The icon name '{0}' resolved to 0 glyph ids")]
     NoGlyphIds(String),
-    #[error("The icon name '{0}' has no ligature")]
-    NoLigature(String),
+    /// `suggestions` are similarly named icons, closest first
+    #[error("The icon name '{name}' has no ligature{}", did_you_mean(.suggestions))]
+    NoLigature {
+        name: String,
+        suggestions: Vec<String>,
+    },
+}
+
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    format!(", did you mean {}?", suggestions.join(" or "))
 }
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum AnimationError {
     #[error("The 'head' table isn't present, {0}")]
-    NoHeadTable(ReadError),
+    NoHeadTable(#[source] ReadError),
     #[error("Unable to draw {0:?}: {1}")]
-    DrawError(GlyphId, DrawError),
+    DrawError(GlyphId, #[source] DrawError),
     #[error("Must have at least 1 keyframe")]
     NoKeyframes,
-    #[error("Keyframe frame must be unique, multiple definitions of {0}")]
-    MultipleValuesForFrame(f64),
+    #[error("Keyframe times must be unique, multiple definitions at {0}s")]
+    MultipleValuesForTime(f64),
+    #[error("No icon named '{0}' to sequence to")]
+    NoSuchSequenceIcon(String),
+    #[error("No outline for {0}")]
+    NoOutline(GlyphId),
+    #[error("Unable to read color layers: {0}")]
+    ColorLayers(#[source] ReadError),
+    #[error("The plan doesn't have both whole and parts versions")]
+    NoPartsVariant,
+    #[error("Time range {0}s..{1}s is empty")]
+    EmptyTimeRange(f64, f64),
+    #[error("Frame rate must be a positive number, not {0}")]
+    InvalidFrameRate(f64),
+    #[error("Duration must be more than zero, not {0:?}")]
+    InvalidDuration(Duration),
+    #[error("Size must be a positive number, not {0}")]
+    InvalidSize(f64),
+    #[error("Can only stretch by a positive number, not {0}")]
+    InvalidStretch(f64),
+    #[error("Segment '{0}' isn't within the animation")]
+    SegmentOutOfRange(String),
+    #[error("Animations can't be blended, they have {0}")]
+    NotBlendable(&'static str),
+    #[error("Unable to approximate spring: {0}")]
+    CubicApproximationError(#[from] CubicApproximationError),
 }
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum LottieError {
-    #[error("Interpolation-incompatible paths: {0:?}")]
-    IncompatiblePaths(Keyframed<BezPath>),
+    #[error("Interpolation-incompatible paths from frame {from} to {to}, {expected} to {actual}: {mismatch}")]
+    IncompatiblePaths {
+        from: f64,
+        to: f64,
+        expected: String,
+        actual: String,
+        #[source]
+        mismatch: Mismatch,
+    },
+    #[error("The template has no placeholders to replace")]
+    NoPlaceholders,
+    #[error("The template is {template}fps but the animation is {animation}fps")]
+    FrameRateMismatch { template: f64, animation: f64 },
+    #[error("Unable to read Lottie with {0}")]
+    Unsupported(String),
+    #[error("Invalid keyframes in Lottie: {0}")]
+    InvalidKeyframes(#[source] AnimationError),
+    #[error("Unable to write Lottie json: {0}")]
+    Json(#[source] serde_json::Error),
+    #[error("Unable to write {key} to Lottie json, {at:?} isn't an object")]
+    MissingObject { at: String, key: &'static str },
 }
 
 #[derive(Debug, Error)]
-pub enum AndroidError {}
+#[non_exhaustive]
+pub enum AndroidError {
+    #[error("pathData can't express the non-finite coordinate {0:?}")]
+    NonFiniteCoordinate(Point),
+    #[error("AVD morphs whole paths, shapes drawn as one path must share keyframes but have {0:?} and {1:?}")]
+    UnalignedShapes(Vec<f64>, Vec<f64>),
+    #[error("AVD can only morph between paths with the same commands, at {time}s {actual} should be {expected}")]
+    IncompatiblePaths {
+        time: f64,
+        expected: String,
+        actual: String,
+    },
+    #[error("Unable to write AVD xml: {0}")]
+    Io(#[source] std::io::Error),
+}
+
+/// Why two paths aren't interpolation compatible, see [`crate::ir::compatible`]
+///
+/// Commands are as in [`crate::path_commands`], e.g. 'C' for a cubic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum Mismatch {
+    #[error("Element {index} is {found} but should be {expected}")]
+    Command {
+        index: usize,
+        expected: char,
+        found: char,
+    },
+    #[error("Element {index}, {expected}, is missing")]
+    Missing { index: usize, expected: char },
+    #[error("Element {index}, {found}, is extra")]
+    Extra { index: usize, found: char },
+}
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum PassError {
+    #[error("No pass named '{0}'")]
+    NoSuchPass(String),
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CubicApproximationError {
     #[error("Unrecognized spring")]
     UnrecognizedSpring,
     #[error("Input took too long to reach equilibrium")]
     RanTooLong,
+    #[error("Unable to fit cubics closely enough, {0:?}")]
+    PoorFit(FitQuality),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::{AnimationError, CubicApproximationError, Error, LottieError};
+
+    #[test]
+    fn stages_convert_and_chain() {
+        let e: Error = AnimationError::from(CubicApproximationError::RanTooLong).into();
+        assert_eq!(
+            "Animation failed: Unable to approximate spring: Input took too long to reach equilibrium",
+            e.to_string()
+        );
+        // The stage error is the source, and its cause the source of that
+        let source = e.source().unwrap();
+        assert!(source.is::<AnimationError>());
+        assert!(source
+            .source()
+            .is_some_and(|source| source.is::<CubicApproximationError>()));
+
+        let e = LottieError::InvalidKeyframes(AnimationError::NoKeyframes);
+        assert!(e
+            .source()
+            .is_some_and(|source| source.is::<AnimationError>()));
+    }
 }
@@ -0,0 +1,147 @@
+//! What an icon font offers: icons, variation axes, and named instances
+
+use std::{collections::HashMap, str::FromStr};
+
+use skrifa::{raw::FontRef, string::StringId, GlyphId, MetadataProvider, Tag};
+
+use crate::{error::Error, ligate};
+
+/// An icon, reachable by ligature
+#[derive(Debug, Clone, PartialEq)]
+pub struct Icon {
+    pub name: String,
+    pub gid: GlyphId,
+    /// The codepoint, typically private use, that maps directly to the icon if there is one
+    pub codepoint: Option<u32>,
+}
+
+/// A variation axis, values in user units
+#[derive(Debug, Clone, PartialEq)]
+pub struct Axis {
+    pub tag: Tag,
+    pub name: Option<String>,
+    pub min: f32,
+    pub default: f32,
+    pub max: f32,
+}
+
+/// A position in designspace the font names, e.g. Bold
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamedInstance {
+    pub name: Option<String>,
+    /// User coordinates, one per axis
+    pub location: Vec<(Tag, f32)>,
+}
+
+/// Every icon in the font, sorted by name
+pub fn icon_names(font: &FontRef) -> Result<Vec<Icon>, Error> {
+    let mut codepoints = HashMap::new();
+    for (cp, gid) in font.charmap().mappings() {
+        codepoints.entry(gid).or_insert(cp);
+    }
+    Ok(ligate::icon_names(font)
+        .map_err(Error::IconNameError)?
+        .into_iter()
+        .map(|(name, gid)| Icon {
+            name,
+            gid,
+            codepoint: codepoints.get(&gid).copied(),
+        })
+        .collect())
+}
+
+pub fn axes(font: &FontRef) -> Vec<Axis> {
+    font.axes()
+        .iter()
+        .map(|axis| Axis {
+            tag: axis.tag(),
+            name: english_name(font, axis.name_id()),
+            min: axis.min_value(),
+            default: axis.default_value(),
+            max: axis.max_value(),
+        })
+        .collect()
+}
+
+pub fn named_instances(font: &FontRef) -> Vec<NamedInstance> {
+    let axes = font.axes();
+    font.named_instances()
+        .iter()
+        .map(|instance| NamedInstance {
+            name: english_name(font, instance.subfamily_name_id()),
+            location: axes
+                .iter()
+                .map(|axis| axis.tag())
+                .zip(instance.user_coords())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Parse a csv of tag:value or instance:Name, e.g. `wght:700,FILL:1`, later entries override
+/// earlier ones. Values are in user units and must be within the font's axes, see
+/// [`check_location`].
+pub fn parse_location(font: &FontRef, raw: &str) -> Result<Vec<(Tag, f32)>, Error> {
+    let mut location = Vec::new();
+    for kv in raw.split(',') {
+        let parts = kv.split(':').collect::<Vec<_>>();
+        if parts.len() != 2 {
+            return Err(Error::InvalidLocation);
+        }
+        if parts[0] == INSTANCE {
+            location.extend(named_instance_location(font, parts[1])?);
+            continue;
+        }
+        let tag = Tag::from_str(parts[0]).map_err(Error::InvalidTag)?;
+        let value = f32::from_str(parts[1]).map_err(Error::InvalidF64)?;
+        location.push((tag, value));
+    }
+    check_location(font, &location)?;
+    Ok(location)
+}
+
+/// Error if a location names an axis the font lacks or a value outside an axis's range, rather
+/// than let [`skrifa::instance::Location`] quietly ignore or clamp it
+pub fn check_location(font: &FontRef, location: &[(Tag, f32)]) -> Result<(), Error> {
+    let axes = axes(font);
+    for (tag, value) in location {
+        let axis = axes
+            .iter()
+            .find(|axis| axis.tag == *tag)
+            .ok_or(Error::NoSuchAxis(*tag))?;
+        if !(axis.min..=axis.max).contains(value) {
+            return Err(Error::OutOfBounds {
+                tag: *tag,
+                value: *value,
+                min: axis.min,
+                max: axis.max,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Prefix for a named instance in a location, e.g. instance:Bold
+const INSTANCE: &str = "instance";
+
+/// Names are matched ignoring case and whitespace, the DSL doesn't allow spaces in a location
+fn named_instance_location(font: &FontRef, name: &str) -> Result<Vec<(Tag, f32)>, Error> {
+    let normalize = |s: &str| -> String {
+        s.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+    let normalized = normalize(name);
+    named_instances(font)
+        .into_iter()
+        .find(|instance| instance.name.as_deref().map(normalize).as_ref() == Some(&normalized))
+        .map(|instance| instance.location)
+        .ok_or_else(|| Error::NoSuchInstance(name.to_string()))
+}
+
+fn english_name(font: &FontRef, id: StringId) -> Option<String> {
+    font.localized_strings(id)
+        .english_or_first()
+        .map(|s| s.to_string())
+}
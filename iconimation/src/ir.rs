@@ -1,469 +1,4088 @@
 //! An intermediate model of simple animation that can be converted to a playback format
 
-use std::collections::HashSet;
+use std::{borrow::Cow, collections::HashSet, ops::Range, str::FromStr, time::Duration};
 
-use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape as KShape, Vec2};
+use kurbo::{
+    Affine, BezPath, CubicBez, Line, ParamCurve, ParamCurveNearest, PathEl, Point, Rect,
+    Shape as KShape, Vec2,
+};
 use ordered_float::OrderedFloat;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use skrifa::{
     instance::{Location, Size},
     outline::DrawSettings,
-    raw::TableProvider,
-    GlyphId, OutlineGlyph,
+    raw::{types::F2Dot14, FontRef, TableProvider},
+    GlyphId, MetadataProvider, OutlineGlyph,
 };
 use write_fonts::pens::{BezPathPen, TransformPen};
 
 use crate::{
-    bezop::{y_up_to_y_down, ContainedPoint},
-    error::AnimationError,
+    bender::MotionBender,
+    bezop::{ContainedPoint, Fit, FontToOutput, FontUnits, Lerp, OutputUnits},
+    colr::color_layers,
+    error::{AnimationError, Error, Mismatch},
+    labels::guess_labels,
+    ligate::icon_name_to_gid,
+    motion::{cached_motion, with_spring_lookups, SPRING_CACHE},
     nth_group_color,
-    plan::AnimationPlan,
+    observe::{observe_phase, GenerationObserver},
+    pass::Pass,
+    path_command,
+    plan::{
+        Anchor, AnimationPlan, Channel, CompositeStep, Granularity, PartSelection, PlanOptions,
+        SequenceStep, VariationMode, PLAN_FRAME_RATE,
+    },
+    spring::{AnimatedValue, AnimatedValueType, Spring},
     GlyphShape,
 };
+#[cfg(feature = "command")]
+use crate::{observe::observe_result, plan::parse_plan};
+
+/// Version of the serialized form of [`Animation`], bumped whenever that changes incompatibly
+pub const SCHEMA_VERSION: u32 = 2;
 
 /// A single distinct animation in a rectangular space starting at (0,0) and extending to (width, height).
-/// Y-down. Timing expressed in frames which can be converted to time using frame_rate.
-#[derive(Debug, Clone)]
+/// Y-down. Timing expressed in seconds; outputs convert to frames at frame_rate, see
+/// [`Animation::frame_at`], which [`Animation::retime`] changes.
+///
+/// Serializes with a `schema_version`, see [`SCHEMA_VERSION`]; other versions fail to deserialize.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Animation {
+    pub(crate) schema_version: SchemaVersion,
     pub(crate) width: f64,
     pub(crate) height: f64,
-    pub(crate) frames: f64,
+    /// How long the animation lasts, in seconds
+    pub(crate) duration: f64,
     pub(crate) frame_rate: f64,
     pub(crate) root: Group,
     #[allow(unused)]
-    pub(crate) src_to_dest_units: Affine,
+    pub(crate) src_to_dest_units: FontToOutput,
+    /// Problems that didn't prevent producing an animation but that a user may want to know about
+    pub(crate) warnings: Vec<String>,
+    /// The glyph animated, if there is one
+    #[serde(serialize_with = "serialize_gid", deserialize_with = "deserialize_gid")]
+    pub(crate) gid: Option<GlyphId>,
+    /// Named stretches of the animation, in order, see [`Animation::add_segment`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) segments: Vec<Segment>,
+    /// Zoom and pan of everything, see [`Animation::set_camera`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) camera: Option<Camera>,
+}
+
+/// A named stretch of an animation, e.g. an intro, a loop, or an outro, a player may seek to.
+/// Starts and ends in seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub name: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// A view of the whole composition that zooms and pans, e.g. to push in on an icon for emphasis
+///
+/// Zoom is about the center of the composition. Pan moves the view, in output units once zoomed,
+/// so the composition appears to move the other way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    /// Percent, 100 shows the composition as it is
+    pub zoom: Keyframed<f64>,
+    pub pan: Keyframed<Vec2>,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            zoom: Keyframed::new(0.0, 100.0),
+            pan: Keyframed::new(0.0, Vec2::ZERO),
+        }
+    }
+}
+
+impl Camera {
+    /// A group, without children, that moves what it contains as the camera sees it
+    pub(crate) fn to_group(&self, width: f64, height: f64) -> Group {
+        Group {
+            center: Point::new(width / 2.0, height / 2.0),
+            translate: self.pan.clone().map(|pan| -pan),
+            scale: self.zoom.clone().map(|zoom| (zoom, zoom)),
+            ..Default::default()
+        }
+    }
 }
 
 impl Animation {
     /// Rigs an animation to handle a Google-style icon font glyph
     pub fn of_icon(plan: &AnimationPlan, glyph_shape: &GlyphShape) -> Result<Self, AnimationError> {
+        Self::of_icon_with_options(plan, glyph_shape, &PlanOptions::default())
+    }
+
+    /// As [`Animation::of_icon`] but with control over how the plan is realized
+    pub fn of_icon_with_options(
+        plan: &AnimationPlan,
+        glyph_shape: &GlyphShape,
+        options: &PlanOptions,
+    ) -> Result<Self, AnimationError> {
+        Self::build(plan, glyph_shape, options, &mut ())
+    }
+
+    fn build(
+        plan: &AnimationPlan,
+        glyph_shape: &GlyphShape,
+        options: &PlanOptions,
+        observer: &mut dyn GenerationObserver,
+    ) -> Result<Self, AnimationError> {
+        let (animation, lookups) = with_spring_lookups(|| {
+            observe_phase(observer, "animate", || {
+                Self::animate(plan, glyph_shape, options)
+            })
+        });
+        for hit in lookups {
+            observer.cache_lookup(SPRING_CACHE, hit);
+        }
+        let mut animation = animation?;
+        options.passes.run_observed(&mut animation, observer);
+        Ok(animation)
+    }
+
+    /// The whole-icon and per-part versions of a plan such as `pulse` or `twirl-whole`, in that order
+    ///
+    /// The glyph is drawn once and shared. Fails with [`AnimationError::NoPartsVariant`] for plans,
+    /// such as `rotate`, that don't come in both versions.
+    pub fn of_icon_whole_and_parts(
+        plan: &AnimationPlan,
+        glyph_shape: &GlyphShape,
+        options: &PlanOptions,
+    ) -> Result<(Self, Self), AnimationError> {
+        let (Some(whole_plan), Some(parts_plan)) = (
+            plan.with_granularity(Granularity::Whole),
+            plan.with_granularity(Granularity::Parts),
+        ) else {
+            return Err(AnimationError::NoPartsVariant);
+        };
+        let rigged = Self::rig(plan, glyph_shape, options)?;
+        let mut whole = rigged
+            .clone()
+            .apply(&whole_plan, Direction::Forward, options)?;
+        let mut parts = rigged.apply(&parts_plan, Direction::Forward, options)?;
+        options.passes.run(&mut whole);
+        options.passes.run(&mut parts);
+        Ok((whole, parts))
+    }
+
+    /// Rig the animation described by plan, without running [`PlanOptions::passes`]
+    fn animate(
+        plan: &AnimationPlan,
+        glyph_shape: &GlyphShape,
+        options: &PlanOptions,
+    ) -> Result<Self, AnimationError> {
+        Self::rig(plan, glyph_shape, options)?.apply(plan, Direction::Forward, options)
+    }
+
+    /// The plan played forward and then backward, e.g. play => pause and pause => play
+    ///
+    /// Both start from rest. Springs are rerun from where the forward animation finishes back to
+    /// where it started rather than having their keyframes reversed, so the backward animation
+    /// settles as a spring should. Shape changes, such as variation, are simply reversed.
+    pub fn toggle_of_icon(
+        plan: &AnimationPlan,
+        glyph_shape: &GlyphShape,
+        options: &PlanOptions,
+    ) -> Result<(Self, Self), AnimationError> {
+        let rigged = Self::rig(plan, glyph_shape, options)?;
+        let mut backward = rigged.clone();
+        backward.visit_mut(&mut ReverseShapes);
+        let mut forward = rigged.apply(plan, Direction::Forward, options)?;
+        let mut backward = backward.apply(plan, Direction::Backward, options)?;
+        options.passes.run(&mut forward);
+        options.passes.run(&mut backward);
+        Ok((forward, backward))
+    }
+
+    /// Draw the glyph as plan requires, without any transform animation
+    fn rig(
+        plan: &AnimationPlan,
+        glyph_shape: &GlyphShape,
+        options: &PlanOptions,
+    ) -> Result<Self, AnimationError> {
         let upem = glyph_shape
             .font
             .head()
             .map_err(AnimationError::NoHeadTable)?
             .units_per_em() as f64;
         let upem_box = Rect::new(0.0, 0.0, upem, upem);
-        let src_to_dest_units = y_up_to_y_down(upem_box, upem_box);
+        let src_to_dest_units =
+            FontToOutput::fit(FontUnits(upem_box), OutputUnits(upem_box), Fit::Meet);
 
         let mut animation = Self {
+            schema_version: SchemaVersion,
             width: upem,
             height: upem,
-            frames: 60.0,
-            frame_rate: 60.0,
+            duration: plan.frames() / PLAN_FRAME_RATE,
+            frame_rate: PLAN_FRAME_RATE,
             root: Group::default(),
             src_to_dest_units,
+            warnings: Vec::new(),
+            gid: Some(glyph_shape.gid),
+            segments: Vec::new(),
+            camera: None,
         };
         let mut root = Group {
             center: (upem / 2.0, upem / 2.0).into(),
             ..Default::default()
         };
-        root.children
-            .push(Element::Shape(Keyframed::<BezPath>::for_glyph(
-                animation.frames,
-                src_to_dest_units,
-                glyph_shape,
-            )?));
-        root.animate(&animation, plan);
+        let shape_of = |glyph_shape: &GlyphShape| match plan {
+            AnimationPlan::Sequence(_, steps) => {
+                Keyframed::<BezPath>::for_sequence(src_to_dest_units, glyph_shape, steps)
+            }
+            _ => match options.variation {
+                VariationMode::Interpolate => Keyframed::<BezPath>::for_glyph(
+                    animation.duration,
+                    src_to_dest_units,
+                    glyph_shape,
+                ),
+                VariationMode::Redraw => Keyframed::<BezPath>::for_glyph_redrawn(
+                    plan,
+                    animation.duration,
+                    src_to_dest_units,
+                    glyph_shape,
+                ),
+            },
+        };
+        let layers =
+            color_layers(glyph_shape.font, glyph_shape.gid).map_err(AnimationError::ColorLayers)?;
+        match layers {
+            // A group per layer so parts plans animate each layer
+            Some(layers) => {
+                for layer in layers {
+                    let layer_shape = GlyphShape::new(
+                        glyph_shape.font,
+                        layer.gid,
+                        glyph_shape.start.clone(),
+                        glyph_shape.end.clone(),
+                    )
+                    .map_err(|_| AnimationError::NoOutline(layer.gid))?
+                    .with_steps(glyph_shape.steps.clone());
+                    root.children.push(Element::Group(Group {
+                        children: vec![Element::Shape(shape_of(&layer_shape)?)],
+                        center: root.center,
+                        fill: layer.color,
+                        ..Default::default()
+                    }));
+                }
+            }
+            None => root.children.push(Element::Shape(shape_of(glyph_shape)?)),
+        }
         animation.root = root;
-
         Ok(animation)
     }
-}
 
-/// Create something form [`Animation`], typically an output format
-pub trait FromAnimation
-where
-    Self: Sized,
-{
-    type Err;
+    /// Animate the transforms of a [`Animation::rig`]ged animation as plan requires
+    fn apply(
+        mut self,
+        plan: &AnimationPlan,
+        direction: Direction,
+        options: &PlanOptions,
+    ) -> Result<Self, AnimationError> {
+        let mut root = std::mem::take(&mut self.root);
+        root.animate(self.duration, plan, direction, options)?;
+        self.root = root;
+        self.segments = plan.segments();
+        if direction == Direction::Backward {
+            self.segments.reverse();
+            for segment in self.segments.iter_mut() {
+                (segment.start, segment.end) =
+                    (self.duration - segment.end, self.duration - segment.start);
+            }
+        }
+        if let Some(contain) = &options.overshoot {
+            contain.run(&mut self);
+        }
+        if let Some(duration) = options.duration {
+            self.set_duration(duration)?;
+        }
+        if let Some(frame_rate) = options.frame_rate {
+            self.retime(frame_rate)?;
+        }
+        if let Some(size) = options.size {
+            self.set_size(size)?;
+        }
+        Ok(self)
+    }
 
-    // Required method
-    fn from_animation(s: &Animation) -> Result<Self, Self::Err>;
-}
+    /// Rigs an animation directly, without an [`AnimationPlan`], for custom choreography
+    ///
+    /// The glyph is drawn at each location to make shape keyframes and the transforms are
+    /// applied about the center of the drawbox. Times, in seconds, need not be sorted but must be
+    /// unique within each kind of keyframe. The animation lasts until the last time given, or a
+    /// second if that is 0. No passes are run; see [`crate::pass::Pipeline::run`].
+    pub fn of_glyph_at_locations(
+        font: &FontRef,
+        gid: GlyphId,
+        locations: &[(f64, Location)],
+        transforms: &[(f64, Transform)],
+    ) -> Result<Self, AnimationError> {
+        let upem = font
+            .head()
+            .map_err(AnimationError::NoHeadTable)?
+            .units_per_em() as f64;
+        let upem_box = Rect::new(0.0, 0.0, upem, upem);
+        let src_to_dest_units =
+            FontToOutput::fit(FontUnits(upem_box), OutputUnits(upem_box), Fit::Meet);
+        let glyph = font
+            .outline_glyphs()
+            .get(gid)
+            .ok_or(AnimationError::NoOutline(gid))?;
 
-/// A set of groups or shapes that animate as one
-///
-/// Only element permitted transform-based animation and definition of fill
-///
-/// Transformation is given in terms of position, scale, and rotation around an anchor
-/// because expressing rotate around point in affine form is tiresome.
-#[derive(Debug, Clone)]
-pub(crate) struct Group {
-    pub(crate) children: Vec<Element>,
-    pub(crate) center: Point,
-    pub(crate) fill: Option<(u8, u8, u8)>,
-    pub(crate) translate: Keyframed<Vec2>,
-    pub(crate) scale: Keyframed<(f64, f64)>,
-    pub(crate) rotate: Keyframed<f64>,
-}
+        let shape: Keyframed<BezPath> = locations
+            .iter()
+            .map(|(time, location)| {
+                draw(src_to_dest_units, location, gid, &glyph).map(|path| (*time, path))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .try_into()?;
 
-impl Default for Group {
-    fn default() -> Self {
-        Self {
-            children: Default::default(),
-            center: Point::default(),
-            fill: None,
-            translate: Keyframed::new(0.0, Vec2::default()),
-            scale: Keyframed::new(0.0, (100.0, 100.0)),
-            rotate: Keyframed::new(0.0, 0.0),
+        let mut root = Group {
+            center: (upem / 2.0, upem / 2.0).into(),
+            ..Default::default()
+        };
+        let mut translate = Vec::new();
+        let mut scale = Vec::new();
+        let mut rotate = Vec::new();
+        let mut skew = Vec::new();
+        for (time, transform) in transforms.iter().copied() {
+            match transform {
+                Transform::Translate(v) => translate.push((time, v)),
+                Transform::Scale(x, y) => scale.push((time, (x, y))),
+                Transform::Rotate(degrees) => rotate.push((time, degrees)),
+                Transform::Skew(degrees, axis) => skew.push((time, (degrees, axis))),
+            }
+        }
+        if !translate.is_empty() {
+            root.translate = translate.try_into()?;
+        }
+        if !scale.is_empty() {
+            root.scale = scale.try_into()?;
+        }
+        if !rotate.is_empty() {
+            root.rotate = rotate.try_into()?;
+        }
+        if !skew.is_empty() {
+            root.skew = skew.try_into()?;
+        }
+        root.children.push(Element::Shape(shape));
+
+        let last_time = locations
+            .iter()
+            .map(|(time, _)| *time)
+            .chain(transforms.iter().map(|(time, _)| *time))
+            .fold(0.0, f64::max);
+        Ok(Self {
+            schema_version: SchemaVersion,
+            width: upem,
+            height: upem,
+            duration: if last_time > 0.0 { last_time } else { 1.0 },
+            frame_rate: 60.0,
+            root,
+            src_to_dest_units,
+            warnings: Vec::new(),
+            gid: Some(gid),
+            segments: Vec::new(),
+            camera: None,
+        })
+    }
+
+    #[cfg(feature = "command")]
+    /// Parses a command and rigs the animation it describes
+    ///
+    /// If [`PlanOptions::placeholder_on_error`] is set an icon that isn't in the font, has no outline
+    /// or can't be drawn is replaced by [`Animation::placeholder`] and the substitution noted in [`Animation::warnings`].
+    pub fn of_command(font: &FontRef, command: &str, options: &PlanOptions) -> Result<Self, Error> {
+        Self::of_command_observed(font, command, options, &mut ())
+    }
+
+    #[cfg(feature = "command")]
+    /// As [`Animation::of_command`], reporting progress to `observer`
+    pub fn of_command_observed(
+        font: &FontRef,
+        command: &str,
+        options: &PlanOptions,
+        observer: &mut dyn GenerationObserver,
+    ) -> Result<Self, Error> {
+        let mut result = Self::of_command_internal(font, command, options, observer);
+        if let Ok(animation) = result.as_mut() {
+            observe_result(observer, animation);
         }
+        result
     }
-}
 
-impl Group {
-    fn animate(&mut self, container: &Animation, plan: &AnimationPlan) {
-        // Variation is apply when creating a shape; here apply transform-based animation
-        match plan {
-            AnimationPlan::None(..) => (),
-            AnimationPlan::TwirlWhole(..) => self.rotate = twirl(0.0, container.frames, 0),
-            AnimationPlan::TwirlParts(..) => {
-                self.group_parts();
-                for (i, g) in self.mutable_child_groups().enumerate() {
-                    g.rotate = twirl(0.0, container.frames, i);
-                }
-            }
-            AnimationPlan::PulseWhole(..) => self.scale = pulse(0.0, container.frames, 0),
-            AnimationPlan::PulseParts(..) => {
-                self.group_parts();
-                for (i, g) in self.mutable_child_groups().enumerate() {
-                    g.scale = pulse(0.0, container.frames, i);
-                }
+    #[cfg(feature = "command")]
+    fn of_command_internal(
+        font: &FontRef,
+        command: &str,
+        options: &PlanOptions,
+        observer: &mut dyn GenerationObserver,
+    ) -> Result<Self, Error> {
+        let result = observe_phase(observer, "parse", || parse_plan(font, command)).and_then(
+            |(plan, glyph_shape)| {
+                Self::build(&plan, &glyph_shape, options, observer).map_err(Error::AnimationError)
+            },
+        );
+        match result {
+            Err(
+                e @ (Error::IconNameError(..)
+                | Error::AnimationError(
+                    AnimationError::NoOutline(..) | AnimationError::DrawError(..),
+                )),
+            ) if options.placeholder_on_error => {
+                let upem = font
+                    .head()
+                    .map_err(|e| Error::AnimationError(AnimationError::NoHeadTable(e)))?
+                    .units_per_em() as f64;
+                Ok(Self::placeholder(
+                    upem,
+                    format!("Substituted a placeholder for '{command}': {e}"),
+                ))
             }
-            _ => todo!("Not implemented: {plan:?}"),
+            result => result,
         }
     }
 
-    fn mutable_child_groups(&mut self) -> impl Iterator<Item = &mut Group> {
-        self.children.iter_mut().filter_map(|e| match e {
-            Element::Group(g) => Some(g),
-            Element::Shape(..) => None,
-        })
+    /// An animation of `root`, `duration` long at 60fps, in a `width` by `height` space; for
+    /// animations built in code rather than from a command
+    pub fn new(width: f64, height: f64, duration: Duration, root: Group) -> Self {
+        let space = Rect::new(0.0, 0.0, width, height);
+        Self {
+            schema_version: SchemaVersion,
+            width,
+            height,
+            duration: duration.as_secs_f64(),
+            frame_rate: PLAN_FRAME_RATE,
+            root,
+            src_to_dest_units: FontToOutput::fit(FontUnits(space), OutputUnits(space), Fit::Meet),
+            warnings: Vec::new(),
+            gid: None,
+            segments: Vec::new(),
+            camera: None,
+        }
     }
-}
 
-/// Produces keyframes suitable for use with [`Group::rotate`]
-fn twirl(start: f64, end: f64, nth_group: usize) -> Keyframed<f64> {
-    assert!(end > start);
-    let nth_group = nth_group as f64;
-    vec![
-        (0.2 * (end - start) * nth_group, 0.0),
-        (0.2 * (end - start) * (nth_group + 2.0), 360.0),
-    ]
-    .try_into()
-    .unwrap()
-}
+    /// A motionless outlined box, the traditional rendering of a missing glyph, in a upem square
+    pub fn placeholder(upem: f64, warning: String) -> Self {
+        let outer = Rect::new(0.0, 0.0, upem, upem).inset(-0.1 * upem);
+        let inner = outer.inset(-0.08 * upem);
+        let mut path = outer.to_path(0.1);
+        path.extend(inner.to_path(0.1).reverse_subpaths());
 
-/// Produces keyframes suitable for use with [`Group::scale`]
-fn pulse(start: f64, end: f64, nth_group: usize) -> Keyframed<(f64, f64)> {
-    assert!(end > start);
-    let nth_group = nth_group as f64;
-    vec![
-        (0.2 * (end - start) * nth_group, (100.0, 100.0)),
-        (0.2 * (end - start) * (nth_group + 1.0), (150.0, 150.0)),
-        (0.2 * (end - start) * (nth_group + 2.0), (100.0, 100.0)),
-    ]
-    .try_into()
-    .unwrap()
-}
+        let mut root = Group {
+            center: (upem / 2.0, upem / 2.0).into(),
+            ..Default::default()
+        };
+        root.children
+            .push(Element::Shape(Keyframed::new(0.0, path)));
+        Self {
+            schema_version: SchemaVersion,
+            width: upem,
+            height: upem,
+            duration: 1.0,
+            frame_rate: PLAN_FRAME_RATE,
+            root,
+            src_to_dest_units: FontToOutput::fit(
+                FontUnits(Rect::new(0.0, 0.0, upem, upem)),
+                OutputUnits(Rect::new(0.0, 0.0, upem, upem)),
+                Fit::Meet,
+            ),
+            warnings: vec![warning],
+            gid: None,
+            segments: Vec::new(),
+            camera: None,
+        }
+    }
 
-/// Piece-wise animation wants to animate "parts" as the eye perceives them; try to so group
-///
-/// Most importantly, if we have a shape and hole(s) cut out of it they should be together.
-///
-/// Make some simplifying assumptions:
-///
-/// 1. Icons don't typically use one subpath to cut a hole in many other subpaths
-/// 1. Icons typically fully contain the holepunch within the ... punchee?
-///
-/// Since we are using non-zero fill, figure out shape by shape what the winding value is. Initially I thought
-/// we could simply look at the direction from [`BezPath::area`] but that ofc isn't enough to know if the final
-/// winding is nonzero.
-fn group_parts(shapes: Vec<Keyframed<BezPath>>) -> Vec<Group> {
-    // group on subpaths; input may have multi-subpath beziers
-    let shapes: Vec<_> = shapes.into_iter().flat_map(|s| s.subpaths()).collect();
+    /// Load an animation serialized with serde, e.g. by `iconimation-cli --emit ir`
+    ///
+    /// Fails if the json is malformed, breaks an invariant such as keyframes being non-empty,
+    /// or is from a different [`SCHEMA_VERSION`].
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(Error::InvalidIrJson)
+    }
 
-    let paths: Vec<_> = shapes.iter().map(|s| &s.earliest().value).collect();
+    /// Only the part of the animation within `range`, in seconds, moved to start at 0
+    ///
+    /// Values at the ends of the range are interpolated, eases split so motion is unchanged,
+    /// e.g. to cut a settle-only variant from the end of a spring.
+    pub fn extract(&self, range: Range<f64>) -> Result<Self, AnimationError> {
+        if range.is_empty() {
+            return Err(AnimationError::EmptyTimeRange(range.start, range.end));
+        }
+        let mut result = self.clone();
+        result.duration = range.end - range.start;
+        result.resolve_shared();
+        result.segments = self
+            .segments
+            .iter()
+            .filter(|s| s.start < range.end && s.end > range.start)
+            .map(|s| Segment {
+                name: s.name.clone(),
+                start: s.start.max(range.start) - range.start,
+                end: s.end.min(range.end) - range.start,
+            })
+            .collect();
+        result.visit_mut(&mut Extract(range));
+        Ok(result)
+    }
 
-    // Figure out what is/isn't filled
-    let filled: Vec<_> = paths
-        .iter()
-        .map(|bez| {
-            let Some(contained) = bez.contained_point() else {
-                if bez.area() != 0.0 {
-                    eprintln!("THERE IS NO CONTAINED POINT?! {}", bez.to_svg());
-                }
-                return false;
-            };
-            let winding: i32 = paths.iter().map(|bez| bez.winding(contained)).sum();
-            winding != 0
-        })
-        .collect();
+    /// Mix two animations of the same rig, e.g. a twirl with a pulse, or an animation with a
+    /// still version of itself to dial down its intensity
+    ///
+    /// `weight` is how much of `b` to use at each frame, 0 is all `a` and 1 is all `b`. The mix
+    /// is sampled every frame and re-fit to linear keyframes. Fill, stroke, the camera and the
+    /// glyph are taken from `a`.
+    pub fn blend(a: &Self, b: &Self, weight: &Keyframed<f64>) -> Result<Self, AnimationError> {
+        if a.frame_rate != b.frame_rate {
+            return Err(AnimationError::NotBlendable("different frame rates"));
+        }
+        let duration = a.duration.max(b.duration);
+        let frames = to_frames(duration, a.frame_rate);
+        let mut samples: Vec<_> = (0..=frames.floor() as usize)
+            .map(|f| f as f64 / a.frame_rate)
+            .collect();
+        if frames.fract() > 0.0 {
+            samples.push(duration);
+        }
+        let blend = Blend { weight, samples };
 
-    // Sort filled ahead of unfilled, smaller before larger (to simplify matching below)
-    let mut ordered: Vec<_> = (0..shapes.len()).collect();
-    ordered.sort_by_cached_key(|i| {
-        (
-            -(filled[*i] as i32),
-            OrderedFloat(paths[*i].bounding_box().area()),
-        )
-    });
+        let mut result = a.clone();
+        result.duration = duration;
+        result.root = blend.group(&a.resolved().root, &b.resolved().root)?;
+        result.warnings.extend(b.warnings.iter().cloned());
+        Ok(result)
+    }
 
-    // Group cutouts with the smallest containing filled subpath
-    // Doesn't generalize but perhaps suffices for icons
-    // In each group [0] must exist and is a filled subpath, [1..n] are optional and are unfilled
-    let mut groups: Vec<Vec<Keyframed<BezPath>>> = Default::default();
-    let mut bboxes = Vec::default(); // the bbox of group[n][0] is bbox[n]
-    for i in ordered {
-        let bez = &paths[i];
-        let shape = &shapes[i];
-        let bbox = bez.bounding_box();
-        if filled[i] {
-            // start a new group for a filled subpath
-            groups.push(vec![shape.clone()]);
-            bboxes.push(bbox);
-        } else {
-            // add cutout to the smallest (first, courtesy of sort above) containing filled subpath
-            if let Some(i) = bboxes
-                .iter()
-                .position(|group_bbox| group_bbox.intersect(bbox) == bbox)
-            {
-                groups[i].push(shape.clone());
-            } else {
-                eprintln!(
-                    "Uh oh, we have an unfilled shape that didn't land anywhere! {}",
-                    bez.to_svg()
-                );
-            }
+    /// Give every group its own copy of any motion it shares, see [`SharedMotion`]
+    pub fn resolve_shared(&mut self) {
+        self.root.resolve_shared();
+    }
+
+    /// As [`Animation::resolve_shared`], borrowing self if nothing is shared
+    pub fn resolved(&self) -> Cow<'_, Self> {
+        if !self.root.has_shared() {
+            return Cow::Borrowed(self);
         }
+        let mut resolved = self.clone();
+        resolved.resolve_shared();
+        Cow::Owned(resolved)
     }
 
-    groups
-        .into_iter()
-        .enumerate()
-        .map(|(i, shapes)| {
-            let rgb = nth_group_color(i);
-            let center = shapes
-                .iter()
-                .map(|k| k.earliest().value.bounding_box())
-                .reduce(|acc, e| acc.union(e))
-                .unwrap() // keyframed must have an entry
-                .center();
-            Group {
-                children: shapes.into_iter().map(Element::Shape).collect(),
-                center,
-                fill: Some(rgb),
-                ..Default::default()
-            }
-        })
-        .collect()
-}
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
 
-impl Group {
-    /// Piece-wise animation wants to animate "parts" as the eye perceives them; try to so group.
-    pub(crate) fn group_parts(&mut self) {
-        let mut frontier = vec![self];
-        while let Some(group) = frontier.pop() {
-            let mut new_children = Vec::new();
-            let mut existing_groups = HashSet::new();
-            while !group.children.is_empty() {
-                // TODO: existing groups => frontier
-                // runs of shapes => group_parts
+    pub fn width(&self) -> f64 {
+        self.width
+    }
 
-                match group.children.remove(0) {
-                    Element::Group(g) => {
-                        existing_groups.insert(new_children.len());
-                        new_children.push(Element::Group(g));
-                    }
-                    Element::Shape(s) => {
-                        let mut shape_run = vec![s];
-                        while matches!(group.children.first(), Some(Element::Shape(..))) {
-                            let Element::Shape(s) = group.children.remove(0) else {
-                                panic!("We just confirmed this to be the case!");
-                            };
-                            shape_run.push(s);
-                        }
-                        let groups = group_parts(shape_run);
-                        new_children.extend(groups.into_iter().map(Element::Group));
-                    }
-                }
-            }
-            group.children = new_children;
+    pub fn height(&self) -> f64 {
+        self.height
+    }
 
-            for (i, el) in group.children.iter_mut().enumerate() {
-                if existing_groups.contains(&i) {
-                    let Element::Group(g) = el else {
-                        unreachable!();
-                    };
-                    frontier.push(g);
-                }
-            }
+    /// How long the animation lasts, in frames at [`Animation::frame_rate`]
+    pub fn frames(&self) -> f64 {
+        to_frames(self.duration, self.frame_rate)
+    }
+
+    /// Frames per second outputs play at
+    pub fn frame_rate(&self) -> f64 {
+        self.frame_rate
+    }
+
+    /// How long the animation lasts
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration)
+    }
+
+    /// When `frame` is reached, from the start of the animation
+    pub fn time_at(&self, frame: f64) -> Duration {
+        Duration::from_secs_f64((frame / self.frame_rate).max(0.0))
+    }
+
+    /// The frame, possibly fractional, reached at `time`
+    pub fn frame_at(&self, time: Duration) -> f64 {
+        to_frames(time.as_secs_f64(), self.frame_rate)
+    }
+
+    /// Play at `frame_rate` frames per second. Timing is kept in seconds so nothing moves,
+    /// outputs convert it to frames at this rate.
+    ///
+    /// Animations are made at 60fps. Passes that work in whole frames, such as
+    /// [`crate::pass::Snap`], use the frame rate at the time they run so retime first.
+    pub fn retime(&mut self, frame_rate: f64) -> Result<(), AnimationError> {
+        if !frame_rate.is_finite() || frame_rate <= 0.0 {
+            return Err(AnimationError::InvalidFrameRate(frame_rate));
+        }
+        self.frame_rate = frame_rate;
+        Ok(())
+    }
+
+    /// Speed up or slow down everything, at the same frame rate, so the animation lasts
+    /// `duration`
+    ///
+    /// Plans take as long as their motion needs, e.g. a spring until it settles, so this
+    /// changes how motion looks, a stiff spring slowed down looks soft.
+    pub fn set_duration(&mut self, duration: Duration) -> Result<(), AnimationError> {
+        if duration.is_zero() {
+            return Err(AnimationError::InvalidDuration(duration));
+        }
+        self.scale_time(duration.as_secs_f64() / self.duration);
+        Ok(())
+    }
+
+    /// Multiply every time, of keyframes, segments and the length, by `factor`
+    fn scale_time(&mut self, factor: f64) {
+        if factor == 1.0 {
+            return;
+        }
+        self.visit_mut(&mut ScaleTime(factor));
+        self.duration *= factor;
+        for segment in self.segments.iter_mut() {
+            segment.start *= factor;
+            segment.end *= factor;
+        }
+    }
+
+    /// Scale everything, drawing and motion alike, so the longer side of the animation is
+    /// `size` output units, e.g. pixels
+    ///
+    /// Plans draw icons as large as the font's units per em.
+    pub fn set_size(&mut self, size: f64) -> Result<(), AnimationError> {
+        if !size.is_finite() || size <= 0.0 {
+            return Err(AnimationError::InvalidSize(size));
+        }
+        let factor = size / self.width.max(self.height);
+        if factor == 1.0 {
+            return Ok(());
+        }
+        self.visit_mut(&mut Resize(factor));
+        self.width *= factor;
+        self.height *= factor;
+        self.src_to_dest_units =
+            FontToOutput::new(Affine::scale(factor) * self.src_to_dest_units.affine());
+        Ok(())
+    }
+
+    /// The glyph animated, see [`crate::ligate::gid_to_icon_name`] to learn its name
+    pub fn gid(&self) -> Option<GlyphId> {
+        self.gid
+    }
+
+    /// Named stretches of the animation, in order. Each step of a composite plan is one.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Name `times`, in seconds, of the animation, e.g. "loop", so players can seek to it
+    pub fn add_segment(
+        &mut self,
+        name: impl Into<String>,
+        times: Range<f64>,
+    ) -> Result<(), AnimationError> {
+        let name = name.into();
+        if times.is_empty() {
+            return Err(AnimationError::EmptyTimeRange(times.start, times.end));
+        }
+        if times.start < 0.0 || times.end > self.duration {
+            return Err(AnimationError::SegmentOutOfRange(name));
+        }
+        self.segments.push(Segment {
+            name,
+            start: times.start,
+            end: times.end,
+        });
+        self.segments
+            .sort_by(|a, b| a.start.total_cmp(&b.start).then(a.end.total_cmp(&b.end)));
+        Ok(())
+    }
+
+    pub fn root(&self) -> &Group {
+        &self.root
+    }
+
+    /// For custom choreography, e.g. [`Group::group_parts`] then animate the parts
+    pub fn root_mut(&mut self) -> &mut Group {
+        &mut self.root
+    }
+
+    /// The zoom and pan of everything, None if the view holds still
+    pub fn camera(&self) -> Option<&Camera> {
+        self.camera.as_ref()
+    }
+
+    /// Zoom and pan everything, outside the root's own motion, e.g. for a punch in at the end
+    pub fn set_camera(&mut self, camera: Option<Camera>) {
+        self.camera = camera;
+    }
+
+    /// The root, wrapped in a group with the camera's motion if there is one
+    pub(crate) fn viewed_root(&self) -> Cow<'_, Group> {
+        let Some(camera) = &self.camera else {
+            return Cow::Borrowed(&self.root);
+        };
+        let mut view = camera.to_group(self.width, self.height);
+        view.children.push(Element::Group(self.root.clone()));
+        Cow::Owned(view)
+    }
+
+    /// Walk the animation, parents before children, offering each element to `visitor`
+    ///
+    /// The camera, which views everything, is offered first.
+    pub fn visit_mut(&mut self, visitor: &mut impl IrVisitor) {
+        if let Some(camera) = self.camera.as_mut() {
+            visitor.visit_keyframes(KeyframesMut::Zoom(&mut camera.zoom));
+            visitor.visit_keyframes(KeyframesMut::Translate(&mut camera.pan));
         }
+        self.root.visit_mut(visitor);
     }
 }
 
-#[derive(Debug, Clone)]
-pub(crate) enum Element {
-    #[allow(unused)]
-    Group(Group),
-    Shape(Keyframed<BezPath>),
+/// Plays every shape change backward, however deeply nested, see [`Animation::toggle_of_icon`]
+struct ReverseShapes;
+
+impl IrVisitor for ReverseShapes {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        if let KeyframesMut::Shape(shape) = keyframes {
+            *shape = shape.clone().reversed();
+        }
+    }
 }
 
-/// Something with keyframes. Must have at least one definition.
-///
-/// Contains (f64, T) tuples sorted by .0 where the f64 is time in seconds. Times must be unique.
-///
-/// Pops into existence at min(time), disappears at max(time).
-#[derive(Debug, Clone)]
-pub struct Keyframed<T>(Vec<Keyframe<T>>);
+/// Cuts every keyframed property to a range, see [`Animation::extract`]
+struct Extract(Range<f64>);
 
-impl<T> Keyframed<T> {
-    pub(crate) fn new(frame: f64, value: T) -> Self {
-        Self(vec![Keyframe::new(frame, value)])
+impl IrVisitor for Extract {
+    fn visit_group(&mut self, group: &mut Group) {
+        let range = &self.0;
+        if let Some(visible) = group.visible.as_mut() {
+            *visible = visible.start.max(range.start) - range.start
+                ..visible.end.min(range.end) - range.start;
+        }
     }
 
-    pub(crate) fn earliest(&self) -> &Keyframe<T> {
-        &self.0[0]
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        let range = self.0.clone();
+        match keyframes {
+            KeyframesMut::Translate(k) => *k = k.extract(range),
+            KeyframesMut::Scale(k) => *k = k.extract(range),
+            KeyframesMut::Rotate(k) => *k = k.extract(range),
+            KeyframesMut::Skew(k) => *k = k.extract(range),
+            KeyframesMut::Opacity(k) => *k = k.extract(range),
+            KeyframesMut::Shape(k) => *k = k.extract(range),
+            KeyframesMut::Zoom(k) => *k = k.extract(range),
+        }
     }
+}
 
-    pub(crate) fn is_animated(&self) -> bool {
-        self.len() > 1
+/// Multiplies every coordinate by a factor, see [`Animation::set_size`]
+struct Resize(f64);
+
+impl IrVisitor for Resize {
+    fn visit_group(&mut self, group: &mut Group) {
+        let factor = self.0;
+        group.center = (group.center.to_vec2() * factor).to_point();
+        if let Some(stroke) = group.stroke.as_mut() {
+            stroke.width *= factor;
+        }
+        match group.gradient.as_mut() {
+            Some(Gradient::Linear { start, end, .. }) => {
+                *start = (start.to_vec2() * factor).to_point();
+                *end = (end.to_vec2() * factor).to_point();
+            }
+            Some(Gradient::Radial { center, radius, .. }) => {
+                *center = (center.to_vec2() * factor).to_point();
+                *radius *= factor;
+            }
+            None => (),
+        }
     }
 
-    pub(crate) fn len(&self) -> usize {
-        self.0.len()
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        match keyframes {
+            KeyframesMut::Translate(k) => k.iter_mut().for_each(|k| k.value *= self.0),
+            KeyframesMut::Shape(k) => k
+                .iter_mut()
+                .for_each(|k| k.value.apply_affine(Affine::scale(self.0))),
+            // Relative, or angles, so unchanged by size
+            KeyframesMut::Scale(..)
+            | KeyframesMut::Rotate(..)
+            | KeyframesMut::Skew(..)
+            | KeyframesMut::Opacity(..)
+            | KeyframesMut::Zoom(..) => (),
+        }
     }
+}
 
-    pub(crate) fn iter(&self) -> impl Iterator<Item = &Keyframe<T>> {
-        self.0.iter()
+/// Multiplies every time by a factor, see [`Animation::set_duration`]
+struct ScaleTime(f64);
+
+impl IrVisitor for ScaleTime {
+    fn visit_group(&mut self, group: &mut Group) {
+        if let Some(shared) = group.shared.as_mut() {
+            shared.scale_delays(self.0);
+        }
+        if let Some(visible) = group.visible.as_mut() {
+            *visible = visible.start * self.0..visible.end * self.0;
+        }
     }
 
-    pub(crate) fn push(&mut self, keyframe: Keyframe<T>) {
-        if let Some(pos) = self.0.iter().position(|kf| kf.frame == keyframe.frame) {
-            self.0[pos] = keyframe;
-        } else {
-            self.0.push(keyframe);
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        match keyframes {
+            KeyframesMut::Translate(k) => k.scale_times(self.0),
+            KeyframesMut::Scale(k) => k.scale_times(self.0),
+            KeyframesMut::Rotate(k) => k.scale_times(self.0),
+            KeyframesMut::Skew(k) => k.scale_times(self.0),
+            KeyframesMut::Opacity(k) => k.scale_times(self.0),
+            KeyframesMut::Shape(k) => k.scale_times(self.0),
+            KeyframesMut::Zoom(k) => k.scale_times(self.0),
         }
     }
 }
 
-impl<T> TryFrom<Vec<(f64, T)>> for Keyframed<T> {
-    type Error = AnimationError;
+struct Blend<'a> {
+    weight: &'a Keyframed<f64>,
+    samples: Vec<f64>,
+}
 
-    fn try_from(value: Vec<(f64, T)>) -> Result<Self, Self::Error> {
-        if value.is_empty() {
-            return Err(AnimationError::NoKeyframes);
+impl Blend<'_> {
+    fn group(&self, a: &Group, b: &Group) -> Result<Group, AnimationError> {
+        if a.center != b.center || a.children.len() != b.children.len() {
+            return Err(AnimationError::NotBlendable("different groups"));
         }
-        let mut value = value;
-        value.sort_by_key(|(frame, _)| OrderedFloat(*frame));
-        for i in 0..value.len() - 1 {
-            if value[i].0 == value[i + 1].0 {
-                return Err(AnimationError::MultipleValuesForFrame(value[i].0));
-            }
+        let children = a
+            .children
+            .iter()
+            .zip(b.children.iter())
+            .map(|pair| match pair {
+                (Element::Group(a), Element::Group(b)) => self.group(a, b).map(Element::Group),
+                (Element::Shape(a), Element::Shape(b)) => Ok(Element::Shape(self.keyframes(a, b))),
+                _ => Err(AnimationError::NotBlendable("different groups")),
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Group {
+            children,
+            center: a.center,
+            name: a.name.clone(),
+            z: a.z,
+            fill: a.fill,
+            gradient: a.gradient.clone(),
+            stroke: a.stroke,
+            translate: self.keyframes(&a.translate, &b.translate),
+            scale: self.keyframes(&a.scale, &b.scale),
+            rotate: self.keyframes(&a.rotate, &b.rotate),
+            skew: self.keyframes(&a.skew, &b.skew),
+            opacity: self.keyframes(&a.opacity, &b.opacity),
+            shared: None,
+            visible: a.visible.clone(),
+            clip: match (&a.clip, &b.clip) {
+                (Some(a), Some(b)) => Some(self.keyframes(a, b)),
+                _ => a.clip.clone(),
+            },
+            matte: a.matte.clone(),
+            motion_blur: a.motion_blur || b.motion_blur,
+        })
+    }
+
+    fn keyframes<T: Lerp + Clone + PartialEq>(
+        &self,
+        a: &Keyframed<T>,
+        b: &Keyframed<T>,
+    ) -> Keyframed<T> {
+        if !a.is_animated() && !b.is_animated() && a.earliest().value == b.earliest().value {
+            return a.clone();
         }
-        Ok(Keyframed(
-            value
-                .into_iter()
-                .map(|(frame, value)| Keyframe::new(frame, value))
+        let mut result = Keyframed(
+            self.samples
+                .iter()
+                .map(|time| {
+                    let value = a
+                        .value_at(*time)
+                        .lerp(&b.value_at(*time), self.weight.value_at(*time));
+                    Keyframe::new(*time, value).with_ease(Ease::LINEAR)
+                })
                 .collect(),
-        ))
+        );
+        result.dedup();
+        let first = result.earliest().value.clone();
+        if result.iter().all(|k| k.value == first) {
+            return Keyframed::new(0.0, first);
+        }
+        result
     }
 }
 
-fn draw(
-    src_to_dest_units: Affine,
-    location: &Location,
-    gid: GlyphId,
-    glyph: &OutlineGlyph,
-) -> Result<BezPath, AnimationError> {
-    let mut bez_pen = BezPathPen::new();
-    let mut transform_pen = TransformPen::new(&mut bez_pen, src_to_dest_units);
+fn serialize_gid<S: Serializer>(gid: &Option<GlyphId>, serializer: S) -> Result<S::Ok, S::Error> {
+    gid.map(|gid| gid.to_u16()).serialize(serializer)
+}
 
-    let settings = DrawSettings::unhinted(Size::unscaled(), location);
-    glyph
-        .draw(settings, &mut transform_pen)
-        .map_err(|e| AnimationError::DrawError(gid, e))?;
-    Ok(bez_pen.into_inner())
+fn deserialize_gid<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<GlyphId>, D::Error> {
+    Ok(Option::<u16>::deserialize(deserializer)?.map(GlyphId::new))
 }
 
-impl Keyframed<BezPath> {
-    pub(crate) fn for_glyph(
-        last_frame: f64,
-        src_to_dest_units: Affine,
-        glyph_shape: &GlyphShape,
-    ) -> Result<Self, AnimationError> {
-        let mut result = Self::new(
-            0.0,
-            draw(
-                src_to_dest_units,
-                &glyph_shape.start,
-                glyph_shape.gid,
-                &glyph_shape.glyph,
-            )?,
-        );
+/// Always [`SCHEMA_VERSION`] when serialized, refuses anything else when deserialized
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct SchemaVersion;
 
-        if let Some(location) = &glyph_shape.end {
-            result.push(Keyframe::new(
-                last_frame,
-                draw(
-                    src_to_dest_units,
-                    location,
-                    glyph_shape.gid,
-                    &glyph_shape.glyph,
-                )?,
-            ));
+impl Serialize for SchemaVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SCHEMA_VERSION.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SchemaVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = u32::deserialize(deserializer)?;
+        if version != SCHEMA_VERSION {
+            return Err(D::Error::custom(format!(
+                "Unsupported schema version {version}, expected {SCHEMA_VERSION}"
+            )));
         }
+        Ok(SchemaVersion)
+    }
+}
 
-        Ok(result)
+/// A transform of the whole glyph, see [`Animation::of_glyph_at_locations`]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Transform {
+    /// Offset in output units
+    Translate(Vec2),
+    /// Percent in x and y, 100 is unscaled
+    Scale(f64, f64),
+    /// Degrees clockwise
+    Rotate(f64),
+    /// Degrees to lean by and of the axis to lean along, see [`Group::skew`]
+    Skew(f64, f64),
+}
+
+/// Mutable access to the keyframes of one animated property
+pub enum KeyframesMut<'a> {
+    /// A group's translation or the [camera](Animation::camera)'s pan
+    Translate(&'a mut Keyframed<Vec2>),
+    Scale(&'a mut Keyframed<(f64, f64)>),
+    Rotate(&'a mut Keyframed<f64>),
+    Skew(&'a mut Keyframed<(f64, f64)>),
+    Opacity(&'a mut Keyframed<f64>),
+    /// A shape or a [clip](Group::clip), which is in the same units
+    Shape(&'a mut Keyframed<BezPath>),
+    /// The [camera](Animation::camera)'s zoom, in percent
+    Zoom(&'a mut Keyframed<f64>),
+}
+
+/// A pass over an [`Animation`], see [`Animation::visit_mut`]
+///
+/// Every hook defaults to doing nothing so a pass need only implement what it cares about.
+pub trait IrVisitor {
+    /// Called for each group before its keyframes and children are visited
+    fn visit_group(&mut self, _group: &mut Group) {}
+
+    /// Called for each shape before its keyframes are visited
+    fn visit_shape(&mut self, _shape: &mut Keyframed<BezPath>) {}
+
+    /// Called for every keyframed property, both group transforms and shapes
+    fn visit_keyframes(&mut self, _keyframes: KeyframesMut<'_>) {}
+}
+
+/// Create something from [`Animation`], typically an output format
+///
+/// This is the one trait an output format implements, everything upstream of it is shared.
+pub trait FromAnimation
+where
+    Self: Sized,
+{
+    type Err;
+
+    fn from_animation(s: &Animation) -> Result<Self, Self::Err>;
+}
+
+/// A set of groups or shapes that animate as one
+///
+/// Only element permitted transform-based animation and definition of fill and stroke
+///
+/// Transformation is given in terms of position, scale, skew and rotation around an anchor
+/// because expressing rotate around point in affine form is tiresome.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Group {
+    pub(crate) children: Vec<Element>,
+    pub(crate) center: Point,
+    /// What the group is, e.g. "outer-ring", see [`crate::labels`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    /// Stacking among siblings, higher draws above lower. Siblings of equal z keep their order.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) z: i32,
+    pub(crate) fill: Option<(u8, u8, u8)>,
+    /// Drawn instead of the fill by outputs that can, see [`Gradient::flat_color`] for the rest
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) gradient: Option<Gradient>,
+    pub(crate) stroke: Option<Stroke>,
+    pub(crate) translate: Keyframed<Vec2>,
+    pub(crate) scale: Keyframed<(f64, f64)>,
+    pub(crate) rotate: Keyframed<f64>,
+    #[serde(default = "unskewed", skip_serializing_if = "is_unskewed")]
+    pub(crate) skew: Keyframed<(f64, f64)>,
+    pub(crate) opacity: Keyframed<f64>,
+    /// Motion some child groups follow, rather than each having a copy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) shared: Option<SharedMotion>,
+    /// If set the group, and all it contains, is only shown from the start time until the end, in
+    /// seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) visible: Option<Range<f64>>,
+    /// If set what the group contains is only drawn inside this path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) clip: Option<Keyframed<BezPath>>,
+    /// If set a sibling that masks the group, see [`Group::set_matte`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) matte: Option<Matte>,
+    /// Moves fast enough to look better blurred, see [`crate::pass::MotionBlur`]
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) motion_blur: bool,
+}
+
+/// Transform motion kept once by a [`Group`] and followed by its parts, e.g. the twirl that
+/// each part of `twirl` performs in turn
+///
+/// Each property keeps the parts that follow it, so parallel channels can move different parts.
+/// Outputs and passes that need each group's own keyframes use [`Animation::resolve_shared`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedMotion {
+    pub(crate) translate: SharedKeyframes<Vec2>,
+    pub(crate) scale: SharedKeyframes<(f64, f64)>,
+    pub(crate) rotate: SharedKeyframes<f64>,
+    pub(crate) opacity: SharedKeyframes<f64>,
+}
+
+impl Default for SharedMotion {
+    fn default() -> Self {
+        let rest = Group::default();
+        Self {
+            translate: rest.translate.into(),
+            scale: rest.scale.into(),
+            rotate: rest.rotate.into(),
+            opacity: rest.opacity.into(),
+        }
     }
+}
 
-    pub(crate) fn subpaths(&self) -> Vec<Keyframed<BezPath>> {
-        // convert each keyframe to subpaths then line 'em up
-        let subpaths: Vec<_> = self.0.iter().map(|s| (s.frame, s.subpaths())).collect();
+impl SharedMotion {
+    /// Whether the nth child group follows any of the motion
+    fn moves(&self, nth: usize) -> bool {
+        self.translate.moves(nth)
+            || self.scale.moves(nth)
+            || self.rotate.moves(nth)
+            || self.opacity.moves(nth)
+    }
 
-        // TODO: should we allow incompatible paths in?
-        assert!(
-            subpaths.iter().all(|s| s.1.len() == subpaths[0].1.len()),
-            "Incompatible subpaths unsupported"
-        );
+    fn scale_delays(&mut self, factor: f64) {
+        self.translate.scale_delays(factor);
+        self.scale.scale_delays(factor);
+        self.rotate.scale_delays(factor);
+        self.opacity.scale_delays(factor);
+    }
+}
 
-        (0..subpaths[0].1.len())
-            .map(|i| {
-                Keyframed(
-                    subpaths
-                        .iter()
-                        .map(|(frame, subpaths)| Keyframe::new(*frame, subpaths[i].clone()))
-                        .collect(),
-                )
-            })
-            .collect()
+/// One property of [`SharedMotion`] and the child groups that follow it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedKeyframes<T> {
+    pub(crate) keyframes: Keyframed<T>,
+    /// By child group, how many seconds late it follows the keyframes, None if it holds still
+    #[serde(default)]
+    pub(crate) delays: Vec<Option<f64>>,
+}
+
+impl<T> From<Keyframed<T>> for SharedKeyframes<T> {
+    fn from(keyframes: Keyframed<T>) -> Self {
+        Self {
+            keyframes,
+            delays: Vec::new(),
+        }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Keyframe<T> {
-    pub frame: f64,
-    pub value: T,
+impl<T: Clone> SharedKeyframes<T> {
+    /// Have `delays` follow `next` instead, if it is animated
+    fn share(&mut self, next: SharedKeyframes<T>, delays: &[Option<f64>]) {
+        if next.keyframes.is_animated() {
+            self.keyframes = next.keyframes;
+            self.delays = delays.to_vec();
+        }
+    }
+
+    fn moves(&self, nth: usize) -> bool {
+        self.keyframes.is_animated() && matches!(self.delays.get(nth), Some(Some(..)))
+    }
+
+    /// Give the nth child group, if it follows, its own delayed copy of the keyframes
+    fn resolve(&self, keyframed: &mut Keyframed<T>, nth: usize) {
+        if let (true, Some(Some(delay))) = (self.keyframes.is_animated(), self.delays.get(nth)) {
+            *keyframed = self.keyframes.clone().delay(*delay);
+        }
+    }
+
+    fn scale_delays(&mut self, factor: f64) {
+        for delay in self.delays.iter_mut().flatten() {
+            *delay *= factor;
+        }
+    }
 }
 
-impl<T> Keyframe<T> {
-    pub fn new(frame: f64, value: T) -> Self {
-        Self { frame, value }
+/// An outline drawn along the shapes of a [`Group`]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stroke {
+    pub color: (u8, u8, u8),
+    pub width: f64,
+}
+
+/// A gradient fill, such as a COLRv1 glyph may have, in the coordinates of the group's shapes
+///
+/// Colors between stops are interpolated, beyond the ends the nearest stop is padded out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gradient {
+    Linear {
+        start: Point,
+        end: Point,
+        stops: Vec<ColorStop>,
+    },
+    Radial {
+        center: Point,
+        radius: f64,
+        stops: Vec<ColorStop>,
+    },
+}
+
+/// A color at an offset, 0 at the start of a [`Gradient`] and 1 at the end
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorStop {
+    pub offset: f64,
+    pub color: (u8, u8, u8),
+}
+
+impl Gradient {
+    /// In order of offset
+    pub fn stops(&self) -> &[ColorStop] {
+        match self {
+            Gradient::Linear { stops, .. } | Gradient::Radial { stops, .. } => stops,
+        }
+    }
+
+    /// The average of the stops, for outputs that can't draw gradients
+    pub fn flat_color(&self) -> (u8, u8, u8) {
+        let stops = self.stops();
+        if stops.is_empty() {
+            return (0, 0, 0);
+        }
+        let channel = |c: fn(&ColorStop) -> u8| {
+            let sum: u32 = stops.iter().map(|s| c(s) as u32).sum();
+            (sum as f64 / stops.len() as f64).round() as u8
+        };
+        (
+            channel(|s| s.color.0),
+            channel(|s| s.color.1),
+            channel(|s| s.color.2),
+        )
     }
 }
 
-impl Keyframe<BezPath> {
-    pub(crate) fn subpaths(&self) -> Vec<BezPath> {
-        let mut paths = Vec::new();
-        let mut last_start = 0;
-        let elements = self.value.elements();
-        for (i, e) in elements.iter().enumerate().skip(1) {
-            if let PathEl::MoveTo(..) = e {
-                paths.push(BezPath::from_vec(elements[last_start..i].to_vec()));
-                last_start = i;
+/// A sibling part, by [name](Group::name), whose coverage masks a group, e.g. a ring cut out of
+/// an icon for a notification dot to sit in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Matte {
+    pub part: String,
+    pub mode: MatteMode,
+}
+
+/// How a [`Matte`] masks
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatteMode {
+    /// Draw only where the matte is
+    Alpha,
+    /// Draw only where the matte isn't, a cutout
+    InvertedAlpha,
+}
+
+fn is_zero(z: &i32) -> bool {
+    *z == 0
+}
+
+fn is_false(b: &bool) -> bool {
+    !b
+}
+
+fn unskewed() -> Keyframed<(f64, f64)> {
+    Keyframed::new(0.0, (0.0, 0.0))
+}
+
+fn is_unskewed(skew: &Keyframed<(f64, f64)>) -> bool {
+    !skew.is_animated() && skew.earliest().value == (0.0, 0.0)
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Self {
+            children: Default::default(),
+            center: Point::default(),
+            name: None,
+            z: 0,
+            fill: None,
+            gradient: None,
+            stroke: None,
+            translate: Keyframed::new(0.0, Vec2::default()),
+            scale: Keyframed::new(0.0, (100.0, 100.0)),
+            rotate: Keyframed::new(0.0, 0.0),
+            skew: unskewed(),
+            opacity: Keyframed::new(0.0, 100.0),
+            shared: None,
+            visible: None,
+            clip: None,
+            matte: None,
+            motion_blur: false,
+        }
+    }
+}
+
+impl Group {
+    /// Animate as `plan` says, over `duration` seconds
+    fn animate(
+        &mut self,
+        duration: f64,
+        plan: &AnimationPlan,
+        direction: Direction,
+        options: &PlanOptions,
+    ) -> Result<(), AnimationError> {
+        if plan.granularity() == Granularity::Parts {
+            self.group_parts_as(options.group_overlaps, options.anchor, !options.plain_parts);
+            if let Some(names) = options.part_hints.names(plan.icon_name()) {
+                self.name_parts(names);
             }
         }
-        if last_start < elements.len() - 1 {
-            paths.push(BezPath::from_vec(elements[last_start..].to_vec()));
+        self.animate_grouped(duration, plan, direction)?;
+        if options.moving_parts_on_top {
+            self.raise_moving_parts();
         }
-        paths
+        Ok(())
+    }
+
+    /// Draw groups that move above their siblings that hold still
+    fn raise_moving_parts(&mut self) {
+        let shared = self.shared.take();
+        for (i, g) in self.mutable_child_groups().enumerate() {
+            if g.moves() || shared.as_ref().is_some_and(|s| s.moves(i)) {
+                g.z = g.z.max(1);
+            }
+            g.raise_moving_parts();
+        }
+        self.shared = shared;
+    }
+
+    /// Whether the group's transform or opacity is animated by its own keyframes, see
+    /// [`SharedMotion`] for the motion it may follow
+    fn moves(&self) -> bool {
+        self.translate.is_animated()
+            || self.scale.is_animated()
+            || self.rotate.is_animated()
+            || self.skew.is_animated()
+            || self.opacity.is_animated()
+    }
+
+    /// As [`Group::animate`] for a group whose parts, if the plan needs them, are already grouped
+    fn animate_grouped(
+        &mut self,
+        duration: f64,
+        plan: &AnimationPlan,
+        direction: Direction,
+    ) -> Result<(), AnimationError> {
+        // Variation is apply when creating a shape; here apply transform-based animation
+        match plan {
+            // Sequences change shape, see Keyframed::for_sequence
+            AnimationPlan::None(..) | AnimationPlan::Sequence(..) | AnimationPlan::Sweep(..) => (),
+            AnimationPlan::TwirlWhole(..) => {
+                self.rotate = timed(twirl(0.0, duration, 0)?, plan, Channel::Rotate, direction)
+            }
+            AnimationPlan::TwirlParts(_, parts) => self.share_motion(
+                SharedMotion {
+                    rotate: timed(twirl(0.0, duration, 0)?, plan, Channel::Rotate, direction)
+                        .into(),
+                    ..Default::default()
+                },
+                PART_STAGGER * duration,
+                *parts,
+            ),
+            AnimationPlan::PulseWhole(..) => {
+                self.scale = timed(pulse(0.0, duration, 0)?, plan, Channel::Scale, direction)
+            }
+            AnimationPlan::PulseParts(_, parts) => self.share_motion(
+                SharedMotion {
+                    scale: timed(pulse(0.0, duration, 0)?, plan, Channel::Scale, direction).into(),
+                    ..Default::default()
+                },
+                PART_STAGGER * duration,
+                *parts,
+            ),
+            AnimationPlan::StandardEnter(..) => {
+                let scale = transition(duration, (80.0, 80.0), (100.0, 100.0));
+                let opacity = transition(duration, 0.0, 100.0);
+                self.scale = timed(scale, plan, Channel::Scale, direction);
+                self.opacity = timed(opacity, plan, Channel::Opacity, direction);
+            }
+            AnimationPlan::StandardExit(..) => {
+                let scale = transition(duration, (100.0, 100.0), (80.0, 80.0));
+                let opacity = transition(duration, 100.0, 0.0);
+                self.scale = timed(scale, plan, Channel::Scale, direction);
+                self.opacity = timed(opacity, plan, Channel::Opacity, direction);
+            }
+            AnimationPlan::Composite(_, steps) => {
+                let still = self.clone();
+                // Plans count in frames, summed as such so steps start on whole frames
+                let mut start = 0.0;
+                let steps: Vec<_> = match direction {
+                    Direction::Forward => steps.iter().collect(),
+                    Direction::Backward => steps.iter().rev().collect(),
+                };
+                for step in steps {
+                    if let CompositeStep::Plan(plan) = step {
+                        let mut moved = still.clone();
+                        moved.animate_grouped(step.frames() / PLAN_FRAME_RATE, plan, direction)?;
+                        moved.resolve_shared();
+                        self.append_motion(moved, start / PLAN_FRAME_RATE);
+                    }
+                    start += step.frames();
+                }
+            }
+            AnimationPlan::Parallel(_, channels) => {
+                for channel in channels {
+                    self.animate_grouped(channel.frames() / PLAN_FRAME_RATE, channel, direction)?;
+                }
+            }
+            AnimationPlan::RotateDegrees(_, degrees) => {
+                let rotation = plan.rotation(*degrees);
+                self.rotate =
+                    driven_keyframes(plan, Channel::Rotate, duration, rotation, direction, |v| v)?;
+            }
+            AnimationPlan::ScaleFromTo(_, from, to) => {
+                let scale = plan.scale(*from, *to);
+                let percent = |v: f64| (v * 100.0, v * 100.0);
+                self.scale =
+                    driven_keyframes(plan, Channel::Scale, duration, scale, direction, percent)?;
+            }
+            AnimationPlan::SquashAndStretch(_, percent) => {
+                let squash = plan.squash(*percent);
+                // As high as it isn't squashed, as wide as keeps the area; overshoot stretches
+                let scale = |squash: f64| (100.0 / (1.0 - squash), 100.0 * (1.0 - squash));
+                self.scale =
+                    driven_keyframes(plan, Channel::Scale, duration, squash, direction, scale)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_shared(&self) -> bool {
+        self.shared.is_some()
+            || self.children.iter().any(|e| match e {
+                Element::Group(g) => g.has_shared(),
+                Element::Shape(..) => false,
+            })
+    }
+
+    /// Have each child group in `parts` follow `motion`, the nth starting `stagger` * n seconds
+    /// late. The rest hold still.
+    ///
+    /// Properties `motion` animates replace those already shared, along with the parts that follow
+    /// them, the rest are kept, so parallel channels can each share their own with their own parts.
+    fn share_motion(&mut self, motion: SharedMotion, stagger: f64, parts: PartSelection<'_>) {
+        let (areas, names): (Vec<_>, Vec<_>) = self
+            .children
+            .iter()
+            .filter_map(|e| match e {
+                Element::Group(g) => {
+                    Some((g.bounding_box().map_or(0.0, |bbox| bbox.area()), g.name()))
+                }
+                Element::Shape(..) => None,
+            })
+            .unzip();
+        let selected = parts.select(&areas, &names);
+        if selected.is_empty() {
+            log::warn!("{parts:?} selects none of the {} parts", areas.len());
+        }
+        let delays: Vec<_> = (0..areas.len())
+            .map(|i| {
+                selected
+                    .iter()
+                    .position(|part| *part == i)
+                    .map(|nth| stagger * nth as f64)
+            })
+            .collect();
+        let shared = self.shared.get_or_insert_with(Default::default);
+        shared.translate.share(motion.translate, &delays);
+        shared.scale.share(motion.scale, &delays);
+        shared.rotate.share(motion.rotate, &delays);
+        shared.opacity.share(motion.opacity, &delays);
+    }
+
+    /// Give every group that follows shared motion its own copy, see [`SharedMotion`]
+    pub fn resolve_shared(&mut self) {
+        let shared = self.shared.take();
+        for (i, child) in self.mutable_child_groups().enumerate() {
+            if let Some(shared) = &shared {
+                shared.translate.resolve(&mut child.translate, i);
+                shared.scale.resolve(&mut child.scale, i);
+                shared.rotate.resolve(&mut child.rotate, i);
+                shared.opacity.resolve(&mut child.opacity, i);
+            }
+            child.resolve_shared();
+        }
+    }
+
+    /// Continue the motion of this group with that of `next`, a copy of it animated from 0,
+    /// starting `start` seconds in
+    ///
+    /// Only properties `next` animates are changed. Rotation is offset by whole turns so it
+    /// continues from where it was rather than spinning back.
+    fn append_motion(&mut self, mut next: Group, start: f64) {
+        if next.rotate.is_animated() {
+            let first = next.rotate.earliest();
+            let current = self.rotate.value_at(start + first.time);
+            let turns = ((current - first.value) / 360.0).round() * 360.0;
+            for keyframe in next.rotate.0.iter_mut() {
+                keyframe.value += turns;
+            }
+        }
+        append_keyframes(&mut self.translate, next.translate, start);
+        append_keyframes(&mut self.scale, next.scale, start);
+        append_keyframes(&mut self.rotate, next.rotate, start);
+        append_keyframes(&mut self.skew, next.skew, start);
+        append_keyframes(&mut self.opacity, next.opacity, start);
+        for (child, next) in self.children.iter_mut().zip(next.children) {
+            if let (Element::Group(child), Element::Group(next)) = (child, next) {
+                child.append_motion(next, start);
+            }
+        }
+    }
+
+    pub fn children(&self) -> &[Element] {
+        &self.children
+    }
+
+    /// Passes that prune or reorder need the actual storage
+    pub fn children_mut(&mut self) -> &mut Vec<Element> {
+        &mut self.children
+    }
+
+    pub fn center(&self) -> Point {
+        self.center
+    }
+
+    /// Rotate and scale about `center` rather than where grouping put it, e.g. to pin one part
+    pub fn set_center(&mut self, center: Point) {
+        self.center = center;
+    }
+
+    /// What the group is, e.g. "dot-1", see [`crate::labels`]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    /// Stacking among siblings, higher draws above lower
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    pub fn set_z(&mut self, z: i32) {
+        self.z = z;
+    }
+
+    /// When the group is shown, in seconds, None if always
+    pub fn visible(&self) -> Option<Range<f64>> {
+        self.visible.clone()
+    }
+
+    /// Show the group only from `visible.start`, popping in, until `visible.end`, popping out,
+    /// e.g. to reveal parts one by one
+    pub fn set_visible(&mut self, visible: Option<Range<f64>>) {
+        self.visible = visible;
+    }
+
+    /// The path outside of which nothing the group contains is drawn, None if unclipped
+    pub fn clip(&self) -> Option<&Keyframed<BezPath>> {
+        self.clip.as_ref()
+    }
+
+    /// Clip to `clip`, in the coordinates of the group's shapes so it moves with the group
+    ///
+    /// Animate the clip to reveal the group, e.g. a rect growing across it for a wipe or a
+    /// circle growing from its center for an iris. Outputs may only clip some groups, see
+    /// [`crate::lottie::LottieOptions::layer_per_part`].
+    pub fn set_clip(&mut self, clip: Option<Keyframed<BezPath>>) {
+        self.clip = clip;
+    }
+
+    /// The sibling that masks the group, None if unmasked
+    pub fn matte(&self) -> Option<&Matte> {
+        self.matte.as_ref()
+    }
+
+    /// Mask the group by the sibling part named `matte.part`
+    ///
+    /// The matte part is only a mask, it isn't drawn itself. Lottie draws mattes of parts of the
+    /// root with [`crate::lottie::LottieOptions::layer_per_part`], elsewhere the group is drawn
+    /// unmasked.
+    pub fn set_matte(&mut self, matte: Option<Matte>) {
+        self.matte = matte;
+    }
+
+    /// Whether the group moves fast enough that outputs which can should blur it
+    pub fn motion_blur(&self) -> bool {
+        self.motion_blur
+    }
+
+    /// Ask for the group, and all it contains, to be motion blurred, only some players can, see
+    /// [`crate::lottie::LottieProfile`]
+    pub fn set_motion_blur(&mut self, motion_blur: bool) {
+        self.motion_blur = motion_blur;
+    }
+
+    /// Whether `child`, a child of the group, is a [matte](Group::matte) of a sibling
+    pub(crate) fn is_matte(&self, child: &Element) -> bool {
+        let Element::Group(child) = child else {
+            return false;
+        };
+        let Some(name) = child.name() else {
+            return false;
+        };
+        self.children.iter().any(|e| match e {
+            Element::Group(g) => g.matte.as_ref().is_some_and(|m| m.part == name),
+            Element::Shape(..) => false,
+        })
+    }
+
+    /// The group, and all it contains, without mattes, for outputs that can't draw them
+    ///
+    /// Matte parts are dropped, as they aren't drawn, and the groups they masked are drawn
+    /// unmasked.
+    pub(crate) fn unmatted(&self) -> Cow<'_, Group> {
+        fn has_mattes(group: &Group) -> bool {
+            group.children.iter().any(|e| match e {
+                Element::Group(g) => g.matte.is_some() || has_mattes(g),
+                Element::Shape(..) => false,
+            })
+        }
+        if !has_mattes(self) {
+            return Cow::Borrowed(self);
+        }
+        let mut group = self.clone();
+        group.children.retain(|e| !self.is_matte(e));
+        for child in group.children.iter_mut() {
+            if let Element::Group(child) = child {
+                if let Some(matte) = child.matte.take() {
+                    log::warn!(
+                        "{:?} is drawn without its matte {:?}",
+                        child.name(),
+                        matte.part
+                    );
+                }
+                *child = child.unmatted().into_owned();
+            }
+        }
+        Cow::Owned(group)
+    }
+
+    /// Opacity, dropping to 0 outside the times the group is [visible](Group::visible)
+    ///
+    /// Keyframes can't jump from a changing value so, if opacity is animated, it holds for the
+    /// last frame shown at `frame_rate`. Outputs render whole frames so that's how it plays.
+    pub(crate) fn visible_opacity(&self, frame_rate: f64) -> Cow<'_, Keyframed<f64>> {
+        let Some(visible) = self.visible.clone() else {
+            return Cow::Borrowed(&self.opacity);
+        };
+        if visible.is_empty() {
+            return Cow::Owned(Keyframed::new(0.0, 0.0));
+        }
+        let mut keyframes = Vec::new();
+        if visible.start > 0.0 {
+            keyframes.push(Keyframe::new(0.0, 0.0).held());
+        }
+        let last_shown = (visible.end - 1.0 / frame_rate).max(visible.start);
+        if self.opacity.is_animated() && last_shown > visible.start {
+            keyframes.extend(
+                self.opacity
+                    .extract(visible.start..last_shown)
+                    .delay(visible.start)
+                    .0,
+            );
+        } else {
+            keyframes.push(Keyframe::new(
+                visible.start,
+                self.opacity.value_at(visible.start),
+            ));
+        }
+        keyframes.last_mut().unwrap().hold = true;
+        if visible.end > last_shown {
+            keyframes.push(Keyframe::new(visible.end, 0.0));
+        }
+        Cow::Owned(Keyframed(keyframes))
+    }
+
+    pub fn fill(&self) -> Option<(u8, u8, u8)> {
+        self.fill
+    }
+
+    pub fn set_fill(&mut self, fill: Option<(u8, u8, u8)>) {
+        self.fill = fill;
+    }
+
+    pub fn gradient(&self) -> Option<&Gradient> {
+        self.gradient.as_ref()
+    }
+
+    /// The fill is left as is, outputs that can't draw the gradient fall back to it if set
+    pub fn set_gradient(&mut self, gradient: Option<Gradient>) {
+        self.gradient = gradient;
+    }
+
+    pub fn translate(&self) -> &Keyframed<Vec2> {
+        &self.translate
+    }
+
+    pub fn set_translate(&mut self, translate: Keyframed<Vec2>) {
+        self.translate = translate;
+    }
+
+    /// Percent in x and y, about [`Group::center`]
+    pub fn scale(&self) -> &Keyframed<(f64, f64)> {
+        &self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: Keyframed<(f64, f64)>) {
+        self.scale = scale;
+    }
+
+    /// Degrees clockwise about [`Group::center`]
+    pub fn rotate(&self) -> &Keyframed<f64> {
+        &self.rotate
+    }
+
+    pub fn set_rotate(&mut self, rotate: Keyframed<f64>) {
+        self.rotate = rotate;
+    }
+
+    /// Degrees to lean by and degrees clockwise of the axis to lean along, about
+    /// [`Group::center`], as Lottie's skew and skew axis
+    ///
+    /// A skew of 0 is none; a positive skew along axis 0 leans the top to the right, as if
+    /// italic. AVD can't animate skew so has that of the first frame.
+    pub fn skew(&self) -> &Keyframed<(f64, f64)> {
+        &self.skew
+    }
+
+    pub fn set_skew(&mut self, skew: Keyframed<(f64, f64)>) {
+        self.skew = skew;
+    }
+
+    /// Whether the group is, at any time, skewed
+    pub(crate) fn is_skewed(&self) -> bool {
+        !is_unskewed(&self.skew)
+    }
+
+    /// Where the group's transform puts what it contains at `time`, the composition Lottie
+    /// uses: scale, skew and rotate about the center then translate
+    ///
+    /// Shared motion is ignored, see [`Group::resolve_shared`].
+    pub(crate) fn affine_at(&self, time: f64) -> Affine {
+        let (scale_x, scale_y) = self.scale.value_at(time);
+        let (skew, axis) = self.skew.value_at(time);
+        let axis = axis.to_radians();
+        let center = self.center.to_vec2();
+        Affine::translate(center + self.translate.value_at(time))
+            * Affine::rotate(self.rotate.value_at(time).to_radians())
+            * Affine::rotate(axis)
+            * Affine::skew(-skew.to_radians().tan(), 0.0)
+            * Affine::rotate(-axis)
+            * Affine::scale_non_uniform(scale_x / 100.0, scale_y / 100.0)
+            * Affine::translate(-center)
+    }
+
+    /// Percent, 0 is fully transparent
+    pub fn opacity(&self) -> &Keyframed<f64> {
+        &self.opacity
+    }
+
+    pub fn set_opacity(&mut self, opacity: Keyframed<f64>) {
+        self.opacity = opacity;
+    }
+
+    pub fn stroke(&self) -> Option<Stroke> {
+        self.stroke
+    }
+
+    pub fn set_stroke(&mut self, stroke: Option<Stroke>) {
+        self.stroke = stroke;
+    }
+
+    /// The bounds of the content at the earliest keyframe, ignoring transforms. None if there is no content.
+    pub fn bounding_box(&self) -> Option<Rect> {
+        self.children
+            .iter()
+            .filter_map(|e| match e {
+                Element::Group(g) => g.bounding_box(),
+                Element::Shape(s) => Some(s.earliest().value.bounding_box()),
+            })
+            .reduce(|acc, e| acc.union(e))
+    }
+
+    fn visit_mut(&mut self, visitor: &mut impl IrVisitor) {
+        visitor.visit_group(self);
+        visitor.visit_keyframes(KeyframesMut::Translate(&mut self.translate));
+        visitor.visit_keyframes(KeyframesMut::Scale(&mut self.scale));
+        visitor.visit_keyframes(KeyframesMut::Rotate(&mut self.rotate));
+        visitor.visit_keyframes(KeyframesMut::Skew(&mut self.skew));
+        visitor.visit_keyframes(KeyframesMut::Opacity(&mut self.opacity));
+        if let Some(clip) = self.clip.as_mut() {
+            visitor.visit_keyframes(KeyframesMut::Shape(clip));
+        }
+        if let Some(shared) = self.shared.as_mut() {
+            visitor.visit_keyframes(KeyframesMut::Translate(&mut shared.translate.keyframes));
+            visitor.visit_keyframes(KeyframesMut::Scale(&mut shared.scale.keyframes));
+            visitor.visit_keyframes(KeyframesMut::Rotate(&mut shared.rotate.keyframes));
+            visitor.visit_keyframes(KeyframesMut::Opacity(&mut shared.opacity.keyframes));
+        }
+        for child in self.children.iter_mut() {
+            match child {
+                Element::Group(g) => g.visit_mut(visitor),
+                Element::Shape(s) => {
+                    visitor.visit_shape(s);
+                    visitor.visit_keyframes(KeyframesMut::Shape(s));
+                }
+            }
+        }
+    }
+
+    fn mutable_child_groups(&mut self) -> impl Iterator<Item = &mut Group> {
+        self.children.iter_mut().filter_map(|e| match e {
+            Element::Group(g) => Some(g),
+            Element::Shape(..) => None,
+        })
+    }
+}
+
+/// The motion of `plan`'s spring, or the default, as keyframes. The spring is fit to frames at
+/// [`PLAN_FRAME_RATE`].
+fn spring_keyframes<T>(
+    spring: Spring,
+    animation: AnimatedValue,
+    value: impl Fn(f64) -> T,
+) -> Result<Keyframed<T>, AnimationError> {
+    let motion = cached_motion(PLAN_FRAME_RATE, animation, spring)
+        .map_err(AnimationError::CubicApproximationError)?;
+    let mut keyframes: Vec<_> = motion
+        .keyframes
+        .into_iter()
+        .map(|k| Keyframe {
+            time: k.frame / PLAN_FRAME_RATE,
+            value: value(k.value),
+            ease: k.ease.map(|(p1, p2)| Ease { p1, p2 }),
+            hold: false,
+        })
+        .collect();
+    // A spring that starts at rest is a single degenerate cubic, start and end on the same frame
+    keyframes.dedup_by(|a, b| a.time == b.time);
+    Ok(Keyframed(keyframes))
+}
+
+/// Keyframes of `value` of `animation`, as the plan animates `channel`: bent, eased or sprung
+///
+/// `animation` is what moves, so a value derived from it, such as both axes of a squash, moves
+/// in step even where a bender overshoots.
+fn driven_keyframes<T>(
+    plan: &AnimationPlan,
+    channel: Channel,
+    duration: f64,
+    animation: AnimatedValue,
+    direction: Direction,
+    value: impl Fn(f64) -> T,
+) -> Result<Keyframed<T>, AnimationError> {
+    let driver = match (plan.bender_for(channel), plan.ease_for(channel)) {
+        (Some(bender), _) => bent(
+            direction.keyframes(eased_keyframes(Ease::LINEAR, duration, animation, |v| v)),
+            Some(bender),
+            PLAN_FRAME_RATE,
+        ),
+        (None, Some(ease)) => {
+            direction.keyframes(eased_keyframes(ease, duration, animation, |v| v))
+        }
+        (None, None) => {
+            spring_keyframes(plan.spring_for(channel), direction.spring(animation), |v| v)?
+        }
+    };
+    Ok(driver.map(value))
+}
+
+/// `animation` from its value to its final value over `duration` seconds with `ease`. An ease has
+/// no notion of initial velocity so that is ignored.
+fn eased_keyframes<T>(
+    ease: Ease,
+    duration: f64,
+    animation: AnimatedValue,
+    value: impl Fn(f64) -> T,
+) -> Keyframed<T> {
+    Keyframed(vec![
+        Keyframe::new(0.0, value(animation.value)).with_ease(ease),
+        Keyframe::new(duration, value(animation.final_value)),
+    ])
+}
+
+/// `keyframed` with every keyframe moving by `ease`, if given, rather than its own ease
+fn eased<T>(mut keyframed: Keyframed<T>, ease: Option<Ease>) -> Keyframed<T> {
+    if let Some(ease) = ease {
+        for keyframe in keyframed.0.iter_mut() {
+            keyframe.ease = Some(ease);
+        }
+    }
+    keyframed
+}
+
+/// `keyframed` with each change sampled every frame at `frame_rate` so it moves by `bender`, if
+/// given, rather than by its own ease. Held keyframes still jump.
+pub(crate) fn bent<T: Lerp + Clone>(
+    keyframed: Keyframed<T>,
+    bender: Option<impl MotionBender>,
+    frame_rate: f64,
+) -> Keyframed<T> {
+    let Some(bender) = bender else {
+        return keyframed;
+    };
+    let mut result = Vec::new();
+    for pair in keyframed.0.windows(2) {
+        let (from, to) = (&pair[0], &pair[1]);
+        if from.hold {
+            result.push(from.clone());
+            continue;
+        }
+        let span = to.time - from.time;
+        let steps = to_frames(span, frame_rate).ceil().max(1.0) as usize;
+        for step in 0..steps {
+            let t = step as f64 / steps as f64;
+            let value = from.value.lerp(&to.value, bender.progress(t));
+            result.push(Keyframe::new(from.time + t * span, value).with_ease(Ease::LINEAR));
+        }
+    }
+    result.extend(keyframed.0.last().cloned());
+    Keyframed(result)
+}
+
+/// `keyframed` moving as `plan` says `channel` should, played backward if need be. Bending comes
+/// after reversal so a bounce still lands at the end.
+fn timed<T: Lerp + Clone>(
+    keyframed: Keyframed<T>,
+    plan: &AnimationPlan,
+    channel: Channel,
+    direction: Direction,
+) -> Keyframed<T> {
+    bent(
+        direction.keyframes(eased(keyframed, plan.ease_for(channel))),
+        plan.bender_for(channel),
+        PLAN_FRAME_RATE,
+    )
+}
+
+fn append_keyframes<T>(keyframed: &mut Keyframed<T>, next: Keyframed<T>, start: f64) {
+    if next.is_animated() {
+        keyframed.append(next.delay(start));
+    }
+}
+
+/// Which way to play a plan, see [`Animation::toggle_of_icon`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    Forward,
+    /// From where the plan finishes back to where it starts
+    Backward,
+}
+
+impl Direction {
+    /// Keyframed motion, played backward if need be
+    fn keyframes<T>(self, keyframed: Keyframed<T>) -> Keyframed<T> {
+        match self {
+            Direction::Forward => keyframed,
+            Direction::Backward => keyframed.reversed(),
+        }
+    }
+
+    /// A spring, run back from its final value if need be. The spring is rerun rather than its
+    /// keyframes reversed so it still settles, rather than starts, with a wobble.
+    fn spring(self, animation: AnimatedValue) -> AnimatedValue {
+        match self {
+            Direction::Forward => animation,
+            Direction::Backward => animation.reversed(),
+        }
+    }
+}
+
+/// Fraction of a parts plan's duration between the start of one part and the next, as the
+/// `nth_group` offset of [`twirl`] and [`pulse`]
+const PART_STAGGER: f64 = 0.2;
+
+/// Frames, at [`PLAN_FRAME_RATE`], between drawings of the glyph for [`VariationMode::Redraw`]
+const REDRAW_FRAMES: f64 = 3.0;
+
+/// `time`, in seconds, in frames at `frame_rate`. Float error is rounded away so times made from
+/// whole frames, e.g. 30 / 60, come back whole rather than as 29.999…
+pub(crate) fn to_frames(time: f64, frame_rate: f64) -> f64 {
+    let frames = time * frame_rate;
+    let whole = frames.round();
+    if (frames - whole).abs() < 1e-9 {
+        whole
+    } else {
+        frames
+    }
+}
+
+/// Produces keyframes suitable for use with [`Group::rotate`], from `start` to `end` seconds
+///
+/// Fails with [`AnimationError::EmptyTimeRange`] unless `end` is after `start`.
+pub(crate) fn twirl(
+    start: f64,
+    end: f64,
+    nth_group: usize,
+) -> Result<Keyframed<f64>, AnimationError> {
+    if (start..end).is_empty() {
+        return Err(AnimationError::EmptyTimeRange(start, end));
+    }
+    let nth_group = nth_group as f64;
+    vec![
+        (0.2 * (end - start) * nth_group, 0.0),
+        (0.2 * (end - start) * (nth_group + 2.0), 360.0),
+    ]
+    .try_into()
+}
+
+/// Produces keyframes suitable for use with [`Group::scale`], from `start` to `end` seconds
+///
+/// Fails with [`AnimationError::EmptyTimeRange`] unless `end` is after `start`.
+pub(crate) fn pulse(
+    start: f64,
+    end: f64,
+    nth_group: usize,
+) -> Result<Keyframed<(f64, f64)>, AnimationError> {
+    if (start..end).is_empty() {
+        return Err(AnimationError::EmptyTimeRange(start, end));
+    }
+    let nth_group = nth_group as f64;
+    vec![
+        (0.2 * (end - start) * nth_group, (100.0, 100.0)),
+        (0.2 * (end - start) * (nth_group + 1.0), (150.0, 150.0)),
+        (0.2 * (end - start) * (nth_group + 2.0), (100.0, 100.0)),
+    ]
+    .try_into()
+}
+
+/// Move from `from` at the start to `to` at `end` with [`Ease::STANDARD`]
+fn transition<T>(end: f64, from: T, to: T) -> Keyframed<T> {
+    Keyframed(vec![
+        Keyframe::new(0.0, from).with_ease(Ease::STANDARD),
+        Keyframe::new(end, to),
+    ])
+}
+
+/// Piece-wise animation wants to animate "parts" as the eye perceives them; try to so group
+///
+/// Most importantly, if we have a shape and hole(s) cut out of it they should be together.
+///
+/// Make some simplifying assumptions:
+///
+/// 1. Icons don't typically use one subpath to cut a hole in many other subpaths
+/// 1. Icons typically fully contain the holepunch within the ... punchee?
+///
+/// Since we are using non-zero fill, figure out shape by shape what the winding value is. Initially I thought
+/// we could simply look at the direction from [`BezPath::area`] but that ofc isn't enough to know if the final
+/// winding is nonzero.
+///
+/// If `group_overlaps` is set filled subpaths that overlap are put in one part so overlaps don't come
+/// apart as parts move. They are only grouped, not unioned; each subpath keeps its own contours.
+///
+/// Each part is centered on its `anchor`; `icon_center` is the center of the icon the parts make up.
+/// If `color_parts` is set each part is filled with a color of its own, to show the grouping.
+fn group_parts(
+    shapes: Vec<Keyframed<BezPath>>,
+    group_overlaps: bool,
+    anchor: Anchor,
+    icon_center: Point,
+    color_parts: bool,
+) -> Vec<Group> {
+    // group on subpaths; input may have multi-subpath beziers
+    let shapes: Vec<_> = shapes
+        .into_iter()
+        .flat_map(Keyframed::into_subpaths)
+        .collect();
+
+    let paths: Vec<_> = shapes.iter().map(|s| &s.earliest().value).collect();
+
+    // Figure out what is/isn't filled
+    let filled: Vec<_> = paths
+        .iter()
+        .map(|bez| {
+            let Some(contained) = bez.contained_point() else {
+                if bez.area() != 0.0 {
+                    log::warn!("No contained point for {}", bez.to_svg());
+                }
+                return false;
+            };
+            let winding: i32 = paths.iter().map(|bez| bez.winding(contained)).sum();
+            winding != 0
+        })
+        .collect();
+
+    // Sort filled ahead of unfilled, smaller before larger (to simplify matching below)
+    let mut ordered: Vec<_> = (0..shapes.len()).collect();
+    ordered.sort_by_cached_key(|i| {
+        (
+            -(filled[*i] as i32),
+            OrderedFloat(paths[*i].bounding_box().area()),
+        )
+    });
+
+    // Group cutouts with the smallest containing filled subpath
+    // Doesn't generalize but perhaps suffices for icons
+    // In each group [0] must exist and is a filled subpath, [1..n] are optional and are unfilled
+    // Groups are of indices into shapes until the end so shapes are moved, never copied
+    let mut groups: Vec<Vec<usize>> = Default::default();
+    let mut bboxes = Vec::default(); // the bbox of group[n][0] is bbox[n]
+    for i in ordered {
+        let bez = &paths[i];
+        let bbox = bez.bounding_box();
+        if filled[i] {
+            // start a new group for a filled subpath
+            groups.push(vec![i]);
+            bboxes.push(bbox);
+        } else {
+            // add cutout to the smallest (first, courtesy of sort above) containing filled subpath
+            if let Some(group) = bboxes
+                .iter()
+                .position(|group_bbox| group_bbox.intersect(bbox) == bbox)
+            {
+                groups[group].push(i);
+            } else {
+                log::warn!(
+                    "Unfilled shape isn't within any filled shape, dropping it: {}",
+                    bez.to_svg()
+                );
+            }
+        }
+    }
+
+    if group_overlaps {
+        groups = group_overlapping(groups, &paths);
+    }
+    log::debug!(
+        "Grouped {} subpaths into {} parts",
+        shapes.len(),
+        groups.len()
+    );
+
+    let mut shapes: Vec<_> = shapes.into_iter().map(Some).collect();
+    let mut parts: Vec<_> = groups
+        .into_iter()
+        .enumerate()
+        .map(|(i, members)| {
+            let shapes: Vec<_> = members
+                .into_iter()
+                .filter_map(|member| shapes[member].take())
+                .collect();
+            let center = part_anchor(&shapes, anchor, icon_center);
+            Group {
+                children: shapes.into_iter().map(Element::Shape).collect(),
+                center,
+                fill: color_parts.then(|| nth_group_color(i)),
+                ..Default::default()
+            }
+        })
+        .collect();
+    // The drawbox starts at the origin, centered on the icon
+    let icon = Rect::new(0.0, 0.0, 2.0 * icon_center.x, 2.0 * icon_center.y);
+    let labels = guess_labels(&parts.iter().collect::<Vec<_>>(), icon);
+    for (part, label) in parts.iter_mut().zip(labels) {
+        part.name = Some(label);
+    }
+    parts
+}
+
+/// The point a part, made by [`group_parts`], rotates and scales about
+fn part_anchor(shapes: &[Keyframed<BezPath>], anchor: Anchor, icon_center: Point) -> Point {
+    let paths: Vec<_> = shapes.iter().map(|k| &k.earliest().value).collect();
+    let bbox_center = || {
+        paths
+            .iter()
+            .map(|path| path.bounding_box())
+            .reduce(|acc, e| acc.union(e))
+            .unwrap() // a part has at least one shape
+            .center()
+    };
+    match anchor {
+        Anchor::BoundingBox => bbox_center(),
+        Anchor::Centroid => centroid(&paths).unwrap_or_else(bbox_center),
+        Anchor::NearestToCenter => nearest_point(&paths, icon_center).unwrap_or_else(bbox_center),
+        Anchor::Fixed(point) => point,
+    }
+}
+
+/// The center of mass of the area `paths` fill under the nonzero rule, cutouts excluded.
+/// None if they fill no area.
+fn centroid(paths: &[&BezPath]) -> Option<Point> {
+    let mut area = 0.0;
+    let mut moment = Vec2::ZERO;
+    for path in paths {
+        let filled = path
+            .contained_point()
+            .is_some_and(|p| paths.iter().map(|path| path.winding(p)).sum::<i32>() != 0);
+        let (path_area, path_centroid) = area_and_centroid(path);
+        let path_area = if filled {
+            path_area.abs()
+        } else {
+            -path_area.abs()
+        };
+        area += path_area;
+        moment += path_area * path_centroid.to_vec2();
+    }
+    (area.abs() > 1e-9).then(|| (moment / area).to_point())
+}
+
+/// Signed area and centroid of the polygon a closed path flattens to
+fn area_and_centroid(path: &BezPath) -> (f64, Point) {
+    let (mut area, mut moment) = (0.0, Vec2::ZERO);
+    let mut edge = |a: Point, b: Point| {
+        let cross = a.to_vec2().cross(b.to_vec2());
+        area += cross / 2.0;
+        moment += cross * (a.to_vec2() + b.to_vec2());
+    };
+    let (mut start, mut last) = (Point::ZERO, Point::ZERO);
+    kurbo::flatten(path.iter(), 0.1, |el| match el {
+        PathEl::MoveTo(p) => (start, last) = (p, p),
+        PathEl::LineTo(p) => {
+            edge(last, p);
+            last = p;
+        }
+        PathEl::ClosePath => {
+            edge(last, start);
+            last = start;
+        }
+        _ => (),
+    });
+    if area == 0.0 {
+        return (0.0, Point::ZERO);
+    }
+    (area, (moment / (6.0 * area)).to_point())
+}
+
+/// The point of the area `paths` fill nearest `target`; `target` itself if it is filled.
+/// None if there are no segments.
+fn nearest_point(paths: &[&BezPath], target: Point) -> Option<Point> {
+    if paths.iter().map(|path| path.winding(target)).sum::<i32>() != 0 {
+        return Some(target);
+    }
+    paths
+        .iter()
+        .flat_map(|path| path.segments())
+        .map(|seg| {
+            let nearest = seg.nearest(target, 1e-6);
+            (nearest.distance_sq, seg.eval(nearest.t))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, point)| point)
+}
+
+/// Combine groups, as made by [`group_parts`], whose areas overlap. Groups are of indices into
+/// `paths`. The paths themselves are left alone, overlapping contours aren't unioned.
+///
+/// The area of a group is its filled subpath less its cutouts so a part that sits within a
+/// cutout, e.g. a dot inside a ring, stays a part of its own.
+fn group_overlapping(groups: Vec<Vec<usize>>, paths: &[&BezPath]) -> Vec<Vec<usize>> {
+    let area = |group: &[usize]| group.iter().map(|i| paths[*i]).collect::<Vec<_>>();
+    // Each merged group is kept as the groups it was made from to compare them one by one
+    let mut merged: Vec<Vec<Vec<usize>>> = Vec::with_capacity(groups.len());
+    for group in groups {
+        let overlapping: Vec<_> = (0..merged.len())
+            .filter(|i| {
+                merged[*i]
+                    .iter()
+                    .any(|other| overlaps(&area(&group), &area(other)))
+            })
+            .collect();
+        // Fold every group this overlaps into the first of them, a group may bridge several
+        let Some((&first, rest)) = overlapping.split_first() else {
+            merged.push(vec![group]);
+            continue;
+        };
+        for i in rest.iter().rev() {
+            let absorbed = merged.remove(*i);
+            merged[first].extend(absorbed);
+        }
+        merged[first].push(group);
+    }
+    merged.into_iter().map(|m| m.concat()).collect()
+}
+
+/// Whether two areas overlap, each a filled subpath, first, and the cutouts within it
+fn overlaps(a: &[&BezPath], b: &[&BezPath]) -> bool {
+    let bbox = a[0].bounding_box().intersect(b[0].bounding_box());
+    if bbox.width() <= 0.0 || bbox.height() <= 0.0 {
+        return false;
+    }
+    if edges_cross(a, b) {
+        return true;
+    }
+    // Otherwise each lies wholly inside, or outside, the other so a point of either tells
+    let inside = |a: &[&BezPath], b: &[&BezPath]| {
+        a[0].contained_point()
+            .is_some_and(|p| b.iter().map(|path| path.winding(p)).sum::<i32>() != 0)
+    };
+    inside(a, b) || inside(b, a)
+}
+
+/// Whether any edge of a subpath in `a` crosses one in `b`
+fn edges_cross(a: &[&BezPath], b: &[&BezPath]) -> bool {
+    let mut lines = Vec::new();
+    for path in b {
+        let (mut start, mut last) = (Point::ZERO, Point::ZERO);
+        kurbo::flatten(path.iter(), 0.1, |el| match el {
+            PathEl::MoveTo(p) => (start, last) = (p, p),
+            PathEl::LineTo(p) => {
+                lines.push(Line::new(last, p));
+                last = p;
+            }
+            PathEl::ClosePath => {
+                lines.push(Line::new(last, start));
+                last = start;
+            }
+            _ => (),
+        });
+    }
+    a.iter().flat_map(|path| path.segments()).any(|seg| {
+        lines
+            .iter()
+            .any(|line| !seg.intersect_line(*line).is_empty())
+    })
+}
+
+impl Group {
+    /// Piece-wise animation wants to animate "parts" as the eye perceives them; try to so group.
+    pub fn group_parts(&mut self) {
+        self.group_parts_as(false, Anchor::default(), true)
+    }
+
+    /// Name child groups in order, e.g. from [`crate::labels::PartHints`], replacing guesses.
+    /// Groups past the end of `names` keep their names.
+    pub fn name_parts(&mut self, names: &[String]) {
+        for (part, name) in self.mutable_child_groups().zip(names) {
+            part.name = Some(name.clone());
+        }
+    }
+
+    /// As [`Group::group_parts`] but overlapping filled subpaths are kept in one part
+    ///
+    /// The subpaths are grouped as they are, not unioned, so the part still draws every contour.
+    pub fn group_overlapping_parts(&mut self) {
+        self.group_parts_as(true, Anchor::default(), true)
+    }
+
+    fn group_parts_as(&mut self, group_overlaps: bool, anchor: Anchor, color_parts: bool) {
+        let mut frontier = vec![self];
+        while let Some(group) = frontier.pop() {
+            let mut new_children = Vec::new();
+            let mut existing_groups = HashSet::new();
+            let mut children = std::mem::take(&mut group.children).into_iter().peekable();
+            while let Some(child) = children.next() {
+                // TODO: existing groups => frontier
+                // runs of shapes => group_parts
+
+                match child {
+                    Element::Group(g) => {
+                        existing_groups.insert(new_children.len());
+                        new_children.push(Element::Group(g));
+                    }
+                    Element::Shape(s) => {
+                        let mut shape_run = vec![s];
+                        while let Some(Element::Shape(..)) = children.peek() {
+                            let Some(Element::Shape(s)) = children.next() else {
+                                unreachable!("We just confirmed this to be the case!");
+                            };
+                            shape_run.push(s);
+                        }
+                        let groups = group_parts(
+                            shape_run,
+                            group_overlaps,
+                            anchor,
+                            group.center,
+                            color_parts,
+                        );
+                        new_children.extend(groups.into_iter().map(Element::Group));
+                    }
+                }
+            }
+            group.children = new_children;
+
+            for (i, el) in group.children.iter_mut().enumerate() {
+                if existing_groups.contains(&i) {
+                    let Element::Group(g) = el else {
+                        unreachable!();
+                    };
+                    frontier.push(g);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Element {
+    Group(Group),
+    Shape(Keyframed<BezPath>),
+}
+
+impl Element {
+    /// See [`Group::z`], shapes are at 0
+    pub fn z(&self) -> i32 {
+        match self {
+            Element::Group(g) => g.z,
+            Element::Shape(..) => 0,
+        }
+    }
+}
+
+/// Something with keyframes. Must have at least one definition.
+///
+/// Contains (f64, T) tuples sorted by .0 where the f64 is time in seconds. Times must be unique.
+///
+/// Holds the first value before min(time) and the last after max(time). To show something for
+/// only part of an animation see [`Group::set_visible`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Keyframe<T>>")]
+pub struct Keyframed<T>(Vec<Keyframe<T>>);
+
+impl<T> Keyframed<T> {
+    /// A single keyframe, `value` at `time`; [`Keyframed::push`] more to animate
+    pub fn new(time: f64, value: T) -> Self {
+        Self(vec![Keyframe::new(time, value)])
+    }
+
+    pub fn earliest(&self) -> &Keyframe<T> {
+        &self.0[0]
+    }
+
+    pub fn latest(&self) -> &Keyframe<T> {
+        &self.0[self.0.len() - 1]
+    }
+
+    /// Start `seconds` later, or earlier if negative
+    pub fn delay(mut self, seconds: f64) -> Self {
+        for keyframe in self.0.iter_mut() {
+            keyframe.time += seconds;
+        }
+        self
+    }
+
+    /// Take `factor` times as long, starting at the same time
+    ///
+    /// Fails with [`AnimationError::InvalidStretch`] unless `factor` is a positive number.
+    pub fn stretch(mut self, factor: f64) -> Result<Self, AnimationError> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(AnimationError::InvalidStretch(factor));
+        }
+        let start = self.earliest().time;
+        for keyframe in self.0.iter_mut() {
+            keyframe.time = start + (keyframe.time - start) * factor;
+        }
+        Ok(self)
+    }
+
+    /// Play `next` once this finishes, its first keyframe taking over from this one's last
+    pub fn concat(mut self, next: Keyframed<T>) -> Self {
+        let start = self.latest().time - next.earliest().time;
+        self.append(next.delay(start));
+        self
+    }
+
+    /// Multiply every time by `factor`, e.g. to change duration
+    pub(crate) fn scale_times(&mut self, factor: f64) {
+        for keyframe in self.0.iter_mut() {
+            keyframe.time *= factor;
+        }
+    }
+
+    /// Continue with `next`, which takes over from its first keyframe; later keyframes are dropped
+    pub(crate) fn append(&mut self, next: Keyframed<T>) {
+        let start = next.earliest().time;
+        self.0.retain(|keyframe| keyframe.time < start);
+        self.0.extend(next.0);
+    }
+
+    /// The same timing with each value replaced by `f` of it
+    pub(crate) fn map<U>(self, f: impl Fn(T) -> U) -> Keyframed<U> {
+        Keyframed(
+            self.0
+                .into_iter()
+                .map(|k| Keyframe {
+                    time: k.time,
+                    value: f(k.value),
+                    ease: k.ease,
+                    hold: k.hold,
+                })
+                .collect(),
+        )
+    }
+
+    /// Play backward over the same times, the last value first, eases reversed to match
+    pub fn reversed(mut self) -> Self {
+        if !self.is_animated() {
+            return self;
+        }
+        let first = self.earliest().time;
+        let last = self.latest().time;
+        // The ease from a keyframe to the next is, reversed, the ease from that next keyframe back.
+        // A hold stays a hold, holding what was the value it jumped to.
+        let (mut ease, mut hold) = (None, false);
+        for keyframe in self.0.iter_mut() {
+            keyframe.time = first + last - keyframe.time;
+            let next_ease = keyframe.ease.map(|e| e.reversed());
+            let next_hold = keyframe.hold;
+            keyframe.ease = ease;
+            keyframe.hold = hold;
+            ease = next_ease;
+            hold = next_hold;
+        }
+        self.0.reverse();
+        self
+    }
+
+    pub fn is_animated(&self) -> bool {
+        self.len() > 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Always false, a [`Keyframed`] has at least one keyframe
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Keyframe<T>> {
+        self.0.iter()
+    }
+
+    /// Mutate values in place. Changing the time of a keyframe must not reorder them.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Keyframe<T>> {
+        self.0.iter_mut()
+    }
+
+    pub fn push(&mut self, keyframe: Keyframe<T>) {
+        if let Some(pos) = self.0.iter().position(|kf| kf.time == keyframe.time) {
+            self.0[pos] = keyframe;
+        } else {
+            self.0.push(keyframe);
+        }
+    }
+
+    /// Round keyframe times to whole frames at `frame_rate`. If several land on the same frame
+    /// the last wins.
+    pub fn snap_to_frames(&mut self, frame_rate: f64) {
+        for keyframe in self.0.iter_mut() {
+            keyframe.time = (keyframe.time * frame_rate).round() / frame_rate;
+        }
+        // keep the last of each run of equal frames
+        self.0.reverse();
+        self.0.dedup_by(|a, b| a.time == b.time);
+        self.0.reverse();
+    }
+}
+
+impl<T: Lerp + Clone> Keyframed<T> {
+    /// The value at any time, eased between keyframes and held before the first and after the last
+    pub fn value_at(&self, time: f64) -> T {
+        let Some(i) = self.0.iter().rposition(|k| k.time <= time) else {
+            return self.earliest().value.clone();
+        };
+        let Some(next) = self.0.get(i + 1) else {
+            return self.0[i].value.clone();
+        };
+        let current = &self.0[i];
+        if current.hold {
+            return current.value.clone();
+        }
+        let t = (time - current.time) / (next.time - current.time);
+        let progress = current.ease.unwrap_or_default().progress(t);
+        current.value.lerp(&next.value, progress)
+    }
+
+    /// Keyframes within range, times relative to its start, plus interpolated values at each end
+    fn extract(&self, range: Range<f64>) -> Self {
+        if !self.is_animated() {
+            return Keyframed::new(0.0, self.earliest().value.clone());
+        }
+        let mut times = vec![range.start];
+        times.extend(
+            self.0
+                .iter()
+                .map(|k| k.time)
+                .filter(|time| range.start < *time && *time < range.end),
+        );
+        times.push(range.end);
+
+        let mut keyframes: Vec<_> = times
+            .windows(2)
+            .map(|window| {
+                let (start, end) = (window[0], window[1]);
+                let mut keyframe = Keyframe::new(start - range.start, self.value_at(start));
+                keyframe.ease = self.ease_between(start, end);
+                keyframe.hold = self.held_at(start);
+                keyframe
+            })
+            .collect();
+        keyframes.push(Keyframe::new(
+            range.end - range.start,
+            self.value_at(range.end),
+        ));
+        Keyframed(keyframes)
+    }
+
+    /// Drop keyframes the motion doesn't need, that is those where easing straight from the
+    /// prior keyframe to the next stays within `tolerance` of the original at every frame at
+    /// `frame_rate`
+    pub fn simplify(&mut self, frame_rate: f64, tolerance: f64, distance: impl Fn(&T, &T) -> f64) {
+        let original = self.clone();
+        let mut i = 1;
+        while i + 1 < self.0.len() {
+            let (prev, next) = (&self.0[i - 1], &self.0[i + 1]);
+            // A jump can't be eased away
+            if prev.hold || self.0[i].hold {
+                i += 1;
+                continue;
+            }
+            let ease = prev.ease.unwrap_or_default();
+            let span = next.time - prev.time;
+            let (first, last) = (
+                to_frames(prev.time, frame_rate).floor() as i64 + 1,
+                to_frames(next.time, frame_rate).ceil() as i64,
+            );
+            let times = (first..last)
+                .map(|f| f as f64 / frame_rate)
+                .chain(std::iter::once(self.0[i].time));
+            let mut within = true;
+            for time in times {
+                let merged = prev
+                    .value
+                    .lerp(&next.value, ease.progress((time - prev.time) / span));
+                if distance(&merged, &original.value_at(time)) > tolerance {
+                    within = false;
+                    break;
+                }
+            }
+            if within {
+                self.0.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Whether the value is held at `time`, waiting to jump at the next keyframe
+    fn held_at(&self, time: f64) -> bool {
+        self.0
+            .iter()
+            .rposition(|k| k.time <= time)
+            .is_some_and(|i| self.0[i].hold && i + 1 < self.0.len())
+    }
+
+    /// The ease from start to end, which must not span a keyframe
+    fn ease_between(&self, start: f64, end: f64) -> Option<Ease> {
+        let i = self.0.iter().rposition(|k| k.time <= start)?;
+        let current = &self.0[i];
+        let next = self.0.get(i + 1)?;
+        let span = next.time - current.time;
+        let local = (start - current.time) / span..(end - current.time) / span;
+        if local == (0.0..1.0) {
+            return current.ease;
+        }
+        Some(current.ease.unwrap_or_default().split(local))
+    }
+}
+
+impl<T: PartialEq> Keyframed<T> {
+    /// Drop keyframes that have the same value as both neighbours; they change nothing.
+    pub fn dedup(&mut self) {
+        let mut i = 1;
+        while i + 1 < self.0.len() {
+            if self.0[i - 1].value == self.0[i].value && self.0[i].value == self.0[i + 1].value {
+                self.0.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+}
+
+impl<T> TryFrom<Vec<(f64, T)>> for Keyframed<T> {
+    type Error = AnimationError;
+
+    fn try_from(value: Vec<(f64, T)>) -> Result<Self, Self::Error> {
+        value
+            .into_iter()
+            .map(|(time, value)| Keyframe::new(time, value))
+            .collect::<Vec<_>>()
+            .try_into()
+    }
+}
+
+impl<T> TryFrom<Vec<Keyframe<T>>> for Keyframed<T> {
+    type Error = AnimationError;
+
+    fn try_from(value: Vec<Keyframe<T>>) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(AnimationError::NoKeyframes);
+        }
+        let mut value = value;
+        value.sort_by_key(|keyframe| OrderedFloat(keyframe.time));
+        for i in 0..value.len() - 1 {
+            if value[i].time == value[i + 1].time {
+                return Err(AnimationError::MultipleValuesForTime(value[i].time));
+            }
+        }
+        Ok(Keyframed(value))
+    }
+}
+
+/// Ok if `a` and `b` can be interpolated, otherwise where `b` first departs from `a`
+///
+/// Paths are compatible if they use the same commands in the same order, e.g. two drawings of a
+/// glyph at different variation positions usually are.
+pub fn compatible(a: &BezPath, b: &BezPath) -> Result<(), Mismatch> {
+    let (a, b) = (a.elements(), b.elements());
+    for index in 0..a.len().max(b.len()) {
+        match (
+            a.get(index).map(path_command),
+            b.get(index).map(path_command),
+        ) {
+            (Some(expected), Some(found)) if expected != found => {
+                return Err(Mismatch::Command {
+                    index,
+                    expected,
+                    found,
+                })
+            }
+            (Some(expected), None) => return Err(Mismatch::Missing { index, expected }),
+            (None, Some(found)) => return Err(Mismatch::Extra { index, found }),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn draw(
+    src_to_dest_units: FontToOutput,
+    location: &Location,
+    gid: GlyphId,
+    glyph: &OutlineGlyph,
+) -> Result<BezPath, AnimationError> {
+    let mut bez_pen = BezPathPen::new();
+    let mut transform_pen = TransformPen::new(&mut bez_pen, src_to_dest_units.affine());
+
+    let settings = DrawSettings::unhinted(Size::unscaled(), location);
+    glyph
+        .draw(settings, &mut transform_pen)
+        .map_err(|e| AnimationError::DrawError(gid, e))?;
+    Ok(bez_pen.into_inner())
+}
+
+impl Keyframed<BezPath> {
+    /// The glyph drawn at its start and, if it varies, moving through its steps to its end at
+    /// `duration` seconds
+    pub(crate) fn for_glyph(
+        duration: f64,
+        src_to_dest_units: FontToOutput,
+        glyph_shape: &GlyphShape,
+    ) -> Result<Self, AnimationError> {
+        let mut result = Self::new(
+            0.0,
+            draw(
+                src_to_dest_units,
+                &glyph_shape.start,
+                glyph_shape.gid,
+                &glyph_shape.glyph,
+            )?,
+        );
+
+        if let Some(location) = &glyph_shape.end {
+            // Steps are evenly spaced and move steadily, together they make one continuous motion
+            let intervals = glyph_shape.steps.len() as f64 + 1.0;
+            for (i, step) in glyph_shape.steps.iter().enumerate() {
+                let time = duration * (i + 1) as f64 / intervals;
+                result.push(Keyframe::new(
+                    time,
+                    draw(src_to_dest_units, step, glyph_shape.gid, &glyph_shape.glyph)?,
+                ));
+            }
+            result.push(Keyframe::new(
+                duration,
+                draw(
+                    src_to_dest_units,
+                    location,
+                    glyph_shape.gid,
+                    &glyph_shape.glyph,
+                )?,
+            ));
+            if !glyph_shape.steps.is_empty() {
+                for keyframe in result.iter_mut() {
+                    keyframe.ease = Some(Ease::LINEAR);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// As [`Keyframed::for_glyph`] but the location moves as `plan` times [`Channel::Variation`],
+    /// the glyph is drawn every [`REDRAW_FRAMES`] where the location has got to and moves steadily
+    /// in between
+    ///
+    /// Glyphs with steps, e.g. a sweep, are already drawn along the way and are left as they are.
+    pub(crate) fn for_glyph_redrawn(
+        plan: &AnimationPlan,
+        duration: f64,
+        src_to_dest_units: FontToOutput,
+        glyph_shape: &GlyphShape,
+    ) -> Result<Self, AnimationError> {
+        let Some(end) = &glyph_shape.end else {
+            return Self::for_glyph(duration, src_to_dest_units, glyph_shape);
+        };
+        if !glyph_shape.steps.is_empty() || duration <= 0.0 {
+            return Self::for_glyph(duration, src_to_dest_units, glyph_shape);
+        }
+        let start = &glyph_shape.start;
+        let draw_at = |progress: f32| {
+            let mut location = start.clone();
+            for (coord, (from, to)) in location
+                .coords_mut()
+                .iter_mut()
+                .zip(start.coords().iter().zip(end.coords()))
+            {
+                let (from, to) = (from.to_f32(), to.to_f32());
+                // A spring may overshoot, but not beyond the axis
+                *coord = F2Dot14::from_f32((from + (to - from) * progress).clamp(-1.0, 1.0));
+            }
+            draw(
+                src_to_dest_units,
+                &location,
+                glyph_shape.gid,
+                &glyph_shape.glyph,
+            )
+        };
+
+        let progress = driven_keyframes(
+            plan,
+            Channel::Variation,
+            duration,
+            AnimatedValue::new(0.0, 1.0, AnimatedValueType::Scale),
+            Direction::Forward,
+            |v| v,
+        )?;
+        let mut result = Self::new(0.0, draw_at(0.0)?);
+        let samples = (to_frames(duration, PLAN_FRAME_RATE) / REDRAW_FRAMES).ceil() as usize;
+        for i in 1..samples {
+            let time = i as f64 * REDRAW_FRAMES / PLAN_FRAME_RATE;
+            result.push(Keyframe::new(
+                time,
+                draw_at(progress.value_at(time) as f32)?,
+            ));
+        }
+        // However far the spring has got, the glyph ends where it was asked to
+        result.push(Keyframe::new(duration, draw_at(1.0)?));
+        for keyframe in result.iter_mut() {
+            keyframe.ease = Some(Ease::LINEAR);
+        }
+        Ok(result)
+    }
+
+    /// Keyframes of the glyph followed by those of each step's icon, all drawn at the start location
+    pub(crate) fn for_sequence(
+        src_to_dest_units: FontToOutput,
+        glyph_shape: &GlyphShape,
+        steps: &[SequenceStep],
+    ) -> Result<Self, AnimationError> {
+        let mut result = Self::new(
+            0.0,
+            draw(
+                src_to_dest_units,
+                &glyph_shape.start,
+                glyph_shape.gid,
+                &glyph_shape.glyph,
+            )?,
+        );
+
+        let outlines = glyph_shape.font.outline_glyphs();
+        let mut frame = 0.0;
+        for step in steps {
+            let gid = icon_name_to_gid(glyph_shape.font, step.icon_name)
+                .map_err(|_| AnimationError::NoSuchSequenceIcon(step.icon_name.to_string()))?;
+            let glyph = outlines.get(gid).ok_or(AnimationError::NoOutline(gid))?;
+            // The ease into this step belongs to the keyframe before it
+            result.0.last_mut().unwrap().ease = step.ease;
+            frame += step.frames;
+            result.push(Keyframe::new(
+                frame / PLAN_FRAME_RATE,
+                draw(src_to_dest_units, &glyph_shape.start, gid, &glyph)?,
+            ));
+        }
+
+        Ok(result)
+    }
+
+    /// A keyframed path per subpath. Each keyframe's subpaths are moved, not copied, into place.
+    pub(crate) fn into_subpaths(self) -> Vec<Keyframed<BezPath>> {
+        let num_keyframes = self.0.len();
+        let mut keyframes_by_subpath: Vec<Vec<Keyframe<BezPath>>> = Vec::new();
+        for (i, keyframe) in self.0.into_iter().enumerate() {
+            let subpaths = keyframe.subpaths();
+            if i == 0 {
+                keyframes_by_subpath
+                    .resize_with(subpaths.len(), || Vec::with_capacity(num_keyframes));
+            }
+            // TODO: should we allow incompatible paths in?
+            assert_eq!(
+                keyframes_by_subpath.len(),
+                subpaths.len(),
+                "Incompatible subpaths unsupported"
+            );
+            for (keyframes, value) in keyframes_by_subpath.iter_mut().zip(subpaths) {
+                keyframes.push(Keyframe {
+                    time: keyframe.time,
+                    value,
+                    ease: keyframe.ease,
+                    hold: keyframe.hold,
+                });
+            }
+        }
+        keyframes_by_subpath.into_iter().map(Keyframed).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    /// Seconds from the start of the animation
+    pub time: f64,
+    pub value: T,
+    /// How to move from this keyframe to the next. None means [`Ease::default`].
+    pub ease: Option<Ease>,
+    /// Keep this value until the next keyframe then jump to it, e.g. to snap FILL partway
+    /// through. The ease is ignored.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub hold: bool,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f64, value: T) -> Self {
+        Self {
+            time,
+            value,
+            ease: None,
+            hold: false,
+        }
+    }
+
+    pub fn with_ease(mut self, ease: Ease) -> Self {
+        self.ease = Some(ease);
+        self
+    }
+
+    /// Hold this value until the next keyframe, see [`Keyframe::hold`]
+    pub fn held(mut self) -> Self {
+        self.hold = true;
+        self
+    }
+}
+
+/// Timing of the change between keyframes as a cubic from (0,0) to (1,1) with control points
+/// p1 and p2, as for CSS [cubic-bezier](https://developer.mozilla.org/en-US/docs/Web/CSS/easing-function#cubic-bezier_easing_function).
+/// X is time, Y is progress.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ease {
+    pub p1: Point,
+    pub p2: Point,
+}
+
+impl Ease {
+    pub const LINEAR: Ease = Ease {
+        p1: Point::new(0.0, 0.0),
+        p2: Point::new(1.0, 1.0),
+    };
+    pub const EASE_IN_OUT: Ease = Ease {
+        p1: Point::new(0.4, 0.0),
+        p2: Point::new(0.6, 1.0),
+    };
+    /// Material's [standard easing](https://m3.material.io/styles/motion/easing-and-duration/tokens-specs)
+    pub const STANDARD: Ease = Ease {
+        p1: Point::new(0.2, 0.0),
+        p2: Point::new(0.0, 1.0),
+    };
+    /// Material's emphasized easing, for expressive moves. Material draws it as two cubics; this
+    /// is the single cubic Material's web components use.
+    pub const EMPHASIZED: Ease = Ease {
+        p1: Point::new(0.3, 0.0),
+        p2: Point::new(0.0, 1.0),
+    };
+    /// Material's standard decelerate easing, for things coming to rest, e.g. entering
+    pub const DECELERATE: Ease = Ease {
+        p1: Point::new(0.0, 0.0),
+        p2: Point::new(0.0, 1.0),
+    };
+    /// Material's standard accelerate easing, for things leaving, e.g. exiting
+    pub const ACCELERATE: Ease = Ease {
+        p1: Point::new(0.3, 0.0),
+        p2: Point::new(1.0, 1.0),
+    };
+    /// Material's emphasized decelerate easing
+    pub const EMPHASIZED_DECELERATE: Ease = Ease {
+        p1: Point::new(0.05, 0.7),
+        p2: Point::new(0.1, 1.0),
+    };
+    /// Material's emphasized accelerate easing
+    pub const EMPHASIZED_ACCELERATE: Ease = Ease {
+        p1: Point::new(0.3, 0.0),
+        p2: Point::new(0.8, 0.15),
+    };
+    /// The names [`Ease::from_str`] accepts
+    pub const NAMES: [&'static str; 8] = [
+        "linear",
+        "ease-in-out",
+        "standard",
+        "emphasized",
+        "decelerate",
+        "accelerate",
+        "emphasized-decelerate",
+        "emphasized-accelerate",
+    ];
+}
+
+impl Ease {
+    fn curve(&self) -> CubicBez {
+        CubicBez::new(Point::ZERO, self.p1, self.p2, Point::new(1.0, 1.0))
+    }
+
+    /// The parameter of [`Ease::curve`] at which x, time, is reached
+    fn param_at(&self, x: f64) -> f64 {
+        let curve = self.curve();
+        let (mut lo, mut hi) = (0.0, 1.0);
+        for _ in 0..64 {
+            let mid = (lo + hi) / 2.0;
+            if curve.eval(mid).x < x {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo + hi) / 2.0
+    }
+
+    /// The same ease played backward, from (1,1) to (0,0)
+    pub fn reversed(&self) -> Ease {
+        Ease {
+            p1: Point::new(1.0 - self.p2.x, 1.0 - self.p2.y),
+            p2: Point::new(1.0 - self.p1.x, 1.0 - self.p1.y),
+        }
+    }
+
+    /// Progress, typically 0..1, at time t in 0..1
+    pub fn progress(&self, t: f64) -> f64 {
+        self.curve().eval(self.param_at(t)).y
+    }
+
+    /// The ease for just the part of the change in times, a sub-range of 0..1, rescaled to be an
+    /// ease in its own right
+    pub fn split(&self, times: Range<f64>) -> Ease {
+        let part = self
+            .curve()
+            .subsegment(self.param_at(times.start)..self.param_at(times.end));
+        let (dx, dy) = (part.p3.x - part.p0.x, part.p3.y - part.p0.y);
+        if dx.abs() < 1e-9 || dy.abs() < 1e-9 {
+            return Ease::LINEAR;
+        }
+        let normalize = |p: Point| Point::new((p.x - part.p0.x) / dx, (p.y - part.p0.y) / dy);
+        Ease {
+            p1: normalize(part.p1),
+            p2: normalize(part.p2),
+        }
+    }
+}
+
+impl Default for Ease {
+    fn default() -> Self {
+        Ease::EASE_IN_OUT
+    }
+}
+
+impl FromStr for Ease {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Ease::LINEAR),
+            "ease-in-out" => Ok(Ease::EASE_IN_OUT),
+            "standard" => Ok(Ease::STANDARD),
+            "emphasized" => Ok(Ease::EMPHASIZED),
+            "decelerate" => Ok(Ease::DECELERATE),
+            "accelerate" => Ok(Ease::ACCELERATE),
+            "emphasized-decelerate" => Ok(Ease::EMPHASIZED_DECELERATE),
+            "emphasized-accelerate" => Ok(Ease::EMPHASIZED_ACCELERATE),
+            _ => Err(Error::UnrecognizedEase(s.to_string())),
+        }
+    }
+}
+
+impl Keyframe<BezPath> {
+    pub(crate) fn subpaths(&self) -> Vec<BezPath> {
+        subpaths(&self.value)
+    }
+}
+
+/// Each subpath of `path`, that is from one move to the next
+pub(crate) fn subpaths(path: &BezPath) -> Vec<BezPath> {
+    let mut paths = Vec::new();
+    let mut last_start = 0;
+    let elements = path.elements();
+    for (i, e) in elements.iter().enumerate().skip(1) {
+        if let PathEl::MoveTo(..) = e {
+            paths.push(BezPath::from_vec(elements[last_start..i].to_vec()));
+            last_start = i;
+        }
+    }
+    if last_start < elements.len() - 1 {
+        paths.push(BezPath::from_vec(elements[last_start..].to_vec()));
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, str::FromStr, time::Duration};
+
+    use kurbo::{BezPath, Circle, Point, Rect, Shape, Vec2};
+
+    use crate::{
+        bender::{Bender, MotionBender},
+        error::{AnimationError, Mismatch},
+        plan::{Anchor, AnimationPlan, CompositeStep, PartSelection, PlanOptions, PLAN_FRAME_RATE},
+        spring::Spring,
+    };
+
+    use super::{
+        bent, compatible, part_anchor, pulse, to_frames, twirl, Animation, Camera, ColorStop,
+        Direction, Ease, Element, Gradient, Group, IrVisitor, Keyframe, Keyframed, KeyframesMut,
+        Matte, MatteMode, ReverseShapes, SCHEMA_VERSION,
+    };
+
+    /// When each keyframe is, in frames at 60fps
+    fn frames<T>(keyframed: &Keyframed<T>) -> Vec<f64> {
+        keyframed
+            .iter()
+            .map(|k| to_frames(k.time, PLAN_FRAME_RATE))
+            .collect()
+    }
+
+    fn two_part_animation() -> Animation {
+        let part = |rect: Rect| Element::Shape(Keyframed::new(0.0, rect.to_path(0.1)));
+        let mut root = Group::default();
+        root.children.push(Element::Group(Group {
+            children: vec![part(Rect::new(0.0, 0.0, 10.0, 10.0))],
+            ..Default::default()
+        }));
+        root.children.push(part(Rect::new(20.0, 20.0, 30.0, 30.0)));
+        Animation::new(100.0, 100.0, Duration::from_secs(1), root)
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        groups: usize,
+        shapes: usize,
+        keyframed: usize,
+    }
+
+    impl IrVisitor for Counter {
+        fn visit_group(&mut self, _group: &mut Group) {
+            self.groups += 1;
+        }
+
+        fn visit_shape(&mut self, _shape: &mut Keyframed<BezPath>) {
+            self.shapes += 1;
+        }
+
+        fn visit_keyframes(&mut self, _keyframes: KeyframesMut<'_>) {
+            self.keyframed += 1;
+        }
+    }
+
+    #[test]
+    fn visits_everything() {
+        let mut animation = two_part_animation();
+        let mut counter = Counter::default();
+        animation.visit_mut(&mut counter);
+        assert_eq!(
+            (2, 2, 12),
+            (counter.groups, counter.shapes, counter.keyframed),
+            "2 groups with 5 animatable properties each plus 2 shapes"
+        );
+    }
+
+    #[test]
+    fn visitor_can_mutate() {
+        struct Recolor;
+        impl IrVisitor for Recolor {
+            fn visit_group(&mut self, group: &mut Group) {
+                group.set_fill(Some((1, 2, 3)));
+            }
+        }
+
+        let mut animation = two_part_animation();
+        animation.visit_mut(&mut Recolor);
+        assert_eq!(Some((1, 2, 3)), animation.root().fill());
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let json = serde_json::to_string(&two_part_animation()).unwrap();
+        let animation = Animation::from_json(&json).unwrap();
+        assert_eq!(json, serde_json::to_string(&animation).unwrap());
+        assert!(json.starts_with(&format!("{{\"schema_version\":{SCHEMA_VERSION},")));
+    }
+
+    #[test]
+    fn reject_other_schema_version() {
+        let json = serde_json::to_string(&two_part_animation())
+            .unwrap()
+            .replacen(
+                &format!("\"schema_version\":{SCHEMA_VERSION}"),
+                "\"schema_version\":999",
+                1,
+            );
+        let err = Animation::from_json(&json).unwrap_err();
+        assert!(err.to_string().contains("schema version 999"), "{err}");
+    }
+
+    #[test]
+    fn value_at_eases() {
+        let mut rotate = Keyframed::new(0.0, 0.0);
+        rotate.push(Keyframe::new(10.0, 100.0));
+        assert_eq!(0.0, rotate.value_at(-5.0));
+        assert!((rotate.value_at(5.0) - 50.0).abs() < 1e-6, "Symmetric ease");
+        assert!(rotate.value_at(2.0) < 20.0, "Should ease in");
+        assert_eq!(100.0, rotate.value_at(15.0));
+    }
+
+    #[test]
+    fn value_at_every_value_type() {
+        let linear = |from, to| {
+            Keyframed(vec![
+                Keyframe::new(0.0, from).with_ease(Ease::LINEAR),
+                Keyframe::new(10.0, to),
+            ])
+        };
+        assert_eq!(
+            (50.0, 25.0),
+            linear((0.0, 0.0), (100.0, 50.0)).value_at(5.0)
+        );
+        assert_eq!(
+            Vec2::new(-5.0, 10.0),
+            linear(Vec2::ZERO, Vec2::new(-10.0, 20.0)).value_at(5.0)
+        );
+        let square = |size| Rect::new(0.0, 0.0, size, size).to_path(0.1);
+        assert_eq!(
+            square(15.0).elements(),
+            linear(square(10.0), square(20.0)).value_at(5.0).elements()
+        );
+    }
+
+    #[test]
+    fn hold_jumps_at_the_next_keyframe() {
+        let fill = Keyframed(vec![
+            Keyframe::new(0.0, 0.0).held(),
+            Keyframe::new(10.0, 1.0),
+        ]);
+        assert_eq!(
+            vec![0.0, 0.0, 1.0],
+            vec![fill.value_at(0.0), fill.value_at(9.9), fill.value_at(10.0)]
+        );
+        // Backward the final value holds instead, still jumping at the end of the span
+        let fill = fill.reversed();
+        assert!(fill.earliest().hold);
+        assert_eq!(
+            vec![1.0, 1.0, 0.0],
+            vec![fill.value_at(0.0), fill.value_at(9.9), fill.value_at(10.0)]
+        );
+    }
+
+    #[test]
+    fn extract_keeps_holds() {
+        let fill = Keyframed(vec![
+            Keyframe::new(0.0, 0.0).held(),
+            Keyframe::new(10.0, 1.0),
+        ]);
+        let part = fill.extract(5.0..10.0);
+        assert!(part.earliest().hold);
+        assert_eq!(0.0, part.value_at(4.9));
+    }
+
+    #[test]
+    fn split_ease_follows_the_original() {
+        let ease = Ease::EASE_IN_OUT;
+        let part = ease.split(0.25..0.75);
+        let (y0, y1) = (ease.progress(0.25), ease.progress(0.75));
+        for t in [0.1, 0.3, 0.5, 0.9] {
+            let expected = ease.progress(0.25 + 0.5 * t);
+            let actual = y0 + (y1 - y0) * part.progress(t);
+            assert!((expected - actual).abs() < 1e-6, "{expected} != {actual}");
+        }
+    }
+
+    fn polygon(points: &[(f64, f64)]) -> BezPath {
+        let mut path = BezPath::new();
+        path.move_to(points[0]);
+        for point in &points[1..] {
+            path.line_to(*point);
+        }
+        path.close_path();
+        path
+    }
+
+    #[test]
+    fn parts_are_colored_unless_plain() {
+        for color_parts in [true, false] {
+            let mut root = Group::default();
+            for x in [0.0, 20.0] {
+                root.children.push(Element::Shape(Keyframed::new(
+                    0.0,
+                    Rect::new(x, 0.0, x + 10.0, 10.0).to_path(0.1),
+                )));
+            }
+            root.group_parts_as(false, Anchor::default(), color_parts);
+            let fills: Vec<_> = root
+                .mutable_child_groups()
+                .map(|g| g.fill.is_some())
+                .collect();
+            assert_eq!(vec![color_parts; 2], fills);
+        }
+    }
+
+    #[test]
+    fn part_anchors() {
+        // An L, the center of its bounding box is off the shape
+        let l = vec![Keyframed::new(
+            0.0,
+            polygon(&[
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (10.0, 2.0),
+                (2.0, 2.0),
+                (2.0, 10.0),
+                (0.0, 10.0),
+            ]),
+        )];
+        let anchor = |anchor, icon_center: (f64, f64)| part_anchor(&l, anchor, icon_center.into());
+        assert_eq!(
+            Point::new(5.0, 5.0),
+            anchor(Anchor::BoundingBox, (0.0, 0.0))
+        );
+        // Bars of area 20 centered on (5, 1) and 16 on (1, 6)
+        let centroid = anchor(Anchor::Centroid, (0.0, 0.0));
+        assert!(
+            (centroid - Point::new(116.0 / 36.0, 116.0 / 36.0)).hypot() < 1e-9,
+            "{centroid:?}"
+        );
+        assert_eq!(
+            Point::new(10.0, 1.0),
+            anchor(Anchor::NearestToCenter, (20.0, 1.0))
+        );
+        assert_eq!(
+            Point::new(1.0, 1.0),
+            anchor(Anchor::NearestToCenter, (1.0, 1.0))
+        );
+        assert_eq!(
+            Point::new(3.0, 4.0),
+            anchor(Anchor::Fixed(Point::new(3.0, 4.0)), (0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn centroid_excludes_cutouts() {
+        let square = polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let hole = polygon(&[(1.0, 1.0), (1.0, 9.0), (5.0, 9.0), (5.0, 1.0)]);
+        let shapes = vec![Keyframed::new(0.0, square), Keyframed::new(0.0, hole)];
+        let centroid = part_anchor(&shapes, Anchor::Centroid, Point::ZERO);
+        // 100 units centered on (5, 5) less 32 centered on (3, 5)
+        assert!(
+            (centroid - Point::new(404.0 / 68.0, 5.0)).hypot() < 1e-9,
+            "{centroid:?}"
+        );
+    }
+
+    #[test]
+    fn named_eases_start_at_rest_and_arrive() {
+        for name in Ease::NAMES {
+            let ease = Ease::from_str(name).unwrap();
+            assert!(
+                (0.0..=1.0).contains(&ease.p1.x) && (0.0..=1.0).contains(&ease.p2.x),
+                "{name} goes back in time"
+            );
+            assert!(ease.progress(0.0).abs() < 1e-9, "{name}");
+            assert!((1.0 - ease.progress(1.0)).abs() < 1e-9, "{name}");
+        }
+    }
+
+    #[test]
+    fn bent_samples_every_frame() {
+        let keyframed = Keyframed(vec![
+            Keyframe::new(0.0, 0.0).with_ease(Ease::STANDARD),
+            Keyframe::new(10.0 / 60.0, 100.0),
+        ]);
+        let bent = bent(keyframed, Some(Bender::Bounce), 60.0);
+        assert_eq!(
+            (0..=10)
+                .map(|f| (f as f64, 100.0 * Bender::Bounce.progress(f as f64 / 10.0)))
+                .collect::<Vec<_>>(),
+            frames(&bent)
+                .into_iter()
+                .zip(bent.iter().map(|k| k.value))
+                .collect::<Vec<_>>()
+        );
+        assert!(bent
+            .iter()
+            .rev()
+            .skip(1)
+            .all(|k| k.ease == Some(Ease::LINEAR)));
+    }
+
+    #[cfg(feature = "command")]
+    #[test]
+    fn bounce_lands_at_the_end_played_backward() {
+        let mut animation = two_part_animation();
+        let plan = AnimationPlan::parse("Animate an_icon: rotate 90 degrees using bounce").unwrap();
+        animation
+            .root_mut()
+            .animate_grouped(animation.duration, &plan, Direction::Backward)
+            .unwrap();
+        let rotate = animation.root().rotate();
+        assert_eq!(90.0, rotate.earliest().value);
+        assert_eq!(0.0, rotate.iter().last().unwrap().value);
+        // Bounces off the end just before arriving
+        let near_end: Vec<_> = rotate.iter().rev().take(6).map(|k| k.value).collect();
+        assert!(near_end.windows(2).any(|w| w[1] < w[0]), "{near_end:?}");
+    }
+
+    #[test]
+    fn retime_keeps_timing() {
+        let mut animation = two_part_animation();
+        animation.root_mut().set_rotate(Keyframed(vec![
+            Keyframe::new(0.0, 0.0),
+            Keyframe::new(0.5, 90.0),
+        ]));
+        animation.add_segment("intro", 0.0..0.5).unwrap();
+        let duration = animation.duration();
+        let turned = Duration::from_millis(500);
+        assert_eq!(30.0, animation.frame_at(turned));
+
+        animation.retime(24.0).unwrap();
+        assert_eq!((24.0, 24.0), (animation.frame_rate(), animation.frames()));
+        assert_eq!(duration, animation.duration());
+        // Time is kept in seconds so nothing moves, only the frame it lands on
+        assert_eq!(
+            vec![0.0, 0.5],
+            animation
+                .root()
+                .rotate()
+                .iter()
+                .map(|k| k.time)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(0.5, animation.segments()[0].end);
+        assert_eq!(turned, animation.time_at(12.0));
+        assert_eq!(12.0, animation.frame_at(turned));
+
+        assert!(matches!(
+            animation.retime(0.0),
+            Err(AnimationError::InvalidFrameRate(..))
+        ));
+    }
+
+    #[test]
+    fn set_duration_keeps_frame_rate() {
+        let mut animation = two_part_animation();
+        animation.root_mut().set_rotate(Keyframed(vec![
+            Keyframe::new(0.0, 0.0),
+            Keyframe::new(0.5, 90.0),
+        ]));
+        animation.set_duration(Duration::from_millis(500)).unwrap();
+        assert_eq!((60.0, 30.0), (animation.frame_rate(), animation.frames()));
+        assert_eq!(0.25, animation.root().rotate().latest().time);
+        assert!(matches!(
+            animation.set_duration(Duration::ZERO),
+            Err(AnimationError::InvalidDuration(..))
+        ));
+    }
+
+    #[test]
+    fn set_size_scales_drawing_and_motion() {
+        let mut animation = two_part_animation();
+        animation.root_mut().center = Point::new(50.0, 50.0);
+        animation.root_mut().set_translate(
+            vec![(0.0, Vec2::ZERO), (30.0, Vec2::new(10.0, 0.0))]
+                .try_into()
+                .unwrap(),
+        );
+        animation.set_size(50.0).unwrap();
+        assert_eq!((50.0, 50.0), (animation.width(), animation.height()));
+        let root = animation.root();
+        assert_eq!(Point::new(25.0, 25.0), root.center);
+        assert_eq!(
+            Vec2::new(5.0, 0.0),
+            root.translate.iter().last().unwrap().value
+        );
+        let Element::Shape(shape) = &root.children[1] else {
+            panic!("The second child should be a shape");
+        };
+        assert_eq!(
+            Rect::new(10.0, 10.0, 15.0, 15.0),
+            shape.earliest().value.bounding_box()
+        );
+        assert!(matches!(
+            animation.set_size(-1.0),
+            Err(AnimationError::InvalidSize(..))
+        ));
+    }
+
+    #[test]
+    fn camera_is_visited() {
+        let mut animation = two_part_animation();
+        animation.set_camera(Some(Camera {
+            zoom: vec![(0.0, 100.0), (0.5, 120.0)].try_into().unwrap(),
+            pan: vec![(0.0, Vec2::ZERO), (1.0, Vec2::new(10.0, 0.0))]
+                .try_into()
+                .unwrap(),
+        }));
+        animation.set_duration(Duration::from_millis(500)).unwrap();
+        let camera = animation.camera().unwrap();
+        assert_eq!(0.25, camera.zoom.latest().time);
+        assert_eq!(0.5, camera.pan.latest().time);
+
+        // Viewed, the root moves opposite the pan
+        let Cow::Owned(view) = animation.viewed_root() else {
+            panic!("The camera should wrap the root");
+        };
+        assert_eq!(Point::new(50.0, 50.0), view.center);
+        assert_eq!(
+            Vec2::new(-10.0, 0.0),
+            view.translate.iter().last().unwrap().value
+        );
+    }
+
+    #[test]
+    fn extract_synthesizes_boundaries() {
+        let mut animation = two_part_animation();
+        animation.root_mut().set_rotate(Keyframed(vec![
+            Keyframe::new(0.0, 0.0).with_ease(Ease::LINEAR),
+            Keyframe::new(0.5, 100.0),
+            Keyframe::new(1.0, 100.0),
+        ]));
+
+        let extracted = animation.extract(0.25..0.75).unwrap();
+        assert_eq!(0.5, extracted.duration);
+        let times_and_values: Vec<_> = extracted
+            .root()
+            .rotate()
+            .iter()
+            .map(|k| (k.time, k.value.round()))
+            .collect();
+        assert_eq!(
+            vec![(0.0, 50.0), (0.25, 100.0), (0.5, 100.0)],
+            times_and_values
+        );
+    }
+
+    #[test]
+    fn extract_empty_range_fails() {
+        assert!(two_part_animation().extract(0.5..0.5).is_err());
+    }
+
+    #[test]
+    fn blend_half_a_pulse() {
+        let still = Animation::placeholder(100.0, String::new());
+        let mut pulsing = still.clone();
+        pulsing.root.scale = pulse(0.0, 1.0, 0).unwrap();
+
+        let blended = Animation::blend(&still, &pulsing, &Keyframed::new(0.0, 0.5)).unwrap();
+        assert_eq!((125.0, 125.0), blended.root.scale.value_at(0.2));
+        assert_eq!((100.0, 100.0), blended.root.scale.value_at(1.0));
+        // Nothing else moves so nothing else is keyframed
+        assert!(!blended.root.rotate.is_animated());
+        let Element::Shape(shape) = &blended.root.children[0] else {
+            panic!("Placeholder should be a shape");
+        };
+        assert!(!shape.is_animated());
+    }
+
+    #[test]
+    fn blend_follows_the_weight() {
+        let still = Animation::placeholder(100.0, String::new());
+        let mut pulsing = still.clone();
+        pulsing.root.scale = pulse(0.0, 1.0, 0).unwrap();
+
+        let weight = Keyframed::try_from(vec![(0.0, 0.0), (0.2, 1.0)]).unwrap();
+        let blended = Animation::blend(&still, &pulsing, &weight).unwrap();
+        assert_eq!(
+            pulsing.root.scale.value_at(0.2),
+            blended.root.scale.value_at(0.2)
+        );
+    }
+
+    #[test]
+    fn blend_requires_matching_frame_rate() {
+        let a = Animation::placeholder(100.0, String::new());
+        let mut b = a.clone();
+        b.frame_rate = 30.0;
+        assert!(matches!(
+            Animation::blend(&a, &b, &Keyframed::new(0.0, 0.5)),
+            Err(AnimationError::NotBlendable(..))
+        ));
+    }
+
+    #[test]
+    fn composite_continues_rotation() {
+        let plan = AnimationPlan::Composite(
+            "an_icon".into(),
+            vec![
+                CompositeStep::Plan(AnimationPlan::TwirlWhole("an_icon".into())),
+                CompositeStep::Hold(10.0),
+                CompositeStep::Plan(AnimationPlan::TwirlWhole("an_icon".into())),
+            ],
+        );
+        let mut root = two_part_animation().root;
+        root.animate(
+            plan.frames() / PLAN_FRAME_RATE,
+            &plan,
+            Direction::Forward,
+            &PlanOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![(0.0, 0.0), (24.0, 360.0), (70.0, 360.0), (94.0, 720.0)],
+            frames(&root.rotate)
+                .into_iter()
+                .zip(root.rotate.iter().map(|k| k.value))
+                .collect::<Vec<_>>()
+        );
+        // Nothing scaled so scale isn't keyframed
+        assert!(!root.scale.is_animated());
+    }
+
+    #[cfg(feature = "command")]
+    #[test]
+    fn squash_keeps_area() {
+        let plan = AnimationPlan::parse("Animate an_icon: squash 30 using elastic").unwrap();
+        let mut root = two_part_animation().root;
+        root.animate(
+            plan.frames() / PLAN_FRAME_RATE,
+            &plan,
+            Direction::Forward,
+            &PlanOptions::default(),
+        )
+        .unwrap();
+
+        let first = root.scale.earliest().value;
+        assert!((70.0 - first.1).abs() < 1e-9, "{first:?}");
+        let last = root.scale.latest().value;
+        assert!((100.0 - last.1).abs() < 0.1, "{last:?}");
+        for keyframe in root.scale.iter() {
+            let (x, y) = keyframe.value;
+            assert!((10000.0 - x * y).abs() < 1e-6, "{keyframe:?}");
+        }
+        // The wobble stretches as well as squashes
+        assert!(root.scale.iter().any(|k| k.value.1 > 100.0));
+    }
+
+    #[test]
+    fn parallel_channels_keep_their_springs() {
+        let plan = AnimationPlan::Parallel(
+            "an_icon".into(),
+            vec![
+                AnimationPlan::RotateDegrees(
+                    ("an_icon", Spring::expressive_spatial()).into(),
+                    90.0,
+                ),
+                AnimationPlan::ScaleFromTo("an_icon".into(), 80.0, 100.0),
+            ],
+        );
+        let mut root = two_part_animation().root;
+        root.animate(
+            plan.frames() / PLAN_FRAME_RATE,
+            &plan,
+            Direction::Forward,
+            &PlanOptions::default(),
+        )
+        .unwrap();
+
+        let rotate = root.rotate.iter().last().unwrap();
+        let scale = root.scale.iter().last().unwrap();
+        assert!((90.0 - rotate.value).abs() < 0.1, "{rotate:?}");
+        assert!((100.0 - scale.value.0).abs() < 0.1, "{scale:?}");
+        // Each spring settles in its own time
+        assert_ne!(rotate.time, scale.time);
+        assert_eq!(
+            plan.frames(),
+            to_frames(rotate.time.max(scale.time), PLAN_FRAME_RATE)
+        );
+    }
+
+    #[test]
+    fn parts_share_motion() {
+        let mut animation = two_part_animation();
+        animation
+            .root
+            .animate(
+                1.0,
+                &AnimationPlan::TwirlParts("an_icon".into(), PartSelection::All),
+                Direction::Forward,
+                &PlanOptions::default(),
+            )
+            .unwrap();
+        let rotations = |root: &Group| {
+            root.children
+                .iter()
+                .filter_map(|e| match e {
+                    Element::Group(g) => Some(frames(&g.rotate)),
+                    Element::Shape(..) => None,
+                })
+                .collect::<Vec<Vec<_>>>()
+        };
+        // The twirl is kept once, the parts hold no keyframes of their own
+        assert!(animation.root.shared.is_some());
+        assert_eq!(vec![vec![0.0], vec![0.0]], rotations(&animation.root));
+
+        animation.resolve_shared();
+        assert!(animation.root.shared.is_none());
+        assert_eq!(
+            vec![vec![0.0, 24.0], vec![12.0, 36.0]],
+            rotations(&animation.root)
+        );
+    }
+
+    #[test]
+    fn unselected_parts_hold_still() {
+        let mut animation = two_part_animation();
+        animation
+            .root
+            .animate(
+                1.0,
+                &AnimationPlan::TwirlParts("an_icon".into(), PartSelection::Range(1, 2)),
+                Direction::Forward,
+                &PlanOptions::default(),
+            )
+            .unwrap();
+        animation.resolve_shared();
+        let rotations: Vec<Vec<_>> = animation
+            .root
+            .children
+            .iter()
+            .filter_map(|e| match e {
+                Element::Group(g) => Some(frames(&g.rotate)),
+                Element::Shape(..) => None,
+            })
+            .collect();
+        // The selected part starts right away rather than waiting its turn
+        assert_eq!(vec![vec![0.0], vec![0.0, 24.0]], rotations);
+    }
+
+    #[test]
+    fn parallel_channels_move_their_own_parts() {
+        let mut animation = two_part_animation();
+        // The second part is the larger
+        animation.root.children[1] = Element::Shape(Keyframed::new(
+            0.0,
+            Rect::new(20.0, 20.0, 50.0, 50.0).to_path(0.1),
+        ));
+        let plan = AnimationPlan::Parallel(
+            "an_icon".into(),
+            vec![
+                AnimationPlan::PulseParts("an_icon".into(), PartSelection::Largest),
+                AnimationPlan::TwirlParts("an_icon".into(), PartSelection::Smallest),
+            ],
+        );
+        animation
+            .root
+            .animate(
+                plan.frames() / PLAN_FRAME_RATE,
+                &plan,
+                Direction::Forward,
+                &PlanOptions::default(),
+            )
+            .unwrap();
+        animation.resolve_shared();
+        let moves: Vec<_> = animation
+            .root
+            .children
+            .iter()
+            .filter_map(|e| match e {
+                Element::Group(g) => Some((g.scale.is_animated(), g.rotate.is_animated())),
+                Element::Shape(..) => None,
+            })
+            .collect();
+        // The smallest twirls, the largest pulses
+        assert_eq!(vec![(false, true), (true, false)], moves);
+    }
+
+    #[test]
+    fn moving_parts_on_top() {
+        let mut animation = two_part_animation();
+        animation
+            .root
+            .animate(
+                1.0,
+                &AnimationPlan::TwirlParts("an_icon".into(), PartSelection::Range(1, 2)),
+                Direction::Forward,
+                &PlanOptions {
+                    moving_parts_on_top: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let z: Vec<_> = animation.root.children.iter().map(Element::z).collect();
+        assert_eq!(vec![0, 1], z);
+    }
+
+    #[test]
+    fn compatibility() {
+        let path = |svg: &str| BezPath::from_svg(svg).unwrap();
+        let triangle = path("M0,0 L10,0 L10,10 Z");
+        assert_eq!(Ok(()), compatible(&triangle, &path("M5,5 L15,0 L10,20 Z")));
+        assert_eq!(
+            Err(Mismatch::Command {
+                index: 2,
+                expected: 'L',
+                found: 'Q'
+            }),
+            compatible(&triangle, &path("M0,0 L10,0 Q10,5 10,10 Z"))
+        );
+        assert_eq!(
+            Err(Mismatch::Missing {
+                index: 3,
+                expected: 'Z'
+            }),
+            compatible(&triangle, &path("M0,0 L10,0 L10,10"))
+        );
+        assert_eq!(
+            Err(Mismatch::Extra {
+                index: 4,
+                found: 'M'
+            }),
+            compatible(&triangle, &path("M0,0 L10,0 L10,10 Z M1,1"))
+        );
+    }
+
+    #[test]
+    fn overlaps_group_into_one_part() {
+        // A plus made of two bars, and a square off to the side
+        let shapes = [
+            Rect::new(0.0, 4.0, 10.0, 6.0),
+            Rect::new(4.0, 0.0, 6.0, 10.0),
+            Rect::new(20.0, 20.0, 30.0, 30.0),
+        ];
+        let parts = |group_overlaps: bool| {
+            let mut root = Group {
+                children: shapes
+                    .iter()
+                    .map(|r| Element::Shape(Keyframed::new(0.0, r.to_path(0.1))))
+                    .collect(),
+                ..Default::default()
+            };
+            if group_overlaps {
+                root.group_overlapping_parts();
+            } else {
+                root.group_parts();
+            }
+            root.children
+                .iter()
+                .map(|e| match e {
+                    Element::Group(g) => g.children.len(),
+                    Element::Shape(..) => panic!("Shape should be in a part"),
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![1, 1, 1], parts(false));
+        assert_eq!(vec![2, 1], parts(true));
+    }
+
+    #[test]
+    fn dot_in_a_ring_stays_apart() {
+        let circle = |x: f64, y: f64, r: f64| Circle::new((x, y), r).to_path(0.1);
+        let ring = || {
+            let mut ring = circle(15.0, 15.0, 10.0);
+            ring.extend(circle(15.0, 15.0, 6.0).reverse_subpaths());
+            ring
+        };
+        let parts = |dot: BezPath| {
+            let mut root = Group {
+                children: vec![
+                    Element::Shape(Keyframed::new(0.0, ring())),
+                    Element::Shape(Keyframed::new(0.0, dot)),
+                ],
+                ..Default::default()
+            };
+            root.group_overlapping_parts();
+            let mut parts: Vec<_> = root
+                .children
+                .iter()
+                .map(|e| match e {
+                    Element::Group(g) => g.children.len(),
+                    Element::Shape(..) => panic!("Shape should be in a part"),
+                })
+                .collect();
+            parts.sort();
+            parts
+        };
+        // In the hole the dot is clear of the ring, on the ring it overlaps
+        assert_eq!(vec![1, 2], parts(circle(15.0, 15.0, 2.0)));
+        assert_eq!(vec![3], parts(circle(15.0, 7.0, 1.0)));
+    }
+
+    #[test]
+    fn reverse_shapes_reaches_nested_groups() {
+        let morph = || {
+            Keyframed(vec![
+                Keyframe::new(0.0, Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1)),
+                Keyframe::new(30.0, Rect::new(0.0, 0.0, 20.0, 20.0).to_path(0.1)),
+            ])
+        };
+        let mut animation = two_part_animation();
+        let Element::Group(layer) = &mut animation.root.children[0] else {
+            panic!("Should start with a group");
+        };
+        layer.children = vec![Element::Shape(morph())];
+        animation.root.children[1] = Element::Shape(morph());
+
+        animation.visit_mut(&mut ReverseShapes);
+        let Element::Group(layer) = &animation.root.children[0] else {
+            unreachable!();
+        };
+        let expected = morph().reversed();
+        for shape in [&layer.children[0], &animation.root.children[1]] {
+            let Element::Shape(shape) = shape else {
+                panic!("Should be a shape");
+            };
+            assert_eq!(
+                expected.earliest().value.to_svg(),
+                shape.earliest().value.to_svg()
+            );
+        }
+    }
+
+    #[test]
+    fn reversed_keyframes_mirror_eases() {
+        let keyframed = Keyframed(vec![
+            Keyframe::new(10.0, 0.0).with_ease(Ease::STANDARD),
+            Keyframe::new(20.0, 50.0).with_ease(Ease::LINEAR),
+            Keyframe::new(40.0, 100.0),
+        ])
+        .reversed();
+        assert_eq!(
+            vec![
+                (10.0, 100.0, Some(Ease::LINEAR)),
+                (30.0, 50.0, Some(Ease::STANDARD.reversed())),
+                (40.0, 0.0, None),
+            ],
+            keyframed
+                .iter()
+                .map(|k| (k.time, k.value, k.ease))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Ease::STANDARD, Ease::STANDARD.reversed().reversed());
+    }
+
+    #[test]
+    fn nonsense_timing_is_an_error() {
+        let turn = Keyframed(vec![Keyframe::new(0.0, 0.0), Keyframe::new(10.0, 90.0)]);
+        for factor in [0.0, -1.0, f64::NAN] {
+            assert!(matches!(
+                turn.clone().stretch(factor),
+                Err(AnimationError::InvalidStretch(..))
+            ));
+        }
+        assert!(matches!(
+            twirl(10.0, 10.0, 0),
+            Err(AnimationError::EmptyTimeRange(..))
+        ));
+        assert!(matches!(
+            pulse(10.0, 0.0, 0),
+            Err(AnimationError::EmptyTimeRange(..))
+        ));
+    }
+
+    #[test]
+    fn keyframes_compose() {
+        let turn = Keyframed(vec![
+            Keyframe::new(0.0, 0.0).with_ease(Ease::STANDARD),
+            Keyframe::new(10.0, 90.0),
+        ]);
+        let keyframed = turn
+            .clone()
+            .delay(5.0)
+            .stretch(2.0)
+            .unwrap()
+            .concat(turn.clone().reversed());
+        assert_eq!(
+            vec![
+                (5.0, 0.0, Some(Ease::STANDARD)),
+                (25.0, 90.0, Some(Ease::STANDARD.reversed())),
+                (35.0, 0.0, None),
+            ],
+            keyframed
+                .iter()
+                .map(|k| (k.time, k.value, k.ease))
+                .collect::<Vec<_>>()
+        );
+        // Wherever the next starts, it follows on
+        let next = Keyframed(vec![
+            Keyframe::new(100.0, 90.0),
+            Keyframe::new(110.0, 180.0),
+        ]);
+        assert_eq!(
+            vec![(0.0, 0.0), (10.0, 90.0), (20.0, 180.0)],
+            turn.concat(next)
+                .iter()
+                .map(|k| (k.time, k.value))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn unmatted_drops_mattes() {
+        let mut root = Group::default();
+        assert!(matches!(root.unmatted(), Cow::Borrowed(..)));
+        for name in ["icon", "cutout"] {
+            let mut part = Group::default();
+            part.set_name(Some(name.to_string()));
+            root.children.push(Element::Group(part));
+        }
+        let Element::Group(icon) = &mut root.children[0] else {
+            unreachable!();
+        };
+        icon.set_matte(Some(Matte {
+            part: "cutout".to_string(),
+            mode: MatteMode::Alpha,
+        }));
+        assert!(root.is_matte(&root.children[1]));
+        assert!(!root.is_matte(&root.children[0]));
+
+        let unmatted = root.unmatted();
+        let [Element::Group(icon)] = unmatted.children.as_slice() else {
+            panic!("Only the icon should remain, {:?}", unmatted.children);
+        };
+        assert_eq!(Some("icon"), icon.name());
+        assert!(icon.matte().is_none());
+    }
+
+    #[test]
+    fn visible_opacity_pops() {
+        let mut group = Group::default();
+        assert!(matches!(group.visible_opacity(60.0), Cow::Borrowed(..)));
+
+        group.set_visible(Some(1.0..2.0));
+        assert_eq!(
+            vec![(0.0, 0.0, true), (1.0, 100.0, true), (2.0, 0.0, false)],
+            group
+                .visible_opacity(10.0)
+                .iter()
+                .map(|k| (k.time, k.value, k.hold))
+                .collect::<Vec<_>>()
+        );
+
+        // A fade plays while visible, holding for the last frame shown, at 10fps 1.9s
+        group.opacity = Keyframed(vec![
+            Keyframe::new(0.0, 0.0).with_ease(Ease::LINEAR),
+            Keyframe::new(3.0, 60.0),
+        ]);
+        let opacity = group.visible_opacity(10.0);
+        assert_eq!(
+            vec![0.0, 10.0, 19.0, 20.0],
+            opacity
+                .iter()
+                .map(|k| to_frames(k.time, 10.0))
+                .collect::<Vec<_>>()
+        );
+        assert!((20.0 - opacity.value_at(1.0)).abs() < 1e-6);
+        assert!((38.0 - opacity.value_at(1.95)).abs() < 1e-6);
+        assert_eq!(0.0, opacity.value_at(2.5));
+    }
+
+    #[test]
+    fn backward_spring_returns_to_start() {
+        let plan = AnimationPlan::RotateDegrees("an_icon".into(), 90.0);
+        let mut forward = two_part_animation().root;
+        forward
+            .animate(
+                plan.frames() / PLAN_FRAME_RATE,
+                &plan,
+                Direction::Forward,
+                &PlanOptions::default(),
+            )
+            .unwrap();
+        let mut backward = two_part_animation().root;
+        backward
+            .animate(
+                plan.frames() / PLAN_FRAME_RATE,
+                &plan,
+                Direction::Backward,
+                &PlanOptions::default(),
+            )
+            .unwrap();
+
+        let values = |g: &Group| {
+            let first = g.rotate.earliest();
+            let last = g.rotate.iter().last().unwrap();
+            (first.time, first.value, last.value.round())
+        };
+        assert_eq!((0.0, 0.0, 90.0), values(&forward));
+        assert_eq!((0.0, 90.0, 0.0), values(&backward));
+    }
+
+    #[test]
+    fn reject_empty_keyframes() {
+        assert!(serde_json::from_str::<Keyframed<f64>>("[]").is_err());
+    }
+
+    #[test]
+    fn gradient_flattens_to_the_average() {
+        let gradient = Gradient::Linear {
+            start: (0.0, 0.0).into(),
+            end: (10.0, 0.0).into(),
+            stops: vec![
+                ColorStop {
+                    offset: 0.0,
+                    color: (255, 0, 0),
+                },
+                ColorStop {
+                    offset: 1.0,
+                    color: (0, 0, 255),
+                },
+            ],
+        };
+        assert_eq!((128, 0, 128), gradient.flat_color());
     }
 }
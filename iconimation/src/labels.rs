@@ -0,0 +1,157 @@
+//! Names for the parts of an icon so commands can pick them out, e.g. `twirl part dot-1`
+//!
+//! Parts are named by guesswork from their size and position, see [`guess_labels`], unless
+//! [`PartHints`] names them for the icon.
+
+use std::collections::HashMap;
+
+use kurbo::{Point, Rect, Shape};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::Error,
+    ir::{Element, Group},
+};
+
+/// Part names by icon name, e.g. `{"schedule": ["face", "hand", "hand"]}`
+///
+/// Names are given in the order [`Group::group_parts`] makes parts. A name may be repeated to
+/// pick out several parts at once.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PartHints(HashMap<String, Vec<String>>);
+
+impl PartHints {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json).map_err(Error::InvalidPartHints)
+    }
+
+    /// The part names given for `icon_name`, if any
+    pub fn names(&self, icon_name: &str) -> Option<&[String]> {
+        self.0.get(icon_name).map(Vec::as_slice)
+    }
+}
+
+/// Parts at most this fraction of the icon each way are dots
+const DOT_FRACTION: f64 = 0.2;
+
+/// Parts at least this fraction of the icon each way may be rings
+const RING_FRACTION: f64 = 0.6;
+
+/// Name parts from their size and position within `icon`, its drawbox:
+///
+/// * a large part around the center of the icon that leaves the center empty is a ring, the
+///   largest "outer-ring" and any others "ring-1", "ring-2" and so on from the outside in
+/// * a small part is a dot, "dot-1", "dot-2" and so on in reading order
+/// * anything else is "part-<n>", n its index
+pub fn guess_labels(parts: &[&Group], icon: Rect) -> Vec<String> {
+    let icon_center = icon.center();
+    let bboxes: Vec<_> = parts
+        .iter()
+        .map(|g| g.bounding_box().unwrap_or_default())
+        .collect();
+    let mut labels: Vec<_> = (0..parts.len()).map(|i| format!("part-{i}")).collect();
+
+    let mut rings: Vec<_> = (0..parts.len())
+        .filter(|i| {
+            let bbox = bboxes[*i];
+            bbox.width() >= RING_FRACTION * icon.width()
+                && bbox.height() >= RING_FRACTION * icon.height()
+                && bbox.contains(icon_center)
+                && !covers(parts[*i], icon_center)
+        })
+        .collect();
+    rings.sort_by(|a, b| bboxes[*b].area().total_cmp(&bboxes[*a].area()));
+    for (nth, i) in rings.into_iter().enumerate() {
+        labels[i] = match nth {
+            0 => "outer-ring".to_string(),
+            _ => format!("ring-{nth}"),
+        };
+    }
+
+    // Rows a dot tall, so dots side by side read left to right despite small differences in y
+    let row_height = DOT_FRACTION * icon.height();
+    let mut dots: Vec<_> = (0..parts.len())
+        .filter(|i| {
+            let bbox = bboxes[*i];
+            bbox.width() <= DOT_FRACTION * icon.width()
+                && bbox.height() <= DOT_FRACTION * icon.height()
+        })
+        .collect();
+    dots.sort_by(|a, b| {
+        let key = |i: usize| {
+            let center = bboxes[i].center();
+            ((center.y / row_height.max(f64::EPSILON)).floor(), center.x)
+        };
+        let (a, b) = (key(*a), key(*b));
+        a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+    });
+    for (nth, i) in dots.into_iter().enumerate() {
+        labels[i] = format!("dot-{}", nth + 1);
+    }
+    labels
+}
+
+/// Whether the shapes of `part` fill `point`, nonzero
+fn covers(part: &Group, point: Point) -> bool {
+    let winding: i32 = part
+        .children()
+        .iter()
+        .filter_map(|e| match e {
+            Element::Shape(s) => Some(s.earliest().value.winding(point)),
+            Element::Group(..) => None,
+        })
+        .sum();
+    winding != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::{BezPath, Circle, Rect, Shape};
+
+    use crate::ir::{Element, Group, Keyframed};
+
+    use super::{guess_labels, PartHints};
+
+    fn part(shapes: Vec<BezPath>) -> Group {
+        let mut group = Group::default();
+        group.children_mut().extend(
+            shapes
+                .into_iter()
+                .map(|shape| Element::Shape(Keyframed::new(0.0, shape))),
+        );
+        group
+    }
+
+    #[test]
+    fn guess_ring_and_dots() {
+        // A ring, its hole wound the other way, with two dots inside and a bar below
+        let ring = part(vec![
+            Circle::new((50.0, 50.0), 50.0).to_path(0.1),
+            Circle::new((50.0, 50.0), 40.0)
+                .to_path(0.1)
+                .reverse_subpaths(),
+        ]);
+        let right_dot = part(vec![Rect::new(60.0, 45.0, 70.0, 55.0).to_path(0.1)]);
+        let left_dot = part(vec![Rect::new(30.0, 46.0, 40.0, 56.0).to_path(0.1)]);
+        let bar = part(vec![Rect::new(30.0, 70.0, 70.0, 80.0).to_path(0.1)]);
+        assert_eq!(
+            vec!["outer-ring", "dot-2", "dot-1", "part-3"],
+            guess_labels(
+                &[&ring, &right_dot, &left_dot, &bar],
+                Rect::new(0.0, 0.0, 100.0, 100.0)
+            )
+        );
+    }
+
+    #[test]
+    fn hints_by_icon_name() {
+        let hints = PartHints::from_json(r#"{"schedule": ["face", "hand", "hand"]}"#).unwrap();
+        assert_eq!(
+            Some(&["face".to_string(), "hand".to_string(), "hand".to_string()][..]),
+            hints.names("schedule")
+        );
+        assert_eq!(None, hints.names("settings"));
+        assert!(PartHints::from_json(r#"{"schedule": "face"}"#).is_err());
+    }
+}
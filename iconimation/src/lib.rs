@@ -1,17 +1,65 @@
-//! Shove glyphs from a variable font into a Lottie template.
+//! Animate icons from a variable font, such as Material Symbols, as Lottie or Android
+//! AnimatedVectorDrawable.
+//!
+//! There is one pipeline:
+//!
+//! 1. A command, e.g. `Animate settings: twirl`, parses to a [`plan::AnimationPlan`]
+//! 2. The plan, applied to the glyph, produces an [`ir::Animation`], a tree of [`ir::Group`]s
+//!    and shapes
+//! 3. [`pass::Pass`]es, if any, adjust the animation
+//! 4. An output format converts it by way of [`ir::FromAnimation`], see [`lottie`] and [`android`]
+//!
+//! A new output format need only implement [`ir::FromAnimation`]. The older Template/Animator
+//! API, see [`compat`], is a thin layer over the same IR.
+//!
+//! Animations needn't start from a command, [`ir::Animation::new`] takes a [`ir::Group`] built
+//! in code; [`prelude`] has what that takes.
+//!
+//! ## Stability
+//!
+//! What [`prelude`] exports changes only incompatibly with a new minor version while we're at
+//! 0.x, as does the serialized IR, which is versioned, see [`ir::SCHEMA_VERSION`]. Error enums are
+//! `#[non_exhaustive]` and may gain variants at any time. Other modules are public for the CLI,
+//! wasm and tests and may change whenever they need to; [`compat`] is deprecated and will go.
 
 pub mod android;
-mod bezop;
+#[cfg(feature = "audit")]
+pub mod audit;
+#[cfg(feature = "command")]
+pub mod batch;
+pub mod bender;
+pub mod bezop;
+pub mod colr;
+pub mod compat;
+#[cfg(feature = "debug")]
+pub mod debug;
 pub mod error;
+pub mod font_info;
 pub mod ir;
+pub mod labels;
 pub mod ligate;
 pub mod lottie;
+pub mod motion;
+pub mod observe;
+pub mod pass;
 pub mod plan;
+pub mod prelude;
+#[cfg(feature = "command")]
+pub mod presets;
+pub mod preview;
 pub mod spring;
 pub mod spring2cubic;
+#[cfg(feature = "command")]
+pub mod syntax;
+
+#[cfg(feature = "command")]
+mod scanner;
 
 use std::fmt::Debug;
 
+#[allow(deprecated)]
+pub use compat::{animate, default_template, Template};
+
 use kurbo::{BezPath, PathEl, Point, Rect};
 use skrifa::{
     instance::Location,
@@ -19,7 +67,10 @@ use skrifa::{
     GlyphId, MetadataProvider, OutlineGlyph,
 };
 
-use crate::error::Error;
+use crate::{
+    bezop::FontUnits,
+    error::{AnimationError, Error},
+};
 
 pub struct GlyphShape<'a> {
     font: &'a FontRef<'a>,
@@ -28,6 +79,8 @@ pub struct GlyphShape<'a> {
     start: Location,
     // If set, animate from start => end
     end: Option<Location>,
+    // Drawn, in order, between start and end
+    steps: Vec<Location>,
 }
 
 impl<'a> Debug for GlyphShape<'a> {
@@ -47,7 +100,7 @@ impl<'a> GlyphShape<'a> {
     ) -> Result<Self, Error> {
         let outline_loader = font.outline_glyphs();
         let Some(glyph) = outline_loader.get(gid) else {
-            return Err(Error::NoOutline(gid));
+            return Err(AnimationError::NoOutline(gid).into());
         };
         if let Some(end_loc) = &end {
             if start.coords() == end_loc.coords() {
@@ -60,29 +113,38 @@ impl<'a> GlyphShape<'a> {
             gid,
             start,
             end,
+            steps: Vec::new(),
         })
     }
 
-    pub fn drawbox(&self) -> Rect {
+    /// Draw at each of `steps` on the way from start to end, rather than interpolate between them
+    pub fn with_steps(mut self, steps: Vec<Location>) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    pub fn drawbox(&self) -> FontUnits<Rect> {
         let upem = self.font.head().unwrap().units_per_em() as f64;
-        (Point::ZERO, Point::new(upem, upem)).into()
+        FontUnits((Point::ZERO, Point::new(upem, upem)).into())
     }
 }
 
 /// Lists the path commands, e.g. MCLZ, used by the path.
 ///
-/// Paths with the same commands are interpolation compatible.
-fn path_commands(bez: &BezPath) -> String {
-    bez.elements()
-        .iter()
-        .map(|e| match e {
-            PathEl::ClosePath => 'Z',
-            PathEl::CurveTo(..) => 'C',
-            PathEl::LineTo(..) => 'L',
-            PathEl::MoveTo(..) => 'M',
-            PathEl::QuadTo(..) => 'Q',
-        })
-        .collect()
+/// Paths with the same commands are interpolation compatible, see [`ir::compatible`].
+pub fn path_commands(bez: &BezPath) -> String {
+    bez.elements().iter().map(path_command).collect()
+}
+
+/// The command, e.g. 'C' for a cubic, of a path element
+fn path_command(el: &PathEl) -> char {
+    match el {
+        PathEl::ClosePath => 'Z',
+        PathEl::CurveTo(..) => 'C',
+        PathEl::LineTo(..) => 'L',
+        PathEl::MoveTo(..) => 'M',
+        PathEl::QuadTo(..) => 'Q',
+    }
 }
 
 /// Hackery to support debugging; it's useful to see the groups
@@ -104,5 +166,10 @@ pub fn nth_group_color(n: usize) -> (u8, u8, u8) {
     COLORS[n % COLORS.len()]
 }
 
+/// A color as `#rrggbb`, as SVG and Android vector drawables write it
+pub(crate) fn hex_color((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
 #[cfg(test)]
 mod tests {}
@@ -1,5 +1,7 @@
 //! Resolve name => gid assuming Google Fonts icon font input
 
+use std::collections::HashMap;
+
 use skrifa::{
     charmap::Charmap,
     raw::{
@@ -46,14 +48,11 @@ fn resolve_ligature(
     Ok(None)
 }
 
-pub fn icon_name_to_gid(font: &FontRef, name: &str) -> Result<GlyphId, IconNameError> {
-    let charmap = Charmap::new(font);
-    let gids = name
-        .chars()
-        .map(|c| charmap.map(c).ok_or(IconNameError::UnmappedCharError(c)))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    // Step 1: try to find a ligature that starts with our first gid
+/// Every ligature substitution subtable in GSUB, looking through extensions
+fn ligature_subtables<'a>(
+    font: &FontRef<'a>,
+) -> Result<Vec<LigatureSubstFormat1<'a>>, IconNameError> {
+    let mut result = Vec::new();
     let gsub = font.gsub().map_err(IconNameError::ReadError)?;
     let lookups = gsub.lookup_list().map_err(IconNameError::ReadError)?;
     for lookup in lookups.lookups().iter() {
@@ -61,10 +60,7 @@ pub fn icon_name_to_gid(font: &FontRef, name: &str) -> Result<GlyphId, IconNameE
         match lookup {
             SubstitutionLookup::Ligature(table) => {
                 for liga in table.subtables().iter() {
-                    let liga = liga.map_err(IconNameError::ReadError)?;
-                    if let Some(gid) = resolve_ligature(&liga, name, &gids)? {
-                        return Ok(gid);
-                    }
+                    result.push(liga.map_err(IconNameError::ReadError)?);
                 }
             }
             SubstitutionLookup::Extension(table) => {
@@ -74,15 +70,159 @@ pub fn icon_name_to_gid(font: &FontRef, name: &str) -> Result<GlyphId, IconNameE
                     else {
                         continue;
                     };
-                    let table = table.extension().map_err(IconNameError::ReadError)?;
-
-                    if let Some(gid) = resolve_ligature(&table, name, &gids)? {
-                        return Ok(gid);
-                    }
+                    result.push(table.extension().map_err(IconNameError::ReadError)?);
                 }
             }
             _ => (),
         }
     }
-    Err(IconNameError::NoLigature(name.to_string()))
+    Ok(result)
+}
+
+pub fn icon_name_to_gid(font: &FontRef, name: &str) -> Result<GlyphId, IconNameError> {
+    let charmap = Charmap::new(font);
+    let gids = name
+        .chars()
+        .map(|c| charmap.map(c).ok_or(IconNameError::UnmappedCharError(c)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Try to find a ligature that starts with our first gid
+    for liga in ligature_subtables(font)? {
+        if let Some(gid) = resolve_ligature(&liga, name, &gids)? {
+            return Ok(gid);
+        }
+    }
+    Err(IconNameError::NoLigature {
+        name: name.to_string(),
+        suggestions: similar_icon_names(font, name).unwrap_or_default(),
+    })
+}
+
+/// Suggest at most this many names for a misspelt one
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Icons whose names are a few edits from `name`, closest first, to suggest in place of a typo
+pub fn similar_icon_names(font: &FontRef, name: &str) -> Result<Vec<String>, IconNameError> {
+    Ok(closest(
+        name,
+        icon_names(font)?.into_iter().map(|(name, _)| name),
+    ))
+}
+
+fn closest(name: &str, candidates: impl Iterator<Item = String>) -> Vec<String> {
+    // Allow about one typo every three chars
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut close: Vec<_> = candidates
+        .filter_map(|candidate| {
+            let distance = edit_distance(name, &candidate);
+            (distance <= max_distance).then_some((distance, candidate))
+        })
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// The fewest single char insertions, deletions or substitutions that turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut prior: Vec<_> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitute = prior[j] + usize::from(a != *b);
+            current.push(substitute.min(prior[j + 1] + 1).min(current[j] + 1));
+        }
+        prior = current;
+    }
+    prior[b.len()]
+}
+
+/// Every icon name, the text of a ligature whose components are all mapped by cmap, and the
+/// glyph it produces. Sorted by name.
+pub fn icon_names(font: &FontRef) -> Result<Vec<(String, GlyphId)>, IconNameError> {
+    // Several codepoints may share a glyph, prefer the spelling icon names use
+    let is_name_char = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_';
+    let mut gid_to_char = HashMap::new();
+    for (c, gid) in Charmap::new(font)
+        .mappings()
+        .filter_map(|(cp, gid)| char::from_u32(cp).map(|c| (c, gid)))
+    {
+        let existing = gid_to_char.entry(gid).or_insert(c);
+        if !is_name_char(*existing) && is_name_char(c) {
+            *existing = c;
+        }
+    }
+
+    let mut names = Vec::new();
+    for liga in ligature_subtables(font)? {
+        let coverage = liga.coverage().map_err(IconNameError::ReadError)?;
+        for (first, set) in coverage.iter().zip(liga.ligature_sets().iter()) {
+            let set = set.map_err(IconNameError::ReadError)?;
+            for liga in set.ligatures().iter() {
+                let liga = liga.map_err(IconNameError::ReadError)?;
+                let name: Option<String> = std::iter::once(first)
+                    .chain(liga.component_glyph_ids().iter().map(|gid| gid.get()))
+                    .map(|gid| gid_to_char.get(&gid).copied())
+                    .collect();
+                if let Some(name) = name {
+                    names.push((name, liga.ligature_glyph()));
+                }
+            }
+        }
+    }
+    names.sort_by(|a, b| a.0.cmp(&b.0));
+    names.dedup_by(|a, b| a.0 == b.0);
+    Ok(names)
+}
+
+/// The name of the icon a glyph is for, the alphabetically first if there are several
+pub fn gid_to_icon_name(font: &FontRef, gid: GlyphId) -> Result<Option<String>, IconNameError> {
+    Ok(icon_names(font)?
+        .into_iter()
+        .find_map(|(name, icon_gid)| (icon_gid == gid).then_some(name)))
+}
+
+/// The name of the icon a codepoint, typically private use, maps to
+pub fn codepoint_to_icon_name(
+    font: &FontRef,
+    codepoint: u32,
+) -> Result<Option<String>, IconNameError> {
+    let Some(gid) = Charmap::new(font).map(codepoint) else {
+        return Ok(None);
+    };
+    gid_to_icon_name(font, gid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{closest, edit_distance};
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(
+            vec![0, 1, 1, 1, 3, 7],
+            vec![
+                edit_distance("close", "close"),
+                edit_distance("setings", "settings"),
+                edit_distance("pase", "pause"),
+                edit_distance("bolt", "volt"),
+                edit_distance("abc", ""),
+                edit_distance("", "abcdefg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn closest_first() {
+        let names = ["pause", "pause_circle", "play_arrow", "phase", "settings"];
+        assert_eq!(
+            vec!["pause".to_string(), "phase".to_string()],
+            closest("pase", names.iter().map(|n| n.to_string()))
+        );
+        assert!(closest("zzzz", names.iter().map(|n| n.to_string())).is_empty());
+    }
 }
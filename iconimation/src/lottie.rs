@@ -1,4 +1,6 @@
-//! Create's Lottie's from Animation's
+//! Create's Lottie's from Animation's, and read simple ones back
+
+use std::{cmp::Reverse, collections::HashSet, ops::Range};
 
 use bodymovin::{
     layers::{AnyLayer, ShapeMixin},
@@ -6,96 +8,1057 @@ use bodymovin::{
         Bezier2d, BezierEase, ControlPoint2d, MultiDimensionalKeyframe, Property, ShapeKeyframe,
         ShapeValue, Value,
     },
-    shapes::{AnyShape, Fill, Group, SubPath, Transform},
+    shapes::{AnyShape, Fill, Group, Rect as LottieRect, Stroke, SubPath, Transform},
     Bodymovin as Lottie,
 };
-use kurbo::{BezPath, PathEl, Point, Shape};
+use kurbo::{Affine, BezPath, PathEl, Point, Rect, Shape, Vec2};
+use serde::{Deserialize, Serialize, Serializer};
 
 use crate::{
+    bezop::{quantize, FontToOutput, Precision},
     error::LottieError,
-    ir::{self, Element, FromAnimation, Keyframed},
+    ir::{self, subpaths, to_frames, Ease, Element, FromAnimation, Keyframed},
     path_commands,
 };
 
-impl FromAnimation for Lottie {
+/// Choices about how an [`ir::Animation`] is expressed in Lottie
+#[derive(Debug, Clone, Default)]
+pub struct LottieOptions {
+    pub paint_order: PaintOrder,
+    /// If set, [`lottie_json`] writes compact json with numbers rounded to this many decimal places
+    pub minify: Option<u32>,
+    /// If set, path coordinates are rounded, see [`Precision`]
+    pub precision: Option<Precision>,
+    /// Make [`lottie_json`] byte-identical on every platform, numbers are rounded to
+    /// [`Precision::STABLE`] unless [`LottieOptions::minify`] rounds them already
+    pub stable: bool,
+    /// Put each top-level part in a shape layer of its own, its transform the layer's, rather than
+    /// everything in one, e.g. to edit parts in After Effects or to [clip](ir::Group::clip) them.
+    /// See [`part_layers`].
+    pub layer_per_part: bool,
+    /// What the player can draw, beyond what every player can
+    pub profile: LottieProfile,
+}
+
+/// The players a Lottie is for, to use features only some support
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum LottieProfile {
+    /// Anything every player draws, e.g. lottie-web
+    #[default]
+    Portable,
+    /// Skia's Skottie, which also draws [motion blur](ir::Group::motion_blur)
+    Skottie,
+}
+
+impl LottieProfile {
+    /// Whether layers may be motion blurred
+    pub fn motion_blur(self) -> bool {
+        match self {
+            LottieProfile::Portable => false,
+            LottieProfile::Skottie => true,
+        }
+    }
+}
+
+/// Whether stroke or fill is drawn on top, as in SVG [paint-order](https://developer.mozilla.org/en-US/docs/Web/SVG/Attribute/paint-order)
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum PaintOrder {
+    /// The stroke is drawn over the fill, as SVG does by default
+    #[default]
+    StrokeAboveFill,
+    FillAboveStroke,
+}
+
+/// A Lottie and the json fields, such as a layer's [index and parent](https://lottiefiles.github.io/lottie-docs/layers/#layer),
+/// track matte or masks, that its model lacks
+///
+/// The fields are kept beside the model, rather than passed through it and perhaps dropped, and
+/// added to the json when it is written, see [`LottieOutput::to_json`].
+#[derive(Debug, Clone, Default)]
+pub struct LottieOutput {
+    lottie: Lottie,
+    extras: Extras,
+}
+
+impl LottieOutput {
+    /// The Lottie, without the fields its model lacks
+    pub fn lottie(&self) -> &Lottie {
+        &self.lottie
+    }
+
+    /// The json of the Lottie with every field
+    pub fn to_json(&self) -> Result<serde_json::Value, LottieError> {
+        let mut json = serde_json::to_value(&self.lottie).map_err(LottieError::Json)?;
+        self.extras.add_to(&mut json)?;
+        Ok(json)
+    }
+}
+
+impl From<Lottie> for LottieOutput {
+    fn from(lottie: Lottie) -> Self {
+        LottieOutput {
+            lottie,
+            extras: Extras::default(),
+        }
+    }
+}
+
+impl Serialize for LottieOutput {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl FromAnimation for LottieOutput {
     type Err = LottieError;
 
     fn from_animation(animation: &crate::ir::Animation) -> Result<Self, Self::Err> {
-        let root_group = to_lottie_group(&animation.root)?;
-        Ok(Lottie {
+        to_lottie(animation, &LottieOptions::default())
+    }
+}
+
+/// Json fields to add, in order, each to the object at a [pointer](serde_json::Value::pointer)
+#[derive(Debug, Clone, Default)]
+struct Extras(Vec<(String, &'static str, serde_json::Value)>);
+
+impl Extras {
+    fn set(&mut self, at: &str, key: &'static str, value: serde_json::Value) {
+        self.0.push((at.to_string(), key, value));
+    }
+
+    fn get(&self, at: &str, key: &str) -> Option<&serde_json::Value> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(p, k, _)| p == at && *k == key)
+            .map(|(_, _, value)| value)
+    }
+
+    /// Take the fields of `other`, whose pointers are relative to `at`
+    fn extend_at(&mut self, at: &str, other: Extras) {
+        self.0.extend(
+            other
+                .0
+                .into_iter()
+                .map(|(p, key, value)| (format!("{at}{p}"), key, value)),
+        );
+    }
+
+    fn add_to(&self, json: &mut serde_json::Value) -> Result<(), LottieError> {
+        for (at, key, value) in &self.0 {
+            let Some(object) = json.pointer_mut(at).and_then(|v| v.as_object_mut()) else {
+                return Err(LottieError::MissingObject {
+                    at: at.clone(),
+                    key,
+                });
+            };
+            object.insert(key.to_string(), value.clone());
+        }
+        Ok(())
+    }
+}
+
+/// A layer and the fields, relative to it, its model lacks
+struct Layer {
+    layer: AnyLayer,
+    extras: Extras,
+}
+
+impl Layer {
+    fn with_field(mut self, key: &'static str, value: serde_json::Value) -> Self {
+        self.extras.set("", key, value);
+        self
+    }
+}
+
+/// As [`FromAnimation::from_animation`] but with control over the output
+pub fn to_lottie(
+    animation: &ir::Animation,
+    options: &LottieOptions,
+) -> Result<LottieOutput, LottieError> {
+    log::debug!("Writing Lottie");
+    // TODO: express shared motion once, e.g. with expressions, rather than a copy per group
+    let animation = animation.resolved();
+    if !options.profile.motion_blur() && is_blurred(&animation.root) {
+        log::warn!(
+            "Motion blur is dropped, {:?} can't draw it",
+            options.profile
+        );
+    }
+    let frame_rate = animation.frame_rate;
+    let layers = if options.layer_per_part {
+        part_layers(&animation, options)?
+    } else if let Some(clip) = &animation.root.clip {
+        let root = animation.root.unmatted();
+        warn_of_clips(&root.children);
+        // Masks are in the space of the layer so the root's transform moves to the layer
+        let content = to_lottie_layer_content(&root, frame_rate, options)?;
+        let shown = 0.0..animation.frames();
+        let layer = shape_layer(content, &root, 1, None, None, shown, frame_rate)?;
+        vec![with_motion_blur(
+            with_mask(layer, clip, frame_rate, options)?,
+            &root,
+            options,
+        )]
+    } else {
+        let root = animation.root.unmatted();
+        warn_of_clips(&root.children);
+        let (root_group, root_extras) = to_lottie_group(&root, frame_rate, options)?;
+        let layer = AnyLayer::Shape(bodymovin::layers::Shape {
             in_point: 0.0,
-            out_point: animation.frames,
-            frame_rate: animation.frame_rate,
+            out_point: animation.frames(),
+            mixin: ShapeMixin {
+                shapes: vec![AnyShape::Group(root_group)],
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut extras = Extras::default();
+        extras.extend_at("/shapes/0", root_extras);
+        vec![with_motion_blur(Layer { layer, extras }, &root, options)]
+    };
+    let layers = match &animation.camera {
+        Some(camera) => with_camera(layers, camera, &animation)?,
+        None => layers,
+    };
+    let mut extras = Extras::default();
+    let layers = layers
+        .into_iter()
+        .enumerate()
+        .map(|(i, layer)| {
+            extras.extend_at(&format!("/layers/{i}"), layer.extras);
+            layer.layer
+        })
+        .collect();
+    Ok(LottieOutput {
+        lottie: Lottie {
+            in_point: 0.0,
+            out_point: animation.frames(),
+            frame_rate,
             width: animation.width as i64,
             height: animation.height as i64,
-            layers: vec![AnyLayer::Shape(bodymovin::layers::Shape {
-                in_point: 0.0,
-                out_point: 60.0, // 60fps total animation = 1s
-                mixin: ShapeMixin {
-                    shapes: vec![AnyShape::Group(root_group)],
-                    ..Default::default()
-                },
-                ..Default::default()
-            })],
+            layers,
+            ..Default::default()
+        },
+        extras,
+    })
+}
+
+/// A shape layer per child group of the root, highest z first as layers draw top down, with the
+/// group's transform as the layer's
+///
+/// Parts without fill or stroke of their own take the root's, as they would nested in it. Shapes
+/// directly in the root share a layer below the parts. If the root moves, parts are parented to
+/// an empty layer, named "root", with its transform. Children don't inherit opacity in Lottie so
+/// the root's is given to parts that are otherwise opaque. A part that isn't always
+/// [visible](ir::Group::visible) has a layer that plays only while it is. A part with a
+/// [matte](ir::Group::matte) has a [track matte](https://lottiefiles.github.io/lottie-docs/layers/#mattes)
+/// layer, of the matte part, above its own.
+fn part_layers(
+    animation: &ir::Animation,
+    options: &LottieOptions,
+) -> Result<Vec<Layer>, LottieError> {
+    let root = &animation.root;
+    let frame_rate = animation.frame_rate;
+    // Visibility is in seconds, layers are shown for frames
+    let within = |visible: Option<Range<f64>>, outer: Range<f64>| match visible {
+        Some(visible) => {
+            outer.start.max(to_frames(visible.start, frame_rate))
+                ..outer.end.min(to_frames(visible.end, frame_rate))
+        }
+        None => outer,
+    };
+    let shown = within(root.visible(), 0.0..animation.frames());
+    if root.clip.is_some() {
+        log::warn!("Part layers can't share the root's clip, it is ignored");
+    }
+    let mut layers = Vec::new();
+    let parent = (!is_still(root)).then_some(1);
+    if parent.is_some() {
+        layers.push(shape_layer(
+            Default::default(),
+            root,
+            1,
+            None,
+            Some("root"),
+            shown.clone(),
+            frame_rate,
+        )?);
+    }
+
+    let mut children: Vec<_> = root.children.iter().collect();
+    children.sort_by_key(|e| Reverse(e.z()));
+    let mut loose = ir::Group {
+        fill: root.fill,
+        gradient: root.gradient.clone(),
+        stroke: root.stroke,
+        opacity: root.opacity.clone(),
+        ..Default::default()
+    };
+    let part_layer = |part: &ir::Group, index: usize| -> Result<Layer, LottieError> {
+        let mut part = part.unmatted().into_owned();
+        if part.fill.is_none() && part.gradient.is_none() {
+            part.fill = root.fill;
+            part.gradient = root.gradient.clone();
+        }
+        part.stroke = part.stroke.or(root.stroke);
+        if !part.opacity.is_animated() && part.opacity.earliest().value == 100.0 {
+            part.opacity = root.opacity.clone();
+        } else if root.opacity.is_animated() || root.opacity.earliest().value != 100.0 {
+            log::warn!(
+                "{:?} has its own opacity so doesn't fade with the root",
+                part.name()
+            );
+        }
+        let visible = within(part.visible.take(), shown.clone());
+        warn_of_clips(&part.children);
+        let content = to_lottie_layer_content(&part, frame_rate, options)?;
+        let layer = shape_layer(
+            content,
+            &part,
+            index,
+            parent,
+            part.name(),
+            visible,
+            frame_rate,
+        )?;
+        let layer = match &part.clip {
+            Some(clip) => with_mask(layer, clip, frame_rate, options)?,
+            None => layer,
+        };
+        // The root moves every part so blurs them all
+        part.motion_blur |= root.motion_blur;
+        Ok(with_motion_blur(layer, &part, options))
+    };
+    for child in children {
+        let part = match child {
+            Element::Group(part) => part,
+            Element::Shape(..) => {
+                loose.children.push(child.clone());
+                continue;
+            }
+        };
+        // Mattes are drawn only as the track matte of the parts they mask
+        if root.is_matte(child) {
+            continue;
+        }
+        let matte = part.matte.as_ref().and_then(|matte| {
+            let found = root.children.iter().find_map(|e| match e {
+                Element::Group(g) if g.name() == Some(matte.part.as_str()) => Some(g),
+                _ => None,
+            });
+            if found.is_none() {
+                log::warn!(
+                    "{:?} is drawn unmasked, there is no part {:?}",
+                    part.name(),
+                    matte.part
+                );
+            }
+            found.map(|g| (g, matte.mode))
+        });
+        let Some((matte, mode)) = matte else {
+            layers.push(part_layer(part, layers.len() + 1)?);
+            continue;
+        };
+        let matte = part_layer(matte, layers.len() + 1)?.with_field("td", 1.into());
+        layers.push(matte);
+        let tt = match mode {
+            ir::MatteMode::Alpha => 1,
+            ir::MatteMode::InvertedAlpha => 2,
+        };
+        let masked = part_layer(part, layers.len() + 1)?.with_field("tt", tt.into());
+        layers.push(masked);
+    }
+    if !loose.children.is_empty() {
+        loose.motion_blur = root.motion_blur;
+        let content = to_lottie_layer_content(&loose, frame_rate, options)?;
+        let index = layers.len() + 1;
+        let layer = shape_layer(content, &loose, index, parent, None, shown, frame_rate)?;
+        layers.push(with_motion_blur(layer, &loose, options));
+    }
+    Ok(layers)
+}
+
+/// Whether `group` leaves where what it contains is as it is, ignoring opacity
+fn is_still(group: &ir::Group) -> bool {
+    !group.translate.is_animated()
+        && !group.scale.is_animated()
+        && !group.rotate.is_animated()
+        && group.translate.earliest().value == Vec2::ZERO
+        && group.scale.earliest().value == (100.0, 100.0)
+        && group.rotate.earliest().value == 0.0
+        && !group.is_skewed()
+}
+
+/// `layers` parented, unless they already have a parent, to an empty layer named "camera" with
+/// the transform of `camera`, so everything zooms and pans together
+fn with_camera(
+    layers: Vec<Layer>,
+    camera: &ir::Camera,
+    animation: &ir::Animation,
+) -> Result<Vec<Layer>, LottieError> {
+    let index = layers.len() + 1;
+    let mut parented = Vec::with_capacity(index);
+    for layer in layers {
+        parented.push(match layer.extras.get("", "parent") {
+            Some(..) => layer,
+            None => layer.with_field("parent", index.into()),
+        });
+    }
+    let view = camera.to_group(animation.width, animation.height);
+    parented.push(shape_layer(
+        Default::default(),
+        &view,
+        index,
+        None,
+        Some("camera"),
+        0.0..animation.frames(),
+        animation.frame_rate,
+    )?);
+    Ok(parented)
+}
+
+/// The shapes of a layer for `group`, without its transform, which is the layer's, and their
+/// fields relative to the layer's shapes
+fn to_lottie_layer_content(
+    group: &ir::Group,
+    frame_rate: f64,
+    options: &LottieOptions,
+) -> Result<(Vec<AnyShape>, Extras), LottieError> {
+    let (items, item_extras) = to_lottie_items(group, frame_rate, options)?;
+    let mut extras = Extras::default();
+    extras.extend_at("/0", item_extras);
+    let group = Group {
+        items,
+        ..Default::default()
+    };
+    Ok((vec![AnyShape::Group(group)], extras))
+}
+
+/// A shape layer of `content`, see [`to_lottie_layer_content`], shown for the frames of `shown`,
+/// with index, parent, name and the transform of `transformed` at `frame_rate`
+fn shape_layer(
+    (shapes, shape_extras): (Vec<AnyShape>, Extras),
+    transformed: &ir::Group,
+    index: usize,
+    parent: Option<usize>,
+    name: Option<&str>,
+    shown: Range<f64>,
+    frame_rate: f64,
+) -> Result<Layer, LottieError> {
+    let layer = AnyLayer::Shape(bodymovin::layers::Shape {
+        in_point: shown.start,
+        out_point: shown.end,
+        mixin: ShapeMixin {
+            shapes,
             ..Default::default()
+        },
+        ..Default::default()
+    });
+    let mut layer = Layer {
+        layer,
+        extras: Extras::default(),
+    }
+    .with_field("ind", index.into());
+    if let Some(parent) = parent {
+        layer = layer.with_field("parent", parent.into());
+    }
+    if let Some(name) = name {
+        layer = layer.with_field("nm", name.into());
+    }
+    let transform = serde_json::to_value(to_lottie_transform(transformed, frame_rate))
+        .map_err(LottieError::Json)?;
+    let mut layer = layer.with_field("ks", transform);
+    for (key, value) in to_lottie_skew(transformed, frame_rate)? {
+        layer.extras.set("/ks", key, value);
+    }
+    layer.extras.extend_at("/shapes", shape_extras);
+    Ok(layer)
+}
+
+/// `layer` [masked](https://lottiefiles.github.io/lottie-docs/layers/#masks) to `clip`, a mask
+/// per subpath, added together
+fn with_mask(
+    layer: Layer,
+    clip: &Keyframed<BezPath>,
+    frame_rate: f64,
+    options: &LottieOptions,
+) -> Result<Layer, LottieError> {
+    let fixed = |k: serde_json::Value| serde_json::json!({"a": 0, "k": k});
+    let mut masks = Vec::new();
+    for i in 0..subpaths(&clip.earliest().value).len() {
+        // Keyframes without the subpath are caught as incompatible
+        let subpath = clip
+            .clone()
+            .map(|path| subpaths(&path).into_iter().nth(i).unwrap_or_default());
+        for subpath in to_lottie_subpath(&subpath, frame_rate, options.precision)? {
+            let subpath = serde_json::to_value(subpath).map_err(LottieError::Json)?;
+            masks.push(serde_json::json!({
+                "nm": "clip",
+                "mode": "a",
+                "inv": false,
+                "pt": subpath["ks"].clone(),
+                "o": fixed(100.into()),
+                "x": fixed(0.into()),
+            }));
+        }
+    }
+    Ok(layer
+        .with_field("hasMask", true.into())
+        .with_field("masksProperties", masks.into()))
+}
+
+/// Whether `group`, or anything in it, is to be [motion blurred](ir::Group::motion_blur)
+fn is_blurred(group: &ir::Group) -> bool {
+    group.motion_blur
+        || group.children.iter().any(|e| match e {
+            Element::Group(g) => is_blurred(g),
+            Element::Shape(..) => false,
         })
+}
+
+/// `layer`, of `group`, [motion blurred](https://lottiefiles.github.io/lottie-docs/layers/#layer)
+/// if anything in it is to be and the player can
+///
+/// Lottie blurs whole layers so a group blurs everything it shares a layer with.
+fn with_motion_blur(layer: Layer, group: &ir::Group, options: &LottieOptions) -> Layer {
+    if !options.profile.motion_blur() || !is_blurred(group) {
+        return layer;
     }
+    layer.with_field("mb", true.into())
 }
 
-fn to_lottie_group(group: &ir::Group) -> Result<Group, LottieError> {
-    // de facto standard for Lottie is groups contains shape(s), fill, transform
-    let mut items: Vec<_> = group
-        .children
-        .iter()
-        .map(|e| match e {
-            Element::Group(g) => to_lottie_group(g).map(|g| vec![AnyShape::Group(g)]),
-            Element::Shape(s) => {
-                to_lottie_subpath(s).map(|s| s.into_iter().map(AnyShape::Shape).collect())
+/// Lottie clips whole layers so groups within one, clipped, are drawn unclipped
+fn warn_of_clips(children: &[Element]) {
+    for child in children {
+        if let Element::Group(group) = child {
+            if group.clip.is_some() {
+                log::warn!("{:?} is drawn unclipped, Lottie clips layers", group.name());
+            }
+            warn_of_clips(&group.children);
+        }
+    }
+}
+
+/// The json of `lottie` with a marker, named and timed as the segment, per [`ir::Segment`]
+///
+/// Markers are in frames, at the frame rate of `lottie`.
+///
+/// The Lottie model has no markers so they are added to the json. Players such as lottie-web
+/// seek to a marker by name, e.g. `playSegments` or `goToAndPlay("loop")`.
+pub fn with_markers(
+    lottie: &LottieOutput,
+    segments: &[ir::Segment],
+) -> Result<serde_json::Value, LottieError> {
+    let mut json = lottie.to_json()?;
+    let frame_rate = lottie.lottie.frame_rate;
+    if !segments.is_empty() {
+        json["markers"] = segments
+            .iter()
+            .map(|segment| {
+                let (start, end) = (
+                    to_frames(segment.start, frame_rate),
+                    to_frames(segment.end, frame_rate),
+                );
+                serde_json::json!({
+                    "cm": segment.name,
+                    "tm": start,
+                    "dr": end - start,
+                })
+            })
+            .collect();
+    }
+    Ok(json)
+}
+
+/// The json of `lottie`, with markers for `segments`, pretty unless [`LottieOptions::minify`] is set
+pub fn lottie_json(
+    lottie: &LottieOutput,
+    segments: &[ir::Segment],
+    options: &LottieOptions,
+) -> Result<String, LottieError> {
+    let mut json = Vec::new();
+    write_lottie_json(&mut json, lottie, segments, options)?;
+    Ok(String::from_utf8(json).expect("serde_json only writes utf-8"))
+}
+
+/// Streams the json of [`lottie_json`] to `writer`, which is best buffered, e.g. by
+/// [`std::io::BufWriter`]
+pub fn write_lottie_json(
+    writer: &mut impl std::io::Write,
+    lottie: &LottieOutput,
+    segments: &[ir::Segment],
+    options: &LottieOptions,
+) -> Result<(), LottieError> {
+    let mut json = with_markers(lottie, segments)?;
+    add_motion_blur_settings(&mut json);
+    match options.minify {
+        Some(decimals) => {
+            minify(&mut json, decimals);
+            serde_json::to_writer(writer, &json)
+        }
+        None => {
+            if options.stable {
+                round_numbers(&mut json, Precision::STABLE);
+            }
+            serde_json::to_writer_pretty(writer, &json)
+        }
+    }
+    .map_err(LottieError::Json)
+}
+
+/// Give the composition `json` the After Effects default shutter if any layer is motion blurred,
+/// players that blur take the shutter from the composition
+fn add_motion_blur_settings(json: &mut serde_json::Value) {
+    let blurred = json["layers"]
+        .as_array()
+        .is_some_and(|layers| layers.iter().any(|layer| layer["mb"] == true));
+    if blurred {
+        json["mb"] = serde_json::json!({"sa": 180, "sp": -90, "spf": 16, "asl": 128});
+    }
+}
+
+/// Round every float in `json` per `precision`
+fn round_numbers(json: &mut serde_json::Value, precision: Precision) {
+    match json {
+        serde_json::Value::Number(n) => {
+            if let Some(v) = n.as_f64().filter(|_| n.is_f64()) {
+                *n = serde_json::Number::from_f64(precision.round(v)).unwrap_or_else(|| n.clone());
+            }
+        }
+        serde_json::Value::Array(values) => {
+            values
+                .iter_mut()
+                .for_each(|value| round_numbers(value, precision));
+        }
+        serde_json::Value::Object(properties) => {
+            properties
+                .values_mut()
+                .for_each(|value| round_numbers(value, precision));
+        }
+        serde_json::Value::Null | serde_json::Value::Bool(..) | serde_json::Value::String(..) => {}
+    }
+}
+
+/// Shrink Lottie json: round numbers to `decimals` places, writing whole ones as integers, and
+/// drop properties that say no more than their absence would
+pub fn minify(json: &mut serde_json::Value, decimals: u32) {
+    let scale = 10f64.powi(decimals as i32);
+    match json {
+        serde_json::Value::Number(n) => {
+            let Some(v) = n.as_f64().filter(|_| n.is_f64()) else {
+                return;
+            };
+            let v = (v * scale).round() / scale;
+            *n = if v.fract() == 0.0 && v.abs() < i64::MAX as f64 {
+                (v as i64).into()
+            } else {
+                serde_json::Number::from_f64(v).unwrap_or_else(|| n.clone())
+            };
+        }
+        serde_json::Value::Array(values) => {
+            for value in values.iter_mut() {
+                minify(value, decimals);
+            }
+        }
+        serde_json::Value::Object(properties) => {
+            properties.retain(|key, value| !is_default_property(key, value));
+            for value in properties.values_mut() {
+                minify(value, decimals);
+            }
+        }
+        serde_json::Value::Null | serde_json::Value::Bool(..) | serde_json::Value::String(..) => {}
+    }
+}
+
+/// Unset, not hidden, or unnamed; players treat these as if absent
+fn is_default_property(key: &str, value: &serde_json::Value) -> bool {
+    match key {
+        "hd" => value == false,
+        "nm" | "mn" => value == "",
+        _ => value.is_null(),
+    }
+}
+
+/// Read a Lottie this crate wrote, or one as simple, back into the IR, e.g. to retime it
+///
+/// Only the first shape layer is read, so not [`LottieOptions::layer_per_part`] output, and masks
+/// aren't, so clips are lost. Groups, paths, fills, strokes and transforms are
+/// understood; anything else, such as rects, trims or animated colors, is refused with
+/// [`LottieError::Unsupported`]. There is no font so the animation has no glyph id.
+impl TryFrom<&serde_json::Value> for ir::Animation {
+    type Error = LottieError;
+
+    fn try_from(json: &serde_json::Value) -> Result<Self, Self::Error> {
+        let lottie = Lottie::deserialize(json).map_err(LottieError::Json)?;
+        let (index, shapes) = lottie
+            .layers
+            .iter()
+            .enumerate()
+            .find_map(|(i, layer)| match layer {
+                AnyLayer::Shape(shape) => Some((i, &shape.mixin.shapes)),
+                _ => None,
+            })
+            .ok_or_else(|| LottieError::Unsupported("no shape layer".to_string()))?;
+        // Skew is read from the json, where the Lottie model may have no place for it
+        let shapes_json = &json["layers"][index]["shapes"];
+        let root = match shapes.as_slice() {
+            [AnyShape::Group(group)] => {
+                from_lottie_group(&group.items, &shapes_json[0]["it"], lottie.frame_rate)?
             }
+            items => from_lottie_group(items, shapes_json, lottie.frame_rate)?,
+        };
+        let (width, height) = (lottie.width as f64, lottie.height as f64);
+        Ok(ir::Animation {
+            schema_version: Default::default(),
+            width,
+            height,
+            duration: (lottie.out_point - lottie.in_point) / lottie.frame_rate,
+            frame_rate: lottie.frame_rate,
+            root,
+            src_to_dest_units: FontToOutput::new(Affine::IDENTITY),
+            warnings: Vec::new(),
+            gid: None,
+            segments: Vec::new(),
+            camera: None,
         })
-        .collect::<Result<Vec<_>, LottieError>>()?
+    }
+}
+
+/// As from json, the fields the Lottie model lacks included
+impl TryFrom<&LottieOutput> for ir::Animation {
+    type Error = LottieError;
+
+    fn try_from(lottie: &LottieOutput) -> Result<Self, Self::Error> {
+        ir::Animation::try_from(&lottie.to_json()?)
+    }
+}
+
+/// Replace every placeholder in `template` with `animation`, fit to the placeholder, keeping
+/// all the surrounding artwork
+///
+/// Each rectangle that doesn't move in each placeholder, see [`find_placeholders`], is replaced
+/// by the animation. The template's timing is kept so its frame rate must match, see
+/// [`ir::Animation::retime`].
+pub fn inject_into_template(
+    template: &mut LottieOutput,
+    animation: &ir::Animation,
+    options: &LottieOptions,
+) -> Result<(), LottieError> {
+    if template.lottie.frame_rate != animation.frame_rate {
+        return Err(LottieError::FrameRateMismatch {
+            template: template.lottie.frame_rate,
+            animation: animation.frame_rate,
+        });
+    }
+    let animation = animation.resolved();
+    let mut replaced = 0;
+    for ((layer, group), placeholder) in placeholders_at_mut(&mut template.lottie)? {
+        let mut items = Vec::with_capacity(placeholder.items.len());
+        for item in placeholder.items.drain(..) {
+            let Some(bounds) = (match &item {
+                AnyShape::Rect(rect) => fixed_rect(rect),
+                _ => None,
+            }) else {
+                items.push(item);
+                continue;
+            };
+            let root = animation.viewed_root().unmatted();
+            let (content, extras) = to_lottie_group(&root, animation.frame_rate, options)?;
+            // The content is the first item of the group that fits it
+            let at = format!("/layers/{layer}/shapes/{group}/it/{}/it/0", items.len());
+            template.extras.extend_at(&at, extras);
+            items.push(AnyShape::Group(fit_to(content, &animation, bounds)));
+            replaced += 1;
+        }
+        placeholder.items = items;
+    }
+    if replaced == 0 {
+        return Err(LottieError::NoPlaceholders);
+    }
+    Ok(())
+}
+
+/// Wrap content, drawn in the space of animation, in a group that centers it within bounds
+fn fit_to(content: Group, animation: &ir::Animation, bounds: Rect) -> Group {
+    let scale = (bounds.width() / animation.width).min(bounds.height() / animation.height);
+    let origin = bounds.center() - 0.5 * scale * Vec2::new(animation.width, animation.height);
+    let mut transform = Transform::default();
+    transform.anchor_point.value = Value::Fixed(vec![0.0, 0.0]);
+    transform.position.value = Value::Fixed(vec![origin.x, origin.y]);
+    transform.scale.value = Value::Fixed(vec![100.0 * scale, 100.0 * scale]);
+    Group {
+        items: vec![AnyShape::Group(content), AnyShape::Transform(transform)],
+        ..Default::default()
+    }
+}
+
+/// Layers or groups named with this prefix, ignoring case, mark placeholders in a template
+pub const PLACEHOLDER_PREFIX: &str = "placeholder";
+
+/// A group in a template that [`inject_into_template`] will fill
+#[derive(Debug, Clone, PartialEq)]
+pub struct Placeholder {
+    /// Index into the template's layers
+    pub layer: usize,
+    /// Index into the shapes of the layer
+    pub group: usize,
+    /// The name of the group, or failing that of its layer
+    pub name: Option<String>,
+    /// Where the animation will go, one per rectangle that doesn't move
+    pub rects: Vec<Rect>,
+}
+
+/// Report what [`inject_into_template`] would replace
+///
+/// A placeholder is a group at the top of a shape layer. If any such group, or its layer, is
+/// named starting with [`PLACEHOLDER_PREFIX`] only those so named are placeholders, otherwise all
+/// of them are. Fails with [`LottieError::NoPlaceholders`] if no placeholder has a rectangle to replace.
+/// Names are read from the template's json, [`LottieError::Json`] if it can't be written as such.
+pub fn find_placeholders(template: &Lottie) -> Result<Vec<Placeholder>, LottieError> {
+    let placeholders: Vec<_> = placeholder_groups(template)?
+        .into_iter()
+        .map(|(layer, group, name)| {
+            let AnyLayer::Shape(shape_layer) = &template.layers[layer] else {
+                unreachable!("placeholders are only found in shape layers");
+            };
+            let AnyShape::Group(placeholder) = &shape_layer.mixin.shapes[group] else {
+                unreachable!("placeholders are groups");
+            };
+            let rects = placeholder
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    AnyShape::Rect(rect) => fixed_rect(rect),
+                    _ => None,
+                })
+                .collect();
+            Placeholder {
+                layer,
+                group,
+                name,
+                rects,
+            }
+        })
+        .collect();
+    if placeholders.iter().all(|p| p.rects.is_empty()) {
+        return Err(LottieError::NoPlaceholders);
+    }
+    Ok(placeholders)
+}
+
+/// (layer, group, name) of every placeholder, see [`find_placeholders`]
+fn placeholder_groups(lottie: &Lottie) -> Result<Vec<(usize, usize, Option<String>)>, LottieError> {
+    // Names are read from json so we don't depend on where the Lottie model keeps them
+    let json = serde_json::to_value(lottie).map_err(LottieError::Json)?;
+    let name_at = |value: &serde_json::Value| value["nm"].as_str().map(str::to_string);
+    let is_marked = |name: &Option<String>| {
+        name.as_ref().is_some_and(|n| {
+            n.to_lowercase()
+                .starts_with(&PLACEHOLDER_PREFIX.to_lowercase())
+        })
+    };
+
+    let mut candidates = Vec::new();
+    for (i, layer) in lottie.layers.iter().enumerate() {
+        let AnyLayer::Shape(shape_layer) = layer else {
+            continue;
+        };
+        let layer_json = &json["layers"][i];
+        let layer_name = name_at(layer_json);
+        for (j, shape) in shape_layer.mixin.shapes.iter().enumerate() {
+            let AnyShape::Group(..) = shape else {
+                continue;
+            };
+            let group_name = name_at(&layer_json["shapes"][j]);
+            let marked = is_marked(&group_name) || is_marked(&layer_name);
+            candidates.push((i, j, group_name.or_else(|| layer_name.clone()), marked));
+        }
+    }
+    let any_marked = candidates.iter().any(|c| c.3);
+    Ok(candidates
+        .into_iter()
+        .filter(|c| c.3 || !any_marked)
+        .map(|(layer, group, name, _)| (layer, group, name))
+        .collect())
+}
+
+/// The groups [`find_placeholders`] would report
+pub(crate) fn placeholders_mut(lottie: &mut Lottie) -> Result<Vec<&mut Group>, LottieError> {
+    Ok(placeholders_at_mut(lottie)?
+        .into_iter()
+        .map(|(_, group)| group)
+        .collect())
+}
+
+/// The groups [`find_placeholders`] would report, by (layer, group)
+fn placeholders_at_mut(
+    lottie: &mut Lottie,
+) -> Result<Vec<((usize, usize), &mut Group)>, LottieError> {
+    let selected: HashSet<_> = placeholder_groups(lottie)?
         .into_iter()
-        .flatten()
+        .map(|(layer, group, _)| (layer, group))
         .collect();
+    let mut groups = Vec::new();
+    for (i, layer) in lottie.layers.iter_mut().enumerate() {
+        let AnyLayer::Shape(shape_layer) = layer else {
+            continue;
+        };
+        for (j, shape) in shape_layer.mixin.shapes.iter_mut().enumerate() {
+            match shape {
+                AnyShape::Group(group) if selected.contains(&(i, j)) => {
+                    groups.push(((i, j), group))
+                }
+                _ => (),
+            }
+        }
+    }
+    Ok(groups)
+}
+
+/// The bounds of a rectangle that doesn't move
+pub(crate) fn fixed_rect(rect: &LottieRect) -> Option<Rect> {
+    let (Value::Fixed(position), Value::Fixed(size)) = (&rect.position.value, &rect.size.value)
+    else {
+        return None;
+    };
+    let (&[x, y], &[w, h]) = (position.as_slice(), size.as_slice()) else {
+        return None;
+    };
+    Some(Rect::from_center_size((x, y), (w, h)))
+}
+
+/// A Lottie group for `group` and the fields, relative to it, the Lottie model lacks
+fn to_lottie_group(
+    group: &ir::Group,
+    frame_rate: f64,
+    options: &LottieOptions,
+) -> Result<(Group, Extras), LottieError> {
+    let (mut items, mut extras) = to_lottie_items(group, frame_rate, options)?;
+    for (key, value) in to_lottie_skew(group, frame_rate)? {
+        extras.set(&format!("/it/{}", items.len()), key, value);
+    }
+    items.push(AnyShape::Transform(to_lottie_transform(group, frame_rate)));
+    Ok((
+        Group {
+            items,
+            ..Default::default()
+        },
+        extras,
+    ))
+}
 
-    let mut fill = Fill::default();
-    if let Some((r, g, b)) = group.fill {
-        fill.color = Property {
-            value: Value::Fixed(vec![r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0]),
+/// The items of [`to_lottie_group`] but the transform
+fn to_lottie_items(
+    group: &ir::Group,
+    frame_rate: f64,
+    options: &LottieOptions,
+) -> Result<(Vec<AnyShape>, Extras), LottieError> {
+    // de facto standard for Lottie is groups contains shape(s), stroke, fill, transform
+    // Items earlier in the list draw on top of later ones.
+    // Draw higher z on top, that is earlier
+    let mut children: Vec<_> = group.children.iter().collect();
+    children.sort_by_key(|e| Reverse(e.z()));
+    let mut items = Vec::new();
+    let mut extras = Extras::default();
+    for child in children {
+        match child {
+            Element::Group(g) => {
+                let (g, child_extras) = to_lottie_group(g, frame_rate, options)?;
+                extras.extend_at(&format!("/it/{}", items.len()), child_extras);
+                items.push(AnyShape::Group(g));
+            }
+            Element::Shape(s) => items.extend(
+                to_lottie_subpath(s, frame_rate, options.precision)?
+                    .into_iter()
+                    .map(AnyShape::Shape),
+            ),
+        }
+    }
+
+    let fill = match &group.gradient {
+        Some(gradient) => to_lottie_gradient_fill(gradient)?,
+        None => {
+            let mut fill = Fill::default();
+            if let Some(rgb) = group.fill {
+                fill.color = to_lottie_color(rgb);
+            }
+            AnyShape::Fill(fill)
+        }
+    };
+    let stroke = group.stroke.map(|s| {
+        let mut stroke = Stroke::default();
+        stroke.color = to_lottie_color(s.color);
+        stroke.width = Property {
+            value: Value::Fixed(s.width),
             ..Default::default()
         };
+        AnyShape::Stroke(stroke)
+    });
+    match (options.paint_order, stroke) {
+        (PaintOrder::StrokeAboveFill, Some(stroke)) => items.extend([stroke, fill]),
+        (PaintOrder::FillAboveStroke, Some(stroke)) => items.extend([fill, stroke]),
+        (_, None) => items.push(fill),
     }
-    items.push(AnyShape::Fill(fill));
-    items.push(AnyShape::Transform(to_lottie_transform(group)));
+    Ok((items, extras))
+}
 
-    Ok(Group {
-        items,
+fn to_lottie_color((r, g, b): (u8, u8, u8)) -> Property<Vec<f64>> {
+    Property {
+        value: Value::Fixed(vec![r as f64 / 255.0, g as f64 / 255.0, b as f64 / 255.0]),
         ..Default::default()
-    })
+    }
+}
+
+/// A Lottie [gradient fill](https://lottiefiles.github.io/lottie-docs/shapes/#gradient-fill)
+///
+/// Written as the json the spec gives and read into the Lottie model, an error if it can't be.
+fn to_lottie_gradient_fill(gradient: &ir::Gradient) -> Result<AnyShape, LottieError> {
+    let fixed = |k: serde_json::Value| serde_json::json!({"a": 0, "k": k});
+    let (kind, start, end) = match gradient {
+        ir::Gradient::Linear { start, end, .. } => (1, *start, *end),
+        // A radial gradient runs from the center to a point on the circle
+        ir::Gradient::Radial { center, radius, .. } => {
+            (2, *center, *center + Vec2::new(*radius, 0.0))
+        }
+    };
+    let stops = gradient.stops();
+    let colors: Vec<f64> = stops
+        .iter()
+        .flat_map(|stop| {
+            let (r, g, b) = stop.color;
+            [
+                stop.offset,
+                r as f64 / 255.0,
+                g as f64 / 255.0,
+                b as f64 / 255.0,
+            ]
+        })
+        .collect();
+    let json = serde_json::json!({
+        "ty": "gf",
+        "nm": "Gradient Fill",
+        "o": fixed(100.into()),
+        "r": 1,
+        "s": fixed(serde_json::json!([start.x, start.y])),
+        "e": fixed(serde_json::json!([end.x, end.y])),
+        "t": kind,
+        "h": fixed(0.into()),
+        "a": fixed(0.into()),
+        "g": {"p": stops.len(), "k": fixed(colors.into())},
+    });
+    serde_json::from_value(json).map_err(LottieError::Json)
 }
 
-fn to_lottie_transform(group: &ir::Group) -> Transform {
+/// The transform of `group` but its skew, see [`to_lottie_skew`], keyframed at `frame_rate`
+pub(crate) fn to_lottie_transform(group: &ir::Group, frame_rate: f64) -> Transform {
     let mut transform = Transform::default();
     let (center_x, center_y) = (group.center.x, group.center.y);
     transform.anchor_point.value = Value::Fixed(vec![center_x, center_y]);
 
-    transform.rotation.animated = group.rotate.is_animated() as i8;
-    transform.rotation.value = if group.rotate.is_animated() {
-        Value::Animated(
-            group
-                .rotate
-                .iter()
-                .map(|keyframe| MultiDimensionalKeyframe {
-                    start_time: keyframe.frame,
-                    start_value: Some(vec![keyframe.value]),
-                    bezier: Some(default_ease()),
-                    ..Default::default()
-                })
-                .collect(),
-        )
-    } else {
-        Value::Fixed(group.rotate.earliest().value)
-    };
+    transform.rotation = to_lottie_scalar(&group.rotate, frame_rate, |degrees| *degrees);
 
     transform.scale.animated = group.scale.is_animated() as i8;
     transform.scale.value = if group.scale.is_animated() {
@@ -104,9 +1067,10 @@ fn to_lottie_transform(group: &ir::Group) -> Transform {
                 .scale
                 .iter()
                 .map(|keyframe| MultiDimensionalKeyframe {
-                    start_time: keyframe.frame,
+                    start_time: to_frames(keyframe.time, frame_rate),
                     start_value: Some(vec![keyframe.value.0, keyframe.value.1]),
-                    bezier: Some(default_ease()),
+                    bezier: Some(to_lottie_ease(keyframe.ease.unwrap_or_default())),
+                    hold: keyframe.hold,
                     ..Default::default()
                 })
                 .collect(),
@@ -123,12 +1087,13 @@ fn to_lottie_transform(group: &ir::Group) -> Transform {
                 .translate
                 .iter()
                 .map(|keyframe| MultiDimensionalKeyframe {
-                    start_time: keyframe.frame,
+                    start_time: to_frames(keyframe.time, frame_rate),
                     start_value: Some(vec![
                         center_x + keyframe.value.x,
                         center_y + keyframe.value.y,
                     ]),
-                    bezier: Some(default_ease()),
+                    bezier: Some(to_lottie_ease(keyframe.ease.unwrap_or_default())),
+                    hold: keyframe.hold,
                     ..Default::default()
                 })
                 .collect(),
@@ -138,43 +1103,110 @@ fn to_lottie_transform(group: &ir::Group) -> Transform {
         Value::Fixed(vec![center_x + value.x, center_y + value.y])
     };
 
+    transform.opacity =
+        to_lottie_scalar(&group.visible_opacity(frame_rate), frame_rate, |percent| {
+            *percent
+        });
     transform
 }
 
-fn default_ease() -> BezierEase {
-    // If https://lottiefiles.github.io/lottie-docs/playground/json_editor/ is to be believed
-    // the bezier ease is usually required since we rarely want to "hold"
+/// The [skew and skew axis](https://lottiefiles.github.io/lottie-docs/concepts/#transform)
+/// fields of the transform of `group`, none if it isn't skewed
+fn to_lottie_skew(
+    group: &ir::Group,
+    frame_rate: f64,
+) -> Result<Vec<(&'static str, serde_json::Value)>, LottieError> {
+    if !group.is_skewed() {
+        return Ok(Vec::new());
+    }
+    let property = |value: fn(&(f64, f64)) -> f64| {
+        serde_json::to_value(to_lottie_scalar(&group.skew, frame_rate, value))
+            .map_err(LottieError::Json)
+    };
+    Ok(vec![
+        ("sk", property(|(degrees, _)| *degrees)?),
+        ("sa", property(|(_, axis)| *axis)?),
+    ])
+}
+
+/// A property of one number, `value` of each keyframe, keyframed at `frame_rate`
+fn to_lottie_scalar<T>(
+    keyframed: &Keyframed<T>,
+    frame_rate: f64,
+    value: impl Fn(&T) -> f64,
+) -> Property<f64> {
+    let value = if keyframed.is_animated() {
+        Value::Animated(
+            keyframed
+                .iter()
+                .map(|keyframe| MultiDimensionalKeyframe {
+                    start_time: to_frames(keyframe.time, frame_rate),
+                    start_value: Some(vec![value(&keyframe.value)]),
+                    bezier: Some(to_lottie_ease(keyframe.ease.unwrap_or_default())),
+                    hold: keyframe.hold,
+                    ..Default::default()
+                })
+                .collect(),
+        )
+    } else {
+        Value::Fixed(value(&keyframed.earliest().value))
+    };
+    Property {
+        animated: keyframed.is_animated() as i8,
+        value,
+        ..Default::default()
+    }
+}
+
+// If https://lottiefiles.github.io/lottie-docs/playground/json_editor/ is to be believed
+// the bezier ease is usually required since we rarely want to "hold"
+fn to_lottie_ease(ease: Ease) -> BezierEase {
     BezierEase::_2D(Bezier2d {
         // the control point incoming to destination
-        in_value: ControlPoint2d { x: 0.6, y: 1.0 },
+        in_value: ControlPoint2d {
+            x: ease.p2.x,
+            y: ease.p2.y,
+        },
         // the control point outgoing from origin
-        out_value: ControlPoint2d { x: 0.4, y: 0.0 },
+        out_value: ControlPoint2d {
+            x: ease.p1.x,
+            y: ease.p1.y,
+        },
     })
 }
 
-fn to_lottie_subpath(path: &Keyframed<BezPath>) -> Result<Vec<SubPath>, LottieError> {
-    // https://lottiefiles.github.io/lottie-docs/playground/json_editor/ doesn't play if there is no ease
-    let ease = default_ease();
-
+/// The subpaths of `path`, keyframed at `frame_rate` if it's animated
+pub(crate) fn to_lottie_subpath(
+    path: &Keyframed<BezPath>,
+    frame_rate: f64,
+    precision: Option<Precision>,
+) -> Result<Vec<SubPath>, LottieError> {
     // In a mildly confusing turn of events an *animated* subpath has keyframes with
     // vectors of paths while a static one just gets a single continuous path so what we
     // produce varies based on whether we're animated
     let first_frame = path.earliest();
     if path.len() < 2 {
-        return Ok(first_frame.subpaths().iter().map(create_subpath).collect());
+        return Ok(first_frame
+            .subpaths()
+            .iter()
+            .map(|subpath| create_subpath(subpath, precision))
+            .collect());
     }
 
     // We're animated!
 
     // TODO: support incompatible paths by cutting between them
     // For now just reject incompatible paths
-    let first_frame_cmds = path_commands(&first_frame.value);
-    if !path
-        .iter()
-        .map(|p| path_commands(&p.value))
-        .all(|commands| first_frame_cmds == commands)
-    {
-        return Err(LottieError::IncompatiblePaths(path.clone()));
+    for (from, to) in path.iter().zip(path.iter().skip(1)) {
+        if let Err(mismatch) = ir::compatible(&from.value, &to.value) {
+            return Err(LottieError::IncompatiblePaths {
+                from: to_frames(from.time, frame_rate),
+                to: to_frames(to.time, frame_rate),
+                expected: path_commands(&from.value),
+                actual: path_commands(&to.value),
+                mismatch,
+            });
+        }
     }
 
     // The shape is animated, make a single subpath whose keyframes have lots of static paths
@@ -182,22 +1214,19 @@ fn to_lottie_subpath(path: &Keyframed<BezPath>) -> Result<Vec<SubPath>, LottieEr
     subpath.vertices.animated = 1;
     let mut keyframes = Vec::with_capacity(path.len());
 
-    if path.len() > 2 {
-        panic!("TODO: support > 2 path keyframes");
-    }
-
     for ir_keyframe in path.iter() {
         keyframes.push(ShapeKeyframe {
-            start_time: ir_keyframe.frame,
+            start_time: to_frames(ir_keyframe.time, frame_rate),
             start_value: Some(
                 ir_keyframe
                     .subpaths()
                     .iter()
-                    .map(create_shapevalue)
+                    .map(|subpath| create_shapevalue(subpath, precision))
                     .collect(),
             ),
-            // no ease, no render
-            bezier: Some(ease.clone()),
+            // https://lottiefiles.github.io/lottie-docs/playground/json_editor/ doesn't play if there is no ease
+            bezier: Some(to_lottie_ease(ir_keyframe.ease.unwrap_or_default())),
+            hold: ir_keyframe.hold,
             ..Default::default()
         })
     }
@@ -250,11 +1279,10 @@ fn add_cubic(shape: &mut ShapeValue, c0: Point, c1: Point, end: Point) {
     shape.vertices.push(end.into());
 }
 
-fn create_subpath(subpath: &BezPath) -> SubPath {
-    // eprintln!("create_subpath, cbox {:?}", path.control_box());
+fn create_subpath(subpath: &BezPath, precision: Option<Precision>) -> SubPath {
     SubPath {
         vertices: Property {
-            value: Value::Fixed(create_shapevalue(subpath)),
+            value: Value::Fixed(create_shapevalue(subpath, precision)),
             ..Default::default()
         },
         // 1.0 = Clockwise = positive area
@@ -268,7 +1296,8 @@ fn create_subpath(subpath: &BezPath) -> SubPath {
     }
 }
 
-fn create_shapevalue(subpath: &BezPath) -> ShapeValue {
+fn create_shapevalue(subpath: &BezPath, precision: Option<Precision>) -> ShapeValue {
+    let subpath = &quantize(subpath, precision);
     let mut value = ShapeValue::default();
     for el in subpath.iter() {
         let last_on: Point = value.vertices.last().cloned().unwrap_or_default().into();
@@ -296,8 +1325,1027 @@ fn create_shapevalue(subpath: &BezPath) -> ShapeValue {
                 == value.vertices.last().cloned().unwrap_or_default(),
         );
     }
+    if let Some(precision) = precision {
+        // Vertices were rounded with the path but quadratics gain new control points and
+        // relative control points pick up float noise in subtraction
+        for coords in value.in_point.iter_mut().chain(value.out_point.iter_mut()) {
+            *coords = precision.round_point((*coords).into()).into();
+        }
+    }
     value
 }
 
+fn unsupported(what: &str) -> LottieError {
+    LottieError::Unsupported(what.to_string())
+}
+
+/// The inverse of [`to_lottie_group`], `json` is that of `items`, keyframed at `frame_rate`
+fn from_lottie_group(
+    items: &[AnyShape],
+    json: &serde_json::Value,
+    frame_rate: f64,
+) -> Result<ir::Group, LottieError> {
+    let mut group = ir::Group::default();
+    for (i, item) in items.iter().enumerate() {
+        match item {
+            AnyShape::Group(child) => group.children.push(Element::Group(from_lottie_group(
+                &child.items,
+                &json[i]["it"],
+                frame_rate,
+            )?)),
+            AnyShape::Shape(subpath) => group
+                .children
+                .push(Element::Shape(from_lottie_subpath(subpath, frame_rate)?)),
+            AnyShape::Fill(fill) => group.fill = Some(from_lottie_color(&fill.color)?),
+            AnyShape::Stroke(stroke) => {
+                let Value::Fixed(width) = &stroke.width.value else {
+                    return Err(unsupported("animated stroke width"));
+                };
+                group.stroke = Some(ir::Stroke {
+                    color: from_lottie_color(&stroke.color)?,
+                    width: *width,
+                });
+            }
+            AnyShape::Transform(transform) => {
+                from_lottie_transform(transform, &json[i], frame_rate, &mut group)?
+            }
+            _ => {
+                return Err(unsupported(
+                    "shapes other than groups, paths, fills and strokes",
+                ))
+            }
+        }
+    }
+    Ok(group)
+}
+
+fn from_lottie_color(color: &Property<Vec<f64>>) -> Result<(u8, u8, u8), LottieError> {
+    let Value::Fixed(rgb) = &color.value else {
+        return Err(unsupported("animated color"));
+    };
+    let channel = |i: usize| (rgb.get(i).copied().unwrap_or_default() * 255.0).round() as u8;
+    Ok((channel(0), channel(1), channel(2)))
+}
+
+/// The inverse of [`to_lottie_transform`] and [`to_lottie_skew`], `json` is that of `transform`
+fn from_lottie_transform(
+    transform: &Transform,
+    json: &serde_json::Value,
+    frame_rate: f64,
+    group: &mut ir::Group,
+) -> Result<(), LottieError> {
+    let center = match &transform.anchor_point.value {
+        Value::Fixed(v) if v.len() >= 2 => Point::new(v[0], v[1]),
+        Value::Fixed(_) => Point::ZERO,
+        Value::Animated(_) => return Err(unsupported("animated anchor point")),
+    };
+    group.center = center;
+    group.translate =
+        from_lottie_vector(&transform.position, frame_rate, |v| v - center.to_vec2())?;
+    group.scale = from_lottie_vector(&transform.scale, frame_rate, |v| (v.x, v.y))?;
+    group.rotate = from_lottie_scalar(&transform.rotation, frame_rate)?;
+    group.opacity = from_lottie_scalar(&transform.opacity, frame_rate)?;
+
+    let skew = |key: &str| match json.get(key) {
+        None | Some(serde_json::Value::Null) => Ok(Keyframed::new(0.0, 0.0)),
+        Some(property) => from_lottie_scalar(
+            &Property::deserialize(property).map_err(LottieError::Json)?,
+            frame_rate,
+        ),
+    };
+    group.skew = from_lottie_skew(skew("sk")?, skew("sa")?)?;
+    Ok(())
+}
+
+/// Skew and its axis, keyframed together as the IR has them
+///
+/// Keyframes are taken from whichever is animated; if both are they must share frames.
+fn from_lottie_skew(
+    skew: Keyframed<f64>,
+    axis: Keyframed<f64>,
+) -> Result<Keyframed<(f64, f64)>, LottieError> {
+    let times = |k: &Keyframed<f64>| k.iter().map(|k| k.time).collect::<Vec<_>>();
+    if skew.is_animated() && axis.is_animated() && times(&skew) != times(&axis) {
+        return Err(unsupported(
+            "skew and skew axis keyframed at different frames",
+        ));
+    }
+    let timing = if axis.is_animated() { &axis } else { &skew };
+    timing
+        .iter()
+        .map(|keyframe| ir::Keyframe {
+            time: keyframe.time,
+            value: (skew.value_at(keyframe.time), axis.value_at(keyframe.time)),
+            ease: keyframe.ease,
+            hold: keyframe.hold,
+        })
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(LottieError::InvalidKeyframes)
+}
+
+fn from_lottie_scalar(
+    property: &Property<f64>,
+    frame_rate: f64,
+) -> Result<Keyframed<f64>, LottieError> {
+    match &property.value {
+        Value::Fixed(value) => Ok(Keyframed::new(0.0, *value)),
+        Value::Animated(keyframes) => {
+            from_lottie_keyframes(keyframes, frame_rate, |v| v.first().copied().ok_or(()))
+        }
+    }
+}
+
+fn from_lottie_vector<T>(
+    property: &Property<Vec<f64>>,
+    frame_rate: f64,
+    convert: impl Fn(Vec2) -> T,
+) -> Result<Keyframed<T>, LottieError> {
+    let vector = |v: &[f64]| match v {
+        [x, y, ..] => Ok(convert(Vec2::new(*x, *y))),
+        _ => Err(()),
+    };
+    match &property.value {
+        Value::Fixed(value) => Ok(Keyframed::new(
+            0.0,
+            vector(value).map_err(|_| unsupported("a vector without x and y"))?,
+        )),
+        Value::Animated(keyframes) => from_lottie_keyframes(keyframes, frame_rate, vector),
+    }
+}
+
+/// Keyframes in seconds from Lottie's, in frames at `frame_rate`
+fn from_lottie_keyframes<T>(
+    keyframes: &[MultiDimensionalKeyframe],
+    frame_rate: f64,
+    convert: impl Fn(&[f64]) -> Result<T, ()>,
+) -> Result<Keyframed<T>, LottieError> {
+    keyframes
+        .iter()
+        .map(|keyframe| {
+            let value = keyframe
+                .start_value
+                .as_deref()
+                .ok_or(())
+                .and_then(&convert)
+                .map_err(|_| unsupported("a keyframe without a usable start value"))?;
+            Ok(ir::Keyframe {
+                time: keyframe.start_time / frame_rate,
+                value,
+                ease: keyframe.bezier.as_ref().and_then(from_lottie_ease),
+                hold: keyframe.hold,
+            })
+        })
+        .collect::<Result<Vec<_>, LottieError>>()?
+        .try_into()
+        .map_err(LottieError::InvalidKeyframes)
+}
+
+/// The inverse of [`to_lottie_ease`], only 2d eases are understood
+fn from_lottie_ease(ease: &BezierEase) -> Option<Ease> {
+    match ease {
+        BezierEase::_2D(Bezier2d {
+            in_value,
+            out_value,
+        }) => Some(Ease {
+            p1: Point::new(out_value.x, out_value.y),
+            p2: Point::new(in_value.x, in_value.y),
+        }),
+        _ => None,
+    }
+}
+
+/// The inverse of [`to_lottie_subpath`]
+fn from_lottie_subpath(
+    subpath: &SubPath,
+    frame_rate: f64,
+) -> Result<Keyframed<BezPath>, LottieError> {
+    match &subpath.vertices.value {
+        Value::Fixed(value) => Ok(Keyframed::new(
+            0.0,
+            from_lottie_shapevalues(std::slice::from_ref(value), true),
+        )),
+        Value::Animated(keyframes) => {
+            let read = |lines| {
+                keyframes
+                    .iter()
+                    .map(|keyframe| {
+                        let values = keyframe
+                            .start_value
+                            .as_deref()
+                            .ok_or_else(|| unsupported("a shape keyframe without a start value"))?;
+                        Ok(ir::Keyframe {
+                            time: keyframe.start_time / frame_rate,
+                            value: from_lottie_shapevalues(values, lines),
+                            ease: keyframe.bezier.as_ref().and_then(from_lottie_ease),
+                            hold: keyframe.hold,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, LottieError>>()
+            };
+            // Lines are cubics with no handles; if that isn't true of every keyframe keep
+            // everything cubic so the paths stay interpolation-compatible
+            let mut keyframes = read(true)?;
+            let first_cmds = keyframes.first().map(|k| path_commands(&k.value));
+            if keyframes
+                .iter()
+                .any(|k| Some(path_commands(&k.value)) != first_cmds)
+            {
+                keyframes = read(false)?;
+            }
+            keyframes.try_into().map_err(LottieError::InvalidKeyframes)
+        }
+    }
+}
+
+fn points<T: Copy + Into<Point>>(coords: &[T]) -> Vec<Point> {
+    coords.iter().map(|c| (*c).into()).collect()
+}
+
+/// The inverse of [`create_shapevalue`], see [`add_cubic`] for the layout
+fn from_lottie_shapevalues(values: &[ShapeValue], lines: bool) -> BezPath {
+    let mut path = BezPath::new();
+    for value in values {
+        let vertices = points(&value.vertices);
+        let in_point = points(&value.in_point);
+        let out_point = points(&value.out_point);
+        let Some(first) = vertices.first() else {
+            continue;
+        };
+        let handles = |from: usize, to: usize| {
+            (
+                out_point.get(from).copied().unwrap_or_default().to_vec2(),
+                in_point.get(to).copied().unwrap_or_default().to_vec2(),
+            )
+        };
+        let segment = |path: &mut BezPath, from: usize, to: usize| {
+            let (out_handle, in_handle) = handles(from, to);
+            if lines && out_handle == Vec2::ZERO && in_handle == Vec2::ZERO {
+                path.line_to(vertices[to]);
+            } else {
+                path.curve_to(
+                    vertices[from] + out_handle,
+                    vertices[to] + in_handle,
+                    vertices[to],
+                );
+            }
+        };
+
+        path.move_to(*first);
+        for i in 1..vertices.len() {
+            segment(&mut path, i - 1, i);
+        }
+        if value.closed == Some(true) {
+            // A closing line is implied by close, only a closing curve needs spelling out
+            let last = vertices.len() - 1;
+            if handles(last, 0) != (Vec2::ZERO, Vec2::ZERO) {
+                segment(&mut path, last, 0);
+            }
+            path.close_path();
+        }
+    }
+    path
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use std::time::Duration;
+
+    use bodymovin::{
+        layers::{AnyLayer, ShapeMixin},
+        properties::{BezierEase, Value},
+        shapes::{AnyShape, Fill, Group, Rect as LottieRect},
+        Bodymovin as Lottie,
+    };
+    use kurbo::{BezPath, Circle, Point, Rect, Shape};
+
+    use crate::{
+        bezop::Precision,
+        error::{LottieError, Mismatch},
+        ir::{self, ColorStop, Ease, Element, Keyframe, Keyframed, Stroke},
+        path_commands,
+    };
+
+    use super::{
+        find_placeholders, from_lottie_subpath, inject_into_template, lottie_json,
+        placeholders_mut, to_lottie, to_lottie_group, to_lottie_subpath, with_markers,
+        LottieOptions, LottieOutput, LottieProfile, PaintOrder,
+    };
+
+    fn stroked_group() -> ir::Group {
+        let mut group = ir::Group::default();
+        group.children.push(Element::Shape(Keyframed::new(
+            0.0,
+            Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1),
+        )));
+        group.set_fill(Some((0, 0, 0)));
+        group.set_stroke(Some(Stroke {
+            color: (255, 0, 0),
+            width: 2.0,
+        }));
+        group
+    }
+
+    #[test]
+    fn higher_z_draws_first() {
+        let mut group = ir::Group::default();
+        for red in [1, 2, 3] {
+            let mut part = stroked_group();
+            part.set_fill(Some((red, 0, 0)));
+            if red == 2 {
+                part.set_z(1);
+            }
+            group.children.push(Element::Group(part));
+        }
+        let reds: Vec<_> = to_lottie_group(&group, 60.0, &Default::default())
+            .unwrap()
+            .0
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                AnyShape::Group(part) => part.items.iter().find_map(|item| match item {
+                    AnyShape::Fill(fill) => match &fill.color.value {
+                        Value::Fixed(rgb) => Some((rgb[0] * 255.0).round() as u8),
+                        _ => None,
+                    },
+                    _ => None,
+                }),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(vec![2, 1, 3], reds);
+    }
+
+    fn item_types(paint_order: PaintOrder) -> Vec<&'static str> {
+        to_lottie_group(
+            &stroked_group(),
+            60.0,
+            &LottieOptions {
+                paint_order,
+                ..Default::default()
+            },
+        )
+        .unwrap()
+        .0
+        .items
+        .iter()
+        .map(|i| match i {
+            AnyShape::Shape(..) => "shape",
+            AnyShape::Stroke(..) => "stroke",
+            AnyShape::Fill(..) => "fill",
+            AnyShape::Transform(..) => "transform",
+            _ => "other",
+        })
+        .collect()
+    }
+
+    fn part_layers_json(animation: &ir::Animation) -> Vec<serde_json::Value> {
+        let options = LottieOptions {
+            layer_per_part: true,
+            ..Default::default()
+        };
+        let mut json = serde_json::to_value(to_lottie(animation, &options).unwrap()).unwrap();
+        let serde_json::Value::Array(layers) = json["layers"].take() else {
+            panic!("Layers should be an array");
+        };
+        layers
+    }
+
+    #[test]
+    fn camera_parents_layers() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.set_camera(Some(ir::Camera {
+            zoom: vec![(0.0, 100.0), (0.5, 150.0)].try_into().unwrap(),
+            ..Default::default()
+        }));
+        let json = serde_json::to_value(to_lottie(&animation, &LottieOptions::default()).unwrap())
+            .unwrap();
+        let layers = json["layers"].as_array().unwrap();
+        let camera = layers.last().unwrap();
+        assert_eq!(Some("camera"), camera["nm"].as_str(), "{json}");
+        assert_eq!(Some(1), camera["ks"]["s"]["a"].as_i64(), "{camera}");
+        assert_eq!(Some(50.0), camera["ks"]["a"]["k"][0].as_f64(), "{camera}");
+        assert_eq!(camera["ind"], layers[0]["parent"], "{json}");
+    }
+
+    #[test]
+    fn motion_blur_needs_profile() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.root.set_motion_blur(true);
+        let json_for = |profile| {
+            let options = LottieOptions {
+                profile,
+                ..Default::default()
+            };
+            let lottie = to_lottie(&animation, &options).unwrap();
+            let json: serde_json::Value =
+                serde_json::from_str(&lottie_json(&lottie, &[], &options).unwrap()).unwrap();
+            json
+        };
+
+        let json = json_for(LottieProfile::Skottie);
+        assert_eq!(Some(true), json["layers"][0]["mb"].as_bool(), "{json}");
+        assert_eq!(Some(180), json["mb"]["sa"].as_i64(), "{json}");
+
+        let json = json_for(LottieProfile::Portable);
+        assert!(json["layers"][0].get("mb").is_none(), "{json}");
+        assert!(json.get("mb").is_none(), "{json}");
+    }
+
+    #[test]
+    fn matte_cuts_out() {
+        let mut root = ir::Group::default();
+        for name in ["icon", "cutout", "dot"] {
+            let mut part = stroked_group();
+            part.set_name(Some(name.to_string()));
+            root.children_mut().push(Element::Group(part));
+        }
+        let Element::Group(icon) = &mut root.children_mut()[0] else {
+            unreachable!();
+        };
+        icon.set_matte(Some(ir::Matte {
+            part: "cutout".to_string(),
+            mode: ir::MatteMode::InvertedAlpha,
+        }));
+        let animation = ir::Animation::new(20.0, 20.0, Duration::from_millis(500), root);
+
+        // The cutout is a track matte just above the icon, and not drawn on its own
+        let layers = part_layers_json(&animation);
+        let summary: Vec<_> = layers
+            .iter()
+            .map(|l| (l["nm"].as_str(), l["td"].as_i64(), l["tt"].as_i64()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("cutout"), Some(1), None),
+                (Some("icon"), None, Some(2)),
+                (Some("dot"), None, None),
+            ],
+            summary
+        );
+
+        // In one layer there are no mattes, the cutout is dropped
+        let json =
+            serde_json::to_value(to_lottie(&animation, &Default::default()).unwrap()).unwrap();
+        let items = json["layers"][0]["shapes"][0]["it"].as_array().unwrap();
+        assert_eq!(
+            2,
+            items.iter().filter(|i| i["ty"] == "gr").count(),
+            "Only the icon and dot should be drawn, {items:?}"
+        );
+    }
+
+    #[test]
+    fn layer_per_part() {
+        let mut root = ir::Group::default();
+        for name in ["low", "high"] {
+            let mut part = stroked_group();
+            part.set_name(Some(name.to_string()));
+            root.children_mut().push(Element::Group(part));
+        }
+        let Element::Group(high) = &mut root.children_mut()[1] else {
+            unreachable!();
+        };
+        high.set_z(1);
+        high.set_rotate(vec![(0.0, 0.0), (0.5, 90.0)].try_into().unwrap());
+        let mut animation = ir::Animation::new(20.0, 20.0, Duration::from_millis(500), root);
+
+        let layers = part_layers_json(&animation);
+        let summary: Vec<_> = layers
+            .iter()
+            .map(|l| {
+                (
+                    l["ind"].as_f64(),
+                    l["nm"].as_str(),
+                    l["parent"].as_f64(),
+                    l["op"].as_f64(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(1.0), Some("high"), None, Some(30.0)),
+                (Some(2.0), Some("low"), None, Some(30.0)),
+            ],
+            summary
+        );
+        // The part's motion is the layer's
+        assert_eq!(Some(1), layers[0]["ks"]["r"]["a"].as_i64());
+        assert_eq!(Some(0), layers[1]["ks"]["r"]["a"].as_i64());
+
+        // Motion of the whole comes from a parent
+        animation.root_mut().set_scale(
+            vec![(0.0, (100.0, 100.0)), (0.5, (50.0, 50.0))]
+                .try_into()
+                .unwrap(),
+        );
+        let layers = part_layers_json(&animation);
+        let summary: Vec<_> = layers
+            .iter()
+            .map(|l| {
+                (
+                    l["nm"].as_str(),
+                    l["parent"].as_f64(),
+                    l["ks"]["s"]["a"].as_i64(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("root"), None, Some(1)),
+                (Some("high"), Some(1.0), Some(0)),
+                (Some("low"), Some(1.0), Some(0)),
+            ],
+            summary
+        );
+    }
+
+    #[test]
+    fn visible_part_pops() {
+        let mut part = stroked_group();
+        part.set_visible(Some(10.0 / 60.0..20.0 / 60.0));
+        let mut root = ir::Group::default();
+        root.children_mut().push(Element::Group(part));
+        let animation = ir::Animation::new(20.0, 20.0, Duration::from_millis(500), root);
+
+        // One layer, the part's opacity steps
+        let json =
+            serde_json::to_value(to_lottie(&animation, &Default::default()).unwrap()).unwrap();
+        let opacity = &json["layers"][0]["shapes"][0]["it"][0]["it"]
+            .as_array()
+            .unwrap()
+            .last()
+            .unwrap()["o"];
+        assert_eq!(Some(1), opacity["a"].as_i64(), "{opacity}");
+        let steps: Vec<_> = opacity["k"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|k| (k["t"].as_f64(), k["s"][0].as_f64()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(0.0), Some(0.0)),
+                (Some(10.0), Some(100.0)),
+                (Some(20.0), Some(0.0))
+            ],
+            steps
+        );
+
+        // A layer that plays only while the part is visible
+        let layers = part_layers_json(&animation);
+        assert_eq!(
+            (Some(10.0), Some(20.0)),
+            (layers[0]["ip"].as_f64(), layers[0]["op"].as_f64())
+        );
+        assert_eq!(Some(0), layers[0]["ks"]["o"]["a"].as_i64());
+    }
+
+    #[test]
+    fn stroke_above_fill() {
+        assert_eq!(
+            vec!["shape", "stroke", "fill", "transform"],
+            item_types(PaintOrder::StrokeAboveFill)
+        );
+    }
+
+    #[test]
+    fn fill_above_stroke() {
+        assert_eq!(
+            vec!["shape", "fill", "stroke", "transform"],
+            item_types(PaintOrder::FillAboveStroke)
+        );
+    }
+
+    #[test]
+    fn gradient_fill() {
+        let mut group = stroked_group();
+        group.set_fill(None);
+        group.set_gradient(Some(ir::Gradient::Linear {
+            start: (0.0, 0.0).into(),
+            end: (10.0, 0.0).into(),
+            stops: vec![
+                ColorStop {
+                    offset: 0.0,
+                    color: (255, 0, 0),
+                },
+                ColorStop {
+                    offset: 1.0,
+                    color: (0, 0, 255),
+                },
+            ],
+        }));
+        let (group, _) = to_lottie_group(&group, 60.0, &LottieOptions::default()).unwrap();
+        let json = serde_json::to_value(&group).unwrap();
+        let items = json["it"].as_array().unwrap();
+        let fills: Vec<_> = items
+            .iter()
+            .filter(|item| item["ty"] == "gf" || item["ty"] == "fl")
+            .collect();
+        assert_eq!(1, fills.len(), "{json}");
+        let fill = fills[0];
+        assert_eq!(Some("gf"), fill["ty"].as_str(), "{fill}");
+        assert_eq!(Some(1), fill["t"].as_i64(), "{fill}");
+        assert_eq!(serde_json::json!([10.0, 0.0]), fill["e"]["k"], "{fill}");
+        assert_eq!(Some(2), fill["g"]["p"].as_i64(), "{fill}");
+        assert_eq!(
+            serde_json::json!([0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0]),
+            fill["g"]["k"]["k"],
+            "{fill}"
+        );
+    }
+
+    #[test]
+    fn no_stroke() {
+        let mut group = stroked_group();
+        group.set_stroke(None);
+        let items = to_lottie_group(&group, 60.0, &LottieOptions::default())
+            .unwrap()
+            .0
+            .items;
+        assert!(!items.iter().any(|i| matches!(i, AnyShape::Stroke(..))));
+    }
+
+    #[test]
+    fn many_shape_keyframes_keep_their_ease() {
+        let square = |size: f64| Rect::new(0.0, 0.0, size, size).to_path(0.1);
+        let mut shape = Keyframed::new(0.0, square(10.0));
+        shape.push(Keyframe::new(10.0, square(20.0)).with_ease(Ease::LINEAR));
+        shape.push(Keyframe::new(20.0, square(30.0)));
+
+        let subpaths = to_lottie_subpath(&shape, 60.0, None).unwrap();
+        assert_eq!(1, subpaths.len());
+        let Value::Animated(keyframes) = &subpaths[0].vertices.value else {
+            panic!("Should be animated");
+        };
+        let eases: Vec<_> = keyframes
+            .iter()
+            .map(|k| match k.bezier.as_ref().unwrap() {
+                BezierEase::_2D(b) => (b.out_value.x, b.in_value.x),
+                _ => panic!("Should be a 2d ease"),
+            })
+            .collect();
+        assert_eq!(vec![(0.4, 0.6), (0.0, 1.0), (0.4, 0.6)], eases);
+    }
+
+    #[test]
+    fn holds_survive_the_round_trip() {
+        let square = |size: f64| Rect::new(0.0, 0.0, size, size).to_path(0.1);
+        let mut shape = Keyframed::new(0.0, square(10.0));
+        shape.push(Keyframe::new(10.0, square(20.0)).held());
+        shape.push(Keyframe::new(20.0, square(30.0)));
+
+        let subpaths = to_lottie_subpath(&shape, 60.0, None).unwrap();
+        let Value::Animated(keyframes) = &subpaths[0].vertices.value else {
+            panic!("Should be animated");
+        };
+        assert_eq!(
+            vec![false, true, false],
+            keyframes.iter().map(|k| k.hold).collect::<Vec<_>>()
+        );
+        let read = from_lottie_subpath(&subpaths[0], 60.0).unwrap();
+        assert_eq!(
+            vec![false, true, false],
+            read.iter().map(|k| k.hold).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn incompatible_paths_say_where() {
+        let mut shape = Keyframed::new(0.0, Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1));
+        shape.push(Keyframe::new(
+            0.5,
+            Rect::new(0.0, 0.0, 20.0, 20.0).to_path(0.1),
+        ));
+        shape.push(Keyframe::new(
+            1.0,
+            Circle::new((10.0, 10.0), 10.0).to_path(0.1),
+        ));
+        let Err(LottieError::IncompatiblePaths {
+            from,
+            to,
+            expected,
+            actual,
+            mismatch,
+        }) = to_lottie_subpath(&shape, 60.0, None)
+        else {
+            panic!("Should be incompatible");
+        };
+        // Lottie counts in frames
+        assert_eq!((30.0, 60.0), (from, to));
+        assert_eq!(("MLLLZ", "MCCCCZ"), (expected.as_str(), actual.as_str()));
+        assert_eq!(
+            Mismatch::Command {
+                index: 1,
+                expected: 'L',
+                found: 'C'
+            },
+            mismatch
+        );
+    }
+
+    #[test]
+    fn precision_rounds_every_coordinate() {
+        let mut path = BezPath::new();
+        path.move_to((0.123456, 1.0 / 3.0));
+        path.quad_to((10.987654, 2.0 / 3.0), (20.0 / 7.0, 5.55555));
+        path.close_path();
+        let precision = Precision(2);
+
+        let subpaths =
+            to_lottie_subpath(&Keyframed::new(0.0, path), 60.0, Some(precision)).unwrap();
+        let Value::Fixed(shape) = &subpaths[0].vertices.value else {
+            panic!("Should be fixed");
+        };
+        assert_eq!(Point::new(0.12, 0.33), shape.vertices[0].into());
+        let coords: Vec<Point> = shape
+            .vertices
+            .iter()
+            .chain(&shape.in_point)
+            .chain(&shape.out_point)
+            .map(|c| (*c).into())
+            .collect();
+        assert!(
+            coords.iter().all(|p| precision.round_point(*p) == *p),
+            "{coords:?}"
+        );
+    }
+
+    /// A template with a placeholder rect at (10, 10) sized 20x20 and a fill
+    fn template() -> Lottie {
+        let mut rect = LottieRect::default();
+        rect.position.value = Value::Fixed(vec![20.0, 20.0]);
+        rect.size.value = Value::Fixed(vec![20.0, 20.0]);
+        let placeholder = Group {
+            items: vec![AnyShape::Rect(rect), AnyShape::Fill(Fill::default())],
+            ..Default::default()
+        };
+        Lottie {
+            frame_rate: 60.0,
+            layers: vec![AnyLayer::Shape(bodymovin::layers::Shape {
+                mixin: ShapeMixin {
+                    shapes: vec![AnyShape::Group(placeholder)],
+                    ..Default::default()
+                },
+                ..Default::default()
+            })],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn inject_replaces_placeholder_rect() {
+        let mut lottie = LottieOutput::from(template());
+        let animation = ir::Animation::placeholder(100.0, String::new());
+        inject_into_template(&mut lottie, &animation, &LottieOptions::default()).unwrap();
+
+        let placeholder = placeholders_mut(&mut lottie.lottie).unwrap().remove(0);
+        assert!(
+            matches!(
+                placeholder.items.as_slice(),
+                [AnyShape::Group(..), AnyShape::Fill(..)]
+            ),
+            "The rect should be replaced and the fill kept"
+        );
+        let AnyShape::Group(fitted) = &placeholder.items[0] else {
+            unreachable!();
+        };
+        let Some(AnyShape::Transform(transform)) = fitted.items.last() else {
+            panic!("The fitted group should end with a transform");
+        };
+        assert!(matches!(&transform.scale.value, Value::Fixed(s) if s == &vec![20.0, 20.0]));
+        assert!(matches!(&transform.position.value, Value::Fixed(p) if p == &vec![10.0, 10.0]));
+    }
+
+    #[test]
+    fn inject_requires_matching_frame_rate() {
+        let mut lottie = template();
+        lottie.frame_rate = 30.0;
+        let animation = ir::Animation::placeholder(100.0, String::new());
+        assert!(matches!(
+            inject_into_template(&mut lottie.into(), &animation, &LottieOptions::default()),
+            Err(LottieError::FrameRateMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn retimed_markers_match_frames() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.add_segment("intro", 0.0..0.5).unwrap();
+        animation.retime(30.0).unwrap();
+        let lottie = to_lottie(&animation, &LottieOptions::default()).unwrap();
+        assert_eq!(
+            (30.0, 30.0),
+            (lottie.lottie().frame_rate, lottie.lottie().out_point)
+        );
+        let json = with_markers(&lottie, animation.segments()).unwrap();
+        assert_eq!(15.0, json["markers"][0]["dr"].as_f64().unwrap());
+    }
+
+    #[test]
+    fn unnamed_groups_are_all_placeholders() {
+        let placeholders = find_placeholders(&template()).unwrap();
+        assert_eq!(1, placeholders.len());
+        assert_eq!(
+            vec![Rect::new(10.0, 10.0, 30.0, 30.0)],
+            placeholders[0].rects
+        );
+    }
+
+    #[test]
+    fn no_placeholders() {
+        let mut lottie = template();
+        lottie.layers.clear();
+        assert!(matches!(
+            find_placeholders(&lottie),
+            Err(LottieError::NoPlaceholders)
+        ));
+    }
+
+    #[test]
+    fn segments_become_markers() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.add_segment("outro", 0.5..1.0).unwrap();
+        animation.add_segment("intro", 0.0..0.25).unwrap();
+        let lottie = to_lottie(&animation, &LottieOptions::default()).unwrap();
+        let json = with_markers(&lottie, animation.segments()).unwrap();
+        assert_eq!(
+            serde_json::json!([
+                {"cm": "intro", "tm": 0.0, "dr": 15.0},
+                {"cm": "outro", "tm": 30.0, "dr": 30.0},
+            ]),
+            json["markers"]
+        );
+    }
+
+    #[test]
+    fn minify_shrinks_json() {
+        let animation = ir::Animation::placeholder(100.0, String::new());
+        let lottie = to_lottie(&animation, &LottieOptions::default()).unwrap();
+        let pretty = lottie_json(&lottie, &[], &LottieOptions::default()).unwrap();
+        let options = LottieOptions {
+            minify: Some(2),
+            ..Default::default()
+        };
+        let minified = lottie_json(&lottie, &[], &options).unwrap();
+        assert!(
+            minified.len() * 3 < pretty.len() * 2,
+            "{} bytes minified, {} pretty",
+            minified.len(),
+            pretty.len()
+        );
+        let reread: serde_json::Value = serde_json::from_str(&minified).unwrap();
+        assert_eq!(serde_json::json!(60), reread["op"]);
+    }
+
+    #[test]
+    fn minify_rounds() {
+        let mut json = serde_json::json!({"a": 1.23456, "b": [2.0, -0.004], "nm": "", "hd": false});
+        super::minify(&mut json, 2);
+        assert_eq!(serde_json::json!({"a": 1.23, "b": [2, 0]}), json);
+    }
+
+    #[test]
+    fn segments_must_be_within_the_animation() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        assert!(animation.add_segment("too long", 0.0..1.5).is_err());
+        assert!(animation.add_segment("empty", 0.5..0.5).is_err());
+        assert!(animation.segments().is_empty());
+    }
+
+    fn summarize<T: Clone>(keyframed: &Keyframed<T>) -> Vec<(f64, T, Ease)> {
+        keyframed
+            .iter()
+            .map(|k| (k.time, k.value.clone(), k.ease.unwrap_or_default()))
+            .collect()
+    }
+
+    #[test]
+    fn read_back_what_we_wrote() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.root.scale = ir::pulse(0.0, 1.0, 0).unwrap();
+        animation.root.set_fill(Some((255, 0, 0)));
+        let lottie = to_lottie(&animation, &LottieOptions::default()).unwrap();
+
+        let read = ir::Animation::try_from(&lottie).unwrap();
+        assert_eq!(
+            (100.0, 100.0, 60.0, 60.0),
+            (read.width, read.height, read.frames(), read.frame_rate)
+        );
+        assert_eq!(animation.root.center, read.root.center);
+        assert_eq!(Some((255, 0, 0)), read.root.fill);
+        assert_eq!(
+            summarize(&animation.root.scale),
+            summarize(&read.root.scale)
+        );
+
+        // Each subpath comes back as its own shape
+        let Element::Shape(original) = &animation.root.children[0] else {
+            panic!("Placeholder should be a shape");
+        };
+        let read_path: Vec<_> = read
+            .root
+            .children
+            .iter()
+            .flat_map(|e| match e {
+                Element::Shape(s) => s.earliest().value.elements().to_vec(),
+                Element::Group(..) => panic!("Should only have shapes"),
+            })
+            .collect();
+        assert_eq!(original.earliest().value.elements(), read_path.as_slice());
+    }
+
+    #[test]
+    fn skew_round_trips() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.root.set_skew(
+            vec![(0.0, (0.0, 30.0)), (0.5, (20.0, 30.0))]
+                .try_into()
+                .unwrap(),
+        );
+        let lottie = to_lottie(&animation, &LottieOptions::default()).unwrap();
+
+        let json = serde_json::to_value(&lottie).unwrap();
+        let transform = json["layers"][0]["shapes"][0]["it"]
+            .as_array()
+            .unwrap()
+            .last()
+            .unwrap();
+        assert_eq!(Some(1), transform["sk"]["a"].as_i64(), "{transform}");
+        assert_eq!(Some(30.0), transform["sa"]["k"].as_f64(), "{transform}");
+
+        let read = ir::Animation::try_from(&lottie).unwrap();
+        assert_eq!(
+            vec![(0.0, (0.0, 30.0)), (0.5, (20.0, 30.0))],
+            read.root
+                .skew()
+                .iter()
+                .map(|k| (k.time, k.value))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn skewed_part_skews_its_layer() {
+        let mut part = stroked_group();
+        part.set_skew(Keyframed::new(0.0, (15.0, 0.0)));
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        animation.root.children = vec![Element::Group(part)];
+        let layers = part_layers_json(&animation);
+        assert_eq!(1, layers.len(), "{layers:?}");
+        assert_eq!(
+            Some(15.0),
+            layers[0]["ks"]["sk"]["k"].as_f64(),
+            "{layers:?}"
+        );
+        assert_eq!(Some(0.0), layers[0]["ks"]["sa"]["k"].as_f64(), "{layers:?}");
+    }
+
+    #[test]
+    fn root_clip_masks_layer() {
+        let mut animation = ir::Animation::placeholder(100.0, String::new());
+        let wipe = |width: f64| Rect::new(0.0, 0.0, width, 100.0).to_path(0.1);
+        animation.root.set_clip(Some(
+            vec![(0.0, wipe(0.0)), (0.5, wipe(100.0))]
+                .try_into()
+                .unwrap(),
+        ));
+        let json = serde_json::to_value(to_lottie(&animation, &LottieOptions::default()).unwrap())
+            .unwrap();
+        let layer = &json["layers"][0];
+        assert_eq!(Some(true), layer["hasMask"].as_bool(), "{layer}");
+        let masks = layer["masksProperties"].as_array().unwrap();
+        assert_eq!(1, masks.len(), "{layer}");
+        assert_eq!(Some("a"), masks[0]["mode"].as_str());
+        assert_eq!(Some(1), masks[0]["pt"]["a"].as_i64(), "{layer}");
+    }
+
+    #[test]
+    fn read_back_animated_shape() {
+        let shape: Keyframed<_> = vec![
+            (0.0, Circle::new((50.0, 50.0), 10.0).to_path(0.1)),
+            (0.5, Circle::new((50.0, 50.0), 40.0).to_path(0.1)),
+        ]
+        .try_into()
+        .unwrap();
+        let subpaths = to_lottie_subpath(&shape, 60.0, None).unwrap();
+        assert_eq!(1, subpaths.len());
+
+        // Control points are stored relative so may not come back bit-for-bit
+        let read = from_lottie_subpath(&subpaths[0], 60.0).unwrap();
+        assert_eq!(shape.len(), read.len());
+        for (expected, actual) in shape.iter().zip(read.iter()) {
+            assert_eq!(expected.time, actual.time);
+            assert_eq!(path_commands(&expected.value), path_commands(&actual.value));
+            assert!((expected.value.area() - actual.value.area()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn refuse_unsupported_shapes() {
+        assert!(matches!(
+            ir::Animation::try_from(&LottieOutput::from(template())),
+            Err(LottieError::Unsupported(..))
+        ));
+    }
+}
@@ -0,0 +1,268 @@
+//! Spring motion expressed as keyframes with cubic eases
+//!
+//! Players such as Lottie ease each keyframe segment independently so when a spring is
+//! approximated by several cubics the velocity at each join has to be made to agree, otherwise
+//! the motion visibly stutters as it crosses from one keyframe to the next.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use kurbo::{CubicBez, Point};
+
+use crate::{
+    error::CubicApproximationError,
+    spring::{AnimatedValue, AnimatedValueType, Spring},
+    spring2cubic::cubic_approximation,
+};
+
+/// The name [`crate::observe::GenerationObserver::cache_lookup`] is given for [`cached_motion`]
+pub(crate) const SPRING_CACHE: &str = "spring";
+
+/// More distinct motions than this and the cache starts over
+const SPRING_CACHE_CAPACITY: usize = 256;
+
+/// A value moving under the influence of a spring, as keyframes
+#[derive(Debug, Clone)]
+pub struct Motion {
+    pub keyframes: Vec<MotionKeyframe>,
+}
+
+/// A keyframe of a [`Motion`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotionKeyframe {
+    /// Frame relative to the start of the motion
+    pub frame: f64,
+    pub value: f64,
+    /// The ease to the next keyframe as the (out, in) control points of a cubic from (0,0) to (1,1),
+    /// the same normalization used by CSS cubic-bezier and Lottie. None for the final keyframe.
+    pub ease: Option<(Point, Point)>,
+}
+
+impl Motion {
+    pub fn new(
+        frame_rate: f64,
+        animation: AnimatedValue,
+        spring: Spring,
+    ) -> Result<Self, CubicApproximationError> {
+        let cubics = chained_cubics(frame_rate, animation, spring)?;
+        let mut keyframes: Vec<_> = cubics
+            .iter()
+            .map(|c| MotionKeyframe {
+                frame: c.p0.x,
+                value: c.p0.y,
+                ease: Some(normalized_ease(c)),
+            })
+            .collect();
+        let last = cubics.last().unwrap().p3; // cubic_approximation never returns empty
+        keyframes.push(MotionKeyframe {
+            frame: last.x,
+            value: last.y,
+            ease: None,
+        });
+        Ok(Motion { keyframes })
+    }
+}
+
+/// Motions already approximated on this thread and, within [`with_spring_lookups`], whether
+/// each lookup hit
+///
+/// Approximating a spring is by far the most expensive part of animating an icon and every part
+/// of an icon, and every icon of a batch, tends to move the same way.
+#[derive(Default)]
+struct SpringCache {
+    motions: HashMap<Vec<u64>, Motion>,
+    lookups: Option<Vec<bool>>,
+}
+
+thread_local! {
+    static SPRING_MOTIONS: RefCell<SpringCache> = RefCell::default();
+}
+
+/// As [`Motion::new`], reusing the result of an identical earlier call on this thread
+///
+/// Failures aren't kept, they are as slow to repeat as to compute.
+pub(crate) fn cached_motion(
+    frame_rate: f64,
+    animation: AnimatedValue,
+    spring: Spring,
+) -> Result<Motion, CubicApproximationError> {
+    let key = cache_key(frame_rate, animation, spring);
+    let cached = SPRING_MOTIONS.with_borrow_mut(|cache| {
+        let cached = cache.motions.get(&key).cloned();
+        if let Some(lookups) = cache.lookups.as_mut() {
+            lookups.push(cached.is_some());
+        }
+        cached
+    });
+    if let Some(motion) = cached {
+        return Ok(motion);
+    }
+    let motion = Motion::new(frame_rate, animation, spring)?;
+    SPRING_MOTIONS.with_borrow_mut(|cache| {
+        if cache.motions.len() >= SPRING_CACHE_CAPACITY {
+            cache.motions.clear();
+        }
+        cache.motions.insert(key, motion.clone());
+    });
+    Ok(motion)
+}
+
+/// Run `f` noting whether each [`cached_motion`] it makes hits, in order
+pub(crate) fn with_spring_lookups<T>(f: impl FnOnce() -> T) -> (T, Vec<bool>) {
+    let outer = SPRING_MOTIONS.with_borrow_mut(|cache| cache.lookups.replace(Vec::new()));
+    let result = f();
+    let lookups = SPRING_MOTIONS
+        .with_borrow_mut(|cache| std::mem::replace(&mut cache.lookups, outer).unwrap_or_default());
+    (result, lookups)
+}
+
+/// Everything that determines a [`Motion`], exactly, floats by their bits
+fn cache_key(frame_rate: f64, animation: AnimatedValue, spring: Spring) -> Vec<u64> {
+    let mut key = vec![
+        frame_rate.to_bits(),
+        animation.value.to_bits(),
+        animation.velocity.to_bits(),
+        animation.final_value.to_bits(),
+        animation.time.to_bits(),
+    ];
+    key.extend(match animation.value_type {
+        AnimatedValueType::Rotation => [0, 0],
+        AnimatedValueType::Scale => [1, 0],
+        AnimatedValueType::Position => [2, 0],
+        AnimatedValueType::Custom { value_threshold } => [3, value_threshold.to_bits()],
+    });
+    key.extend(match spring {
+        Spring::Overdamped {
+            gamma_plus,
+            gamma_minus,
+        } => [0, gamma_plus.to_bits(), gamma_minus.to_bits(), 0],
+        Spring::CriticallyDamped { natural_freq } => [1, natural_freq.to_bits(), 0, 0],
+        Spring::Underdamped {
+            damping,
+            natural_freq,
+            damped_freq,
+        } => [
+            2,
+            damping.to_bits(),
+            natural_freq.to_bits(),
+            damped_freq.to_bits(),
+        ],
+    });
+    key
+}
+
+/// Cubics approximating the spring with tangents at each join matching the spring velocity.
+///
+/// The start of the first cubic matches the initial velocity, if any, and the end of the last is
+/// left alone; the approximation is free to cheat there.
+fn chained_cubics(
+    frame_rate: f64,
+    animation: AnimatedValue,
+    spring: Spring,
+) -> Result<Vec<CubicBez>, CubicApproximationError> {
+    let mut cubics = cubic_approximation(frame_rate, animation, spring)?;
+    if animation.velocity != 0.0 {
+        set_outgoing_slope(&mut cubics[0], animation.velocity / frame_rate);
+    }
+    for i in 1..cubics.len() {
+        // x is frames, the spring works in seconds
+        let join = cubics[i].p0;
+        let state = spring.update(animation.time + join.x / frame_rate, animation);
+        let slope = state.velocity / frame_rate;
+        set_incoming_slope(&mut cubics[i - 1], slope);
+        set_outgoing_slope(&mut cubics[i], slope);
+    }
+    Ok(cubics)
+}
+
+/// Rotate the handle into p3 to match slope, preserving the horizontal extent of the handle
+fn set_incoming_slope(cubic: &mut CubicBez, slope: f64) {
+    let mut dx = cubic.p3.x - cubic.p2.x;
+    if dx <= 0.0 {
+        dx = (cubic.p3.x - cubic.p0.x) / 3.0;
+    }
+    cubic.p2 = (cubic.p3.x - dx, cubic.p3.y - slope * dx).into();
+}
+
+/// Rotate the handle out of p0 to match slope, preserving the horizontal extent of the handle
+fn set_outgoing_slope(cubic: &mut CubicBez, slope: f64) {
+    let mut dx = cubic.p1.x - cubic.p0.x;
+    if dx <= 0.0 {
+        dx = (cubic.p3.x - cubic.p0.x) / 3.0;
+    }
+    cubic.p1 = (cubic.p0.x + dx, cubic.p0.y + slope * dx).into();
+}
+
+fn normalized_ease(cubic: &CubicBez) -> (Point, Point) {
+    let dx = cubic.p3.x - cubic.p0.x;
+    let dy = cubic.p3.y - cubic.p0.y;
+    let normalize = |p: Point| -> Point {
+        let x = (p.x - cubic.p0.x) / dx;
+        // A segment that doesn't change value has no meaningful y scale, hold the shape of the curve
+        let y = if dy.abs() > f64::EPSILON {
+            (p.y - cubic.p0.y) / dy
+        } else {
+            x
+        };
+        (x, y).into()
+    };
+    (normalize(cubic.p1), normalize(cubic.p2))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spring::{AnimatedValue, AnimatedValueType, Spring};
+
+    use super::{cached_motion, chained_cubics, with_spring_lookups, Motion};
+
+    #[test]
+    fn velocity_continuous_at_joins() {
+        let frame_rate = 60.0;
+        let spring = Spring::expressive_spatial();
+        let animation = AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale);
+        let cubics = chained_cubics(frame_rate, animation, spring).unwrap();
+        assert!(cubics.len() > 1, "Need a join to test");
+
+        for pair in cubics.windows(2) {
+            let (before, after) = (pair[0], pair[1]);
+            let incoming = (before.p3.y - before.p2.y) / (before.p3.x - before.p2.x);
+            let outgoing = (after.p1.y - after.p0.y) / (after.p1.x - after.p0.x);
+            assert!(
+                (incoming - outgoing).abs() < 1e-9,
+                "{incoming} != {outgoing}\n{cubics:#?}"
+            );
+
+            let state = spring.update(after.p0.x / frame_rate, animation);
+            assert!((outgoing - state.velocity / frame_rate).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn keyframes_span_motion() {
+        let motion = Motion::new(
+            60.0,
+            AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale),
+            Spring::standard(),
+        )
+        .unwrap();
+        let first = motion.keyframes.first().unwrap();
+        let last = motion.keyframes.last().unwrap();
+        assert_eq!((0.0, 0.0), (first.frame, first.value));
+        assert!((last.value - 100.0).abs() < 1e-9);
+        assert!(first.ease.is_some());
+        assert!(last.ease.is_none());
+    }
+
+    #[test]
+    fn cached_motion_reports_lookups() {
+        // A start no other test uses so the first lookup misses
+        let animation = AnimatedValue::new(12.34, 56.78, AnimatedValueType::Position);
+        let ((first, second), lookups) = with_spring_lookups(|| {
+            let first = cached_motion(60.0, animation, Spring::standard()).unwrap();
+            let second = cached_motion(60.0, animation, Spring::standard()).unwrap();
+            cached_motion(30.0, animation, Spring::standard()).unwrap();
+            (first, second)
+        });
+        assert_eq!(first.keyframes, second.keyframes);
+        assert_eq!(vec![false, true, false], lookups);
+    }
+}
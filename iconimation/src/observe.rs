@@ -0,0 +1,134 @@
+//! Hooks so a host can collect metrics about generation without us depending on a telemetry stack
+
+use crate::ir::{Animation, Group, IrVisitor, KeyframesMut};
+
+/// Receives events as an animation is generated, see [`Animation::of_command_observed`]
+///
+/// Every hook defaults to doing nothing. There is deliberately no clock here, `std::time::Instant`
+/// isn't available everywhere we run (wasm), so observers that want timing read their own clock
+/// when a phase starts and finishes.
+pub trait GenerationObserver {
+    /// A phase, such as "parse", "animate", or the name of a [`crate::pass::Pass`], is starting
+    fn phase_started(&mut self, _phase: &str) {}
+
+    fn phase_finished(&mut self, _phase: &str) {}
+
+    /// The size of something generated, e.g. the number of "keyframes"
+    fn size(&mut self, _what: &str, _size: usize) {}
+
+    /// See [`Animation::warnings`]
+    fn warning(&mut self, _warning: &str) {}
+
+    /// A lookup in a cache, named by `cache`, did or didn't find what it wanted.
+    /// Hit rate is hits / lookups.
+    ///
+    /// The caches are "spring", approximations of spring motion, and "batch", repeated commands
+    /// in [`crate::batch::animate_many_observed`].
+    fn cache_lookup(&mut self, _cache: &str, _hit: bool) {}
+}
+
+/// Observes nothing
+impl GenerationObserver for () {}
+
+/// Bracket `f` with [`GenerationObserver::phase_started`] and [`GenerationObserver::phase_finished`]
+pub(crate) fn observe_phase<T>(
+    observer: &mut dyn GenerationObserver,
+    phase: &str,
+    f: impl FnOnce() -> T,
+) -> T {
+    log::debug!("{phase} started");
+    observer.phase_started(phase);
+    let result = f();
+    observer.phase_finished(phase);
+    log::debug!("{phase} finished");
+    result
+}
+
+/// Report the size and warnings of a completed animation
+#[cfg(feature = "command")]
+pub(crate) fn observe_result(observer: &mut dyn GenerationObserver, animation: &mut Animation) {
+    let mut counter = KeyframeCounter::default();
+    animation.visit_mut(&mut counter);
+    observer.size("groups", counter.groups);
+    observer.size("keyframes", counter.keyframes);
+    for warning in animation.warnings() {
+        observer.warning(warning);
+    }
+}
+
+/// Events kept to be replayed, in order, to another observer, e.g. from work done on another thread
+#[cfg(feature = "command")]
+#[derive(Debug, Default)]
+pub(crate) struct Recording(Vec<Event>);
+
+#[cfg(feature = "command")]
+#[derive(Debug)]
+enum Event {
+    PhaseStarted(String),
+    PhaseFinished(String),
+    Size(String, usize),
+    Warning(String),
+    CacheLookup(String, bool),
+}
+
+#[cfg(feature = "command")]
+impl Recording {
+    pub(crate) fn replay(self, observer: &mut dyn GenerationObserver) {
+        for event in self.0 {
+            match event {
+                Event::PhaseStarted(phase) => observer.phase_started(&phase),
+                Event::PhaseFinished(phase) => observer.phase_finished(&phase),
+                Event::Size(what, size) => observer.size(&what, size),
+                Event::Warning(warning) => observer.warning(&warning),
+                Event::CacheLookup(cache, hit) => observer.cache_lookup(&cache, hit),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "command")]
+impl GenerationObserver for Recording {
+    fn phase_started(&mut self, phase: &str) {
+        self.0.push(Event::PhaseStarted(phase.to_string()));
+    }
+
+    fn phase_finished(&mut self, phase: &str) {
+        self.0.push(Event::PhaseFinished(phase.to_string()));
+    }
+
+    fn size(&mut self, what: &str, size: usize) {
+        self.0.push(Event::Size(what.to_string(), size));
+    }
+
+    fn warning(&mut self, warning: &str) {
+        self.0.push(Event::Warning(warning.to_string()));
+    }
+
+    fn cache_lookup(&mut self, cache: &str, hit: bool) {
+        self.0.push(Event::CacheLookup(cache.to_string(), hit));
+    }
+}
+
+#[derive(Default)]
+struct KeyframeCounter {
+    groups: usize,
+    keyframes: usize,
+}
+
+impl IrVisitor for KeyframeCounter {
+    fn visit_group(&mut self, _group: &mut Group) {
+        self.groups += 1;
+    }
+
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        self.keyframes += match keyframes {
+            KeyframesMut::Translate(k) => k.len(),
+            KeyframesMut::Scale(k) => k.len(),
+            KeyframesMut::Rotate(k) => k.len(),
+            KeyframesMut::Skew(k) => k.len(),
+            KeyframesMut::Opacity(k) => k.len(),
+            KeyframesMut::Shape(k) => k.len(),
+            KeyframesMut::Zoom(k) => k.len(),
+        };
+    }
+}
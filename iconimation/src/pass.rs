@@ -0,0 +1,1065 @@
+//! An ordered, configurable set of passes run over an [`Animation`] once it is built
+//!
+//! Think of it as a tiny asset compiler: the default pipeline does cleanup that is almost always
+//! desirable, users can disable passes by name or insert their own anywhere in the order.
+
+use std::{fmt::Debug, time::Duration};
+
+use kurbo::{BezPath, Line, ParamCurveNearest, PathEl, Point, Rect, Vec2};
+
+use crate::{
+    bezop::to_cubics,
+    error::PassError,
+    ir::{Animation, Group, IrVisitor, Keyframe, Keyframed, KeyframesMut},
+    observe::{observe_phase, GenerationObserver},
+    path_commands,
+};
+
+/// Something that transforms an [`Animation`], typically implemented with an [`IrVisitor`]
+///
+/// Passes may run on several threads at once, see [`crate::batch::animate_many`].
+pub trait Pass: Send + Sync {
+    /// Identifies the pass so it can be enabled, disabled, or used as an insertion point
+    fn name(&self) -> &str;
+
+    fn run(&self, animation: &mut Animation);
+}
+
+struct Entry {
+    pass: Box<dyn Pass>,
+    enabled: bool,
+}
+
+/// Passes in the order they will be run
+pub struct Pipeline {
+    entries: Vec<Entry>,
+}
+
+impl Debug for Pipeline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(
+                self.entries
+                    .iter()
+                    .map(|e| (e.pass.name(), if e.enabled { "on" } else { "off" })),
+            )
+            .finish()
+    }
+}
+
+impl Default for Pipeline {
+    /// Cubics and dedupe are on, snap, loop, simplify, simplify-paths, and motion-blur are
+    /// available but off
+    fn default() -> Self {
+        let mut pipeline = Self::empty();
+        pipeline.push(Cubics);
+        pipeline.push(Dedupe);
+        pipeline.push(Snap);
+        pipeline.disable(Snap.name()).unwrap();
+        pipeline.push(Loop::default());
+        pipeline.disable(Loop::default().name()).unwrap();
+        pipeline.push(Simplify::default());
+        pipeline.disable(Simplify::default().name()).unwrap();
+        pipeline.push(SimplifyPaths::default());
+        pipeline.disable(SimplifyPaths::default().name()).unwrap();
+        pipeline.push(MotionBlur::default());
+        pipeline.disable(MotionBlur::default().name()).unwrap();
+        pipeline
+    }
+}
+
+impl Pipeline {
+    /// A pipeline that does nothing
+    pub fn empty() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Names of the passes that will run, in order
+    pub fn enabled(&self) -> impl Iterator<Item = &str> {
+        self.entries
+            .iter()
+            .filter(|e| e.enabled)
+            .map(|e| e.pass.name())
+    }
+
+    fn position(&self, name: &str) -> Result<usize, PassError> {
+        self.entries
+            .iter()
+            .position(|e| e.pass.name() == name)
+            .ok_or_else(|| PassError::NoSuchPass(name.to_string()))
+    }
+
+    /// Add a pass to the end of the pipeline
+    pub fn push(&mut self, pass: impl Pass + 'static) -> &mut Self {
+        self.entries.push(Entry {
+            pass: Box::new(pass),
+            enabled: true,
+        });
+        self
+    }
+
+    pub fn insert_before(
+        &mut self,
+        name: &str,
+        pass: impl Pass + 'static,
+    ) -> Result<(), PassError> {
+        let pos = self.position(name)?;
+        self.entries.insert(
+            pos,
+            Entry {
+                pass: Box::new(pass),
+                enabled: true,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn insert_after(&mut self, name: &str, pass: impl Pass + 'static) -> Result<(), PassError> {
+        let pos = self.position(name)?;
+        self.entries.insert(
+            pos + 1,
+            Entry {
+                pass: Box::new(pass),
+                enabled: true,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn enable(&mut self, name: &str) -> Result<(), PassError> {
+        let pos = self.position(name)?;
+        self.entries[pos].enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self, name: &str) -> Result<(), PassError> {
+        let pos = self.position(name)?;
+        self.entries[pos].enabled = false;
+        Ok(())
+    }
+
+    pub fn run(&self, animation: &mut Animation) {
+        self.run_observed(animation, &mut ());
+    }
+
+    /// As [`Pipeline::run`], reporting each pass as a phase to `observer`
+    pub fn run_observed(&self, animation: &mut Animation, observer: &mut dyn GenerationObserver) {
+        for entry in self.entries.iter().filter(|e| e.enabled) {
+            observe_phase(observer, entry.pass.name(), || entry.pass.run(animation));
+        }
+    }
+}
+
+/// Draws a shape only with cubics if its keyframes otherwise differ in commands, e.g. because
+/// one master of a font draws quadratics and another cubics
+///
+/// Shapes whose keyframes already agree are left alone so lines stay lines.
+pub struct Cubics;
+
+impl IrVisitor for Cubics {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        let KeyframesMut::Shape(k) = keyframes else {
+            return;
+        };
+        let commands = path_commands(&k.earliest().value);
+        if k.iter().all(|k| path_commands(&k.value) == commands) {
+            return;
+        }
+        for k in k.iter_mut() {
+            k.value = to_cubics(&k.value);
+        }
+    }
+}
+
+impl Pass for Cubics {
+    fn name(&self) -> &str {
+        "cubics"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        animation.visit_mut(&mut Cubics);
+    }
+}
+
+/// Drops keyframes that don't change anything
+pub struct Dedupe;
+
+impl IrVisitor for Dedupe {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        match keyframes {
+            KeyframesMut::Translate(k) => k.dedup(),
+            KeyframesMut::Scale(k) => k.dedup(),
+            KeyframesMut::Rotate(k) => k.dedup(),
+            KeyframesMut::Skew(k) => k.dedup(),
+            KeyframesMut::Opacity(k) => k.dedup(),
+            KeyframesMut::Shape(k) => k.dedup(),
+            KeyframesMut::Zoom(k) => k.dedup(),
+        }
+    }
+}
+
+impl Pass for Dedupe {
+    fn name(&self) -> &str {
+        "dedupe"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        animation.visit_mut(&mut Dedupe);
+    }
+}
+
+/// Drops keyframes that barely change the motion, e.g. from spring approximation or
+/// [`Animation::blend`], to shrink output for the web
+///
+/// Runs after dedupe to catch what it can't: keyframes that are nearly, rather than exactly,
+/// on the way from their neighbours.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Simplify {
+    /// How far, in the units of the property, motion may stray from the original at any frame.
+    /// Degrees for rotation, percent for scale, zoom and opacity, output units for translation and
+    /// shape.
+    pub tolerance: f64,
+}
+
+impl Default for Simplify {
+    fn default() -> Self {
+        Simplify { tolerance: 0.1 }
+    }
+}
+
+impl Pass for Simplify {
+    fn name(&self) -> &str {
+        "simplify"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        animation.visit_mut(&mut SimplifyVisitor {
+            tolerance: self.tolerance,
+            frame_rate: animation.frame_rate(),
+        });
+    }
+}
+
+struct SimplifyVisitor {
+    tolerance: f64,
+    /// Motion is checked at every frame
+    frame_rate: f64,
+}
+
+impl IrVisitor for SimplifyVisitor {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        let (frame_rate, tolerance) = (self.frame_rate, self.tolerance);
+        match keyframes {
+            KeyframesMut::Translate(k) => {
+                k.simplify(frame_rate, tolerance, |a, b| (*a - *b).hypot())
+            }
+            KeyframesMut::Scale(k) => k.simplify(frame_rate, tolerance, |a, b| {
+                (a.0 - b.0).abs().max((a.1 - b.1).abs())
+            }),
+            KeyframesMut::Rotate(k) => k.simplify(frame_rate, tolerance, |a, b| (a - b).abs()),
+            KeyframesMut::Skew(k) => k.simplify(frame_rate, tolerance, |a, b| {
+                (a.0 - b.0).abs().max((a.1 - b.1).abs())
+            }),
+            KeyframesMut::Opacity(k) => k.simplify(frame_rate, tolerance, |a, b| (a - b).abs()),
+            KeyframesMut::Shape(k) => k.simplify(frame_rate, tolerance, path_distance),
+            KeyframesMut::Zoom(k) => k.simplify(frame_rate, tolerance, |a, b| (a - b).abs()),
+        }
+    }
+}
+
+fn points(el: &PathEl) -> Vec<Point> {
+    match *el {
+        PathEl::MoveTo(p) | PathEl::LineTo(p) => vec![p],
+        PathEl::QuadTo(p0, p1) => vec![p0, p1],
+        PathEl::CurveTo(p0, p1, p2) => vec![p0, p1, p2],
+        PathEl::ClosePath => vec![],
+    }
+}
+
+/// The furthest any point of `a` is from its counterpart in `b`, infinite if they differ in structure
+fn path_distance(a: &BezPath, b: &BezPath) -> f64 {
+    if a.elements().len() != b.elements().len() {
+        return f64::INFINITY;
+    }
+    let mut furthest: f64 = 0.0;
+    for (a, b) in a.elements().iter().zip(b.elements()) {
+        if std::mem::discriminant(a) != std::mem::discriminant(b) {
+            return f64::INFINITY;
+        }
+        for (a, b) in points(a).into_iter().zip(points(b)) {
+            furthest = furthest.max(a.distance(b));
+        }
+    }
+    furthest
+}
+
+/// Removes path segments that don't change the drawing, to shrink output
+///
+/// A line that continues the line before it is merged into it and segments that never stray
+/// further than [`SimplifyPaths::tolerance`] from where they start are dropped. A segment is only
+/// removed if it can be removed at every keyframe of its shape so shapes remain interpolation
+/// compatible.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SimplifyPaths {
+    /// How far, in output units, the outline may move
+    pub tolerance: f64,
+}
+
+impl Default for SimplifyPaths {
+    fn default() -> Self {
+        SimplifyPaths { tolerance: 0.01 }
+    }
+}
+
+impl IrVisitor for SimplifyPaths {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        if let KeyframesMut::Shape(k) = keyframes {
+            simplify_paths(k, self.tolerance);
+        }
+    }
+}
+
+impl Pass for SimplifyPaths {
+    fn name(&self) -> &str {
+        "simplify-paths"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        animation.visit_mut(&mut SimplifyPaths {
+            tolerance: self.tolerance,
+        });
+    }
+}
+
+fn simplify_paths(keyframed: &mut Keyframed<BezPath>, tolerance: f64) {
+    let paths: Vec<_> = keyframed.iter().map(|k| k.value.elements()).collect();
+    let commands = path_commands(&keyframed.earliest().value);
+    if keyframed
+        .iter()
+        .any(|k| path_commands(&k.value) != commands)
+    {
+        // Already incompatible, leave it for emission to report
+        return;
+    }
+
+    // Where each keyframe's path is, and where its subpath started, given what we've removed so far
+    let mut current = vec![Point::ZERO; paths.len()];
+    let mut start = vec![Point::ZERO; paths.len()];
+    let mut keep = Vec::with_capacity(commands.len());
+    for i in 0..commands.len() {
+        let removable = paths.iter().enumerate().all(|(k, els)| {
+            let from = current[k];
+            match els[i] {
+                PathEl::MoveTo(..) | PathEl::ClosePath => false,
+                PathEl::LineTo(p) => {
+                    let next = match els.get(i + 1) {
+                        Some(PathEl::LineTo(next)) => Some(*next),
+                        Some(PathEl::ClosePath) => Some(start[k]),
+                        _ => None,
+                    };
+                    p.distance(from) <= tolerance
+                        || next.is_some_and(|next| {
+                            Line::new(from, next).nearest(p, 1e-9).distance_sq
+                                <= tolerance * tolerance
+                        })
+                }
+                el => points(&el).iter().all(|p| p.distance(from) <= tolerance),
+            }
+        });
+        keep.push(!removable);
+        for (k, els) in paths.iter().enumerate() {
+            match els[i] {
+                PathEl::MoveTo(p) => (start[k], current[k]) = (p, p),
+                PathEl::ClosePath => current[k] = start[k],
+                el if !removable => current[k] = el.end_point().unwrap(),
+                _ => (),
+            }
+        }
+    }
+
+    if keep.iter().all(|k| *k) {
+        return;
+    }
+    for k in keyframed.iter_mut() {
+        k.value = BezPath::from_vec(
+            k.value
+                .elements()
+                .iter()
+                .zip(keep.iter())
+                .filter_map(|(el, keep)| keep.then_some(*el))
+                .collect(),
+        );
+    }
+}
+
+/// Moves keyframes onto whole frames, at the animation's frame rate, useful for players that
+/// dislike fractional times
+pub struct Snap;
+
+impl Pass for Snap {
+    fn name(&self) -> &str {
+        "snap"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        // Delays of shared motion may be fractional too
+        animation.resolve_shared();
+        animation.visit_mut(&mut SnapVisitor(animation.frame_rate()));
+    }
+}
+
+/// Snaps to the frames of a frame rate
+struct SnapVisitor(f64);
+
+impl IrVisitor for SnapVisitor {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        match keyframes {
+            KeyframesMut::Translate(k) => k.snap_to_frames(self.0),
+            KeyframesMut::Scale(k) => k.snap_to_frames(self.0),
+            KeyframesMut::Rotate(k) => k.snap_to_frames(self.0),
+            KeyframesMut::Skew(k) => k.snap_to_frames(self.0),
+            KeyframesMut::Opacity(k) => k.snap_to_frames(self.0),
+            KeyframesMut::Shape(k) => k.snap_to_frames(self.0),
+            KeyframesMut::Zoom(k) => k.snap_to_frames(self.0),
+        }
+    }
+}
+
+/// Makes an animation loop seamlessly, every animated property ends where it started
+///
+/// A property that doesn't, typically a spring that settles somewhere new, is returned to where it
+/// started over [`Loop::return_duration`] and a warning added. Rotation by whole turns already
+/// loops. The animation is cut to end with its last keyframe so it doesn't pause before repeating.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Loop {
+    pub return_duration: Duration,
+}
+
+impl Default for Loop {
+    /// Return over 200ms
+    fn default() -> Self {
+        Loop {
+            return_duration: Duration::from_millis(200),
+        }
+    }
+}
+
+impl Pass for Loop {
+    fn name(&self) -> &str {
+        "loop"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        // Each group may need its own return
+        animation.resolve_shared();
+        let mut last = LastTime(None);
+        animation.visit_mut(&mut last);
+        let Some(end) = last.0 else {
+            return;
+        };
+        let return_time = self.return_duration.as_secs_f64();
+        let mut visitor = LoopVisitor {
+            end,
+            return_time,
+            warnings: Vec::new(),
+        };
+        animation.visit_mut(&mut visitor);
+        animation.duration = if visitor.warnings.is_empty() {
+            end
+        } else {
+            end + return_time
+        };
+        animation.warnings.extend(visitor.warnings);
+    }
+}
+
+/// When the last keyframe of anything animated is, in seconds
+struct LastTime(Option<f64>);
+
+impl IrVisitor for LastTime {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        let last = match keyframes {
+            KeyframesMut::Translate(k) => last_animated_time(k),
+            KeyframesMut::Scale(k) => last_animated_time(k),
+            KeyframesMut::Rotate(k) => last_animated_time(k),
+            KeyframesMut::Skew(k) => last_animated_time(k),
+            KeyframesMut::Opacity(k) => last_animated_time(k),
+            KeyframesMut::Shape(k) => last_animated_time(k),
+            KeyframesMut::Zoom(k) => last_animated_time(k),
+        };
+        self.0 = match (self.0, last) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+    }
+}
+
+fn last_animated_time<T>(keyframed: &Keyframed<T>) -> Option<f64> {
+    keyframed
+        .is_animated()
+        .then(|| keyframed.iter().map(|k| k.time).fold(f64::MIN, f64::max))
+}
+
+struct LoopVisitor {
+    /// Seconds
+    end: f64,
+    /// Seconds
+    return_time: f64,
+    warnings: Vec<String>,
+}
+
+impl IrVisitor for LoopVisitor {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        let (what, returned) = match keyframes {
+            KeyframesMut::Translate(k) => ("translate", self.close(k, |_, first| *first)),
+            KeyframesMut::Scale(k) => ("scale", self.close(k, |_, first| *first)),
+            KeyframesMut::Skew(k) => ("skew", self.close(k, |_, first| *first)),
+            KeyframesMut::Opacity(k) => ("opacity", self.close(k, |_, first| *first)),
+            KeyframesMut::Shape(k) => ("shape", self.close(k, |_, first| first.clone())),
+            KeyframesMut::Zoom(k) => ("zoom", self.close(k, |_, first| *first)),
+            // Go to whichever whole turn from the start is nearest
+            KeyframesMut::Rotate(k) => (
+                "rotate",
+                self.close(k, |last, first| {
+                    first + ((last - first) / 360.0).round() * 360.0
+                }),
+            ),
+        };
+        if returned {
+            self.warnings.push(format!(
+                "{what} doesn't end where it started, returning over {}s so it loops",
+                self.return_time
+            ));
+        }
+    }
+}
+
+impl LoopVisitor {
+    /// If `keyframed` doesn't end at `loop_value(last, first)` go there after the end.
+    /// True if a return was added.
+    fn close<T: Clone + PartialEq>(
+        &self,
+        keyframed: &mut Keyframed<T>,
+        loop_value: impl Fn(&T, &T) -> T,
+    ) -> bool {
+        if !keyframed.is_animated() {
+            return false;
+        }
+        let first = keyframed.earliest().value.clone();
+        let last = keyframed.iter().last().unwrap().clone();
+        let target = loop_value(&last.value, &first);
+        if target == last.value {
+            return false;
+        }
+        if last.time < self.end {
+            keyframed.push(Keyframe::new(self.end, last.value));
+        }
+        keyframed.push(Keyframe::new(self.end + self.return_time, target));
+        true
+    }
+}
+
+/// Fills every group with a single color, replacing the debug coloring of parts
+pub struct Theme(pub (u8, u8, u8));
+
+impl IrVisitor for Theme {
+    fn visit_group(&mut self, group: &mut Group) {
+        group.set_fill(Some(self.0));
+    }
+}
+
+impl Pass for Theme {
+    fn name(&self) -> &str {
+        "theme"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        animation.visit_mut(&mut Theme(self.0));
+    }
+}
+
+/// How [`Contain`] brings an out of bounds value back in bounds
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContainMode {
+    /// Cut off the excess, the motion flattens at the limit
+    Clamp,
+    /// Shrink the excursion so the peak just reaches the limit, the motion keeps its shape
+    Scale,
+}
+
+/// Keeps scale and translation, typically spring overshoot, within a margin of the drawbox.
+///
+/// Each group is considered on its own; transforms of enclosing groups and rotation are not
+/// taken into account.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Contain {
+    /// Fraction of the drawbox size content may extend beyond it, e.g. 0.05 for 5%
+    pub margin: f64,
+    pub mode: ContainMode,
+}
+
+impl Pass for Contain {
+    fn name(&self) -> &str {
+        "contain"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        let bounds = Rect::new(0.0, 0.0, animation.width, animation.height).inflate(
+            self.margin * animation.width,
+            self.margin * animation.height,
+        );
+        // Containment is per group so each needs its own motion
+        animation.resolve_shared();
+        animation.visit_mut(&mut ContainVisitor {
+            bounds,
+            mode: self.mode,
+        });
+    }
+}
+
+struct ContainVisitor {
+    bounds: Rect,
+    mode: ContainMode,
+}
+
+impl IrVisitor for ContainVisitor {
+    fn visit_group(&mut self, group: &mut Group) {
+        let Some(bbox) = group.bounding_box() else {
+            return;
+        };
+        let center = group.center;
+
+        // Largest scale, in percent, about center that stays in bounds
+        let max_scale = |lo: f64, hi: f64, c: f64, bounds_lo: f64, bounds_hi: f64| {
+            let mut limit = f64::INFINITY;
+            if hi > c {
+                limit = limit.min((bounds_hi - c) / (hi - c));
+            }
+            if lo < c {
+                limit = limit.min((c - bounds_lo) / (c - lo));
+            }
+            limit * 100.0
+        };
+        let max_sx = max_scale(bbox.x0, bbox.x1, center.x, self.bounds.x0, self.bounds.x1);
+        let max_sy = max_scale(bbox.y0, bbox.y1, center.y, self.bounds.y0, self.bounds.y1);
+        if group.scale.is_animated() {
+            let sx: Vec<_> = group.scale.iter().map(|k| k.value.0).collect();
+            let sy: Vec<_> = group.scale.iter().map(|k| k.value.1).collect();
+            let sx = self.contain(sx, 100.0, f64::NEG_INFINITY, max_sx);
+            let sy = self.contain(sy, 100.0, f64::NEG_INFINITY, max_sy);
+            for (i, k) in group.scale.iter_mut().enumerate() {
+                k.value = (sx[i], sy[i]);
+            }
+        }
+
+        if group.translate.is_animated() {
+            let dx: Vec<_> = group.translate.iter().map(|k| k.value.x).collect();
+            let dy: Vec<_> = group.translate.iter().map(|k| k.value.y).collect();
+            let dx = self.contain(dx, 0.0, self.bounds.x0 - bbox.x0, self.bounds.x1 - bbox.x1);
+            let dy = self.contain(dy, 0.0, self.bounds.y0 - bbox.y0, self.bounds.y1 - bbox.y1);
+            for (i, k) in group.translate.iter_mut().enumerate() {
+                k.value = Vec2::new(dx[i], dy[i]);
+            }
+        }
+    }
+}
+
+impl ContainVisitor {
+    /// Bring values into [min, max], treating rest as the value excursions are measured from
+    fn contain(&self, values: Vec<f64>, rest: f64, min: f64, max: f64) -> Vec<f64> {
+        match self.mode {
+            ContainMode::Clamp => values.into_iter().map(|v| v.max(min).min(max)).collect(),
+            ContainMode::Scale => {
+                // One factor for the whole property so the motion keeps its shape
+                let factor = values
+                    .iter()
+                    .map(|v| {
+                        if *v > max && max > rest {
+                            (max - rest) / (v - rest)
+                        } else if *v < min && min < rest {
+                            (rest - min) / (rest - v)
+                        } else {
+                            1.0
+                        }
+                    })
+                    .fold(1.0, f64::min);
+                values
+                    .into_iter()
+                    .map(|v| rest + (v - rest) * factor)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Asks for groups that spin or travel faster than a limit to be
+/// [motion blurred](Group::set_motion_blur)
+///
+/// Speed is the change from one keyframe to the next over the frames between, at the animation's
+/// frame rate, so an eased segment peaks faster than it measures. Springs have a keyframe every few
+/// frames so measure closely.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MotionBlur {
+    /// Degrees of rotation per frame
+    pub degrees_per_frame: f64,
+    /// Translation per frame, as a fraction of the animation's width
+    pub width_per_frame: f64,
+}
+
+impl Default for MotionBlur {
+    /// Two turns a second, or crossing the icon in a third of a second, at 60fps
+    fn default() -> Self {
+        MotionBlur {
+            degrees_per_frame: 12.0,
+            width_per_frame: 0.05,
+        }
+    }
+}
+
+impl Pass for MotionBlur {
+    fn name(&self) -> &str {
+        "motion-blur"
+    }
+
+    fn run(&self, animation: &mut Animation) {
+        // Speed is per group so each needs its own motion
+        animation.resolve_shared();
+        animation.visit_mut(&mut MotionBlurVisitor {
+            degrees_per_frame: self.degrees_per_frame,
+            distance_per_frame: self.width_per_frame * animation.width,
+            frame_rate: animation.frame_rate(),
+        });
+    }
+}
+
+struct MotionBlurVisitor {
+    degrees_per_frame: f64,
+    distance_per_frame: f64,
+    frame_rate: f64,
+}
+
+impl IrVisitor for MotionBlurVisitor {
+    fn visit_group(&mut self, group: &mut Group) {
+        /// The fastest change from one keyframe to the next, per frame at `frame_rate`
+        fn top_speed<T>(
+            keyframed: &Keyframed<T>,
+            frame_rate: f64,
+            distance: impl Fn(&T, &T) -> f64,
+        ) -> f64 {
+            let keyframes: Vec<_> = keyframed.iter().collect();
+            keyframes
+                .windows(2)
+                .filter(|w| w[1].time > w[0].time && !w[0].hold)
+                .map(|w| {
+                    distance(&w[0].value, &w[1].value) / ((w[1].time - w[0].time) * frame_rate)
+                })
+                .fold(0.0, f64::max)
+        }
+        let spin = top_speed(&group.rotate, self.frame_rate, |a, b| (b - a).abs());
+        let travel = top_speed(&group.translate, self.frame_rate, |a, b| (*b - *a).hypot());
+        if spin > self.degrees_per_frame || travel > self.distance_per_frame {
+            group.set_motion_blur(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kurbo::{BezPath, Rect, Shape};
+
+    use crate::{
+        ir::{to_frames, Animation, Ease, Element, Group, Keyframe, Keyframed},
+        observe::GenerationObserver,
+    };
+
+    use super::{
+        Contain, ContainMode, Cubics, Dedupe, Loop, MotionBlur, Pass, Pipeline, Simplify,
+        SimplifyPaths, Snap, Theme,
+    };
+
+    fn pulsing_square() -> Animation {
+        let mut root = Group {
+            center: (50.0, 50.0).into(),
+            ..Default::default()
+        };
+        root.children.push(Element::Shape(Keyframed::new(
+            0.0,
+            Rect::new(0.0, 0.0, 100.0, 100.0).to_path(0.1),
+        )));
+        root.scale = vec![
+            (0.0, (100.0, 100.0)),
+            (0.25, (150.0, 140.0)),
+            (0.5, (100.0, 100.0)),
+        ]
+        .try_into()
+        .unwrap();
+        Animation::new(100.0, 100.0, Duration::from_millis(500), root)
+    }
+
+    fn scales(animation: &Animation) -> Vec<(f64, f64)> {
+        animation.root().scale.iter().map(|k| k.value).collect()
+    }
+
+    #[test]
+    fn default_pipeline() {
+        assert_eq!(
+            vec!["cubics", "dedupe"],
+            Pipeline::default().enabled().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn enable_insert_disable() {
+        let mut pipeline = Pipeline::default();
+        pipeline.enable(Snap.name()).unwrap();
+        pipeline
+            .insert_before(Dedupe.name(), Theme((0, 0, 0)))
+            .unwrap();
+        pipeline.disable(Dedupe.name()).unwrap();
+        assert_eq!(
+            vec!["cubics", "theme", "snap"],
+            pipeline.enabled().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn unknown_pass() {
+        assert!(Pipeline::default().disable("loopify").is_err());
+    }
+
+    #[derive(Default)]
+    struct Phases(Vec<String>);
+
+    impl GenerationObserver for Phases {
+        fn phase_started(&mut self, phase: &str) {
+            self.0.push(format!("start {phase}"));
+        }
+
+        fn phase_finished(&mut self, phase: &str) {
+            self.0.push(format!("finish {phase}"));
+        }
+    }
+
+    #[test]
+    fn observe_passes() {
+        let mut pipeline = Pipeline::default();
+        pipeline.enable(Snap.name()).unwrap();
+        let mut phases = Phases::default();
+        pipeline.run_observed(&mut pulsing_square(), &mut phases);
+        assert_eq!(
+            vec![
+                "start cubics",
+                "finish cubics",
+                "start dedupe",
+                "finish dedupe",
+                "start snap",
+                "finish snap"
+            ],
+            phases.0
+        );
+    }
+
+    #[test]
+    fn snap_uses_the_frame_rate() {
+        let mut animation = pulsing_square();
+        animation.root_mut().rotate = vec![(0.0, 0.0), (0.51, 90.0)].try_into().unwrap();
+        animation.retime(30.0).unwrap();
+        Snap.run(&mut animation);
+        assert_eq!(
+            vec![0.0, 0.5],
+            animation
+                .root()
+                .rotate
+                .iter()
+                .map(|k| k.time)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn loop_leaves_a_loop_alone() {
+        let mut animation = pulsing_square();
+        animation.duration = 1.0;
+        Loop::default().run(&mut animation);
+        assert_eq!(3, animation.root().scale.len());
+        assert!(animation.warnings().is_empty());
+        // No pause before repeating
+        assert_eq!(0.5, animation.duration);
+    }
+
+    #[test]
+    fn loop_returns_to_the_start() {
+        let mut animation = pulsing_square();
+        animation.root_mut().rotate = vec![(0.0, 0.0), (0.75, 90.0)].try_into().unwrap();
+        Loop::default().run(&mut animation);
+        assert_eq!(
+            vec![(0.0, 0.0), (45.0, 90.0), (57.0, 0.0)],
+            animation
+                .root()
+                .rotate
+                .iter()
+                .map(|k| (to_frames(k.time, 60.0), k.value))
+                .collect::<Vec<_>>()
+        );
+        // The pulse ended early but where it started
+        assert_eq!(3, animation.root().scale.len());
+        assert_eq!(1, animation.warnings().len());
+        assert_eq!(57.0, animation.frames());
+    }
+
+    #[test]
+    fn loop_accepts_whole_turns() {
+        let mut animation = pulsing_square();
+        animation.root_mut().rotate = vec![(0.0, 0.0), (0.5, 720.0)].try_into().unwrap();
+        Loop::default().run(&mut animation);
+        assert_eq!(2, animation.root().rotate.len());
+        assert!(animation.warnings().is_empty());
+    }
+
+    #[test]
+    fn simplify_drops_what_motion_doesnt_need() {
+        let mut animation = pulsing_square();
+        // Dense linear samples of a curve, as blend produces
+        let curve = |frame: f64| frame * frame / 100.0;
+        let dense: Keyframed<f64> = (0..=60)
+            .map(|f| Keyframe::new(f as f64 / 60.0, curve(f as f64)).with_ease(Ease::LINEAR))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+        animation.root_mut().rotate = dense.clone();
+        let simplify = Simplify { tolerance: 0.5 };
+        simplify.run(&mut animation);
+
+        let rotate = &animation.root().rotate;
+        assert!(rotate.len() < dense.len() / 4, "{} keyframes", rotate.len());
+        for frame in 0..=60 {
+            let time = frame as f64 / 60.0;
+            let error = (rotate.value_at(time) - dense.value_at(time)).abs();
+            assert!(error <= simplify.tolerance, "off by {error} at {frame}");
+        }
+        // The pulse has nothing to spare
+        assert_eq!(3, animation.root().scale.len());
+    }
+
+    #[test]
+    fn simplify_paths_keeps_shapes_compatible() {
+        let mut animation = pulsing_square();
+        // The midpoint of the top edge is redundant at both keyframes, the bottom edge's only at one
+        let at = |bottom_mid: (f64, f64)| {
+            BezPath::from_svg(&format!(
+                "M0,0 L50,0 L100,0 L100,100 L{},{} L0,100 L0,0.001 Z",
+                bottom_mid.0, bottom_mid.1
+            ))
+            .unwrap()
+        };
+        animation.root_mut().children = vec![Element::Shape(
+            vec![(0.0, at((50.0, 100.0))), (0.5, at((50.0, 90.0)))]
+                .try_into()
+                .unwrap(),
+        )];
+        SimplifyPaths::default().run(&mut animation);
+
+        let Element::Shape(shape) = &animation.root().children[0] else {
+            panic!("Should still be a shape");
+        };
+        assert_eq!(
+            vec![
+                "M0,0 L100,0 L100,100 L50,100 L0,100 Z",
+                "M0,0 L100,0 L100,100 L50,90 L0,100 Z"
+            ],
+            shape.iter().map(|k| k.value.to_svg()).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn cubics_reconcile_quadratics() {
+        let mut animation = pulsing_square();
+        let quad = BezPath::from_svg("M0,0 Q50,-30 100,0 L0,0 Z").unwrap();
+        let cubic = BezPath::from_svg("M0,0 C30,-20 70,-20 100,0 L0,0 Z").unwrap();
+        let lines = Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1);
+        animation.root_mut().children = vec![
+            Element::Shape(vec![(0.0, quad), (0.5, cubic)].try_into().unwrap()),
+            Element::Shape(Keyframed::new(0.0, lines.clone())),
+        ];
+        Cubics.run(&mut animation);
+
+        let paths: Vec<Vec<_>> = animation
+            .root()
+            .children
+            .iter()
+            .map(|e| match e {
+                Element::Shape(k) => k.iter().map(|k| k.value.to_svg()).collect(),
+                Element::Group(..) => panic!("Should still be shapes"),
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                vec![
+                    "M0,0 C33.33333333333333,-20 66.66666666666667,-20 100,0 C100,0 0,0 0,0 Z",
+                    "M0,0 C30,-20 70,-20 100,0 C100,0 0,0 0,0 Z"
+                ],
+                // Already compatible
+                vec![lines.to_svg()],
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn contain_clamp() {
+        let mut animation = pulsing_square();
+        Contain {
+            margin: 0.1,
+            mode: ContainMode::Clamp,
+        }
+        .run(&mut animation);
+        // 10% margin on each side allows the 100 wide square to reach 120
+        let scales = scales(&animation);
+        assert_eq!((100.0, 100.0), scales[0]);
+        assert!((scales[1].0 - 120.0).abs() < 1e-9, "{scales:?}");
+        assert!((scales[1].1 - 120.0).abs() < 1e-9, "{scales:?}");
+    }
+
+    #[test]
+    fn contain_scale() {
+        let mut animation = pulsing_square();
+        Contain {
+            margin: 0.1,
+            mode: ContainMode::Scale,
+        }
+        .run(&mut animation);
+        let scales = scales(&animation);
+        assert_eq!((100.0, 100.0), scales[0]);
+        // x peaked at +50, y at +40; each is compressed to peak at +20
+        assert!((scales[1].0 - 120.0).abs() < 1e-9, "{scales:?}");
+        assert!((scales[1].1 - 120.0).abs() < 1e-9, "{scales:?}");
+    }
+
+    #[test]
+    fn contain_leaves_in_bounds_alone() {
+        let mut animation = pulsing_square();
+        Contain {
+            margin: 1.0,
+            mode: ContainMode::Scale,
+        }
+        .run(&mut animation);
+        assert_eq!((150.0, 140.0), scales(&animation)[1]);
+    }
+
+    #[test]
+    fn fast_spins_blur() {
+        let mut animation = pulsing_square();
+        animation.root.rotate = vec![(0.0, 0.0), (0.25, 360.0)].try_into().unwrap();
+        MotionBlur::default().run(&mut animation);
+        assert!(animation.root().motion_blur());
+
+        let mut animation = pulsing_square();
+        animation.root.rotate = vec![(0.0, 0.0), (1.0, 360.0)].try_into().unwrap();
+        MotionBlur::default().run(&mut animation);
+        assert!(!animation.root().motion_blur());
+    }
+}
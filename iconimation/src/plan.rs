@@ -1,335 +1,642 @@
-//! Quick & dirty text input to icon animation definition
+//! Icon animation definitions; with the `command` feature, parsed from quick & dirty text input
+
+use std::{collections::HashMap, str::FromStr, time::Duration};
+
+use kurbo::Point;
+use serde::Serialize;
+use skrifa::{raw::FontRef, Tag};
+
+use crate::{
+    bender::Bender,
+    error::Error,
+    font_info,
+    ir::{Ease, Segment},
+    labels::PartHints,
+    pass::{Contain, Pipeline},
+    spring::{AnimatedValue, AnimatedValueType, Spring, DEFAULT_MAX_DURATION},
+};
+
+#[cfg(feature = "command")]
+mod parse;
+
+#[cfg(feature = "command")]
+pub use parse::parse_plan;
+
+/// A property a plan can animate, each may have its own spring
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum Channel {
+    Translate,
+    Scale,
+    Rotate,
+    Opacity,
+    /// The position between the start and end locations in the designspace, as
+    /// [`VariationMode::Redraw`] draws it
+    Variation,
+}
 
-use std::{str::FromStr, sync::OnceLock};
+impl Channel {
+    /// The names [`Channel::from_str`] accepts, including aliases such as "rotation"
+    pub const NAMES: [&'static str; 7] = [
+        "translate",
+        "position",
+        "scale",
+        "rotate",
+        "rotation",
+        "opacity",
+        "variation",
+    ];
+
+    /// Whether Material motion considers the channel spatial, that is it moves, resizes or rotates
+    pub fn is_spatial(&self) -> bool {
+        !matches!(self, Channel::Opacity | Channel::Variation)
+    }
+}
 
-use regex::{Captures, Regex};
-use skrifa::{raw::FontRef, MetadataProvider, Tag};
+impl FromStr for Channel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "translate" | "position" => Ok(Channel::Translate),
+            "scale" => Ok(Channel::Scale),
+            "rotate" | "rotation" => Ok(Channel::Rotate),
+            "opacity" => Ok(Channel::Opacity),
+            "variation" => Ok(Channel::Variation),
+            _ => Err(Error::UnrecognizedChannel(s.to_string())),
+        }
+    }
+}
 
-use crate::{error::Error, ligate::icon_name_to_gid, spring::Spring, GlyphShape};
+/// Names in a `using` clause that pick the spatial or non-spatial spring to suit each channel
+pub const SPRING_SCHEMES: [&str; 2] = ["smooth", "expressive"];
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct NameAndVariation<'a> {
     icon_name: &'a str,
     spring: Option<Spring>,
+    /// Springs for specific channels, these take precedence over `spring`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    channel_springs: Vec<(Channel, Spring)>,
+    /// A fixed ease instead of a spring, e.g. `using ease:emphasized`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ease: Option<Ease>,
+    /// Eases for specific channels, these take precedence over `spring` and `ease`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    channel_eases: Vec<(Channel, Ease)>,
+    /// A classic easing family instead of a spring, e.g. `using bounce`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bender: Option<Bender>,
+    /// Benders for specific channels, these take precedence over `spring`, `ease` and `bender`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    channel_benders: Vec<(Channel, Bender)>,
+    initial_velocity: Option<f64>,
     vary_from: Option<&'a str>,
     vary_to: Option<&'a str>,
 }
 
 impl<'a> NameAndVariation<'a> {
-    fn from_captures(
-        captures: &Captures<'a>,
-        name_idx: usize,
-        spring_idx: usize,
-        vary_from_idx: usize,
-        vary_to_idx: usize,
-    ) -> Result<Self, Error> {
-        let icon_name = captures
-            .get(name_idx)
-            .ok_or(Error::NoCapture("icon name", name_idx))?
-            .as_str();
-        let spring = captures
-            .get(spring_idx)
-            .map(|m| Spring::from_str(m.as_str()))
-            .transpose()
-            .map_err(|_| Error::UnrecognizedSpring)?;
-        let vary_from = captures.get(vary_from_idx).map(|m| m.as_str());
-        let vary_to = captures.get(vary_to_idx).map(|m| m.as_str());
-        Ok(NameAndVariation {
-            icon_name,
-            spring,
-            vary_from,
-            vary_to,
-        })
+    /// Whether a spring, ease or bender is given for `channel` specifically
+    fn times_channel(&self, channel: Channel) -> bool {
+        self.channel_springs.iter().any(|(c, _)| *c == channel)
+            || self.channel_eases.iter().any(|(c, _)| *c == channel)
+            || self.channel_benders.iter().any(|(c, _)| *c == channel)
     }
 }
 
 type UserLocation = Vec<(Tag, f32)>;
 
+/// Choices about how a plan is realized that are not part of the command itself
+#[derive(Debug, Default)]
+pub struct PlanOptions {
+    /// Run over the [`crate::ir::Animation`] once the plan has been applied
+    pub passes: Pipeline,
+    /// Substitute a placeholder for icons that can't be found or drawn rather than failing,
+    /// see [`crate::ir::Animation::of_command`]
+    pub placeholder_on_error: bool,
+    /// If set, keep overshoot within a margin of the drawbox. Runs before [`PlanOptions::passes`].
+    pub overshoot: Option<Contain>,
+    /// How a change of variation, e.g. `vary FILL:0 to FILL:1`, becomes motion
+    pub variation: VariationMode,
+    /// Keep overlapping contours in one part, without unioning them, when animating parts, see
+    /// [`crate::ir::Group::group_overlapping_parts`]
+    pub group_overlaps: bool,
+    /// Where each part rotates and scales about when animating parts
+    pub anchor: Anchor,
+    /// Names for the parts of icons, to select them by, e.g. `twirl part hand`
+    pub part_hints: PartHints,
+    /// Draw parts that move above those that hold still, see [`crate::ir::Group::z`]
+    pub moving_parts_on_top: bool,
+    /// Leave parts to take the icon's fill rather than coloring each to show how the icon was
+    /// grouped
+    pub plain_parts: bool,
+    /// If set, play the plan at this many frames a second rather than the 60 plans are made at,
+    /// see [`crate::ir::Animation::retime`]
+    pub frame_rate: Option<f64>,
+    /// If set, speed up or slow down the plan to last this long, see
+    /// [`crate::ir::Animation::set_duration`]
+    pub duration: Option<Duration>,
+    /// If set, draw the icon this many output units, e.g. pixels, square rather than at the
+    /// font's units per em, see [`crate::ir::Animation::set_size`]
+    pub size: Option<f64>,
+}
+
+/// The point a part pivots on, see [`PlanOptions::anchor`]
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Anchor {
+    /// The center of the part's bounding box
+    #[default]
+    BoundingBox,
+    /// The center of mass of the area the part fills; truer for lopsided parts
+    Centroid,
+    /// The point of the part closest to the center of the icon, as if pinned where it attaches
+    NearestToCenter,
+    /// The same point for every part, in the coordinates of the [`crate::ir::Animation`]
+    Fixed(Point),
+}
+
+impl FromStr for Anchor {
+    type Err = Error;
+
+    /// bbox, centroid, nearest, or a fixed point as x,y
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bbox" => Ok(Anchor::BoundingBox),
+            "centroid" => Ok(Anchor::Centroid),
+            "nearest" => Ok(Anchor::NearestToCenter),
+            _ => {
+                let point = s.split_once(',').and_then(|(x, y)| {
+                    Some(Point::new(x.trim().parse().ok()?, y.trim().parse().ok()?))
+                });
+                point
+                    .map(Anchor::Fixed)
+                    .ok_or_else(|| Error::UnrecognizedAnchor(s.to_string()))
+            }
+        }
+    }
+}
+
+/// How the shape changes between two variable font locations
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum VariationMode {
+    /// Draw the glyph at the start and end and ease the points from one to the other
+    #[default]
+    Interpolate,
+    /// Move the position in the designspace as the plan times [`Channel::Variation`] and draw the
+    /// glyph every few frames along the way, as the font itself would interpolate. Larger output,
+    /// truer shapes.
+    Redraw,
+}
+
+/// Frames per second plans are timed in, see [`AnimationPlan::frames`]
+pub(crate) const PLAN_FRAME_RATE: f64 = 60.0;
+
+/// Frames a plan takes unless it says otherwise, one second at 60fps
+const DEFAULT_PLAN_FRAMES: f64 = 60.0;
+
+/// Material's medium2 duration, 300ms, at 60fps; the length of an enter or exit transition
+const STANDARD_TRANSITION_FRAMES: f64 = 18.0;
+
+/// Material's long2 duration, 500ms, at 60fps; a bounce or wobble needs longer than a transition
+/// to read
+const BENT_TRANSITION_FRAMES: f64 = 30.0;
+
+/// One icon in a [`AnimationPlan::Sequence`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SequenceStep<'a> {
+    pub icon_name: &'a str,
+    /// How long the morph from the prior icon into this one takes
+    pub frames: f64,
+    /// How to morph from the prior icon into this one. None means [`Ease::default`].
+    pub ease: Option<Ease>,
+}
+
+/// The axis positions an [`AnimationPlan::Sweep`] draws the icon at
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AxisSweep<'a> {
+    pub tag: &'a str,
+    pub from: f64,
+    pub to: f64,
+    /// How many equal steps from `from` to `to`, the icon is drawn steps + 1 times
+    pub steps: usize,
+}
+
+impl AxisSweep<'_> {
+    /// Where to draw, from `from` to `to` inclusive
+    pub fn values(&self) -> Vec<f64> {
+        (0..=self.steps)
+            .map(|i| self.from + (self.to - self.from) * i as f64 / self.steps as f64)
+            .collect()
+    }
+}
+
+/// One step of a [`AnimationPlan::Composite`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum CompositeStep<'a> {
+    Plan(AnimationPlan<'a>),
+    /// Hold still for a number of frames
+    Hold(f64),
+}
+
+impl CompositeStep<'_> {
+    pub fn frames(&self) -> f64 {
+        match self {
+            CompositeStep::Plan(plan) => plan.frames(),
+            CompositeStep::Hold(frames) => *frames,
+        }
+    }
+}
+
 /// Describes animation. Apply to a [`crate::ir::Animation`] to actually do something.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum AnimationPlan<'a> {
     None(NameAndVariation<'a>),
     RotateDegrees(NameAndVariation<'a>, f64),
     ScaleFromTo(NameAndVariation<'a>, f64, f64),
+    /// Squash flat by percent and spring back, stretching tall as it overshoots. Width changes
+    /// with height so the area stays the same, both driven by one spring.
+    SquashAndStretch(NameAndVariation<'a>, f64),
     PulseWhole(NameAndVariation<'a>),
-    PulseParts(NameAndVariation<'a>),
+    PulseParts(NameAndVariation<'a>, PartSelection<'a>),
     TwirlWhole(NameAndVariation<'a>),
-    TwirlParts(NameAndVariation<'a>),
+    TwirlParts(NameAndVariation<'a>, PartSelection<'a>),
+    /// Fade in while scaling up from 80%, per Material's enter transition
+    StandardEnter(NameAndVariation<'a>),
+    /// Fade out while scaling down to 80%, per Material's exit transition
+    StandardExit(NameAndVariation<'a>),
+    /// Morph from the named icon through each step in turn. The icons must be interpolation compatible.
+    Sequence(NameAndVariation<'a>, Vec<SequenceStep<'a>>),
+    /// Vary the icon along an axis, drawing it at each step rather than interpolating between the
+    /// ends, e.g. `sweep wght 100 to 700 in 10 steps`
+    Sweep(NameAndVariation<'a>, AxisSweep<'a>),
+    /// Plans one after another, e.g. `twirl then hold 10 then pulse`, each starting where the
+    /// last left off. The icon and variation are shared by every step.
+    Composite(NameAndVariation<'a>, Vec<CompositeStep<'a>>),
+    /// Plans at the same time, e.g. `rotate 90 degrees using expressive-spatial while scale 80 to
+    /// 100 using standard`. Each channel keeps its own spring; the icon and variation are shared.
+    /// Where channels animate the same property the last wins.
+    Parallel(NameAndVariation<'a>, Vec<AnimationPlan<'a>>),
 }
 
-fn get_f64(name: &'static str, captures: &Captures<'_>, i: usize) -> Result<f64, Error> {
-    let raw = captures.get(i).ok_or(Error::NoCapture(name, i))?;
-    raw.as_str().parse::<f64>().map_err(Error::InvalidF64)
+/// The parts a parts plan moves, e.g. the `parts 2..4` of `twirl parts 2..4`. The rest hold still.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize)]
+pub enum PartSelection<'a> {
+    #[default]
+    All,
+    /// Parts with a name, ignoring case, e.g. `part dot-1`; see [`crate::labels`]
+    Named(&'a str),
+    /// Parts from start up to but not including end, numbered from 0 in the order
+    /// [`crate::ir::Group::group_parts`] makes them
+    Range(usize, usize),
+    /// The part with the largest bounding box
+    Largest,
+    /// The part with the smallest bounding box
+    Smallest,
 }
 
-impl AnimationPlan<'_> {
-    fn parse(animation: &str) -> Result<AnimationPlan, Error> {
-        const ANIMATE: &str = r"^Animate\s+(\w+)\s*:\s*";
-        const SPRING: &str = r"(?:\s+using\s+([\w-]+))?";
-        const VARIATION: &str = r"(?:\s+vary\s+(\S+)\s+to\s+(\S+))?";
-        static ROTATE: OnceLock<Regex> = OnceLock::new();
-        static SCALE: OnceLock<Regex> = OnceLock::new();
-        static ONLY_NAME: OnceLock<Regex> = OnceLock::new();
-
-        let rotate = ROTATE.get_or_init(|| {
-            Regex::new(
-                &(ANIMATE.to_string() + r"rotate\s+(\d+)\s+degrees" + SPRING + VARIATION + "$"),
-            )
-            .unwrap()
-        });
-        let scale = SCALE.get_or_init(|| {
-            Regex::new(
-                &(ANIMATE.to_string() + r"scale\s+(\d+)\s+to\s+(\d+)" + SPRING + VARIATION + "$"),
-            )
-            .unwrap()
-        });
-        let only_name = ONLY_NAME.get_or_init(|| {
-            Regex::new(
-                &(ANIMATE.to_string()
-                    + r"(pulse|pulse-whole|twirl|twirl-whole)?"
-                    + SPRING
-                    + VARIATION
-                    + "$"),
-            )
-            .unwrap()
-        });
-
-        Ok(if let Some(captures) = rotate.captures_at(animation, 0) {
-            let nv = NameAndVariation::from_captures(&captures, 1, 3, 4, 5)?;
-            let degrees = get_f64("degrees", &captures, 2)?;
-            AnimationPlan::RotateDegrees(nv, degrees)
-        } else if let Some(captures) = scale.captures_at(animation, 0) {
-            let nv = NameAndVariation::from_captures(&captures, 1, 4, 5, 6)?;
-            let from = get_f64("from", &captures, 2)?;
-            let to = get_f64("to", &captures, 3)?;
-            AnimationPlan::ScaleFromTo(nv, from, to)
-        } else if let Some(captures) = only_name.captures_at(animation, 0) {
-            eprintln!("only_name captures\n{captures:?}");
-            let nv = NameAndVariation::from_captures(&captures, 1, 3, 4, 5)?;
-            let command = captures.get(2).map(|m| m.as_str()).unwrap_or("none");
-            match command {
-                "none" => AnimationPlan::None(nv),
-                "pulse" => AnimationPlan::PulseParts(nv),
-                "pulse-whole" => AnimationPlan::PulseWhole(nv),
-                "twirl" => AnimationPlan::TwirlParts(nv),
-                "twirl-whole" => AnimationPlan::TwirlWhole(nv),
-                _ => return Err(Error::UnrecognizedCommand),
-            }
-        } else {
-            return Err(Error::UnrecognizedCommand);
-        })
-    }
-
-    pub fn icon_name(&self) -> &str {
+impl<'a> PartSelection<'a> {
+    /// The indices of the selected parts given the area of each part's bounding box and its name
+    pub fn select(&self, areas: &[f64], names: &[Option<&str>]) -> Vec<usize> {
+        let by_area = |a: &(usize, &f64), b: &(usize, &f64)| a.1.total_cmp(b.1);
         match self {
-            AnimationPlan::None(nv, ..)
-            | AnimationPlan::RotateDegrees(nv, ..)
-            | AnimationPlan::ScaleFromTo(nv, ..)
-            | AnimationPlan::PulseWhole(nv, ..)
-            | AnimationPlan::PulseParts(nv, ..)
-            | AnimationPlan::TwirlWhole(nv, ..)
-            | AnimationPlan::TwirlParts(nv, ..) => nv.icon_name,
+            PartSelection::All => (0..areas.len()).collect(),
+            PartSelection::Named(name) => (0..names.len())
+                .filter(|i| names[*i].is_some_and(|n| n.eq_ignore_ascii_case(name)))
+                .collect(),
+            PartSelection::Range(start, end) => (*start..(*end).min(areas.len())).collect(),
+            PartSelection::Largest => areas
+                .iter()
+                .enumerate()
+                .max_by(by_area)
+                .map(|(i, _)| i)
+                .into_iter()
+                .collect(),
+            PartSelection::Smallest => areas
+                .iter()
+                .enumerate()
+                .min_by(by_area)
+                .map(|(i, _)| i)
+                .into_iter()
+                .collect(),
         }
     }
+}
 
-    pub fn spring(&self) -> Option<Spring> {
+/// Whether a plan moves the icon as one or part by part, see [`AnimationPlan::with_granularity`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum Granularity {
+    Whole,
+    Parts,
+}
+
+impl Granularity {
+    /// For naming output, e.g. lottie-whole.json
+    pub fn name(&self) -> &'static str {
         match self {
-            AnimationPlan::None(nv, ..)
-            | AnimationPlan::RotateDegrees(nv, ..)
-            | AnimationPlan::ScaleFromTo(nv, ..)
-            | AnimationPlan::PulseWhole(nv, ..)
-            | AnimationPlan::PulseParts(nv, ..)
-            | AnimationPlan::TwirlWhole(nv, ..)
-            | AnimationPlan::TwirlParts(nv, ..) => nv.spring,
+            Granularity::Whole => "whole",
+            Granularity::Parts => "parts",
         }
     }
+}
 
-    pub fn variation(&self) -> Result<(UserLocation, UserLocation), Error> {
-        let nv = match self {
+impl<'a> AnimationPlan<'a> {
+    fn name_and_variation(&self) -> &NameAndVariation<'a> {
+        match self {
             AnimationPlan::None(nv, ..)
             | AnimationPlan::RotateDegrees(nv, ..)
             | AnimationPlan::ScaleFromTo(nv, ..)
+            | AnimationPlan::SquashAndStretch(nv, ..)
             | AnimationPlan::PulseWhole(nv, ..)
             | AnimationPlan::PulseParts(nv, ..)
             | AnimationPlan::TwirlWhole(nv, ..)
-            | AnimationPlan::TwirlParts(nv, ..) => nv,
-        };
-        let from = nv
-            .vary_from
-            .map(parse_location)
-            .unwrap_or_else(|| Ok(vec![]))?;
-        let to = nv
-            .vary_to
-            .map(parse_location)
-            .unwrap_or_else(|| Ok(vec![]))?;
-        Ok((from, to))
+            | AnimationPlan::TwirlParts(nv, ..)
+            | AnimationPlan::StandardEnter(nv, ..)
+            | AnimationPlan::StandardExit(nv, ..)
+            | AnimationPlan::Sequence(nv, ..)
+            | AnimationPlan::Sweep(nv, ..)
+            | AnimationPlan::Composite(nv, ..)
+            | AnimationPlan::Parallel(nv, ..) => nv,
+        }
     }
-}
 
-fn parse_location(raw: &str) -> Result<UserLocation, Error> {
-    raw.split(',')
-        .map(|kv| {
-            let parts = kv.split(':').collect::<Vec<_>>();
-            if parts.len() != 2 {
-                return Err(Error::InvalidLocation);
+    /// How long the plan takes, in frames at 60fps
+    pub fn frames(&self) -> f64 {
+        match self {
+            AnimationPlan::Sequence(_, steps) => steps.iter().map(|s| s.frames).sum(),
+            AnimationPlan::Composite(_, steps) => steps.iter().map(CompositeStep::frames).sum(),
+            AnimationPlan::Parallel(_, channels) => channels
+                .iter()
+                .map(AnimationPlan::frames)
+                .fold(0.0, f64::max),
+            AnimationPlan::StandardEnter(..) | AnimationPlan::StandardExit(..) => {
+                STANDARD_TRANSITION_FRAMES
             }
-            let tag = Tag::from_str(parts[0]).map_err(Error::InvalidTag)?;
-            let value = f32::from_str(parts[1]).map_err(Error::InvalidF64)?;
-            Ok((tag, value))
-        })
-        .collect::<Result<_, _>>()
-}
-
-pub fn parse_plan<'a, 'b>(
-    font: &'a FontRef,
-    command: &'b str,
-) -> Result<(AnimationPlan<'b>, GlyphShape<'a>), Error> {
-    let command = AnimationPlan::parse(command)?;
+            // An eased rotate or scale takes as long as a transition, a spring until it settles
+            AnimationPlan::RotateDegrees(..) if self.bender_for(Channel::Rotate).is_some() => {
+                BENT_TRANSITION_FRAMES
+            }
+            AnimationPlan::ScaleFromTo(..) | AnimationPlan::SquashAndStretch(..)
+                if self.bender_for(Channel::Scale).is_some() =>
+            {
+                BENT_TRANSITION_FRAMES
+            }
+            AnimationPlan::RotateDegrees(..) if self.ease_for(Channel::Rotate).is_some() => {
+                STANDARD_TRANSITION_FRAMES
+            }
+            AnimationPlan::ScaleFromTo(..) | AnimationPlan::SquashAndStretch(..)
+                if self.ease_for(Channel::Scale).is_some() =>
+            {
+                STANDARD_TRANSITION_FRAMES
+            }
+            AnimationPlan::RotateDegrees(_, degrees) => {
+                self.spring_frames(Channel::Rotate, self.rotation(*degrees))
+            }
+            AnimationPlan::ScaleFromTo(_, from, to) => {
+                self.spring_frames(Channel::Scale, self.scale(*from, *to))
+            }
+            AnimationPlan::SquashAndStretch(_, percent) => {
+                self.spring_frames(Channel::Scale, self.squash(*percent))
+            }
+            _ => DEFAULT_PLAN_FRAMES,
+        }
+    }
 
-    let gid = icon_name_to_gid(font, command.icon_name()).map_err(Error::IconNameError)?;
+    /// The spring to animate `channel` with: the one given for the channel, else the one given for
+    /// the plan, else [`Spring::standard`]
+    pub fn spring_for(&self, channel: Channel) -> Spring {
+        let nv = self.name_and_variation();
+        nv.channel_springs
+            .iter()
+            .find(|(c, _)| *c == channel)
+            .map(|(_, spring)| *spring)
+            .or(nv.spring)
+            .unwrap_or_else(Spring::standard)
+    }
 
-    let (raw_from, raw_to) = command.variation()?;
-    let from = font.axes().location(raw_from);
-    let to = font.axes().location(raw_to);
+    /// The ease to animate `channel` with instead of a spring, if any: the one given for the
+    /// channel, else the one given for the plan unless something else was given for the channel
+    pub fn ease_for(&self, channel: Channel) -> Option<Ease> {
+        let nv = self.name_and_variation();
+        if let Some((_, ease)) = nv.channel_eases.iter().find(|(c, _)| *c == channel) {
+            return Some(*ease);
+        }
+        if nv.times_channel(channel) {
+            return None;
+        }
+        nv.ease
+    }
 
-    let glyph_shape = GlyphShape::new(font, gid, from, Some(to))?;
+    /// The bender to animate `channel` with instead of a spring or ease, if any: the one given for
+    /// the channel, else the one given for the plan unless something else was given for the channel
+    pub fn bender_for(&self, channel: Channel) -> Option<Bender> {
+        let nv = self.name_and_variation();
+        if let Some((_, bender)) = nv.channel_benders.iter().find(|(c, _)| *c == channel) {
+            return Some(*bender);
+        }
+        if nv.times_channel(channel) {
+            return None;
+        }
+        nv.bender
+    }
 
-    Ok((command, glyph_shape))
-}
+    /// The start of a rotation by degrees, velocity in degrees per second
+    pub(crate) fn rotation(&self, degrees: f64) -> AnimatedValue {
+        AnimatedValue::new(0.0, degrees, AnimatedValueType::Rotation)
+            .with_velocity(self.initial_velocity())
+    }
 
-#[cfg(test)]
-mod tests {
-    use crate::spring::Spring;
+    /// The start of a scale between percentages, as fractions the way springs expect
+    pub(crate) fn scale(&self, from: f64, to: f64) -> AnimatedValue {
+        AnimatedValue::new(from / 100.0, to / 100.0, AnimatedValueType::Scale)
+            .with_velocity(self.initial_velocity() / 100.0)
+    }
 
-    use super::{AnimationPlan, NameAndVariation};
+    /// The start of a squash, by a percentage of the height, springing back to none
+    pub(crate) fn squash(&self, percent: f64) -> AnimatedValue {
+        AnimatedValue::new(percent / 100.0, 0.0, AnimatedValueType::Scale)
+            .with_velocity(self.initial_velocity() / 100.0)
+    }
 
-    impl<'a> From<&'a str> for NameAndVariation<'a> {
-        fn from(icon_name: &'a str) -> Self {
-            NameAndVariation {
-                icon_name,
-                spring: None,
-                vary_from: None,
-                vary_to: None,
-            }
-        }
+    /// Frames until the spring settles, at least one and at most [`DEFAULT_MAX_DURATION`]'s worth
+    fn spring_frames(&self, channel: Channel, animation: AnimatedValue) -> f64 {
+        let seconds = self
+            .spring_for(channel)
+            .settle_time_of(animation)
+            .map_or(DEFAULT_MAX_DURATION, |settle| {
+                settle.as_secs_f64().min(DEFAULT_MAX_DURATION)
+            });
+        (seconds * PLAN_FRAME_RATE).ceil().max(1.0)
     }
 
-    impl<'a> From<(&'a str, Spring)> for NameAndVariation<'a> {
-        fn from(value: (&'a str, Spring)) -> Self {
-            NameAndVariation {
-                icon_name: value.0,
-                spring: Some(value.1),
-                vary_from: None,
-                vary_to: None,
-            }
+    /// The command that names the plan, e.g. "twirl" or "rotate"
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnimationPlan::None(..) => "none",
+            AnimationPlan::RotateDegrees(..) => "rotate",
+            AnimationPlan::ScaleFromTo(..) => "scale",
+            AnimationPlan::SquashAndStretch(..) => "squash",
+            AnimationPlan::PulseWhole(..) => "pulse-whole",
+            AnimationPlan::PulseParts(..) => "pulse",
+            AnimationPlan::TwirlWhole(..) => "twirl-whole",
+            AnimationPlan::TwirlParts(..) => "twirl",
+            AnimationPlan::StandardEnter(..) => "standard-enter",
+            AnimationPlan::StandardExit(..) => "standard-exit",
+            AnimationPlan::Sequence(..) => "sequence",
+            AnimationPlan::Sweep(..) => "sweep",
+            AnimationPlan::Composite(..) => "composite",
+            AnimationPlan::Parallel(..) => "parallel",
         }
     }
 
-    impl<'a> From<(&'a str, &'a str, &'a str)> for NameAndVariation<'a> {
-        fn from(value: (&'a str, &'a str, &'a str)) -> Self {
-            NameAndVariation {
-                icon_name: value.0,
-                spring: None,
-                vary_from: Some(value.1),
-                vary_to: Some(value.2),
-            }
+    /// A segment per step of a [`AnimationPlan::Composite`], named for the step; empty for
+    /// anything else. Repeated names are numbered, e.g. twirl, hold, twirl-2.
+    ///
+    /// Segments are timed in seconds, as the [`crate::ir::Animation`] they mark up.
+    pub fn segments(&self) -> Vec<Segment> {
+        let AnimationPlan::Composite(_, steps) = self else {
+            return Vec::new();
+        };
+        let mut segments = Vec::with_capacity(steps.len());
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        let mut start = 0.0;
+        for step in steps {
+            let name = match step {
+                CompositeStep::Plan(plan) => plan.name(),
+                CompositeStep::Hold(..) => "hold",
+            };
+            let n = uses.entry(name).or_default();
+            *n += 1;
+            let end = start + step.frames();
+            segments.push(Segment {
+                name: match *n {
+                    1 => name.to_string(),
+                    n => format!("{name}-{n}"),
+                },
+                start: start / PLAN_FRAME_RATE,
+                end: end / PLAN_FRAME_RATE,
+            });
+            start = end;
         }
+        segments
     }
 
-    impl<'a> From<(&'a str, Spring, &'a str, &'a str)> for NameAndVariation<'a> {
-        fn from(value: (&'a str, Spring, &'a str, &'a str)) -> Self {
-            NameAndVariation {
-                icon_name: value.0,
-                spring: Some(value.1),
-                vary_from: Some(value.2),
-                vary_to: Some(value.3),
+    /// Whether the plan moves parts of the icon independently, which requires grouping them
+    pub fn granularity(&self) -> Granularity {
+        match self {
+            AnimationPlan::PulseParts(..) | AnimationPlan::TwirlParts(..) => Granularity::Parts,
+            AnimationPlan::Composite(_, steps) => {
+                let parts = steps.iter().any(|step| match step {
+                    CompositeStep::Plan(plan) => plan.granularity() == Granularity::Parts,
+                    CompositeStep::Hold(..) => false,
+                });
+                if parts {
+                    Granularity::Parts
+                } else {
+                    Granularity::Whole
+                }
             }
+            AnimationPlan::Parallel(_, channels)
+                if channels
+                    .iter()
+                    .any(|channel| channel.granularity() == Granularity::Parts) =>
+            {
+                Granularity::Parts
+            }
+            _ => Granularity::Whole,
         }
     }
 
-    #[test]
-    fn parse_rotate_with_spring() {
-        let cmd =
-            AnimationPlan::parse("Animate settings: rotate 360 degrees using expressive-spatial")
-                .unwrap();
-        assert_eq!(
-            AnimationPlan::RotateDegrees(("settings", Spring::expressive_spatial()).into(), 360.0),
-            cmd
-        );
+    pub fn icon_name(&self) -> &str {
+        self.name_and_variation().icon_name
     }
 
-    #[test]
-    fn parse_scale() {
-        let cmd = AnimationPlan::parse("Animate check_circle: scale 0 to 100").unwrap();
-        assert_eq!(
-            AnimationPlan::ScaleFromTo(("check_circle").into(), 0.0, 100.0),
-            cmd
-        );
+    pub fn spring(&self) -> Option<Spring> {
+        self.name_and_variation().spring
     }
 
-    #[test]
-    fn parse_pulse() {
-        let cmd = AnimationPlan::parse("Animate close: pulse").unwrap();
-        assert_eq!(AnimationPlan::PulseParts(("close").into()), cmd);
+    /// Velocity, in value units per second, the spring starts with. Zero unless given.
+    pub fn initial_velocity(&self) -> f64 {
+        self.name_and_variation()
+            .initial_velocity
+            .unwrap_or_default()
     }
 
-    #[test]
-    fn parse_rotate_with_variation() {
-        let cmd = AnimationPlan::parse(
-            "Animate settings: rotate 360 degrees using smooth-spatial vary blah:99 to blah:101",
-        )
-        .unwrap();
-        assert_eq!(
-            AnimationPlan::RotateDegrees(
-                ("settings", Spring::smooth_spatial(), "blah:99", "blah:101").into(),
-                360.0
-            ),
-            cmd
-        );
+    /// The same motion applied as `granularity` requires, None if the plan doesn't come in both versions
+    pub fn with_granularity(&self, granularity: Granularity) -> Option<Self> {
+        Some(match (self, granularity) {
+            (
+                AnimationPlan::PulseWhole(nv) | AnimationPlan::PulseParts(nv, _),
+                Granularity::Whole,
+            ) => AnimationPlan::PulseWhole(nv.clone()),
+            (AnimationPlan::PulseWhole(nv), Granularity::Parts) => {
+                AnimationPlan::PulseParts(nv.clone(), PartSelection::All)
+            }
+            (
+                AnimationPlan::TwirlWhole(nv) | AnimationPlan::TwirlParts(nv, _),
+                Granularity::Whole,
+            ) => AnimationPlan::TwirlWhole(nv.clone()),
+            (AnimationPlan::TwirlWhole(nv), Granularity::Parts) => {
+                AnimationPlan::TwirlParts(nv.clone(), PartSelection::All)
+            }
+            (AnimationPlan::PulseParts(..) | AnimationPlan::TwirlParts(..), Granularity::Parts) => {
+                self.clone()
+            }
+            _ => return None,
+        })
     }
 
-    #[test]
-    fn parse_minimal_twirl() {
-        let cmd = AnimationPlan::parse("Animate an_icon: twirl-whole").unwrap();
-        assert_eq!(AnimationPlan::TwirlWhole(("an_icon").into()), cmd);
+    /// The user locations to vary from and to, named instances are resolved against font
+    pub fn variation(&self, font: &FontRef) -> Result<(UserLocation, UserLocation), Error> {
+        if let AnimationPlan::Sweep(_, sweep) = self {
+            let tag = Tag::from_str(sweep.tag).map_err(Error::InvalidTag)?;
+            let (from, to) = (vec![(tag, sweep.from as f32)], vec![(tag, sweep.to as f32)]);
+            font_info::check_location(font, &from)?;
+            font_info::check_location(font, &to)?;
+            return Ok((from, to));
+        }
+        let nv = self.name_and_variation();
+        let from = nv
+            .vary_from
+            .map(|raw| font_info::parse_location(font, raw))
+            .unwrap_or_else(|| Ok(vec![]))?;
+        let to = nv
+            .vary_to
+            .map(|raw| font_info::parse_location(font, raw))
+            .unwrap_or_else(|| Ok(vec![]))?;
+        Ok((from, to))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AxisSweep, PartSelection};
 
     #[test]
-    fn parse_only_variation() {
-        let cmd = AnimationPlan::parse("Animate an_icon: vary FILL:0 to FILL:1").unwrap();
-        assert_eq!(
-            AnimationPlan::None(("an_icon", "FILL:0", "FILL:1").into()),
-            cmd
-        );
+    fn sweep_values() {
+        let sweep = AxisSweep {
+            tag: "wght",
+            from: 100.0,
+            to: 700.0,
+            steps: 3,
+        };
+        assert_eq!(vec![100.0, 300.0, 500.0, 700.0], sweep.values());
     }
 
     #[test]
-    fn parse_scale_with_variation_and_spring() {
-        let cmd = AnimationPlan::parse("Animate check_circle: scale 0 to 100 using expressive-spatial vary wght:400,FILL:1 to wght:700,FILL:0")
-            .unwrap();
+    fn select_parts() {
+        let areas = [4.0, 9.0, 1.0, 9.0];
+        let names = [Some("hand"), None, Some("face"), Some("Hand")];
+        assert_eq!(vec![0, 1, 2, 3], PartSelection::All.select(&areas, &names));
         assert_eq!(
-            AnimationPlan::ScaleFromTo(
-                (
-                    "check_circle",
-                    Spring::expressive_spatial(),
-                    "wght:400,FILL:1",
-                    "wght:700,FILL:0"
-                )
-                    .into(),
-                0.0,
-                100.0
-            ),
-            cmd
+            vec![2, 3],
+            PartSelection::Range(2, 6).select(&areas, &names)
         );
-    }
-
-    #[test]
-    fn parse_pulse_with_variation_and_spring() {
-        let cmd = AnimationPlan::parse("Animate close: pulse using standard vary FILL:0 to FILL:1")
-            .unwrap();
+        assert!(PartSelection::Range(5, 6).select(&areas, &names).is_empty());
+        assert_eq!(vec![3], PartSelection::Largest.select(&areas, &names));
+        assert_eq!(vec![2], PartSelection::Smallest.select(&areas, &names));
+        assert!(PartSelection::Largest.select(&[], &[]).is_empty());
         assert_eq!(
-            AnimationPlan::PulseParts(("close", Spring::standard(), "FILL:0", "FILL:1").into()),
-            cmd
+            vec![0, 3],
+            PartSelection::Named("hand").select(&areas, &names)
         );
     }
 }
@@ -0,0 +1,1204 @@
+//! The command language, e.g. `Animate settings: twirl`, parsed to an [`AnimationPlan`]
+
+use std::str::FromStr;
+
+use kurbo::Point;
+use skrifa::{raw::FontRef, MetadataProvider, Tag};
+
+use crate::{
+    bender::Bender, error::Error, ir::Ease, ligate::icon_name_to_gid, presets, scanner::Scanner,
+    spring::Spring, GlyphShape,
+};
+
+use super::{
+    AnimationPlan, AxisSweep, Channel, CompositeStep, NameAndVariation, PartSelection,
+    SequenceStep, UserLocation, SPRING_SCHEMES,
+};
+
+/// Pick a spring by name for `channel`. A scheme, "smooth" or "expressive", resolves to its
+/// spatial or non-spatial spring as suits the channel.
+fn spring_for_channel(name: &str, channel: Channel) -> Result<Spring, Error> {
+    match (name, channel.is_spatial()) {
+        ("smooth", true) => Ok(Spring::smooth_spatial()),
+        ("smooth", false) => Ok(Spring::smooth_non_spatial()),
+        ("expressive", true) => Ok(Spring::expressive_spatial()),
+        ("expressive", false) => Ok(Spring::expressive_non_spatial()),
+        _ => Spring::from_str(name).map_err(|_| Error::UnrecognizedSpring),
+    }
+}
+
+const CHANNELS: [Channel; 5] = [
+    Channel::Translate,
+    Channel::Scale,
+    Channel::Rotate,
+    Channel::Opacity,
+    Channel::Variation,
+];
+
+/// Parse the `(x1, y1, x2, y2)` that follows `cubic-bezier`, as for CSS. Time, x, must stay
+/// within 0..=1 for players to accept the ease.
+fn parse_cubic_bezier(s: &mut Scanner) -> Result<Ease, Error> {
+    s.expect_punct('(', "'(' and the points of a cubic-bezier")?;
+    let x1 = s.number_within("an x between 0 and 1", 0.0..=1.0)?;
+    s.expect_punct(',', "','")?;
+    let y1 = s.number("a y")?;
+    s.expect_punct(',', "','")?;
+    let x2 = s.number_within("an x between 0 and 1", 0.0..=1.0)?;
+    s.expect_punct(',', "','")?;
+    let y2 = s.number("a y")?;
+    s.expect_punct(')', "')'")?;
+    Ok(Ease {
+        p1: Point::new(x1, y1),
+        p2: Point::new(x2, y2),
+    })
+}
+
+/// Parse the optional `(amplitude)` that may follow the name of a [`Bender`], if `name` is one
+fn parse_bender(name: &str, s: &mut Scanner) -> Result<Option<Bender>, Error> {
+    let amplitude = |s: &mut Scanner, expected, range, default| -> Result<f64, Error> {
+        if !s.punct('(') {
+            return Ok(default);
+        }
+        let amplitude = s.number_within(expected, range)?;
+        s.expect_punct(')', "')'")?;
+        Ok(amplitude)
+    };
+    Ok(Some(match name {
+        "bounce" => Bender::Bounce,
+        "elastic" => Bender::Elastic {
+            amplitude: amplitude(
+                s,
+                "an amplitude between 1 and 10",
+                1.0..=10.0,
+                Bender::DEFAULT_ELASTIC_AMPLITUDE,
+            )?,
+        },
+        "overshoot" => Bender::Overshoot {
+            amplitude: amplitude(
+                s,
+                "an amplitude between 0 and 10",
+                0.0..=10.0,
+                Bender::DEFAULT_OVERSHOOT_AMPLITUDE,
+            )?,
+        },
+        _ => return Ok(None),
+    }))
+}
+
+/// Words that start a clause following a command, e.g. the `using` of `pulse using standard`
+pub(crate) const CLAUSES: [&str; 5] = ["using", "with", "vary", "then", "while"];
+
+fn is_clause(word: &str) -> bool {
+    CLAUSES
+        .iter()
+        .any(|clause| word.eq_ignore_ascii_case(clause))
+}
+
+impl<'a> NameAndVariation<'a> {
+    /// Parse the optional `using`, `with velocity` and `vary` clauses, in that order
+    fn parse<'s: 'a>(icon_name: &'a str, s: &mut Scanner<'s>) -> Result<Self, Error> {
+        let mut nv = NameAndVariation {
+            icon_name,
+            spring: None,
+            channel_springs: Vec::new(),
+            ease: None,
+            channel_eases: Vec::new(),
+            bender: None,
+            channel_benders: Vec::new(),
+            initial_velocity: None,
+            vary_from: None,
+            vary_to: None,
+        };
+        if s.keyword("using") {
+            nv.parse_using(s)?;
+        }
+        if s.keyword("with") {
+            s.expect_keyword("velocity")?;
+            nv.initial_velocity = Some(s.number("a velocity")?);
+        }
+        if s.keyword("vary") {
+            nv.vary_from = Some(s.chunk("a location to vary from")?);
+            s.expect_keyword("to")?;
+            nv.vary_to = Some(s.chunk("a location to vary to")?);
+        }
+        Ok(nv)
+    }
+
+    /// Parse the springs, eases or benders following "using", e.g. `standard`,
+    /// `expressive-spatial for scale, smooth-non-spatial for rotation`, `ease:emphasized`,
+    /// `cubic-bezier(0.4, 0, 0.2, 1) for rotation` or `overshoot(3) for scale`. Later choices win.
+    fn parse_using(&mut self, s: &mut Scanner) -> Result<(), Error> {
+        loop {
+            let name = s.word("a spring or cubic-bezier")?.to_ascii_lowercase();
+            let ease = if name == "cubic-bezier" {
+                Some(parse_cubic_bezier(s)?)
+            } else if name == "ease" && s.punct(':') {
+                let ease = s.word("an ease")?.to_ascii_lowercase();
+                Some(Ease::from_str(&ease)?)
+            } else {
+                None
+            };
+            let bender = parse_bender(&name, s)?;
+            if s.keyword("for") {
+                let channel = s.word("a channel")?.to_ascii_lowercase();
+                self.set_channel_timing(Channel::from_str(&channel)?, &name, ease, bender)?;
+            } else if ease.is_some() || bender.is_some() {
+                self.spring = None;
+                self.ease = ease;
+                self.bender = bender;
+            } else if SPRING_SCHEMES.contains(&name.as_str()) {
+                for channel in CHANNELS {
+                    self.set_channel_timing(channel, &name, None, None)?;
+                }
+            } else {
+                self.ease = None;
+                self.bender = None;
+                self.spring = Some(Spring::from_str(&name).map_err(|_| Error::UnrecognizedSpring)?);
+            }
+            if !s.punct(',') {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Animate `channel` with `ease` or `bender` if given, else the spring `name`, replacing any
+    /// prior choice
+    fn set_channel_timing(
+        &mut self,
+        channel: Channel,
+        name: &str,
+        ease: Option<Ease>,
+        bender: Option<Bender>,
+    ) -> Result<(), Error> {
+        self.channel_springs.retain(|(c, _)| *c != channel);
+        self.channel_eases.retain(|(c, _)| *c != channel);
+        self.channel_benders.retain(|(c, _)| *c != channel);
+        match (ease, bender) {
+            (Some(ease), _) => self.channel_eases.push((channel, ease)),
+            (None, Some(bender)) => self.channel_benders.push((channel, bender)),
+            (None, None) => self
+                .channel_springs
+                .push((channel, spring_for_channel(name, channel)?)),
+        }
+        Ok(())
+    }
+}
+
+/// Frames per step of a [`AnimationPlan::Sequence`] if not specified
+const DEFAULT_STEP_FRAMES: f64 = 30.0;
+
+impl<'a> SequenceStep<'a> {
+    /// Parse a comma separated list of steps, each "icon_name [frames] [ease]"
+    fn parse_all<'s: 'a>(s: &mut Scanner<'s>) -> Result<Vec<Self>, Error> {
+        let mut steps = vec![Self::parse(s)?];
+        while s.punct(',') {
+            steps.push(Self::parse(s)?);
+        }
+        Ok(steps)
+    }
+
+    fn parse<'s: 'a>(s: &mut Scanner<'s>) -> Result<Self, Error> {
+        let icon_name = s.name("an icon name")?;
+        let mut step = SequenceStep {
+            icon_name,
+            frames: DEFAULT_STEP_FRAMES,
+            ease: None,
+        };
+        if s.at_number() {
+            let frames = s.number("frames")?;
+            if frames <= 0.0 {
+                return Err(Error::InvalidSequenceStep(format!("{icon_name} {frames}")));
+            }
+            step.frames = frames;
+        }
+        if s.keyword("cubic-bezier") {
+            step.ease = Some(parse_cubic_bezier(s)?);
+        } else if let Some(ease) = s.peek_word().filter(|word| !is_clause(word)) {
+            step.ease = Some(Ease::from_str(&ease.to_ascii_lowercase())?);
+            s.word("an ease")?;
+        }
+        Ok(step)
+    }
+}
+
+/// Steps of a [`AnimationPlan::Sweep`] if not specified
+const DEFAULT_SWEEP_STEPS: usize = 10;
+
+impl<'a> PartSelection<'a> {
+    /// Parse an optional `part <name>`, `parts <n>[..<m>]`, `largest` or `smallest`
+    fn parse<'s: 'a>(s: &mut Scanner<'s>) -> Result<Self, Error> {
+        if s.keyword("part") {
+            return Ok(PartSelection::Named(s.name("a part name")?));
+        }
+        if s.keyword("largest") {
+            return Ok(PartSelection::Largest);
+        }
+        if s.keyword("smallest") {
+            return Ok(PartSelection::Smallest);
+        }
+        if !s.keyword("parts") {
+            return Ok(PartSelection::All);
+        }
+        let start = s.whole("a part number", 0)?;
+        let end = if s.symbol("..") {
+            s.whole("a part number past the first", start + 1)?
+        } else {
+            start + 1
+        };
+        Ok(PartSelection::Range(start, end))
+    }
+}
+
+/// The variation of plans that share an icon. A spring, ease, bender or variation may be given on
+/// any of them, the first given wins.
+fn shared_variation<'a, 'b: 'a>(
+    icon_name: &'b str,
+    plans: impl Iterator<Item = &'a AnimationPlan<'b>>,
+) -> Result<NameAndVariation<'b>, Error> {
+    plans
+        .map(AnimationPlan::name_and_variation)
+        .fold(None, |nv: Option<NameAndVariation>, next| {
+            Some(match nv {
+                None => next.clone(),
+                Some(nv) => NameAndVariation {
+                    icon_name,
+                    spring: nv
+                        .spring
+                        .or(next.spring)
+                        .filter(|_| nv.ease.is_none() && nv.bender.is_none()),
+                    ease: nv
+                        .ease
+                        .or(next.ease)
+                        .filter(|_| nv.spring.is_none() && nv.bender.is_none()),
+                    bender: nv
+                        .bender
+                        .or(next.bender)
+                        .filter(|_| nv.spring.is_none() && nv.ease.is_none()),
+                    channel_springs: nv
+                        .channel_springs
+                        .iter()
+                        .chain(
+                            next.channel_springs
+                                .iter()
+                                .filter(|(channel, _)| !nv.times_channel(*channel)),
+                        )
+                        .copied()
+                        .collect(),
+                    channel_eases: nv
+                        .channel_eases
+                        .iter()
+                        .chain(
+                            next.channel_eases
+                                .iter()
+                                .filter(|(channel, _)| !nv.times_channel(*channel)),
+                        )
+                        .copied()
+                        .collect(),
+                    channel_benders: nv
+                        .channel_benders
+                        .iter()
+                        .chain(
+                            next.channel_benders
+                                .iter()
+                                .filter(|(channel, _)| !nv.times_channel(*channel)),
+                        )
+                        .copied()
+                        .collect(),
+                    initial_velocity: nv.initial_velocity.or(next.initial_velocity),
+                    vary_from: nv.vary_from.or(next.vary_from),
+                    vary_to: nv.vary_to.or(next.vary_to),
+                },
+            })
+        })
+        .ok_or(Error::UnrecognizedCommand)
+}
+
+impl<'a> AnimationPlan<'a> {
+    pub(crate) fn parse(animation: &str) -> Result<AnimationPlan, Error> {
+        let mut s = Scanner::new(animation);
+        s.expect_keyword("animate")?;
+        let icon_name = s.name("an icon name")?;
+        s.expect_punct(':', "':' after the icon name")?;
+        let plan = Self::parse_steps(icon_name, &mut s)?;
+        s.expect_end()?;
+        Ok(plan)
+    }
+
+    /// Parse one or more plans, or holds, separated by "then"
+    fn parse_steps<'b, 's: 'b>(
+        icon_name: &'b str,
+        s: &mut Scanner<'s>,
+    ) -> Result<AnimationPlan<'b>, Error> {
+        let mut steps = Vec::new();
+        loop {
+            let start = s.position();
+            let step = if s.keyword("hold") {
+                CompositeStep::Hold(s.number("frames to hold")?)
+            } else {
+                CompositeStep::Plan(Self::parse_channels(icon_name, s)?)
+            };
+            steps.push((s.since(start), step));
+            if !s.keyword("then") {
+                break;
+            }
+        }
+        if steps.len() == 1 {
+            return match steps.remove(0) {
+                (_, CompositeStep::Plan(plan)) => Ok(plan),
+                (_, CompositeStep::Hold(..)) => Err(Error::UnrecognizedCommand),
+            };
+        }
+        if let Some((segment, _)) = steps.iter().find(|(_, step)| match step {
+            CompositeStep::Plan(plan) => !plan.is_chainable(),
+            CompositeStep::Hold(..) => false,
+        }) {
+            return Err(Error::NotChainable(segment.to_string()));
+        }
+        let steps: Vec<_> = steps.into_iter().map(|(_, step)| step).collect();
+        let nv = shared_variation(
+            icon_name,
+            steps.iter().filter_map(|step| match step {
+                CompositeStep::Plan(plan) => Some(plan),
+                CompositeStep::Hold(..) => None,
+            }),
+        )?;
+        Ok(AnimationPlan::Composite(nv, steps))
+    }
+
+    /// Parse one or more plans separated by "while"
+    fn parse_channels<'b, 's: 'b>(
+        icon_name: &'b str,
+        s: &mut Scanner<'s>,
+    ) -> Result<AnimationPlan<'b>, Error> {
+        let mut channels = Vec::new();
+        loop {
+            let start = s.position();
+            let plan = Self::parse_body(icon_name, s)?;
+            channels.push((s.since(start), plan));
+            if !s.keyword("while") {
+                break;
+            }
+        }
+        if channels.len() == 1 {
+            return Ok(channels.remove(0).1);
+        }
+        if let Some((channel, _)) = channels.iter().find(|(_, plan)| !plan.is_chainable()) {
+            return Err(Error::NotChainable(channel.to_string()));
+        }
+        let channels: Vec<_> = channels.into_iter().map(|(_, plan)| plan).collect();
+        let nv = shared_variation(icon_name, channels.iter())?;
+        Ok(AnimationPlan::Parallel(nv, channels))
+    }
+
+    /// Parse one plan, e.g. `rotate 90 degrees using standard`
+    fn parse_body<'b, 's: 'b>(
+        icon_name: &'b str,
+        s: &mut Scanner<'s>,
+    ) -> Result<AnimationPlan<'b>, Error> {
+        const NAMED: [&str; 6] = [
+            "pulse",
+            "pulse-whole",
+            "twirl",
+            "twirl-whole",
+            "standard-enter",
+            "standard-exit",
+        ];
+
+        Ok(if s.keyword("preset") {
+            let name = s.word("a preset name")?.to_ascii_lowercase();
+            let preset = presets::preset(&name).ok_or(Error::UnknownPreset(name))?;
+            let mut preset_command = Scanner::new(preset.command);
+            let plan = Self::parse_channels(icon_name, &mut preset_command)?;
+            preset_command.expect_end()?;
+            plan
+        } else if s.keyword("rotate") {
+            let degrees = s.number("degrees to rotate")?;
+            s.expect_keyword("degrees")?;
+            AnimationPlan::RotateDegrees(NameAndVariation::parse(icon_name, s)?, degrees)
+        } else if s.keyword("scale") {
+            let from = s.number("a scale to start from")?;
+            s.expect_keyword("to")?;
+            let to = s.number("a scale to end at")?;
+            AnimationPlan::ScaleFromTo(NameAndVariation::parse(icon_name, s)?, from, to)
+        } else if s.keyword("squash") {
+            let percent = s.number_within("a squash between 0 and 90 percent", 0.0..=90.0)?;
+            AnimationPlan::SquashAndStretch(NameAndVariation::parse(icon_name, s)?, percent)
+        } else if s.keyword("sequence") {
+            let steps = SequenceStep::parse_all(s)?;
+            AnimationPlan::Sequence(NameAndVariation::parse(icon_name, s)?, steps)
+        } else if s.keyword("sweep") {
+            let tag = match s.peek_word() {
+                Some(tag) if tag.len() == 4 => s.word("an axis tag")?,
+                _ => return Err(s.error("a four letter axis tag")),
+            };
+            let from = s.number("a value to sweep from")?;
+            s.expect_keyword("to")?;
+            let to = s.number("a value to sweep to")?;
+            let steps = if s.keyword("in") {
+                let steps = s.count("a number of steps")?;
+                s.expect_keyword("steps")?;
+                steps
+            } else {
+                DEFAULT_SWEEP_STEPS
+            };
+            let nv = NameAndVariation {
+                icon_name,
+                spring: None,
+                channel_springs: Vec::new(),
+                ease: None,
+                channel_eases: Vec::new(),
+                bender: None,
+                channel_benders: Vec::new(),
+                initial_velocity: None,
+                vary_from: None,
+                vary_to: None,
+            };
+            AnimationPlan::Sweep(
+                nv,
+                AxisSweep {
+                    tag,
+                    from,
+                    to,
+                    steps,
+                },
+            )
+        } else {
+            let command = match s.peek_word() {
+                Some(word) if NAMED.iter().any(|name| word.eq_ignore_ascii_case(name)) => {
+                    s.word("a command")?.to_ascii_lowercase()
+                }
+                Some(word) if !is_clause(word) => return Err(s.error("a command")),
+                _ => "none".to_string(),
+            };
+            let parts = if matches!(command.as_str(), "pulse" | "twirl") {
+                PartSelection::parse(s)?
+            } else {
+                PartSelection::All
+            };
+            let nv = NameAndVariation::parse(icon_name, s)?;
+            match command.as_str() {
+                "none" => AnimationPlan::None(nv),
+                "pulse" => AnimationPlan::PulseParts(nv, parts),
+                "pulse-whole" => AnimationPlan::PulseWhole(nv),
+                "twirl" => AnimationPlan::TwirlParts(nv, parts),
+                "twirl-whole" => AnimationPlan::TwirlWhole(nv),
+                "standard-enter" => AnimationPlan::StandardEnter(nv),
+                "standard-exit" => AnimationPlan::StandardExit(nv),
+                _ => return Err(Error::UnrecognizedCommand),
+            }
+        })
+    }
+
+    /// Whether the plan can be one step of a composite or one channel of a parallel plan
+    fn is_chainable(&self) -> bool {
+        !matches!(self, AnimationPlan::Sequence(..) | AnimationPlan::Sweep(..))
+    }
+}
+
+/// The user locations between those of [`AnimationPlan::variation`] to draw at, each step of a
+/// [`AnimationPlan::Sweep`]; empty for anything else
+fn interior_variation(plan: &AnimationPlan) -> Result<Vec<UserLocation>, Error> {
+    let AnimationPlan::Sweep(_, sweep) = plan else {
+        return Ok(Vec::new());
+    };
+    let tag = Tag::from_str(sweep.tag).map_err(Error::InvalidTag)?;
+    let values = sweep.values();
+    Ok(values[1..values.len() - 1]
+        .iter()
+        .map(|value| vec![(tag, *value as f32)])
+        .collect())
+}
+
+/// Parse `command`, e.g. `Animate settings: twirl`, and find the icon it names in `font`
+pub fn parse_plan<'a, 'b>(
+    font: &'a FontRef,
+    command: &'b str,
+) -> Result<(AnimationPlan<'b>, GlyphShape<'a>), Error> {
+    let command = AnimationPlan::parse(command)?;
+
+    let gid = icon_name_to_gid(font, command.icon_name()).map_err(Error::IconNameError)?;
+    // Look up the icons a sequence morphs through now, so an unknown one is reported as such
+    if let AnimationPlan::Sequence(_, steps) = &command {
+        for step in steps {
+            icon_name_to_gid(font, step.icon_name).map_err(Error::IconNameError)?;
+        }
+    }
+
+    let (raw_from, raw_to) = command.variation(font)?;
+    let from = font.axes().location(raw_from);
+    let to = font.axes().location(raw_to);
+
+    let steps = interior_variation(&command)?
+        .into_iter()
+        .map(|location| font.axes().location(location))
+        .collect();
+
+    let glyph_shape = GlyphShape::new(font, gid, from, Some(to))?.with_steps(steps);
+
+    Ok((command, glyph_shape))
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::Point;
+
+    use crate::{
+        bender::Bender,
+        error::Error,
+        ir::Ease,
+        plan::{
+            AnimationPlan, AxisSweep, Channel, CompositeStep, Granularity, NameAndVariation,
+            PartSelection, SequenceStep, BENT_TRANSITION_FRAMES,
+        },
+        presets::PRESETS,
+        spring::Spring,
+    };
+
+    use super::{DEFAULT_STEP_FRAMES, DEFAULT_SWEEP_STEPS};
+
+    impl<'a> From<&'a str> for NameAndVariation<'a> {
+        fn from(icon_name: &'a str) -> Self {
+            NameAndVariation {
+                icon_name,
+                spring: None,
+                channel_springs: vec![],
+                ease: None,
+                channel_eases: vec![],
+                bender: None,
+                channel_benders: vec![],
+                initial_velocity: None,
+                vary_from: None,
+                vary_to: None,
+            }
+        }
+    }
+
+    impl<'a> From<(&'a str, Spring)> for NameAndVariation<'a> {
+        fn from(value: (&'a str, Spring)) -> Self {
+            NameAndVariation {
+                icon_name: value.0,
+                spring: Some(value.1),
+                channel_springs: vec![],
+                ease: None,
+                channel_eases: vec![],
+                bender: None,
+                channel_benders: vec![],
+                initial_velocity: None,
+                vary_from: None,
+                vary_to: None,
+            }
+        }
+    }
+
+    impl<'a> From<(&'a str, &'a str, &'a str)> for NameAndVariation<'a> {
+        fn from(value: (&'a str, &'a str, &'a str)) -> Self {
+            NameAndVariation {
+                icon_name: value.0,
+                spring: None,
+                channel_springs: vec![],
+                ease: None,
+                channel_eases: vec![],
+                bender: None,
+                channel_benders: vec![],
+                initial_velocity: None,
+                vary_from: Some(value.1),
+                vary_to: Some(value.2),
+            }
+        }
+    }
+
+    impl<'a> From<(&'a str, Spring, &'a str, &'a str)> for NameAndVariation<'a> {
+        fn from(value: (&'a str, Spring, &'a str, &'a str)) -> Self {
+            NameAndVariation {
+                icon_name: value.0,
+                spring: Some(value.1),
+                channel_springs: vec![],
+                ease: None,
+                channel_eases: vec![],
+                bender: None,
+                channel_benders: vec![],
+                initial_velocity: None,
+                vary_from: Some(value.2),
+                vary_to: Some(value.3),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_rotate_with_spring() {
+        let cmd =
+            AnimationPlan::parse("Animate settings: rotate 360 degrees using expressive-spatial")
+                .unwrap();
+        assert_eq!(
+            AnimationPlan::RotateDegrees(("settings", Spring::expressive_spatial()).into(), 360.0),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_scale() {
+        let cmd = AnimationPlan::parse("Animate check_circle: scale 0 to 100").unwrap();
+        assert_eq!(
+            AnimationPlan::ScaleFromTo(("check_circle").into(), 0.0, 100.0),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_squash() {
+        let cmd = AnimationPlan::parse("Animate check_circle: squash 30").unwrap();
+        assert_eq!(
+            AnimationPlan::SquashAndStretch(("check_circle").into(), 30.0),
+            cmd
+        );
+        // Squashed flat it would need to be infinitely wide
+        assert!(matches!(
+            AnimationPlan::parse("Animate check_circle: squash 100"),
+            Err(Error::Syntax { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_pulse() {
+        let cmd = AnimationPlan::parse("Animate close: pulse").unwrap();
+        assert_eq!(
+            AnimationPlan::PulseParts(("close").into(), PartSelection::All),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_rotate_with_variation() {
+        let cmd = AnimationPlan::parse(
+            "Animate settings: rotate 360 degrees using smooth-spatial vary blah:99 to blah:101",
+        )
+        .unwrap();
+        assert_eq!(
+            AnimationPlan::RotateDegrees(
+                ("settings", Spring::smooth_spatial(), "blah:99", "blah:101").into(),
+                360.0
+            ),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_minimal_twirl() {
+        let cmd = AnimationPlan::parse("Animate an_icon: twirl-whole").unwrap();
+        assert_eq!(AnimationPlan::TwirlWhole(("an_icon").into()), cmd);
+    }
+
+    #[test]
+    fn whole_and_parts_of_twirl() {
+        let cmd = AnimationPlan::parse("Animate an_icon: twirl").unwrap();
+        assert_eq!(
+            (
+                Some(AnimationPlan::TwirlWhole(("an_icon").into())),
+                Some(AnimationPlan::TwirlParts(
+                    ("an_icon").into(),
+                    PartSelection::All
+                ))
+            ),
+            (
+                cmd.with_granularity(Granularity::Whole),
+                cmd.with_granularity(Granularity::Parts)
+            )
+        );
+    }
+
+    #[test]
+    fn rotate_has_no_parts() {
+        let cmd = AnimationPlan::parse("Animate an_icon: rotate 90 degrees").unwrap();
+        assert_eq!(None, cmd.with_granularity(Granularity::Parts));
+    }
+
+    #[test]
+    fn parse_standard_enter_and_exit() {
+        let cmd = AnimationPlan::parse("Animate an_icon: standard-enter").unwrap();
+        assert_eq!(AnimationPlan::StandardEnter(("an_icon").into()), cmd);
+        let cmd = AnimationPlan::parse("Animate an_icon: standard-exit").unwrap();
+        assert_eq!(AnimationPlan::StandardExit(("an_icon").into()), cmd);
+    }
+
+    #[test]
+    fn parse_composite() {
+        let cmd =
+            AnimationPlan::parse("Animate an_icon: twirl then hold 10 then pulse-whole").unwrap();
+        assert_eq!(
+            AnimationPlan::Composite(
+                ("an_icon").into(),
+                vec![
+                    CompositeStep::Plan(AnimationPlan::TwirlParts(
+                        ("an_icon").into(),
+                        PartSelection::All
+                    )),
+                    CompositeStep::Hold(10.0),
+                    CompositeStep::Plan(AnimationPlan::PulseWhole(("an_icon").into())),
+                ]
+            ),
+            cmd
+        );
+        assert_eq!(130.0, cmd.frames());
+        assert_eq!(Granularity::Parts, cmd.granularity());
+    }
+
+    #[test]
+    fn composite_shares_variation() {
+        let cmd = AnimationPlan::parse("Animate an_icon: pulse then twirl vary FILL:0 to FILL:1")
+            .unwrap();
+        let AnimationPlan::Composite(nv, _) = cmd else {
+            panic!("Should be composite, is {cmd:?}");
+        };
+        assert_eq!(NameAndVariation::from(("an_icon", "FILL:0", "FILL:1")), nv);
+    }
+
+    #[test]
+    fn parse_parallel() {
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: rotate 90 degrees using expressive-spatial while scale 80 to 100 vary FILL:0 to FILL:1",
+        )
+        .unwrap();
+        assert_eq!(
+            AnimationPlan::Parallel(
+                ("an_icon", Spring::expressive_spatial(), "FILL:0", "FILL:1").into(),
+                vec![
+                    AnimationPlan::RotateDegrees(
+                        ("an_icon", Spring::expressive_spatial()).into(),
+                        90.0
+                    ),
+                    AnimationPlan::ScaleFromTo(("an_icon", "FILL:0", "FILL:1").into(), 80.0, 100.0),
+                ]
+            ),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parallel_within_composite() {
+        let cmd =
+            AnimationPlan::parse("Animate an_icon: twirl while pulse then standard-exit").unwrap();
+        let AnimationPlan::Composite(_, steps) = &cmd else {
+            panic!("Should be composite, is {cmd:?}");
+        };
+        assert!(matches!(
+            steps[0],
+            CompositeStep::Plan(AnimationPlan::Parallel(..))
+        ));
+        assert_eq!(60.0 + 18.0, cmd.frames());
+    }
+
+    #[test]
+    fn parse_channel_springs() {
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: scale 80 to 100 using expressive-spatial for scale, smooth-non-spatial for rotation",
+        )
+        .unwrap();
+        assert_eq!(None, cmd.spring());
+        assert_eq!(Spring::expressive_spatial(), cmd.spring_for(Channel::Scale));
+        assert_eq!(
+            Spring::smooth_non_spatial(),
+            cmd.spring_for(Channel::Rotate)
+        );
+        assert_eq!(Spring::standard(), cmd.spring_for(Channel::Opacity));
+    }
+
+    #[test]
+    fn channel_springs_override_plan_spring() {
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: rotate 90 degrees using standard, expressive for rotate",
+        )
+        .unwrap();
+        assert_eq!(Some(Spring::standard()), cmd.spring());
+        assert_eq!(
+            Spring::expressive_spatial(),
+            cmd.spring_for(Channel::Rotate)
+        );
+        assert_eq!(Spring::standard(), cmd.spring_for(Channel::Scale));
+    }
+
+    #[test]
+    fn parse_cubic_bezier_ease() {
+        let material = Ease {
+            p1: Point::new(0.4, 0.0),
+            p2: Point::new(0.2, 1.0),
+        };
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: rotate 90 degrees using cubic-bezier( 0.4, 0,0.2 , 1 )",
+        )
+        .unwrap();
+        assert_eq!(None, cmd.spring());
+        assert_eq!(Some(material), cmd.ease_for(Channel::Rotate));
+        assert_eq!(18.0, cmd.frames());
+
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: standard-enter using cubic-bezier(0.4, 0, 0.2, 1), expressive for opacity",
+        )
+        .unwrap();
+        assert_eq!(Some(material), cmd.ease_for(Channel::Scale));
+        assert_eq!(None, cmd.ease_for(Channel::Opacity));
+
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: scale 80 to 100 using standard, cubic-bezier(0, 0, 1, 1) for scale",
+        )
+        .unwrap();
+        assert_eq!(Some(Ease::LINEAR), cmd.ease_for(Channel::Scale));
+        assert_eq!(None, cmd.ease_for(Channel::Rotate));
+    }
+
+    #[test]
+    fn parse_named_ease() {
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: standard-exit using ease:Emphasized, ease : accelerate for opacity",
+        )
+        .unwrap();
+        assert_eq!(Some(Ease::EMPHASIZED), cmd.ease_for(Channel::Scale));
+        assert_eq!(Some(Ease::ACCELERATE), cmd.ease_for(Channel::Opacity));
+        assert!(matches!(
+            AnimationPlan::parse("Animate an_icon: pulse using ease:bouncy"),
+            Err(Error::UnrecognizedEase(..))
+        ));
+    }
+
+    #[test]
+    fn cubic_bezier_time_stays_within_zero_to_one() {
+        let Err(Error::Syntax {
+            position, expected, ..
+        }) = AnimationPlan::parse("Animate an_icon: pulse using cubic-bezier(0.4, 0, 1.2, 1)")
+        else {
+            panic!("Should be a syntax error");
+        };
+        assert_eq!((50, "an x between 0 and 1"), (position, expected));
+        // y may overshoot
+        assert!(AnimationPlan::parse(
+            "Animate an_icon: pulse using cubic-bezier(0.3, -0.5, 0.6, 1.5)"
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn parse_benders() {
+        let cmd = AnimationPlan::parse(
+            "Animate an_icon: scale 80 to 100 using standard, elastic(1.5) for scale, overshoot for opacity",
+        )
+        .unwrap();
+        assert_eq!(
+            Some(Bender::Elastic { amplitude: 1.5 }),
+            cmd.bender_for(Channel::Scale)
+        );
+        assert_eq!(
+            Some(Bender::Overshoot {
+                amplitude: Bender::DEFAULT_OVERSHOOT_AMPLITUDE
+            }),
+            cmd.bender_for(Channel::Opacity)
+        );
+        assert_eq!(None, cmd.bender_for(Channel::Rotate));
+        assert_eq!(Some(Spring::standard()), cmd.spring());
+        assert_eq!(BENT_TRANSITION_FRAMES, cmd.frames());
+
+        // A later plan-wide choice replaces the bender
+        let cmd =
+            AnimationPlan::parse("Animate an_icon: rotate 90 degrees using bounce, ease:standard")
+                .unwrap();
+        assert_eq!(None, cmd.bender_for(Channel::Rotate));
+        assert_eq!(Some(Ease::STANDARD), cmd.ease_for(Channel::Rotate));
+
+        let Err(Error::Syntax { expected, .. }) =
+            AnimationPlan::parse("Animate an_icon: twirl using elastic(0.5)")
+        else {
+            panic!("Elastic amplitude should be at least 1");
+        };
+        assert_eq!("an amplitude between 1 and 10", expected);
+    }
+
+    #[test]
+    fn spring_scheme_follows_channel() {
+        let cmd = AnimationPlan::parse("Animate an_icon: standard-enter using smooth").unwrap();
+        assert_eq!(Spring::smooth_spatial(), cmd.spring_for(Channel::Scale));
+        assert_eq!(Spring::smooth_spatial(), cmd.spring_for(Channel::Translate));
+        assert_eq!(
+            Spring::smooth_non_spatial(),
+            cmd.spring_for(Channel::Opacity)
+        );
+    }
+
+    #[test]
+    fn unrecognized_channel() {
+        assert!(matches!(
+            AnimationPlan::parse("Animate an_icon: pulse using standard for wobble"),
+            Err(Error::UnrecognizedChannel(..))
+        ));
+    }
+
+    #[test]
+    fn parse_preset() {
+        assert_eq!(
+            AnimationPlan::parse("Animate close: scale 80 to 100 using expressive-spatial")
+                .unwrap(),
+            AnimationPlan::parse("Animate close: preset emphasize").unwrap(),
+        );
+    }
+
+    #[test]
+    fn every_preset_parses() {
+        for preset in PRESETS {
+            let command = format!("Animate an_icon: preset {}", preset.name);
+            assert!(
+                AnimationPlan::parse(&command).is_ok(),
+                "{} doesn't parse",
+                preset.name
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_preset() {
+        assert!(matches!(
+            AnimationPlan::parse("Animate an_icon: preset wiggle"),
+            Err(Error::UnknownPreset(..))
+        ));
+    }
+
+    #[test]
+    fn composite_segments() {
+        let cmd = AnimationPlan::parse("Animate an_icon: twirl then hold 10 then twirl then pulse")
+            .unwrap();
+        assert_eq!(
+            vec![
+                ("twirl", 0.0, 60.0),
+                ("hold", 60.0, 70.0),
+                ("twirl-2", 70.0, 130.0),
+                ("pulse", 130.0, 190.0),
+            ],
+            cmd.segments()
+                .iter()
+                .map(|s| (s.name.as_str(), s.start, s.end))
+                .collect::<Vec<_>>()
+        );
+        assert!(AnimationPlan::parse("Animate an_icon: twirl")
+            .unwrap()
+            .segments()
+            .is_empty());
+    }
+
+    #[test]
+    fn sequence_is_not_chainable() {
+        assert!(matches!(
+            AnimationPlan::parse("Animate an_icon: pulse then sequence menu"),
+            Err(Error::NotChainable(..))
+        ));
+    }
+
+    #[test]
+    fn parse_only_variation() {
+        let cmd = AnimationPlan::parse("Animate an_icon: vary FILL:0 to FILL:1").unwrap();
+        assert_eq!(
+            AnimationPlan::None(("an_icon", "FILL:0", "FILL:1").into()),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_scale_with_variation_and_spring() {
+        let cmd = AnimationPlan::parse("Animate check_circle: scale 0 to 100 using expressive-spatial vary wght:400,FILL:1 to wght:700,FILL:0")
+            .unwrap();
+        assert_eq!(
+            AnimationPlan::ScaleFromTo(
+                (
+                    "check_circle",
+                    Spring::expressive_spatial(),
+                    "wght:400,FILL:1",
+                    "wght:700,FILL:0"
+                )
+                    .into(),
+                0.0,
+                100.0
+            ),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_pulse_with_variation_and_spring() {
+        let cmd = AnimationPlan::parse("Animate close: pulse using standard vary FILL:0 to FILL:1")
+            .unwrap();
+        assert_eq!(
+            AnimationPlan::PulseParts(
+                ("close", Spring::standard(), "FILL:0", "FILL:1").into(),
+                PartSelection::All,
+            ),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_scale_with_velocity() {
+        let cmd = AnimationPlan::parse(
+            "Animate check_circle: scale 0 to 100 using standard with velocity -250.5",
+        )
+        .unwrap();
+        assert_eq!(-250.5, cmd.initial_velocity());
+        assert_eq!(Some(Spring::standard()), cmd.spring());
+    }
+
+    #[test]
+    fn velocity_defaults_to_zero() {
+        let cmd = AnimationPlan::parse("Animate close: pulse using standard").unwrap();
+        assert_eq!(0.0, cmd.initial_velocity());
+    }
+
+    #[test]
+    fn parse_sweep() {
+        assert_eq!(
+            AnimationPlan::Sweep(
+                "settings".into(),
+                AxisSweep {
+                    tag: "wght",
+                    from: 100.0,
+                    to: 700.0,
+                    steps: 6,
+                }
+            ),
+            AnimationPlan::parse("Animate settings: sweep wght 100 to 700 in 6 steps").unwrap()
+        );
+        let AnimationPlan::Sweep(_, sweep) =
+            AnimationPlan::parse("Animate settings: sweep FILL 0 to 1").unwrap()
+        else {
+            panic!("Should be a sweep");
+        };
+        assert_eq!(DEFAULT_SWEEP_STEPS, sweep.steps);
+    }
+
+    #[test]
+    fn parse_part_selection() {
+        for (command, parts) in [
+            ("twirl parts 2..4", PartSelection::Range(2, 4)),
+            ("twirl parts 0 using standard", PartSelection::Range(0, 1)),
+            ("pulse LARGEST", PartSelection::Largest),
+            ("pulse smallest then twirl", PartSelection::Smallest),
+            (
+                "twirl part \"hour hand\"",
+                PartSelection::Named("hour hand"),
+            ),
+        ] {
+            let command = format!("Animate an_icon: {command}");
+            let plan = AnimationPlan::parse(&command).unwrap();
+            let plan = match plan {
+                AnimationPlan::Composite(_, steps) => match &steps[0] {
+                    CompositeStep::Plan(plan) => plan.clone(),
+                    CompositeStep::Hold(..) => panic!("{command}"),
+                },
+                plan => plan,
+            };
+            let (AnimationPlan::PulseParts(_, actual) | AnimationPlan::TwirlParts(_, actual)) =
+                plan
+            else {
+                panic!("{command}: {plan:?}");
+            };
+            assert_eq!(parts, actual, "{command}");
+        }
+        for command in ["twirl parts 3..3", "twirl parts", "pulse-whole largest"] {
+            let command = format!("Animate an_icon: {command}");
+            assert!(
+                matches!(AnimationPlan::parse(&command), Err(Error::Syntax { .. })),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn sweep_is_not_chainable() {
+        assert!(matches!(
+            AnimationPlan::parse("Animate settings: sweep wght 100 to 700 then pulse"),
+            Err(Error::NotChainable(..))
+        ));
+    }
+
+    #[test]
+    fn parse_sequence() {
+        let cmd = AnimationPlan::parse(
+            "Animate volume_mute: sequence volume_down, volume_up 20 linear using standard",
+        )
+        .unwrap();
+        assert_eq!(
+            AnimationPlan::Sequence(
+                ("volume_mute", Spring::standard()).into(),
+                vec![
+                    SequenceStep {
+                        icon_name: "volume_down",
+                        frames: DEFAULT_STEP_FRAMES,
+                        ease: None,
+                    },
+                    SequenceStep {
+                        icon_name: "volume_up",
+                        frames: 20.0,
+                        ease: Some(Ease::LINEAR),
+                    },
+                ]
+            ),
+            cmd
+        );
+    }
+
+    #[test]
+    fn parse_sequence_bad_step() {
+        assert!(
+            AnimationPlan::parse("Animate volume_mute: sequence volume_down 20 linear extra")
+                .is_err()
+        );
+        assert!(
+            AnimationPlan::parse("Animate volume_mute: sequence volume_down, , volume_up").is_err()
+        );
+    }
+
+    #[test]
+    fn parse_ignores_spacing_and_case() {
+        assert_eq!(
+            AnimationPlan::parse("Animate settings: rotate 360 degrees using expressive-spatial")
+                .unwrap(),
+            AnimationPlan::parse(
+                "  ANIMATE settings :rotate   360 Degrees\tUSING Expressive-Spatial  "
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn parse_quoted_icon_names() {
+        let cmd = AnimationPlan::parse(r#"Animate "10k": sequence "looks_3" 20, looks_4"#).unwrap();
+        let AnimationPlan::Sequence(nv, steps) = cmd else {
+            panic!("Should be a sequence, is {cmd:?}");
+        };
+        assert_eq!("10k", nv.icon_name);
+        assert_eq!(
+            vec![("looks_3", 20.0), ("looks_4", DEFAULT_STEP_FRAMES)],
+            steps
+                .iter()
+                .map(|step| (step.icon_name, step.frames))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn syntax_errors_say_where() {
+        let Err(Error::Syntax {
+            position,
+            expected,
+            found,
+        }) = AnimationPlan::parse("Animate an_icon: rotate ninety degrees")
+        else {
+            panic!("Should be a syntax error");
+        };
+        assert_eq!(
+            (24, "degrees to rotate", "'ninety'"),
+            (position, expected, found.as_str())
+        );
+        assert!(matches!(
+            AnimationPlan::parse("Animate an_icon: pulse using standard extra"),
+            Err(Error::Syntax { position: 38, .. })
+        ));
+        assert!(matches!(
+            AnimationPlan::parse(r#"Animate "an_icon: pulse"#),
+            Err(Error::Syntax { position: 8, .. })
+        ));
+    }
+}
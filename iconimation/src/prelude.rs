@@ -0,0 +1,15 @@
+//! What most users of the crate need, `use iconimation::prelude::*;`
+//!
+//! The IR, to read or build animations with, and the functions that output it. See the crate
+//! docs for what is stable.
+
+pub use crate::{
+    android::{to_avd, AndroidOptions, AnimatedVectorDrawable},
+    error::Error,
+    ir::{
+        Animation, ColorStop, Ease, Element, FromAnimation, Gradient, Group, IrVisitor, Keyframe,
+        Keyframed, KeyframesMut, Segment, Stroke,
+    },
+    lottie::{lottie_json, to_lottie, write_lottie_json, LottieOptions, LottieOutput},
+    plan::PlanOptions,
+};
@@ -0,0 +1,49 @@
+//! Canned animations following Material 3 icon guidance, e.g. `Animate close: preset emphasize`
+//!
+//! A preset is shorthand for a plan; its duration, springs, and channels are those of the command
+//! it expands to.
+
+use serde::Serialize;
+
+/// A named animation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// What the preset expands to, the part of a command after "Animate icon_name:"
+    pub command: &'static str,
+}
+
+/// Every preset, in the order we suggest them
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "enter",
+        description: "Fade in while scaling up, for an icon appearing",
+        command: "standard-enter",
+    },
+    Preset {
+        name: "exit",
+        description: "Fade out while scaling down, for an icon going away",
+        command: "standard-exit",
+    },
+    Preset {
+        name: "emphasize",
+        description: "Pop with a bouncy scale to draw attention to an icon",
+        command: "scale 80 to 100 using expressive-spatial",
+    },
+    Preset {
+        name: "toggle-fill",
+        description: "Fill in with a gentle settle, for an icon turning on",
+        command: "scale 90 to 100 using smooth-spatial vary FILL:0 to FILL:1",
+    },
+    Preset {
+        name: "squash-and-stretch",
+        description: "Squash flat and spring back, stretching tall, for an icon landing or tapped",
+        command: "squash 30 using elastic",
+    },
+];
+
+/// The preset named `name`, if there is one
+pub fn preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
@@ -0,0 +1,221 @@
+//! Still frames of an [`Animation`] as SVG, to eyeball motion without a player
+//!
+//! Shapes take their group's fill, or the flat color of its gradient, and stroke. Clips and
+//! motion blur aren't drawn, nor are matte parts; the parts they mask are drawn unmasked.
+
+use std::fmt::Write;
+
+use kurbo::BezPath;
+
+use crate::{
+    bezop::Precision,
+    hex_color,
+    ir::{Animation, Element, Gradient, Group, Keyframed},
+};
+
+/// `count` frames evenly spaced from the first to the last, inclusive
+pub fn sample_frames(frames: f64, count: usize) -> Vec<f64> {
+    match count {
+        0 => Vec::new(),
+        1 => vec![0.0],
+        _ => (0..count)
+            .map(|i| i as f64 * frames / (count - 1) as f64)
+            .collect(),
+    }
+}
+
+/// The animation as it stands at `frame`, an SVG the size of the composition
+pub fn frame_svg(animation: &Animation, frame: f64) -> String {
+    filmstrip_svg(animation, &[frame])
+}
+
+/// The animation at each of `frames`, at its frame rate, side by side from left to right, see
+/// [`sample_frames`]
+pub fn filmstrip_svg(animation: &Animation, frames: &[f64]) -> String {
+    let mut animation = animation.clone();
+    animation.resolve_shared();
+    let view = animation.viewed_root();
+    let root = view.unmatted();
+    let precision = Precision::STABLE;
+    let (width, height) = (animation.width(), animation.height());
+    let frame_rate = animation.frame_rate();
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        width * frames.len() as f64,
+        height,
+        width * frames.len() as f64,
+        height,
+    )
+    .unwrap();
+    for (i, frame) in frames.iter().enumerate() {
+        write!(
+            svg,
+            r#"<g transform="translate({} 0)"><title>frame {}</title>"#,
+            width * i as f64,
+            precision.round(*frame)
+        )
+        .unwrap();
+        write_group(&mut svg, &root, *frame / frame_rate, frame_rate, precision);
+        svg.push_str("</g>");
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// `group` as it stands `time` seconds in, shown for whole frames at `frame_rate`
+fn write_group(svg: &mut String, group: &Group, time: f64, frame_rate: f64, precision: Precision) {
+    let opacity = group.visible_opacity(frame_rate).value_at(time) / 100.0;
+    if opacity <= 0.0 {
+        return;
+    }
+    let coeffs = group
+        .affine_at(time)
+        .as_coeffs()
+        .map(|c| precision.round(c));
+    write!(
+        svg,
+        r#"<g transform="matrix({} {} {} {} {} {})""#,
+        coeffs[0], coeffs[1], coeffs[2], coeffs[3], coeffs[4], coeffs[5]
+    )
+    .unwrap();
+    if opacity < 1.0 {
+        write!(svg, r#" opacity="{}""#, precision.round(opacity)).unwrap();
+    }
+    svg.push('>');
+
+    // Runs of shapes draw as one path so they cut holes in each other, higher z draws later
+    let mut ordered: Vec<_> = group.children().iter().collect();
+    ordered.sort_by_key(|e| e.z());
+    let mut shapes = Vec::new();
+    for child in ordered {
+        match child {
+            Element::Group(g) => {
+                write_path(svg, group, &shapes, time, precision);
+                shapes.clear();
+                write_group(svg, g, time, frame_rate, precision);
+            }
+            Element::Shape(s) => shapes.push(s),
+        }
+    }
+    write_path(svg, group, &shapes, time, precision);
+    svg.push_str("</g>");
+}
+
+fn write_path(
+    svg: &mut String,
+    group: &Group,
+    shapes: &[&Keyframed<BezPath>],
+    time: f64,
+    precision: Precision,
+) {
+    if shapes.is_empty() {
+        return;
+    }
+    let mut path = BezPath::new();
+    for shape in shapes {
+        path.extend(shape.value_at(time).iter());
+    }
+    let fill = group
+        .fill()
+        .or_else(|| group.gradient().map(Gradient::flat_color))
+        .unwrap_or_default();
+    write!(
+        svg,
+        r#"<path d="{}" fill="{}""#,
+        precision.round_path(&path).to_svg(),
+        hex_color(fill)
+    )
+    .unwrap();
+    if let Some(stroke) = group.stroke() {
+        write!(
+            svg,
+            r#" stroke="{}" stroke-width="{}""#,
+            hex_color(stroke.color),
+            precision.round(stroke.width)
+        )
+        .unwrap();
+    }
+    svg.push_str("/>");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kurbo::{Rect, Shape};
+
+    use crate::ir::{Animation, Element, Group, Keyframed, Matte, MatteMode};
+
+    use super::{filmstrip_svg, frame_svg, sample_frames};
+
+    fn spinning_square() -> Animation {
+        let mut root = Group::default();
+        root.children_mut().push(Element::Shape(Keyframed::new(
+            0.0,
+            Rect::new(0.0, 0.0, 10.0, 10.0).to_path(0.1),
+        )));
+        root.set_center((5.0, 5.0).into());
+        root.set_fill(Some((255, 0, 0)));
+        root.set_rotate(vec![(0.0, 0.0), (1.0, 90.0)].try_into().unwrap());
+        Animation::new(10.0, 10.0, Duration::from_secs(1), root)
+    }
+
+    #[test]
+    fn samples_include_the_ends() {
+        assert_eq!(vec![0.0, 30.0, 60.0], sample_frames(60.0, 3));
+        assert_eq!(vec![0.0], sample_frames(60.0, 1));
+        assert!(sample_frames(60.0, 0).is_empty());
+    }
+
+    #[test]
+    fn frames_follow_the_motion() {
+        let animation = spinning_square();
+        let start = frame_svg(&animation, 0.0);
+        assert!(
+            start.contains(r#"<g transform="matrix(1 0 0 1 0 0)">"#),
+            "{start}"
+        );
+        assert!(start.contains(r##"fill="#ff0000""##), "{start}");
+        let end = frame_svg(&animation, 60.0);
+        assert!(
+            end.contains(r#"<g transform="matrix(0 1 -1 0 10 0)">"#),
+            "{end}"
+        );
+    }
+
+    #[test]
+    fn filmstrip_lays_frames_side_by_side() {
+        let svg = filmstrip_svg(&spinning_square(), &sample_frames(60.0, 3));
+        assert!(svg.contains(r#"width="30" height="10""#), "{svg}");
+        assert_eq!(3, svg.matches("<title>").count(), "{svg}");
+        assert!(svg.contains(r#"translate(20 0)"#), "{svg}");
+    }
+
+    #[test]
+    fn mattes_are_not_drawn() {
+        let mut animation = spinning_square();
+        let mut cutout = Group::default();
+        cutout.set_name(Some("cutout".to_string()));
+        cutout.set_fill(Some((0, 0, 255)));
+        cutout.children_mut().push(Element::Shape(Keyframed::new(
+            0.0,
+            Rect::new(2.0, 2.0, 8.0, 8.0).to_path(0.1),
+        )));
+        let mut icon = Group::default();
+        icon.set_fill(Some((255, 0, 0)));
+        icon.set_matte(Some(Matte {
+            part: "cutout".to_string(),
+            mode: MatteMode::Alpha,
+        }));
+        icon.children_mut().append(animation.root.children_mut());
+        animation.root.children_mut().push(Element::Group(icon));
+        animation.root.children_mut().push(Element::Group(cutout));
+
+        let svg = frame_svg(&animation, 0.0);
+        assert!(svg.contains(r##"fill="#ff0000""##), "{svg}");
+        assert!(!svg.contains(r##"fill="#0000ff""##), "{svg}");
+    }
+}
@@ -0,0 +1,234 @@
+//! A cursor over command text for the hand-written parser in [`crate::plan`]
+//!
+//! Whitespace between tokens is skipped, any amount of it. Keywords match ignoring case. Errors
+//! say where, in characters from the start of the command, parsing went wrong.
+
+use std::ops::RangeInclusive;
+
+use crate::error::Error;
+
+pub(crate) struct Scanner<'a> {
+    src: &'a str,
+    /// Byte offset of the next unread char
+    pos: usize,
+}
+
+/// Chars that make up a word, e.g. an icon name, keyword, or spring name
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+impl<'a> Scanner<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        Scanner { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Byte offset of the next token
+    pub(crate) fn position(&mut self) -> usize {
+        self.skip_whitespace();
+        self.pos
+    }
+
+    /// The text from `start`, a [`Scanner::position`], to what has been read so far
+    pub(crate) fn since(&self, start: usize) -> &'a str {
+        self.src[start..self.pos].trim_end()
+    }
+
+    pub(crate) fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.pos == self.src.len()
+    }
+
+    pub(crate) fn expect_end(&mut self) -> Result<(), Error> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.error("the end of the command"))
+        }
+    }
+
+    /// A syntax error at the next token
+    pub(crate) fn error(&mut self, expected: &'static str) -> Error {
+        self.skip_whitespace();
+        let found = match self.rest().split_whitespace().next() {
+            Some(token) => format!("'{token}'"),
+            None => "the end of the command".to_string(),
+        };
+        Error::Syntax {
+            position: self.src[..self.pos].chars().count(),
+            expected,
+            found,
+        }
+    }
+
+    /// The next word without consuming it
+    pub(crate) fn peek_word(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+        (len > 0).then_some(&rest[..len])
+    }
+
+    /// Consume the next word if it is `keyword`, ignoring case
+    pub(crate) fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek_word() {
+            Some(word) if word.eq_ignore_ascii_case(keyword) => {
+                self.pos += word.len();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(crate) fn expect_keyword(&mut self, keyword: &'static str) -> Result<(), Error> {
+        if self.keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.error(keyword))
+        }
+    }
+
+    /// Consume the next word, `expected` describes it for errors
+    pub(crate) fn word(&mut self, expected: &'static str) -> Result<&'a str, Error> {
+        let word = self.peek_word().ok_or_else(|| self.error(expected))?;
+        self.pos += word.len();
+        Ok(word)
+    }
+
+    /// A word, or any text in double quotes
+    pub(crate) fn name(&mut self, expected: &'static str) -> Result<&'a str, Error> {
+        self.skip_whitespace();
+        let Some(quoted) = self.rest().strip_prefix('"') else {
+            return self.word(expected);
+        };
+        let Some(len) = quoted.find('"') else {
+            return Err(self.error("a closing quote"));
+        };
+        let name = &quoted[..len];
+        if name.is_empty() {
+            return Err(self.error(expected));
+        }
+        self.pos += len + 2;
+        Ok(name)
+    }
+
+    /// Consume `c` if it is next
+    pub(crate) fn punct(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consume `symbol`, e.g. `..`, if it is next
+    pub(crate) fn symbol(&mut self, symbol: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(symbol) {
+            self.pos += symbol.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub(crate) fn expect_punct(&mut self, c: char, expected: &'static str) -> Result<(), Error> {
+        if self.punct(c) {
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    /// Whether a number is next
+    pub(crate) fn at_number(&mut self) -> bool {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let digits = rest.strip_prefix('-').unwrap_or(rest);
+        // 10k is a word, not a number
+        digits.starts_with(|c: char| c.is_ascii_digit())
+            && !rest[self.number_len()..].starts_with(|c: char| is_word_char(c) || c == '.')
+    }
+
+    /// Length of the number at pos; digits, optionally signed, optionally with a fraction
+    fn number_len(&self) -> usize {
+        let rest = self.rest();
+        let mut len = usize::from(rest.starts_with('-'));
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        len += digits(&rest[len..]);
+        if rest[len..].starts_with('.') && digits(&rest[len + 1..]) > 0 {
+            len += 1 + digits(&rest[len + 1..]);
+        }
+        len
+    }
+
+    /// A number, e.g. 90, -250.5
+    pub(crate) fn number(&mut self, expected: &'static str) -> Result<f64, Error> {
+        if !self.at_number() {
+            return Err(self.error(expected));
+        }
+        let len = self.number_len();
+        let value = self.rest()[..len].parse().map_err(Error::InvalidF64)?;
+        self.pos += len;
+        Ok(value)
+    }
+
+    /// A number within `range`, else an error at the number
+    pub(crate) fn number_within(
+        &mut self,
+        expected: &'static str,
+        range: RangeInclusive<f64>,
+    ) -> Result<f64, Error> {
+        let start = self.position();
+        let value = self.number(expected)?;
+        if !range.contains(&value) {
+            self.pos = start;
+            return Err(self.error(expected));
+        }
+        Ok(value)
+    }
+
+    /// A whole number greater than zero
+    pub(crate) fn count(&mut self, expected: &'static str) -> Result<usize, Error> {
+        self.whole(expected, 1)
+    }
+
+    /// A whole number of at least `min`
+    pub(crate) fn whole(&mut self, expected: &'static str, min: usize) -> Result<usize, Error> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        match rest[..len].parse::<usize>() {
+            Ok(value) if value >= min && !rest[len..].starts_with(is_word_char) => {
+                self.pos += len;
+                Ok(value)
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    /// Everything up to the next whitespace, e.g. a location such as `wght:400,FILL:1`
+    pub(crate) fn chunk(&mut self, expected: &'static str) -> Result<&'a str, Error> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(self.error(expected));
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+}
@@ -1,10 +1,22 @@
 //! Spring-based animation, ported from [Android's implmentation](https://cs.android.com/android/platform/superproject/main/+/main:frameworks/base/core/java/com/android/internal/dynamicanimation/animation/)
 
-use std::str::FromStr;
+use std::{
+    f64::consts::{FRAC_PI_2, PI},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::Serialize;
 
 use crate::error::SpringBuildError;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// How long, in seconds, [`SpringFrames`] runs before giving up on reaching equilibrium
+pub const DEFAULT_MAX_DURATION: f64 = 5.0;
+
+/// How closely, in seconds, [`Spring::settle_time`] solves for when the spring comes to rest
+const SETTLE_PRECISION: f64 = 1e-9;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 pub enum Spring {
     Overdamped {
         gamma_plus: f64,
@@ -21,6 +33,15 @@ pub enum Spring {
 }
 
 impl Spring {
+    /// The names [`Spring::from_str`] accepts
+    pub const NAMES: [&'static str; 5] = [
+        "standard",
+        "smooth-spatial",
+        "smooth-non-spatial",
+        "expressive-spatial",
+        "expressive-non-spatial",
+    ];
+
     pub fn new(damping: f64, stiffness: f64) -> Result<Self, SpringBuildError> {
         if damping < 0.0 {
             return Err(SpringBuildError::InvalidDamping);
@@ -111,7 +132,7 @@ impl Spring {
                     * (damping * natural_freq * last_displacement + last.velocity);
                 let value = (-damping * natural_freq * delta_t).exp()
                     * (cos_coeff * (damped_freq * delta_t).cos()
-                        + sin_coeff * (damped_freq * delta_t.sin()));
+                        + sin_coeff * (damped_freq * delta_t).sin());
                 let velocity = value * -natural_freq * damping
                     + (-damping * natural_freq * delta_t).exp()
                         * (-damped_freq * cos_coeff * (damped_freq * delta_t).sin()
@@ -127,6 +148,301 @@ impl Spring {
             value_type: last.value_type,
         }
     }
+
+    /// The state at every frame from `from` until the spring comes to rest at `to`
+    pub fn iter_frames(
+        &self,
+        from: f64,
+        to: f64,
+        value_type: AnimatedValueType,
+        frame_rate: f64,
+    ) -> SpringFrames {
+        SpringFrames::new(*self, AnimatedValue::new(from, to, value_type), frame_rate)
+    }
+
+    /// Every state of [`Spring::iter_frames`]; the last is at equilibrium unless the spring
+    /// didn't settle within [`DEFAULT_MAX_DURATION`]
+    pub fn sample(
+        &self,
+        from: f64,
+        to: f64,
+        value_type: AnimatedValueType,
+        frame_rate: f64,
+    ) -> Vec<AnimatedValue> {
+        self.iter_frames(from, to, value_type, frame_rate).collect()
+    }
+
+    /// How long the spring takes to come to rest moving from `from` to `to`, whatever the frame
+    /// rate; None if it never does, e.g. with no damping
+    ///
+    /// Solved from the closed form rather than by stepping, it's when the spring last moves beyond
+    /// [`AnimatedValue::is_at_equilibrium`]'s thresholds. A lightly damped spring can momentarily be
+    /// within them on an earlier frame, where [`Spring::iter_frames`] stops.
+    pub fn settle_time(
+        &self,
+        from: f64,
+        to: f64,
+        value_type: AnimatedValueType,
+    ) -> Option<Duration> {
+        self.settle_time_of(AnimatedValue::new(from, to, value_type))
+    }
+
+    /// As [`Spring::settle_time`] for `animation`, which may have an initial velocity
+    pub fn settle_time_of(&self, animation: AnimatedValue) -> Option<Duration> {
+        self.settle_seconds(animation)
+            // Round up so the spring is at rest at the time returned
+            .map(|seconds| Duration::from_nanos((seconds * 1e9).ceil() as u64))
+    }
+
+    fn settle_seconds(&self, animation: AnimatedValue) -> Option<f64> {
+        let thresholds = animation.value_type.thresholds();
+        let [displacement, velocity] = self.decays(animation);
+        let displacement = displacement.last_beyond(thresholds.value_threshold)?;
+        let velocity = velocity.last_beyond(thresholds.velocity_threshold)?;
+        Some(displacement.max(velocity))
+    }
+
+    /// Displacement from the final value and velocity over time, as [`Spring::update`] computes
+    fn decays(&self, animation: AnimatedValue) -> [Decay; 2] {
+        let displacement = animation.value - animation.final_value;
+        match *self {
+            Spring::Overdamped {
+                gamma_plus,
+                gamma_minus,
+            } => {
+                let b =
+                    (gamma_minus * displacement - animation.velocity) / (gamma_minus - gamma_plus);
+                let a = displacement - b;
+                [
+                    Decay::Exponential {
+                        a,
+                        alpha: gamma_minus,
+                        b,
+                        beta: gamma_plus,
+                    },
+                    Decay::Exponential {
+                        a: a * gamma_minus,
+                        alpha: gamma_minus,
+                        b: b * gamma_plus,
+                        beta: gamma_plus,
+                    },
+                ]
+            }
+            Spring::CriticallyDamped { natural_freq } => {
+                let p = displacement;
+                let q = animation.velocity + natural_freq * displacement;
+                [
+                    Decay::Linear {
+                        p,
+                        q,
+                        rate: natural_freq,
+                    },
+                    Decay::Linear {
+                        p: q - natural_freq * p,
+                        q: -natural_freq * q,
+                        rate: natural_freq,
+                    },
+                ]
+            }
+            Spring::Underdamped {
+                damping,
+                natural_freq,
+                damped_freq,
+            } => {
+                let rate = damping * natural_freq;
+                let cos = displacement;
+                let sin = (rate * displacement + animation.velocity) / damped_freq;
+                [
+                    Decay::Oscillating {
+                        cos,
+                        sin,
+                        rate,
+                        freq: damped_freq,
+                    },
+                    Decay::Oscillating {
+                        cos: -rate * cos + damped_freq * sin,
+                        sin: -rate * sin - damped_freq * cos,
+                        rate,
+                        freq: damped_freq,
+                    },
+                ]
+            }
+        }
+    }
+}
+
+/// Something, displacement or velocity, that decays to 0 over time as a spring settles
+#[derive(Debug, Clone, Copy)]
+enum Decay {
+    /// a·e^(alpha·t) + b·e^(beta·t), overdamped
+    Exponential {
+        a: f64,
+        alpha: f64,
+        b: f64,
+        beta: f64,
+    },
+    /// (p + q·t)·e^(-rate·t), critically damped
+    Linear { p: f64, q: f64, rate: f64 },
+    /// e^(-rate·t)·(cos·cos(freq·t) + sin·sin(freq·t)), underdamped
+    Oscillating {
+        cos: f64,
+        sin: f64,
+        rate: f64,
+        freq: f64,
+    },
+}
+
+impl Decay {
+    fn at(&self, t: f64) -> f64 {
+        match *self {
+            Decay::Exponential { a, alpha, b, beta } => {
+                a * (alpha * t).exp() + b * (beta * t).exp()
+            }
+            Decay::Linear { p, q, rate } => (p + q * t) * (-rate * t).exp(),
+            Decay::Oscillating {
+                cos,
+                sin,
+                rate,
+                freq,
+            } => (-rate * t).exp() * (cos * (freq * t).cos() + sin * (freq * t).sin()),
+        }
+    }
+
+    /// The last time the magnitude is at least `threshold`, 0 if never; None if it doesn't decay
+    ///
+    /// Finds an interval where the magnitude falls below `threshold` for good, then bisects it.
+    fn last_beyond(&self, threshold: f64) -> Option<f64> {
+        let beyond = |t: f64| self.at(t).abs() >= threshold;
+        let (mut lo, mut hi) = match *self {
+            Decay::Oscillating {
+                cos,
+                sin,
+                rate,
+                freq,
+            } => {
+                if cos == 0.0 && sin == 0.0 {
+                    return Some(0.0);
+                }
+                if rate <= 0.0 {
+                    return None;
+                }
+                // Peaks in magnitude are half a period apart, each smaller by the same factor
+                let half_period = PI / freq;
+                let (d_cos, d_sin) = (-rate * cos + freq * sin, -rate * sin - freq * cos);
+                let first_peak = (d_sin.atan2(d_cos) + FRAC_PI_2).rem_euclid(PI) / freq;
+                let peak = self.at(first_peak).abs();
+                if peak < threshold {
+                    (0.0, first_peak)
+                } else {
+                    let peaks = ((peak / threshold).ln() / (rate * half_period)).floor();
+                    let last_peak = first_peak + peaks * half_period;
+                    (last_peak, last_peak + half_period)
+                }
+            }
+            Decay::Exponential { .. } | Decay::Linear { .. } => {
+                // At most one extremum, after which the magnitude only falls
+                let (still, rate, extremum) = match *self {
+                    Decay::Exponential { a, alpha, b, beta } => (
+                        a == 0.0 && b == 0.0,
+                        -alpha.max(beta),
+                        (-b * beta / (a * alpha)).ln() / (alpha - beta),
+                    ),
+                    Decay::Linear { p, q, rate } => {
+                        (p == 0.0 && q == 0.0, rate, 1.0 / rate - p / q)
+                    }
+                    Decay::Oscillating { .. } => unreachable!(),
+                };
+                if still {
+                    return Some(0.0);
+                }
+                if rate.is_nan() || rate <= 0.0 {
+                    return None;
+                }
+                let start = if extremum > 0.0 && extremum.is_finite() {
+                    extremum
+                } else {
+                    0.0
+                };
+                if start > 0.0 && !beyond(start) {
+                    (0.0, start)
+                } else {
+                    let mut end = start + 1.0 / rate;
+                    while beyond(end) {
+                        end += end - start;
+                    }
+                    (start, end)
+                }
+            }
+        };
+        if !beyond(lo) {
+            return Some(lo);
+        }
+        while hi - lo > SETTLE_PRECISION {
+            let mid = 0.5 * (lo + hi);
+            if beyond(mid) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        Some(hi)
+    }
+}
+
+/// Samples a spring once per frame, see [`Spring::iter_frames`]
+///
+/// The first item is the starting state and the last is the first at equilibrium, unless
+/// the max duration is reached first; check [`AnimatedValue::is_at_equilibrium`] on the last
+/// item if that matters.
+#[derive(Debug, Clone)]
+pub struct SpringFrames {
+    spring: Spring,
+    current: AnimatedValue,
+    frame_rate: f64,
+    max_duration: f64,
+    frame: usize,
+    done: bool,
+}
+
+impl SpringFrames {
+    /// Sample from `animation`, which may have an initial velocity
+    pub fn new(spring: Spring, animation: AnimatedValue, frame_rate: f64) -> Self {
+        SpringFrames {
+            spring,
+            current: animation,
+            frame_rate,
+            max_duration: DEFAULT_MAX_DURATION,
+            frame: 0,
+            done: false,
+        }
+    }
+
+    /// Stop after `seconds` even if not at equilibrium, defaults to [`DEFAULT_MAX_DURATION`]
+    pub fn with_max_duration(mut self, seconds: f64) -> Self {
+        self.max_duration = seconds;
+        self
+    }
+}
+
+impl Iterator for SpringFrames {
+    type Item = AnimatedValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let elapsed = self.frame as f64 / self.frame_rate;
+        if self.done || elapsed > self.max_duration {
+            self.done = true;
+            return None;
+        }
+        self.current = if self.frame == 0 {
+            self.current
+        } else {
+            self.spring
+                .update(self.current.time + 1.0 / self.frame_rate, self.current)
+        };
+        self.frame += 1;
+        self.done = self.current.is_at_equilibrium();
+        Some(self.current)
+    }
 }
 
 impl FromStr for Spring {
@@ -168,6 +484,22 @@ impl AnimatedValue {
         }
     }
 
+    /// Start already moving, in value units per second, such as when handing off from a gesture
+    pub fn with_velocity(mut self, velocity: f64) -> Self {
+        self.velocity = velocity;
+        self
+    }
+
+    /// Head back from where this would finish to where it starts, moving the opposite way
+    pub fn reversed(self) -> Self {
+        AnimatedValue {
+            value: self.final_value,
+            final_value: self.value,
+            velocity: -self.velocity,
+            ..self
+        }
+    }
+
     /// <https://cs.android.com/android/platform/superproject/main/+/main:frameworks/base/core/java/com/android/internal/dynamicanimation/animation/SpringForce.java;l=221-228;drc=b7d26a383dbb3c7fa3f276d8ad1afdac5bb5443f>
     pub fn is_at_equilibrium(&self) -> bool {
         let thresholds = self.value_type.thresholds();
@@ -217,27 +549,22 @@ impl AnimatedValueType {
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use crate::spring::AnimatedValueType;
     use ordered_float::OrderedFloat;
 
     use super::AnimatedValue;
     use super::Spring;
+    use super::SpringFrames;
 
     #[test]
     fn from_zero_to_100() {
         let spring = Spring::expressive_spatial();
 
-        // 60fps, run until complete or 5s
-        let mut animated_value = AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale);
-        let mut frame_values = Vec::new();
-        for frame in 0..300 {
-            let time = frame as f64 / 60.0;
-            animated_value = spring.update(time, animated_value);
-            frame_values.push(animated_value);
-            if animated_value.is_at_equilibrium() {
-                break;
-            }
-        }
+        let frame_values: Vec<_> = spring
+            .iter_frames(0.0, 100.0, AnimatedValueType::Scale, 60.0)
+            .collect();
 
         assert!(
             frame_values.len() < 50,
@@ -262,4 +589,275 @@ mod tests {
             "Should end very near the end\n{frame_values:#?}"
         );
     }
+
+    #[test]
+    fn initial_velocity_carries_value() {
+        let spring = Spring::standard();
+        let start = AnimatedValue::new(0.0, 0.0, AnimatedValueType::Position).with_velocity(100.0);
+        assert!(!start.is_at_equilibrium());
+        let moved = spring.update(1.0 / 60.0, start);
+        assert!(moved.value > 0.0, "{moved:?}");
+        let settled = spring.update(2.0, start);
+        assert!(settled.value.abs() < 0.01, "{settled:?}");
+    }
+
+    #[test]
+    fn frames_stop_at_max_duration() {
+        let frames: Vec<_> = SpringFrames::new(
+            Spring::standard(),
+            AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale),
+            60.0,
+        )
+        .with_max_duration(0.1)
+        .collect();
+        assert_eq!(7, frames.len(), "{frames:#?}");
+        assert!(!frames.last().unwrap().is_at_equilibrium());
+    }
+
+    #[test]
+    fn frames_at_rest_yield_only_the_start() {
+        let frames: Vec<_> = Spring::standard()
+            .iter_frames(5.0, 5.0, AnimatedValueType::Position, 60.0)
+            .collect();
+        assert_eq!(1, frames.len(), "{frames:#?}");
+    }
+
+    #[test]
+    fn settle_time_is_independent_of_frame_rate() {
+        let spring = Spring::expressive_spatial();
+        let settle = spring
+            .settle_time(0.0, 1.0, AnimatedValueType::Scale)
+            .unwrap();
+        for frame_rate in [30.0, 60.0, 120.0] {
+            let samples = spring.sample(0.0, 1.0, AnimatedValueType::Scale, frame_rate);
+            let last = samples.last().unwrap();
+            assert!(last.is_at_equilibrium());
+            // Settled by the frame at, or just after, the settle time
+            let late = last.time - settle.as_secs_f64();
+            assert!(
+                (-0.001..1.0 / frame_rate + 0.001).contains(&late),
+                "{frame_rate}fps settles at {}s, expected {settle:?}",
+                last.time
+            );
+        }
+        assert_eq!(
+            Some(Duration::ZERO),
+            spring.settle_time(5.0, 5.0, AnimatedValueType::Position)
+        );
+    }
+
+    /// Android's `SpringForce.updateValues`, transcribed with doubles for the maths and floats for
+    /// the `MassState` it returns, evaluated for:
+    ///
+    /// ([damping, stiffness, final value, value, velocity], elapsed ms, [value, velocity])
+    const ANDROID_UPDATES: &[([f64; 5], u32, [f64; 2])] = &[
+        // Underdamped
+        (
+            [0.8, 380.0, 100.0, 0.0, 0.0],
+            16,
+            [4.114194393157959, 470.9789123535156],
+        ),
+        (
+            [0.8, 380.0, 100.0, 0.0, 0.0],
+            100,
+            [65.98310852050781, 628.8326416015625],
+        ),
+        (
+            [0.8, 380.0, 0.0, 50.0, -200.0],
+            33,
+            [38.84092712402344, -416.2797546386719],
+        ),
+        (
+            [0.5, 1500.0, 1.0, 0.0, 4.0],
+            16,
+            [0.19775086641311646, 18.428224563598633],
+        ),
+        (
+            [0.2, 50.0, -10.0, 10.0, 0.0],
+            250,
+            [-9.425336837768555, -100.03730010986328],
+        ),
+        // Critically damped
+        (
+            [1.0, 380.0, 100.0, 0.0, 0.0],
+            16,
+            [3.9616811275482178, 445.0904235839844],
+        ),
+        (
+            [1.0, 190.0, 1.0, 0.8, 3.0],
+            50,
+            [0.9057085514068604, 1.4217939376831055],
+        ),
+        (
+            [1.0, 380.0, 0.0, 0.0, 100.0],
+            100,
+            [1.4236531257629395, -13.51557731628418],
+        ),
+        // Overdamped
+        (
+            [1.5, 380.0, 100.0, 0.0, 0.0],
+            16,
+            [3.6170129776000977, 388.58575439453125],
+        ),
+        (
+            [3.0, 200.0, 90.0, 0.0, 0.0],
+            100,
+            [17.24931526184082, 176.4652862548828],
+        ),
+        (
+            [1.2, 1500.0, 0.0, 5.0, -50.0],
+            33,
+            [2.949733257293701, -56.327659606933594],
+        ),
+    ];
+
+    /// Android's state is floats, so only that precise
+    fn assert_close_to_android(expected: f64, actual: f64, what: &str) {
+        assert!(
+            (expected - actual).abs() <= 1e-5 * expected.abs().max(1.0),
+            "{what}: {actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn update_matches_android() {
+        for (
+            [damping, stiffness, final_value, value, velocity],
+            elapsed,
+            [expected, expected_velocity],
+        ) in ANDROID_UPDATES.iter().copied()
+        {
+            let spring = Spring::new(damping, stiffness).unwrap();
+            let last = AnimatedValue::new(value, final_value, AnimatedValueType::Position)
+                .with_velocity(velocity);
+            let state = spring.update(elapsed as f64 / 1000.0, last);
+            let what = format!("{spring:?} from {last:?} after {elapsed}ms");
+            assert_close_to_android(expected, state.value, &what);
+            assert_close_to_android(expected_velocity, state.velocity, &what);
+        }
+    }
+
+    #[test]
+    fn frames_match_android() {
+        // Android stepping expressive-spatial from 0 to 100 at 16ms a frame
+        let expected = [
+            0.0,
+            4.114194393157959,
+            13.89652156829834,
+            26.376888275146484,
+            39.5471305847168,
+            52.13565444946289,
+            63.41464614868164,
+            73.0417251586914,
+            80.93451690673828,
+            87.1747817993164,
+            91.93768310546875,
+            95.44181823730469,
+            97.91551971435547,
+        ];
+        let frames = Spring::expressive_spatial().iter_frames(
+            0.0,
+            100.0,
+            AnimatedValueType::Scale,
+            1000.0 / 16.0,
+        );
+        for (frame, (expected, actual)) in expected.into_iter().zip(frames).enumerate() {
+            assert_close_to_android(expected, actual.value, &format!("frame {frame}"));
+        }
+    }
+
+    #[test]
+    fn damped_springs_dont_overshoot() {
+        for spring in [Spring::new(1.0, 380.0), Spring::new(1.5, 380.0)] {
+            let spring = spring.unwrap();
+            let frames = spring.sample(0.0, 100.0, AnimatedValueType::Position, 60.0);
+            assert!(
+                frames.windows(2).all(|w| w[0].value < w[1].value),
+                "{spring:?} should approach without overshoot\n{frames:#?}"
+            );
+            assert!(frames.last().unwrap().is_at_equilibrium());
+        }
+    }
+
+    #[test]
+    fn critically_damped_matches_closed_form() {
+        let spring = Spring::new(1.0, 100.0).unwrap();
+        let start = AnimatedValue::new(0.0, 1.0, AnimatedValueType::Scale);
+        for time in [0.05_f64, 0.1, 0.25, 0.5] {
+            // x(t) = x0·(1 + ωt)·e^(-ωt) from rest, ω = 10
+            let expected = 1.0 - (1.0 + 10.0 * time) * (-10.0 * time).exp();
+            let actual = spring.update(time, start).value;
+            assert!(
+                (expected - actual).abs() < 1e-9,
+                "{time}: {actual} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn stepping_matches_one_update() {
+        for damping in [0.5, 0.8, 1.0, 1.5] {
+            let spring = Spring::new(damping, 380.0).unwrap();
+            let start =
+                AnimatedValue::new(0.0, 10.0, AnimatedValueType::Position).with_velocity(50.0);
+            let stepped = SpringFrames::new(spring, start, 60.0).nth(12).unwrap();
+            let direct = spring.update(stepped.time, start);
+            assert!(
+                (stepped.value - direct.value).abs() < 1e-9
+                    && (stepped.velocity - direct.velocity).abs() < 1e-9,
+                "{damping}: {stepped:?} != {direct:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn settle_time_matches_step_simulation() {
+        let frame_rate = 1000.0;
+        for damping in [0.3, 0.8, 1.0, 1.5, 3.0] {
+            for stiffness in [50.0, 190.0, 380.0, 1500.0] {
+                let spring = Spring::new(damping, stiffness).unwrap();
+                for start in [
+                    AnimatedValue::new(0.0, 1.0, AnimatedValueType::Scale),
+                    AnimatedValue::new(0.0, 90.0, AnimatedValueType::Rotation),
+                    AnimatedValue::new(0.8, 1.2, AnimatedValueType::Scale).with_velocity(3.0),
+                    AnimatedValue::new(0.0, 0.0, AnimatedValueType::Position).with_velocity(100.0),
+                    AnimatedValue::new(10.0, 0.0, AnimatedValueType::Position).with_velocity(-40.0),
+                ] {
+                    let settle = spring.settle_seconds(start).unwrap();
+                    // At rest from then on
+                    for step in 0..1000 {
+                        let time = settle + step as f64 / frame_rate;
+                        let state = spring.update(time, start);
+                        assert!(
+                            state.is_at_equilibrium(),
+                            "{spring:?} {start:?} moving at {time}, after {settle}"
+                        );
+                    }
+                    let last = SpringFrames::new(spring, start, frame_rate)
+                        .with_max_duration(60.0)
+                        .last()
+                        .unwrap();
+                    // Frames stop on the first at rest, for a bouncy spring that can be a
+                    // moment it passes through rest, otherwise it's the one after settling
+                    let late = last.time - settle;
+                    let bound = if damping < 0.8 { f64::MIN } else { -1e-6 };
+                    assert!(
+                        (bound..1.0 / frame_rate + 1e-6).contains(&late),
+                        "{spring:?} {start:?} settles at {settle}, frames stop at {}",
+                        last.time
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn undamped_never_settles() {
+        let spring = Spring::new(0.0, 380.0).unwrap();
+        assert_eq!(None, spring.settle_time(0.0, 1.0, AnimatedValueType::Scale));
+        assert_eq!(
+            Some(Duration::ZERO),
+            spring.settle_time(1.0, 1.0, AnimatedValueType::Scale)
+        );
+    }
 }
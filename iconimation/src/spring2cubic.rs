@@ -1,28 +1,276 @@
-//! Hand-written cubics for Spring motion.
+//! Cubics for Spring motion.
 //!
-//! As per <https://github.com/rsheeter/iconimation/pull/24>, this seems feasible to automate
-//! but at time of writing hand-written curves are winning.
+//! Springs are sampled analytically and a minimal set of cubics is fit to them, see [`SpringFitter`].
+//!
+//! As per <https://github.com/rsheeter/iconimation/pull/24> hand-written curves were at one point
+//! winning. They remain available, deprecated, via [`handwritten_cubic_approximation`].
 //!
 //! <https://codepen.io/rs42/pen/JjzpPyP> shows drafting of the manual curves.
 
-use kurbo::{Affine, CubicBez};
+use std::ops::Range;
+
+use kurbo::{
+    fit_to_bezpath_opt, Affine, CubicBez, CurveFitSample, ParamCurve, ParamCurveFit,
+    ParamCurveNearest, Point, Vec2,
+};
 
 use crate::{
     error::CubicApproximationError,
-    spring::{AnimatedValue, Spring},
+    spring::{AnimatedValue, Spring, SpringFrames},
 };
 
-static TIME_LIMIT: f64 = 5.0;
+/// Default for [`cubic_approximation_with_tolerance`]
+pub const DEFAULT_TOLERANCE: f64 = 0.5;
+
+/// A fit is rejected if value error, normalized to 0..100, exceeds this multiple of the tolerance
+const MAX_ERROR_MULTIPLE: f64 = 2.0;
+
+/// How many times to tighten the tolerance on a rejected fit before giving up
+const MAX_REFINEMENTS: usize = 4;
 
 /// Creates cubics to approximate a spring animation.
 ///
+/// Uses [`DEFAULT_TOLERANCE`], see [`cubic_approximation_with_tolerance`].
+pub fn cubic_approximation(
+    frame_rate: f64,
+    animation: AnimatedValue,
+    spring: Spring,
+) -> Result<Vec<CubicBez>, CubicApproximationError> {
+    cubic_approximation_with_tolerance(frame_rate, animation, spring, DEFAULT_TOLERANCE)
+}
+
+/// Creates the minimal set of cubics that approximate a spring animation within tolerance.
+///
+/// X is time in frames, Y is the value. Tolerance is the maximum distance between
+/// the spring and the cubics with both time and value normalized to 0..100 so the same
+/// tolerance works regardless of the range being animated.
+///
+/// The result is checked with [`fit_quality`]; if the value error is too large the fit
+/// is retried with tighter tolerance and eventually fails with
+/// [`CubicApproximationError::PoorFit`].
+pub fn cubic_approximation_with_tolerance(
+    frame_rate: f64,
+    animation: AnimatedValue,
+    spring: Spring,
+    tolerance: f64,
+) -> Result<Vec<CubicBez>, CubicApproximationError> {
+    let fitter = SpringFitter::new(frame_rate, animation, spring)?;
+    if fitter.num_frames == 0 || fitter.value_extent == 0.0 {
+        let p = Point::new(0.0, animation.value);
+        return Ok(vec![CubicBez::new(p, p, p, p)]);
+    }
+
+    // The fit error metric is approximate, confirm the result is good and refine if not
+    let mut fit_tolerance = tolerance;
+    let mut attempts = 0;
+    loop {
+        let cubics = fit_to_bezpath_opt(&fitter, fit_tolerance)
+            .segments()
+            .map(|s| s.to_cubic())
+            .collect();
+        let mut cubics = fitter.denormalize(cubics);
+        // Like Android, once at equilibrium snap to the final value
+        cubics.last_mut().unwrap().p3.y = animation.final_value;
+
+        let quality = fit_quality(frame_rate, animation, spring, &cubics)?;
+        if quality.max_value_error * fitter.value_scale().abs() <= MAX_ERROR_MULTIPLE * tolerance {
+            return Ok(cubics);
+        }
+        attempts += 1;
+        if attempts > MAX_REFINEMENTS {
+            return Err(CubicApproximationError::PoorFit(quality));
+        }
+        fit_tolerance /= 2.0;
+    }
+}
+
+/// How closely cubics track the spring they approximate, see [`fit_quality`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FitQuality {
+    /// The largest difference, in value units, between the spring and the cubics at the same frame
+    pub max_value_error: f64,
+    pub mean_value_error: f64,
+    /// The largest difference in time, in frames, between a spring sample and the nearest point on the cubics
+    pub max_frame_error: f64,
+    pub mean_frame_error: f64,
+}
+
+/// Compare cubics, such as those from [`cubic_approximation`], to the spring at every frame
+pub fn fit_quality(
+    frame_rate: f64,
+    animation: AnimatedValue,
+    spring: Spring,
+    cubics: &[CubicBez],
+) -> Result<FitQuality, CubicApproximationError> {
+    let fitter = SpringFitter::new(frame_rate, animation, spring)?;
+    // Nearest is measured in the normalized space so time and value are weighted similarly
+    let normalized = fitter.normalize(cubics.to_vec());
+    let to_frames = fitter.num_frames as f64 / 100.0;
+
+    let mut quality = FitQuality {
+        max_value_error: 0.0,
+        mean_value_error: 0.0,
+        max_frame_error: 0.0,
+        mean_frame_error: 0.0,
+    };
+    if fitter.num_frames == 0 {
+        return Ok(quality);
+    }
+    let samples = fitter.num_frames + 1;
+    for frame in 0..samples {
+        let frame = frame as f64;
+        let (expected, _) = fitter.sample_pt_deriv(frame / fitter.num_frames as f64);
+        let expected_value = expected.y / fitter.value_scale() + animation.value;
+
+        let value_error = (value_at(cubics, frame) - expected_value).abs();
+        quality.max_value_error = quality.max_value_error.max(value_error);
+        quality.mean_value_error += value_error;
+
+        let frame_error = normalized
+            .iter()
+            .map(|c| {
+                let nearest = c.nearest(expected, 1e-6);
+                (nearest.distance_sq, c.eval(nearest.t))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, p)| (p.x - expected.x).abs() * to_frames)
+            .unwrap_or_default();
+        quality.max_frame_error = quality.max_frame_error.max(frame_error);
+        quality.mean_frame_error += frame_error;
+    }
+    quality.mean_value_error /= samples as f64;
+    quality.mean_frame_error /= samples as f64;
+    Ok(quality)
+}
+
+/// The value of cubics, whose x is monotonically increasing frames, at frame
+fn value_at(cubics: &[CubicBez], frame: f64) -> f64 {
+    let Some(cubic) = cubics.iter().find(|c| frame <= c.p3.x).or(cubics.last()) else {
+        return 0.0;
+    };
+    // Bisect for the t at frame
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.0;
+        if cubic.eval(mid).x < frame {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    cubic.eval((lo + hi) / 2.0).y
+}
+
+/// Exposes a spring as a curve of (frame, value) for fitting.
+///
+/// Both frame and value are normalized to go from 0 to 100 so the curve is roughly square,
+/// which suits the distance-based fit. Use [`SpringFitter::denormalize`] to restore the original
+/// ranges.
+pub struct SpringFitter {
+    frame_rate: f64,
+    animation: AnimatedValue,
+    spring: Spring,
+    num_frames: usize,
+    /// The span of value normalized to 0..100; the change in value unless that is zero,
+    /// as when an initial velocity carries a value away and back, in which case the peak excursion
+    value_extent: f64,
+}
+
+impl SpringFitter {
+    pub fn new(
+        frame_rate: f64,
+        animation: AnimatedValue,
+        spring: Spring,
+    ) -> Result<Self, CubicApproximationError> {
+        let num_frames = num_frames(frame_rate, animation, spring)?;
+        let mut value_extent = animation.final_value - animation.value;
+        if value_extent == 0.0 {
+            value_extent = SpringFrames::new(spring, animation, frame_rate)
+                .map(|state| state.value - animation.value)
+                .fold(
+                    0.0,
+                    |acc: f64, delta| {
+                        if delta.abs() > acc.abs() {
+                            delta
+                        } else {
+                            acc
+                        }
+                    },
+                );
+        }
+        Ok(Self {
+            frame_rate,
+            animation,
+            spring,
+            num_frames,
+            value_extent,
+        })
+    }
+
+    /// The number of frames until the spring reaches equilibrium
+    pub fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    fn denormalize_transform(&self) -> Affine {
+        Affine::scale_non_uniform(self.num_frames as f64 / 100.0, self.value_extent / 100.0)
+            .then_translate((0.0, self.animation.value).into())
+    }
+
+    /// Map from the normalized 0..100 space used for fitting back to frames and value
+    pub fn denormalize(&self, cubics: Vec<CubicBez>) -> Vec<CubicBez> {
+        let transform = self.denormalize_transform();
+        cubics.into_iter().map(|c| transform * c).collect()
+    }
+
+    /// Map from frames and value to the normalized 0..100 space used for fitting
+    pub fn normalize(&self, cubics: Vec<CubicBez>) -> Vec<CubicBez> {
+        let transform = self.denormalize_transform().inverse();
+        cubics.into_iter().map(|c| transform * c).collect()
+    }
+
+    fn value_scale(&self) -> f64 {
+        100.0 / self.value_extent
+    }
+}
+
+impl ParamCurveFit for SpringFitter {
+    fn sample_pt_tangent(&self, t: f64, _sign: f64) -> CurveFitSample {
+        let (p, tangent) = self.sample_pt_deriv(t);
+        CurveFitSample { p, tangent }
+    }
+
+    fn sample_pt_deriv(&self, t: f64) -> (Point, Vec2) {
+        let frames = self.num_frames as f64;
+        let frame = t * frames;
+        let state = self.spring.update(
+            self.animation.time + frame / self.frame_rate,
+            self.animation,
+        );
+        let scale = self.value_scale();
+        // d(value)/dt is velocity (per second) converted to per frame then to per unit t
+        (
+            Point::new(t * 100.0, (state.value - self.animation.value) * scale),
+            Vec2::new(100.0, state.velocity / self.frame_rate * frames * scale),
+        )
+    }
+
+    fn break_cusp(&self, _range: Range<f64>) -> Option<f64> {
+        // Springs are smooth
+        None
+    }
+}
+
+/// Creates cubics to approximate a spring animation using hand-written curves.
+///
 /// Supports only well known springs due to <https://github.com/rsheeter/iconimation/issues/29>:
 /// * [`Spring::standard`]
 /// * [`Spring::smooth_spatial`]
 /// * [`Spring::smooth_non_spatial`]
 /// * [`Spring::expressive_spatial`]
 /// * [`Spring::expressive_non_spatial`]
-pub fn cubic_approximation(
+#[deprecated(note = "use cubic_approximation, which fits any spring")]
+pub fn handwritten_cubic_approximation(
     frame_rate: f64,
     animation: AnimatedValue,
     spring: Spring,
@@ -89,15 +337,137 @@ fn num_frames(
     spring: Spring,
 ) -> Result<usize, CubicApproximationError> {
     // Run the specified animation to equilibrium to learn it's bounds
-    let mut frame = 0;
-    let mut animated_value = animation;
-    while !animated_value.is_at_equilibrium() {
-        let time = frame as f64 / frame_rate;
-        if time > TIME_LIMIT {
-            return Err(CubicApproximationError::RanTooLong);
+    let last = SpringFrames::new(spring, animation, frame_rate)
+        .enumerate()
+        .last()
+        .filter(|(_, state)| state.is_at_equilibrium());
+    match last {
+        Some((frame, _)) => Ok(frame),
+        None => Err(CubicApproximationError::RanTooLong),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::ParamCurve;
+
+    use crate::spring::{AnimatedValue, AnimatedValueType, Spring};
+
+    use super::{
+        cubic_approximation, cubic_approximation_with_tolerance, fit_quality, DEFAULT_TOLERANCE,
+    };
+
+    fn assert_fits(spring: Spring, from: f64, to: f64) {
+        let frame_rate = 60.0;
+        let animation = AnimatedValue::new(from, to, AnimatedValueType::Scale);
+        let cubics = cubic_approximation(frame_rate, animation, spring).unwrap();
+
+        assert_eq!(from, cubics.first().unwrap().p0.y);
+        assert!((to - cubics.last().unwrap().p3.y).abs() < 0.01 * (to - from).abs());
+
+        // Every cubic should start where the last left off
+        for pair in cubics.windows(2) {
+            assert!((pair[0].p3 - pair[1].p0).hypot() < 1e-9, "{cubics:#?}");
+        }
+        // Endpoints should be on the spring
+        for cubic in cubics.iter() {
+            let expected = spring.update(cubic.p3.x / frame_rate, animation).value;
+            let actual = cubic.eval(1.0).y;
+            assert!(
+                (expected - actual).abs() < 0.01 * (to - from).abs(),
+                "{expected} != {actual}"
+            );
+        }
+    }
+
+    #[test]
+    fn fits_well_known_springs() {
+        for spring in [
+            Spring::standard(),
+            Spring::smooth_spatial(),
+            Spring::expressive_spatial(),
+        ] {
+            assert_fits(spring, 0.0, 100.0);
+        }
+    }
+
+    #[test]
+    fn fits_arbitrary_spring_and_range() {
+        assert_fits(Spring::new(0.5, 200.0).unwrap(), 360.0, -90.0);
+    }
+
+    #[test]
+    fn fits_critically_and_overdamped_springs() {
+        assert_fits(Spring::new(1.0, 200.0).unwrap(), 360.0, -90.0);
+        assert_fits(Spring::new(1.5, 200.0).unwrap(), 360.0, -90.0);
+    }
+
+    #[test]
+    fn fit_quality_within_tolerance() {
+        let frame_rate = 60.0;
+        let animation = AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale);
+        let spring = Spring::smooth_spatial();
+        let cubics = cubic_approximation(frame_rate, animation, spring).unwrap();
+        let quality = fit_quality(frame_rate, animation, spring, &cubics).unwrap();
+        assert!(
+            quality.max_value_error <= 2.0 * DEFAULT_TOLERANCE,
+            "{quality:?}"
+        );
+        assert!(quality.mean_value_error <= quality.max_value_error);
+        assert!(quality.max_frame_error < 1.0, "{quality:?}");
+    }
+
+    #[test]
+    fn fit_quality_detects_bad_fit() {
+        let frame_rate = 60.0;
+        let animation = AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale);
+        let spring = Spring::smooth_spatial();
+        let mut cubics = cubic_approximation(frame_rate, animation, spring).unwrap();
+        for cubic in cubics.iter_mut() {
+            cubic.p1.y += 20.0;
         }
-        animated_value = spring.update(time, animated_value);
-        frame += 1;
+        let quality = fit_quality(frame_rate, animation, spring, &cubics).unwrap();
+        assert!(quality.max_value_error > 1.0, "{quality:?}");
+    }
+
+    #[test]
+    fn fits_initial_velocity() {
+        let frame_rate = 60.0;
+        let spring = Spring::standard();
+        let animation =
+            AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale).with_velocity(2000.0);
+        let cubics = cubic_approximation(frame_rate, animation, spring).unwrap();
+        let quality = fit_quality(frame_rate, animation, spring, &cubics).unwrap();
+        assert!(quality.max_value_error <= 2.0, "{quality:?}");
+        // Moving at the start, not easing in from rest
+        let first = cubics.first().unwrap();
+        assert!(first.p1.y > first.p0.y, "{first:?}");
+    }
+
+    #[test]
+    fn fits_velocity_without_change_in_value() {
+        let frame_rate = 60.0;
+        let spring = Spring::standard();
+        let animation =
+            AnimatedValue::new(100.0, 100.0, AnimatedValueType::Scale).with_velocity(-500.0);
+        let cubics = cubic_approximation(frame_rate, animation, spring).unwrap();
+        assert!(cubics.len() > 1, "{cubics:#?}");
+        let lowest = cubics.iter().map(|c| c.p3.y).fold(f64::MAX, f64::min);
+        assert!(lowest < 100.0, "{cubics:#?}");
+        assert_eq!(100.0, cubics.last().unwrap().p3.y);
+    }
+
+    #[test]
+    fn tighter_tolerance_means_more_cubics() {
+        let animation = AnimatedValue::new(0.0, 100.0, AnimatedValueType::Scale);
+        let spring = Spring::standard();
+        let loose = cubic_approximation_with_tolerance(60.0, animation, spring, 2.0).unwrap();
+        let tight = cubic_approximation_with_tolerance(60.0, animation, spring, 0.1).unwrap();
+        assert!(
+            tight.len() > loose.len(),
+            "{} !> {}",
+            tight.len(),
+            loose.len()
+        );
     }
-    Ok(frame)
 }
@@ -0,0 +1,237 @@
+//! What the command parser understands, as data, so help text and editors can follow the parser
+//!
+//! Tests check that everything listed here parses, so a change to the parser that isn't
+//! reflected here fails them.
+
+use serde::Serialize;
+
+use crate::{
+    bender::Bender,
+    ir::Ease,
+    plan::{Channel, SPRING_SCHEMES},
+    presets::{Preset, PRESETS},
+    spring::Spring,
+};
+
+/// One piece of a command, e.g. `rotate` or `using`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Clause {
+    /// The word it starts with
+    pub name: &'static str,
+    /// How to write it, optional parts in [brackets]
+    pub syntax: &'static str,
+    pub description: &'static str,
+    /// Something to try, the part of a command after "Animate icon_name:"
+    pub example: &'static str,
+    /// Whether it may be combined with others by `then` and `while`
+    pub chainable: bool,
+}
+
+/// The overall shape of a command
+pub const COMMAND: &str =
+    "Animate <icon>: <plan> [while <plan>]... [then <plan> [while <plan>]... | then hold <frames>]...";
+
+/// What a plan may be, followed by any [`MODIFIERS`]
+pub const PLANS: &[Clause] = &[
+    Clause {
+        name: "pulse",
+        syntax: "pulse [part <name> | parts <n>[..<m>] | largest | smallest]",
+        description: "Scale each part of the icon down and back up, one after another",
+        example: "pulse part dot-1",
+        chainable: true,
+    },
+    Clause {
+        name: "pulse-whole",
+        syntax: "pulse-whole",
+        description: "Scale the whole icon down and back up",
+        example: "pulse-whole",
+        chainable: true,
+    },
+    Clause {
+        name: "twirl",
+        syntax: "twirl [part <name> | parts <n>[..<m>] | largest | smallest]",
+        description: "Spin each part of the icon, one after another, or just the parts selected",
+        example: "twirl parts 1..3",
+        chainable: true,
+    },
+    Clause {
+        name: "twirl-whole",
+        syntax: "twirl-whole",
+        description: "Spin the whole icon",
+        example: "twirl-whole",
+        chainable: true,
+    },
+    Clause {
+        name: "standard-enter",
+        syntax: "standard-enter",
+        description: "Fade in while scaling up from 80%, per Material's enter transition",
+        example: "standard-enter",
+        chainable: true,
+    },
+    Clause {
+        name: "standard-exit",
+        syntax: "standard-exit",
+        description: "Fade out while scaling down to 80%, per Material's exit transition",
+        example: "standard-exit",
+        chainable: true,
+    },
+    Clause {
+        name: "rotate",
+        syntax: "rotate <degrees> degrees",
+        description: "Rotate the whole icon",
+        example: "rotate 90 degrees",
+        chainable: true,
+    },
+    Clause {
+        name: "scale",
+        syntax: "scale <percent> to <percent>",
+        description: "Scale the whole icon",
+        example: "scale 80 to 100",
+        chainable: true,
+    },
+    Clause {
+        name: "squash",
+        syntax: "squash <percent>",
+        description:
+            "Squash the whole icon flat and spring back, stretching as it overshoots, keeping its area",
+        example: "squash 30 using elastic",
+        chainable: true,
+    },
+    Clause {
+        name: "sequence",
+        syntax: "sequence <icon> [frames] [ease | cubic-bezier(x1, y1, x2, y2)], ...",
+        description: "Morph into each icon in turn, the icons must be interpolation compatible",
+        example: "sequence volume_down, volume_up 20 linear",
+        chainable: false,
+    },
+    Clause {
+        name: "sweep",
+        syntax: "sweep <tag> <from> to <to> [in <steps> steps]",
+        description: "Vary along an axis, drawing the icon at each step",
+        example: "sweep wght 100 to 700 in 6 steps",
+        chainable: false,
+    },
+    Clause {
+        name: "preset",
+        syntax: "preset <name>",
+        description: "A canned animation, see presets",
+        example: "preset emphasize",
+        chainable: true,
+    },
+];
+
+/// What may follow a plan, in this order
+pub const MODIFIERS: &[Clause] = &[
+    Clause {
+        name: "using",
+        syntax: "using <spring | ease:<ease> | cubic-bezier(x1, y1, x2, y2) | <bender>[(<amplitude>)]> [for <channel>], ...",
+        description: "Pick springs, fixed eases or benders such as bounce, for the whole plan or per channel",
+        example: "scale 80 to 100 using expressive-spatial for scale, cubic-bezier(0.4, 0, 0.2, 1) for opacity",
+        chainable: true,
+    },
+    Clause {
+        name: "with",
+        syntax: "with velocity <velocity>",
+        description: "Start moving rather than at rest",
+        example: "rotate 90 degrees with velocity -250.5",
+        chainable: true,
+    },
+    Clause {
+        name: "vary",
+        syntax: "vary <location> to <location>",
+        description:
+            "Change variation over the animation, e.g. FILL:0 to FILL:1, or a named instance",
+        example: "pulse vary wght:400,FILL:0 to wght:700,FILL:1",
+        chainable: true,
+    },
+];
+
+/// Everything the command parser understands, see [`syntax`]
+#[derive(Debug, Clone, Serialize)]
+pub struct Syntax {
+    pub command: &'static str,
+    pub plans: &'static [Clause],
+    pub modifiers: &'static [Clause],
+    pub springs: &'static [&'static str],
+    /// Spring names that resolve to a spatial or non-spatial spring to suit each channel
+    pub spring_schemes: &'static [&'static str],
+    pub channels: &'static [&'static str],
+    /// Eases for `using ease:<ease>` and the steps of a sequence
+    pub eases: &'static [&'static str],
+    /// Classic easing families for `using`, elastic and overshoot take an amplitude
+    pub benders: &'static [&'static str],
+    pub presets: &'static [Preset],
+}
+
+/// Everything the command parser understands, e.g. to offer completions or print help
+pub fn syntax() -> Syntax {
+    Syntax {
+        command: COMMAND,
+        plans: PLANS,
+        modifiers: MODIFIERS,
+        springs: &Spring::NAMES,
+        spring_schemes: &SPRING_SCHEMES,
+        channels: &Channel::NAMES,
+        eases: &Ease::NAMES,
+        benders: &Bender::NAMES,
+        presets: PRESETS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{
+        bender::Bender,
+        ir::Ease,
+        plan::{AnimationPlan, Channel, SPRING_SCHEMES},
+        spring::Spring,
+    };
+
+    use super::{MODIFIERS, PLANS};
+
+    fn assert_parses(plan: &str) {
+        let command = format!("Animate an_icon: {plan}");
+        if let Err(e) = AnimationPlan::parse(&command) {
+            panic!("{command}: {e}");
+        }
+    }
+
+    #[test]
+    fn every_clause_parses() {
+        for clause in PLANS.iter().chain(MODIFIERS) {
+            assert!(clause.example.contains(clause.name), "{clause:?}");
+            assert_parses(clause.example);
+        }
+    }
+
+    #[test]
+    fn chainable_as_listed() {
+        for plan in PLANS {
+            let command = format!("Animate an_icon: pulse then {}", plan.example);
+            assert_eq!(
+                plan.chainable,
+                AnimationPlan::parse(&command).is_ok(),
+                "{plan:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn every_name_parses() {
+        for name in Spring::NAMES.iter().chain(SPRING_SCHEMES.iter()) {
+            assert_parses(&format!("pulse using {name}"));
+        }
+        for name in Channel::NAMES {
+            assert!(Channel::from_str(name).is_ok(), "{name}");
+        }
+        for name in Ease::NAMES {
+            assert_parses(&format!("pulse using ease:{name}"));
+            assert_parses(&format!("sequence volume_up {name}"));
+        }
+        for name in Bender::NAMES {
+            assert_parses(&format!("pulse using {name}"));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Pre-flight checks report problems with an icon rather than fail
+
+use iconimation::{
+    audit::{check_icon, check_icon_varying, Variation},
+    error::Error,
+};
+
+mod common;
+
+#[test]
+fn animatable_icon_passes() {
+    let report = check_icon(&common::font(), "settings").unwrap();
+    assert!(report.drawable, "{report:?}");
+    assert!(report.parts > 0, "{report:?}");
+    assert_eq!(
+        (Some(true), Some(true), None),
+        (
+            report.fill_compatible,
+            report.wght_compatible,
+            report.variation_compatible
+        )
+    );
+    assert!(report.warnings.is_empty(), "{report:?}");
+}
+
+#[test]
+fn problems_are_reported() {
+    let font = common::font();
+    for (variation, problem) in [
+        ("wdth:75..100", "no wdth axis"),
+        ("FILL:0..2", "Unable to vary"),
+    ] {
+        let variation: Variation = variation.parse().unwrap();
+        let report = check_icon_varying(&font, "settings", Some(&variation)).unwrap();
+        assert!(report.drawable, "{report:?}");
+        assert_eq!(Some(false), report.variation_compatible);
+        assert!(
+            report.warnings.iter().any(|w| w.contains(problem)),
+            "{problem} missing from {report:?}"
+        );
+    }
+}
+
+#[test]
+fn unknown_icon_is_an_error() {
+    assert!(matches!(
+        check_icon(&common::font(), "setings"),
+        Err(Error::IconNameError(..))
+    ));
+}
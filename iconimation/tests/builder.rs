@@ -0,0 +1,34 @@
+//! An animation built in code, with only the prelude, rather than from a command
+
+use std::time::Duration;
+
+use iconimation::prelude::*;
+use kurbo::{Rect, Shape};
+
+#[test]
+fn build_and_output() {
+    let mut rotate = Keyframed::new(0.0, 0.0);
+    rotate.push(Keyframe::new(0.5, 90.0).with_ease(Ease::default()));
+    let mut square = Group::default();
+    square.set_center((50.0, 50.0).into());
+    square.set_rotate(rotate);
+    square.children_mut().push(Element::Shape(Keyframed::new(
+        0.0,
+        Rect::new(25.0, 25.0, 75.0, 75.0).to_path(0.1),
+    )));
+    let mut root = Group::default();
+    root.children_mut().push(Element::Group(square));
+    let animation = Animation::new(100.0, 100.0, Duration::from_millis(500), root);
+
+    let lottie = to_lottie(&animation, &LottieOptions::default()).unwrap();
+    let json = lottie_json(&lottie, animation.segments(), &LottieOptions::default()).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(Some(30.0), json["op"].as_f64());
+    assert_eq!(Some(100.0), json["w"].as_f64());
+
+    let xml = to_avd(&animation, &AndroidOptions::default())
+        .unwrap()
+        .to_avd_xml()
+        .unwrap();
+    assert!(xml.contains(r#"android:pivotX="50""#), "{xml}");
+}
@@ -0,0 +1,23 @@
+//! Shared by the integration tests
+
+use skrifa::raw::FontRef;
+
+/// A handful of Material Symbols, see resources/fonts/README.md
+pub const FONT: &[u8] = include_bytes!("../../../resources/fonts/Symbols-reduced.ttf");
+
+/// Icons in [`FONT`]
+#[allow(unused)]
+pub const ICONS: &[&str] = &[
+    "pause_circle",
+    "play_circle",
+    "stadia_controller",
+    "more_horiz",
+    "settings",
+    "bolt",
+    "check_circle",
+    "check_box",
+];
+
+pub fn font() -> FontRef<'static> {
+    FontRef::new(FONT).unwrap()
+}
@@ -0,0 +1,85 @@
+//! What the test font offers, icons, axes and named instances, and instances as locations
+
+use iconimation::{
+    error::Error,
+    font_info::{axes, icon_names, named_instances, parse_location},
+};
+use skrifa::Tag;
+
+mod common;
+
+#[test]
+fn icons_sorted_by_name() {
+    let icons = icon_names(&common::font()).unwrap();
+    let names: Vec<_> = icons.iter().map(|icon| icon.name.as_str()).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(sorted, names);
+    for icon in common::ICONS {
+        assert!(names.contains(icon), "{icon} missing from {names:?}");
+    }
+    let settings = icons.iter().find(|icon| icon.name == "settings").unwrap();
+    assert_eq!(Some(0xe8b8), settings.codepoint);
+}
+
+#[test]
+fn axes_in_user_units() {
+    let axes = axes(&common::font());
+    assert_eq!(
+        vec!["FILL", "GRAD", "opsz", "wght"],
+        axes.iter()
+            .map(|axis| axis.tag.to_string())
+            .collect::<Vec<_>>()
+    );
+    let wght = &axes[3];
+    assert_eq!(Some("Weight"), wght.name.as_deref());
+    assert_eq!((100.0, 400.0, 700.0), (wght.min, wght.default, wght.max));
+}
+
+#[test]
+fn named_instances_by_weight() {
+    let instances = named_instances(&common::font());
+    assert_eq!(
+        vec![
+            "Thin",
+            "ExtraLight",
+            "Light",
+            "Regular",
+            "Medium",
+            "SemiBold",
+            "Bold"
+        ],
+        instances
+            .iter()
+            .map(|instance| instance.name.as_deref().unwrap())
+            .collect::<Vec<_>>()
+    );
+    let bold = instances.last().unwrap();
+    assert!(bold.location.contains(&(Tag::new(b"wght"), 700.0)));
+}
+
+#[test]
+fn instance_as_location() {
+    let font = common::font();
+    assert_eq!(
+        vec![
+            (Tag::new(b"FILL"), 0.0),
+            (Tag::new(b"GRAD"), 0.0),
+            (Tag::new(b"opsz"), 24.0),
+            (Tag::new(b"wght"), 700.0),
+        ],
+        parse_location(&font, "instance:Bold").unwrap()
+    );
+    // Case doesn't matter, and later entries override the instance
+    let location = parse_location(&font, "instance:semibold,FILL:1").unwrap();
+    assert!(location.contains(&(Tag::new(b"wght"), 600.0)));
+    assert_eq!(Some(&(Tag::new(b"FILL"), 1.0)), location.last());
+}
+
+#[test]
+fn no_such_instance() {
+    assert!(matches!(
+        parse_location(&common::font(), "instance:ExtraBlack"),
+        Err(Error::NoSuchInstance(name)) if name == "ExtraBlack"
+    ));
+}
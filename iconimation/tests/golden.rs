@@ -0,0 +1,110 @@
+//! Stable output of representative icons and plans compared byte for byte with fixtures
+//!
+//! Fixtures live in tests/golden and are only written if ICONIMATION_BLESS is set, so intended
+//! changes, and new fixtures, are accepted by rerunning with it set and reviewing the diff.
+
+use std::{env, fs, path::PathBuf};
+
+use iconimation::{
+    android::{to_avd, AndroidOptions},
+    ir::Animation,
+    lottie::{lottie_json, to_lottie, LottieOptions},
+    plan::PlanOptions,
+};
+
+mod common;
+
+fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if env::var_os("ICONIMATION_BLESS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        eprintln!("Wrote {}", path.display());
+        return;
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!(
+            "{} is missing, set ICONIMATION_BLESS to write it",
+            path.display()
+        );
+    };
+    assert!(
+        expected == actual,
+        "{} differs from the output, set ICONIMATION_BLESS to accept the output",
+        path.display()
+    );
+}
+
+fn assert_goldens(name: &str, command: &str) {
+    let font = common::font();
+    let animation = Animation::of_command(&font, command, &PlanOptions::default()).unwrap();
+
+    let options = LottieOptions {
+        stable: true,
+        ..Default::default()
+    };
+    let lottie = to_lottie(&animation, &options).unwrap();
+    let lottie = lottie_json(&lottie, animation.segments(), &options).unwrap();
+    assert_golden(&format!("{name}.json"), &lottie);
+
+    let avd = to_avd(
+        &animation,
+        &AndroidOptions {
+            stable: true,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .to_avd_xml()
+    .unwrap();
+    assert_golden(&format!("{name}.xml"), &avd);
+}
+
+#[test]
+fn pulse_whole() {
+    assert_goldens("pulse_whole", "Animate more_horiz: pulse-whole");
+}
+
+#[test]
+fn twirl_parts() {
+    assert_goldens("twirl_parts", "Animate settings: twirl");
+}
+
+#[test]
+fn rotate_with_spring() {
+    assert_goldens(
+        "rotate_with_spring",
+        "Animate bolt: rotate 90 degrees using expressive-spatial",
+    );
+}
+
+#[test]
+fn vary_fill() {
+    assert_goldens(
+        "vary_fill",
+        "Animate check_circle: scale 80 to 100 vary FILL:0 to FILL:1",
+    );
+}
+
+#[test]
+fn output_is_stable() {
+    let font = common::font();
+    let generate = || {
+        let animation =
+            Animation::of_command(&font, "Animate play_circle: twirl", &PlanOptions::default())
+                .unwrap();
+        let options = LottieOptions {
+            stable: true,
+            ..Default::default()
+        };
+        lottie_json(
+            &to_lottie(&animation, &options).unwrap(),
+            animation.segments(),
+            &options,
+        )
+        .unwrap()
+    };
+    assert_eq!(generate(), generate());
+}
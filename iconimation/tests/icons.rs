@@ -0,0 +1,410 @@
+//! Every icon of the bundled font animated by a matrix of commands, checking the structure of
+//! the result rather than just that nothing panicked
+
+use std::collections::HashMap;
+
+use iconimation::{
+    android::{to_avd, AndroidOptions},
+    batch::{animate_many, animate_many_observed},
+    error::IconNameError,
+    ir::{Animation, Ease, Element, IrVisitor, Keyframed, KeyframesMut},
+    ligate::{codepoint_to_icon_name, gid_to_icon_name, icon_name_to_gid},
+    lottie::{lottie_json, to_lottie, LottieOptions},
+    observe::GenerationObserver,
+    path_commands,
+    plan::PlanOptions,
+};
+use skrifa::{raw::TableProvider, GlyphId};
+
+mod common;
+
+/// What a command should animate
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Expect {
+    rotate: bool,
+    scale: bool,
+    shape: bool,
+}
+
+const ROTATE: Expect = Expect {
+    rotate: true,
+    scale: false,
+    shape: false,
+};
+const SCALE: Expect = Expect {
+    rotate: false,
+    scale: true,
+    shape: false,
+};
+
+const COMMANDS: &[(&str, Expect)] = &[
+    ("pulse", SCALE),
+    ("pulse-whole", SCALE),
+    ("twirl", ROTATE),
+    ("twirl-whole", ROTATE),
+    ("rotate 90 degrees using expressive-spatial", ROTATE),
+    ("scale 80 to 100 using smooth-spatial", SCALE),
+    ("rotate 45 degrees using standard", ROTATE),
+    (
+        "rotate 90 degrees using cubic-bezier(0.4, 0, 0.2, 1)",
+        ROTATE,
+    ),
+    (
+        "pulse using cubic-bezier(0.3, -0.5, 0.6, 1.5) for scale",
+        SCALE,
+    ),
+    ("rotate 90 degrees using bounce", ROTATE),
+    ("pulse using elastic(1.5) for scale", SCALE),
+    (
+        "pulse-whole vary FILL:0 to FILL:1",
+        Expect {
+            rotate: false,
+            scale: true,
+            shape: true,
+        },
+    ),
+];
+
+/// Checks every keyframed property as it goes, noting what was animated
+#[derive(Default)]
+struct Structure {
+    frames: f64,
+    frame_rate: f64,
+    animated: Expect,
+    problems: Vec<String>,
+}
+
+impl Structure {
+    fn check<T>(&mut self, what: &str, keyframed: &Keyframed<T>) -> bool {
+        let frames: Vec<_> = keyframed.iter().map(|k| k.time * self.frame_rate).collect();
+        if frames.windows(2).any(|w| w[0] >= w[1]) {
+            self.problems
+                .push(format!("{what} keyframes out of order {frames:?}"));
+        }
+        // Times are in seconds so may stray from whole frames by float error
+        if frames.iter().any(|f| *f < 0.0 || *f > self.frames + 1e-6) {
+            self.problems.push(format!(
+                "{what} keyframes {frames:?} outside 0..{}",
+                self.frames
+            ));
+        }
+        // Players require eases to move forward in time
+        let in_bounds =
+            |ease: Ease| (0.0..=1.0).contains(&ease.p1.x) && (0.0..=1.0).contains(&ease.p2.x);
+        if let Some(ease) = keyframed
+            .iter()
+            .filter_map(|k| k.ease)
+            .find(|e| !in_bounds(*e))
+        {
+            self.problems.push(format!(
+                "{what} has an ease that goes back in time {ease:?}"
+            ));
+        }
+        keyframed.is_animated()
+    }
+}
+
+impl IrVisitor for Structure {
+    fn visit_keyframes(&mut self, keyframes: KeyframesMut<'_>) {
+        match keyframes {
+            KeyframesMut::Translate(k) => {
+                self.check("translate", k);
+            }
+            KeyframesMut::Scale(k) => self.animated.scale |= self.check("scale", k),
+            KeyframesMut::Rotate(k) => self.animated.rotate |= self.check("rotate", k),
+            KeyframesMut::Skew(k) => {
+                self.check("skew", k);
+            }
+            KeyframesMut::Zoom(k) => {
+                self.check("zoom", k);
+            }
+            KeyframesMut::Opacity(k) => {
+                self.check("opacity", k);
+            }
+            KeyframesMut::Shape(k) => {
+                self.animated.shape |= self.check("shape", k);
+                let commands = path_commands(&k.earliest().value);
+                if k.iter().any(|k| path_commands(&k.value) != commands) {
+                    self.problems
+                        .push("shape keyframes aren't interpolation compatible".to_string());
+                }
+            }
+        }
+    }
+}
+
+fn check(icon: &str, command: &str, expect: Expect) -> Vec<String> {
+    let font = common::font();
+    let command = format!("Animate {icon}: {command}");
+    let mut animation = match Animation::of_command(&font, &command, &PlanOptions::default()) {
+        Ok(animation) => animation,
+        Err(e) => return vec![format!("{command}: {e}")],
+    };
+    let mut structure = Structure {
+        frames: animation.frames(),
+        frame_rate: animation.frame_rate(),
+        ..Default::default()
+    };
+    // Shared motion is only checked once resolved onto each group
+    animation.resolve_shared();
+    animation.visit_mut(&mut structure);
+    let mut problems = structure.problems;
+    if structure.animated != expect {
+        problems.push(format!(
+            "animated {:?}, expected {expect:?}",
+            structure.animated
+        ));
+    }
+
+    match to_lottie(&animation, &LottieOptions::default())
+        .and_then(|lottie| lottie_json(&lottie, animation.segments(), &Default::default()))
+    {
+        Ok(json) => {
+            let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            if json["op"].as_f64() != Some(animation.frames()) {
+                problems.push(format!(
+                    "Lottie ends at {}, expected {}",
+                    json["op"],
+                    animation.frames()
+                ));
+            }
+        }
+        Err(e) => problems.push(format!("Lottie: {e}")),
+    }
+
+    match to_avd(&animation, &AndroidOptions::default()).and_then(|avd| avd.to_avd_xml()) {
+        Ok(xml) => {
+            if expect.shape && !xml.contains(r#"android:propertyName="pathData""#) {
+                problems.push("AVD doesn't morph the shape".to_string());
+            }
+        }
+        Err(e) => problems.push(format!("AVD: {e}")),
+    }
+
+    problems
+        .into_iter()
+        .map(|p| format!("{command}: {p}"))
+        .collect()
+}
+
+#[test]
+fn every_icon_every_command() {
+    let problems: Vec<_> = common::ICONS
+        .iter()
+        .flat_map(|icon| {
+            COMMANDS
+                .iter()
+                .flat_map(move |(command, expect)| check(icon, command, *expect))
+        })
+        .collect();
+    assert!(problems.is_empty(), "{}", problems.join("\n"));
+}
+
+#[test]
+fn springs_are_sampled() {
+    let font = common::font();
+    let spring = Animation::of_command(
+        &font,
+        "Animate bolt: rotate 90 degrees using expressive-spatial",
+        &PlanOptions::default(),
+    )
+    .unwrap();
+    let eased = Animation::of_command(
+        &font,
+        "Animate bolt: rotate 90 degrees using standard",
+        &PlanOptions::default(),
+    )
+    .unwrap();
+    let rotations = |animation: &Animation| animation.resolved().root().rotate().len();
+    // A bouncy spring overshoots so takes more than a start and an end to express
+    assert!(rotations(&spring) > 2, "{}", rotations(&spring));
+    assert_eq!(2, rotations(&eased));
+}
+
+#[test]
+fn cubic_bezier_replaces_spring() {
+    let font = common::font();
+    let animation = Animation::of_command(
+        &font,
+        "Animate bolt: rotate 90 degrees using cubic-bezier(0.4, 0, 0.2, 1)",
+        &PlanOptions::default(),
+    )
+    .unwrap();
+    let animation = animation.resolved();
+    let rotate = animation.root().rotate();
+    assert_eq!(
+        vec![(0.0, 0.0), (animation.frames(), 90.0)],
+        rotate
+            .iter()
+            .map(|k| ((k.time * animation.frame_rate()).round(), k.value))
+            .collect::<Vec<_>>()
+    );
+    let ease = rotate.earliest().ease.unwrap();
+    assert_eq!(
+        (0.4, 0.0, 0.2, 1.0),
+        (ease.p1.x, ease.p1.y, ease.p2.x, ease.p2.y)
+    );
+}
+
+#[test]
+fn twirl_one_named_part() {
+    let font = common::font();
+    let animation = Animation::of_command(
+        &font,
+        "Animate more_horiz: twirl part dot-2",
+        &PlanOptions::default(),
+    )
+    .unwrap();
+    let animation = animation.resolved();
+    let parts: Vec<_> = animation
+        .root()
+        .children()
+        .iter()
+        .filter_map(|e| match e {
+            Element::Group(g) => Some((g.name(), g.rotate().is_animated())),
+            Element::Shape(..) => None,
+        })
+        .collect();
+    let mut names: Vec<_> = parts.iter().filter_map(|(name, _)| *name).collect();
+    names.sort();
+    assert_eq!(vec!["dot-1", "dot-2", "dot-3"], names);
+    assert_eq!(
+        vec![(Some("dot-2"), true)],
+        parts
+            .into_iter()
+            .filter(|(_, animated)| *animated)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn animate_many_in_order() {
+    let font = common::font();
+    let mut commands: Vec<_> = common::ICONS
+        .iter()
+        .map(|icon| format!("Animate {icon}: twirl"))
+        .collect();
+    commands.insert(1, "Animate no_such_icon: twirl".to_string());
+    let animations = animate_many(&font, &commands, &PlanOptions::default());
+    assert_eq!(commands.len(), animations.len());
+    for (command, animation) in commands.iter().zip(animations) {
+        let expected = Animation::of_command(&font, command, &PlanOptions::default());
+        match (expected, animation) {
+            (Ok(expected), Ok(animation)) => {
+                assert_eq!(expected.gid(), animation.gid(), "{command}")
+            }
+            (Err(..), Err(..)) => (),
+            (expected, animation) => panic!("{command}: {expected:?} != {animation:?}"),
+        }
+    }
+}
+
+/// Hits and misses, by cache
+#[derive(Default)]
+struct CacheCounter(HashMap<String, (usize, usize)>);
+
+impl GenerationObserver for CacheCounter {
+    fn cache_lookup(&mut self, cache: &str, hit: bool) {
+        let (hits, misses) = self.0.entry(cache.to_string()).or_default();
+        if hit {
+            *hits += 1;
+        } else {
+            *misses += 1;
+        }
+    }
+}
+
+#[test]
+fn caches_report_lookups() {
+    let font = common::font();
+    let commands = [
+        "Animate settings: twirl",
+        "Animate no_such_icon: twirl",
+        "Animate settings: twirl",
+        "Animate no_such_icon: twirl",
+        "Animate bolt: twirl",
+    ];
+    let mut counter = CacheCounter::default();
+    let animations = animate_many_observed(&font, &commands, &PlanOptions::default(), &mut counter);
+    assert_eq!(
+        vec![true, false, true, false, true],
+        animations.iter().map(|a| a.is_ok()).collect::<Vec<_>>()
+    );
+    // Failures aren't reused
+    assert_eq!(Some(&(1, 4)), counter.0.get("batch"));
+
+    // Animating the same thing again on the same thread approximates no new springs
+    let mut counter = CacheCounter::default();
+    let command = "Animate bolt: twirl";
+    Animation::of_command_observed(&font, command, &PlanOptions::default(), &mut counter).unwrap();
+    let (hits, misses) = counter.0["spring"];
+    assert!(hits + misses > 0, "twirl should be sprung");
+    Animation::of_command_observed(&font, command, &PlanOptions::default(), &mut counter).unwrap();
+    assert_eq!((2 * hits + misses, misses), counter.0["spring"]);
+}
+
+#[test]
+fn misspelt_icon_suggests_names() {
+    let font = common::font();
+    let Err(IconNameError::NoLigature { suggestions, .. }) = icon_name_to_gid(&font, "setings")
+    else {
+        panic!("setings shouldn't resolve");
+    };
+    assert_eq!(vec!["settings".to_string()], suggestions);
+}
+
+#[test]
+fn icon_names_round_trip() {
+    let font = common::font();
+    for icon in common::ICONS {
+        let gid = icon_name_to_gid(&font, icon).unwrap();
+        assert_eq!(
+            Some(icon.to_string()),
+            gid_to_icon_name(&font, gid).unwrap()
+        );
+    }
+    assert_eq!(None, gid_to_icon_name(&font, GlyphId::NOTDEF).unwrap());
+}
+
+#[test]
+fn codepoint_to_name() {
+    let font = common::font();
+    assert_eq!(
+        Some("settings".to_string()),
+        codepoint_to_icon_name(&font, 0xe8b8).unwrap()
+    );
+    // Letters spell the ligatures but aren't icons themselves
+    assert_eq!(None, codepoint_to_icon_name(&font, 'a' as u32).unwrap());
+    assert_eq!(None, codepoint_to_icon_name(&font, 0x10ffff).unwrap());
+}
+
+#[test]
+fn placeholder_on_error() {
+    let font = common::font();
+    let command = "Animate setings: twirl";
+    assert!(Animation::of_command(&font, command, &PlanOptions::default()).is_err());
+
+    let options = PlanOptions {
+        placeholder_on_error: true,
+        ..Default::default()
+    };
+    let animation = Animation::of_command(&font, command, &options).unwrap();
+    let upem = font.head().unwrap().units_per_em() as f64;
+    assert_eq!((upem, upem), (animation.width(), animation.height()));
+    assert_eq!(None, animation.gid());
+    let box_of = |animation: &Animation| match animation.root().children() {
+        [Element::Shape(shape)] => path_commands(&shape.earliest().value),
+        children => panic!("Expected a single shape, got {children:?}"),
+    };
+    assert_eq!(
+        box_of(&Animation::placeholder(upem, String::new())),
+        box_of(&animation)
+    );
+    let [warning] = animation.warnings() else {
+        panic!("Expected one warning, got {:?}", animation.warnings());
+    };
+    assert!(
+        warning.starts_with("Substituted a placeholder for 'Animate setings: twirl'"),
+        "{warning}"
+    );
+}
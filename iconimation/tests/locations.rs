@@ -0,0 +1,168 @@
+//! Variation locations are checked against the font's axes rather than clamped
+
+use iconimation::{
+    error::{AnimationError, Error},
+    font_info::parse_location,
+    ir::{compatible, Animation, Element, Keyframed},
+    ligate::icon_name_to_gid,
+    plan::{PlanOptions, VariationMode},
+    spring::{AnimatedValue, AnimatedValueType, Spring},
+};
+use kurbo::{BezPath, PathEl, Point};
+use skrifa::{instance::Location, MetadataProvider, Tag};
+
+mod common;
+
+#[test]
+fn parse_in_range() {
+    assert_eq!(
+        vec![(Tag::new(b"wght"), 700.0), (Tag::new(b"FILL"), 1.0)],
+        parse_location(&common::font(), "wght:700,FILL:1").unwrap()
+    );
+}
+
+#[test]
+fn out_of_range() {
+    assert!(matches!(
+        parse_location(&common::font(), "FILL:2"),
+        Err(Error::OutOfBounds {
+            value,
+            min,
+            max,
+            ..
+        }) if (value, min, max) == (2.0, 0.0, 1.0)
+    ));
+}
+
+#[test]
+fn no_such_axis() {
+    assert!(matches!(
+        parse_location(&common::font(), "wdth:100"),
+        Err(Error::NoSuchAxis(tag)) if tag == Tag::new(b"wdth")
+    ));
+}
+
+#[test]
+fn commands_are_checked() {
+    let font = common::font();
+    for command in [
+        "Animate settings: pulse vary FILL:0 to FILL:2",
+        "Animate settings: sweep wght 0 to 700",
+    ] {
+        assert!(matches!(
+            Animation::of_command(&font, command, &PlanOptions::default()),
+            Err(Error::OutOfBounds { .. })
+        ));
+    }
+}
+
+fn points(path: &BezPath) -> Vec<Point> {
+    path.elements()
+        .iter()
+        .flat_map(|el| match *el {
+            PathEl::MoveTo(p) | PathEl::LineTo(p) => vec![p],
+            PathEl::QuadTo(p1, p2) => vec![p1, p2],
+            PathEl::CurveTo(p1, p2, p3) => vec![p1, p2, p3],
+            PathEl::ClosePath => Vec::new(),
+        })
+        .collect()
+}
+
+#[test]
+fn redraw_follows_the_spring() {
+    let font = common::font();
+    let shape = |command: &str, variation| -> Keyframed<BezPath> {
+        let options = PlanOptions {
+            variation,
+            ..Default::default()
+        };
+        let animation = Animation::of_command(&font, command, &options).unwrap();
+        let [Element::Shape(shape)] = animation.root().children() else {
+            panic!("{command} should draw one shape");
+        };
+        shape.clone()
+    };
+    let drawn_at = |fill: f64| {
+        let command = format!("Animate bolt: none vary FILL:{fill} to FILL:{fill}");
+        points(&shape(&command, VariationMode::Interpolate).earliest().value)
+    };
+    let redrawn = shape(
+        "Animate bolt: none vary FILL:0 to FILL:1 using expressive-non-spatial for variation",
+        VariationMode::Redraw,
+    );
+
+    // How far the points move from FILL:0 to FILL:1, to judge closeness by
+    let reach = drawn_at(0.0)
+        .iter()
+        .zip(drawn_at(1.0))
+        .map(|(empty, full)| empty.distance(full))
+        .fold(0.0, f64::max);
+    assert!(reach > 0.0, "FILL should change the shape");
+    let spring = Spring::expressive_non_spatial();
+    let animation = AnimatedValue::new(0.0, 1.0, AnimatedValueType::Scale);
+    assert!(redrawn.len() > 2, "{redrawn:?}");
+    for keyframe in redrawn.iter() {
+        let fill = spring
+            .update(keyframe.time, animation)
+            .value
+            .clamp(0.0, 1.0);
+        let expected = drawn_at(fill);
+        let actual = points(&keyframe.value);
+        assert_eq!(expected.len(), actual.len());
+        let off = expected
+            .iter()
+            .zip(actual)
+            .map(|(e, a)| e.distance(a))
+            .fold(0.0, f64::max);
+        assert!(
+            off < 0.02 * reach + 0.5,
+            "At {}s the glyph should be drawn at FILL:{fill}, off by {off} of {reach}",
+            keyframe.time
+        );
+    }
+}
+
+fn filled(fill: f32) -> Location {
+    common::font().axes().location([(Tag::new(b"FILL"), fill)])
+}
+
+#[test]
+fn glyph_at_locations() {
+    let font = common::font();
+    let gid = icon_name_to_gid(&font, "bolt").unwrap();
+    // Out of order, they're sorted by time
+    let locations = [(0.75, filled(1.0)), (0.0, filled(0.0)), (0.5, filled(0.5))];
+    let animation = Animation::of_glyph_at_locations(&font, gid, &locations, &[]).unwrap();
+    let [Element::Shape(shape)] = animation.root().children() else {
+        panic!("Should draw one shape");
+    };
+    assert_eq!(
+        vec![0.0, 0.5, 0.75],
+        shape.iter().map(|k| k.time).collect::<Vec<_>>()
+    );
+    assert_eq!(45.0, animation.frames());
+    let first = &shape.earliest().value;
+    for keyframe in shape.iter() {
+        compatible(first, &keyframe.value).unwrap();
+    }
+    assert_ne!(points(first), points(&shape.latest().value));
+}
+
+#[test]
+fn glyph_at_no_or_repeated_locations() {
+    let font = common::font();
+    let gid = icon_name_to_gid(&font, "bolt").unwrap();
+    assert!(matches!(
+        Animation::of_glyph_at_locations(&font, gid, &[], &[]),
+        Err(AnimationError::NoKeyframes)
+    ));
+    assert!(matches!(
+        Animation::of_glyph_at_locations(
+            &font,
+            gid,
+            &[(0.5, filled(0.0)), (0.5, filled(1.0))],
+            &[]
+        ),
+        Err(AnimationError::MultipleValuesForTime(time)) if time == 0.5
+    ));
+}
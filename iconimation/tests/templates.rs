@@ -0,0 +1,170 @@
+//! The deprecated Template and Animator API, see iconimation::compat, run against the templates
+//! in resources/templates
+#![allow(deprecated)]
+
+use bodymovin::{
+    layers::AnyLayer,
+    properties::{Property, Value},
+    shapes::{AnyShape, Group, Transform},
+    Bodymovin as Lottie,
+};
+use iconimation::{
+    animate::{Animator, BentTwirl, Pulse, Still, Twirl},
+    bender::Bender,
+    ligate::icon_name_to_gid,
+    GlyphShape, Template,
+};
+use skrifa::instance::Location;
+
+mod common;
+
+const TEMPLATES: &[&str] = &[
+    "JustKeyframes.json",
+    "ScalePosition.json",
+    "ScaleRotate.json",
+    "ScaleRotatePosition.json",
+    "SparseKeyframes.json",
+    "Still.json",
+    "dsquare.json",
+];
+
+fn templates() -> Vec<(&'static str, Lottie)> {
+    TEMPLATES
+        .iter()
+        .map(|name| {
+            let path = format!(
+                "{}/../resources/templates/{name}",
+                env!("CARGO_MANIFEST_DIR")
+            );
+            let json = std::fs::read_to_string(&path).unwrap();
+            (*name, serde_json::from_str(&json).unwrap())
+        })
+        .collect()
+}
+
+/// Each template has one shape layer whose first shape is the placeholder
+fn placeholder(lottie: &Lottie) -> &Group {
+    let AnyLayer::Shape(layer) = &lottie.layers[0] else {
+        panic!("Templates should start with a shape layer");
+    };
+    let AnyShape::Group(group) = &layer.mixin.shapes[0] else {
+        panic!("The placeholder should be a group");
+    };
+    group
+}
+
+fn transform(lottie: &Lottie) -> &Transform {
+    placeholder(lottie)
+        .items
+        .iter()
+        .find_map(|item| match item {
+            AnyShape::Transform(transform) => Some(transform),
+            _ => None,
+        })
+        .expect("The placeholder should keep its transform")
+}
+
+/// The value of each keyframe of a property of a transform, a single value if it's fixed
+fn values(property: &Property<Vec<f64>>) -> Vec<Vec<f64>> {
+    match &property.value {
+        Value::Fixed(value) => vec![value.clone()],
+        Value::Animated(keyframes) => keyframes
+            .iter()
+            .filter_map(|k| k.start_value.clone())
+            .collect(),
+    }
+}
+
+fn rotations(transform: &Transform) -> Vec<f64> {
+    match &transform.rotation.value {
+        Value::Fixed(degrees) => vec![*degrees],
+        Value::Animated(keyframes) => keyframes
+            .iter()
+            .filter_map(|k| k.start_value.as_ref().map(|v| v[0]))
+            .collect(),
+    }
+}
+
+#[test]
+fn replace_shape_draws_the_glyph() {
+    let font = common::font();
+    let gid = icon_name_to_gid(&font, "bolt").unwrap();
+    let glyph_shape = GlyphShape::new(&font, gid, Location::default(), None).unwrap();
+    for (name, mut lottie) in templates() {
+        lottie.replace_shape(&glyph_shape).unwrap();
+        let items = &placeholder(&lottie).items;
+        assert!(
+            !items.iter().any(|i| matches!(i, AnyShape::Rect(..))),
+            "{name} should have its rect replaced"
+        );
+        assert!(
+            items.iter().any(|i| matches!(i, AnyShape::Shape(..))),
+            "{name} should draw the glyph"
+        );
+        assert!(
+            items.iter().any(|i| matches!(i, AnyShape::Fill(..))),
+            "{name} should keep its fill"
+        );
+    }
+}
+
+#[test]
+fn still_holds_every_template_still() {
+    for (name, mut lottie) in templates() {
+        Still.animate(&mut lottie).unwrap();
+        let transform = transform(&lottie);
+        assert_eq!(vec![0.0], rotations(transform), "{name}");
+        assert_eq!(vec![vec![100.0, 100.0]], values(&transform.scale), "{name}");
+        assert_eq!(
+            vec![vec![0.0, 0.0]],
+            values(&transform.anchor_point),
+            "{name}"
+        );
+        assert!(
+            matches!(transform.position.value, Value::Fixed(..)),
+            "{name} shouldn't keep its keyframed position"
+        );
+    }
+}
+
+#[test]
+fn pulse_scales_up_and_back() {
+    for (name, mut lottie) in templates() {
+        Pulse.animate(&mut lottie).unwrap();
+        let transform = transform(&lottie);
+        assert_eq!(
+            vec![vec![100.0, 100.0], vec![150.0, 150.0], vec![100.0, 100.0]],
+            values(&transform.scale),
+            "{name}"
+        );
+        assert_eq!(vec![0.0], rotations(transform), "{name}");
+    }
+}
+
+#[test]
+fn twirl_turns_once() {
+    for (name, mut lottie) in templates() {
+        Twirl.animate(&mut lottie).unwrap();
+        let transform = transform(&lottie);
+        assert_eq!(vec![0.0, 360.0], rotations(transform), "{name}");
+        assert_eq!(vec![vec![100.0, 100.0]], values(&transform.scale), "{name}");
+    }
+}
+
+#[test]
+fn bent_twirl_bounces_into_place() {
+    for (name, mut lottie) in templates() {
+        BentTwirl(Bender::Bounce).animate(&mut lottie).unwrap();
+        let rotations = rotations(transform(&lottie));
+        assert!(rotations.len() > 2, "{name} should bounce: {rotations:?}");
+        let (first, last) = (rotations[0], rotations[rotations.len() - 1]);
+        assert!(
+            first.abs() < 1e-6,
+            "{name} should start at rest: {rotations:?}"
+        );
+        assert!(
+            (360.0 - last).abs() < 1e-6,
+            "{name} should finish a turn: {rotations:?}"
+        );
+    }
+}